@@ -0,0 +1,20 @@
+-- Add migration script here
+CREATE TABLE interactions (
+  id SERIAL PRIMARY KEY,
+  feed_id INTEGER NOT NULL,
+  item_id INTEGER NULL,
+  actor VARCHAR NOT NULL,
+  activity_id VARCHAR NULL,
+  object_id VARCHAR NOT NULL,
+  kind VARCHAR NOT NULL,
+  content VARCHAR NULL,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+ALTER TABLE interactions ADD CONSTRAINT interactions_feed_fk FOREIGN KEY (feed_id) REFERENCES feeds(id) ON DELETE CASCADE;
+ALTER TABLE interactions ADD CONSTRAINT interactions_item_fk FOREIGN KEY (item_id) REFERENCES items(id) ON DELETE CASCADE;
+
+CREATE UNIQUE INDEX interactions_unique ON interactions(feed_id, actor, object_id, kind);
+CREATE INDEX interactions_actor ON interactions(actor);
+CREATE INDEX interactions_object_id ON interactions(object_id);
@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE feeds ADD profile_updated_at TIMESTAMP WITH TIME ZONE NULL;
@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE feeds ADD also_known_as TEXT NULL;
+ALTER TABLE feeds ADD moved_to TEXT NULL;
@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE feeds ADD previous_public_key TEXT NULL;
+ALTER TABLE feeds ADD key_rotated_at TIMESTAMP WITH TIME ZONE NULL;
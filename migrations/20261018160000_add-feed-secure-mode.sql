@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE feeds ADD secure_mode BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,15 @@
+-- Add migration script here
+ALTER TABLE feeds ADD manually_approves_followers BOOLEAN NOT NULL DEFAULT false;
+
+CREATE TABLE follow_requests (
+  id SERIAL PRIMARY KEY,
+  feed_id INTEGER NOT NULL,
+  actor VARCHAR NOT NULL,
+  activity_id VARCHAR NOT NULL,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+ALTER TABLE follow_requests ADD CONSTRAINT follow_requests_feed_fk FOREIGN KEY (feed_id) REFERENCES feeds(id) ON DELETE CASCADE;
+
+CREATE UNIQUE INDEX follow_requests_unique ON follow_requests(feed_id, actor);
@@ -0,0 +1,13 @@
+-- Add migration script here
+CREATE TABLE feed_blocks (
+  id SERIAL PRIMARY KEY,
+  feed_id INTEGER NOT NULL,
+  kind VARCHAR NOT NULL,
+  target VARCHAR NOT NULL,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+ALTER TABLE feed_blocks ADD CONSTRAINT feed_blocks_feed_fk FOREIGN KEY (feed_id) REFERENCES feeds(id) ON DELETE CASCADE;
+
+CREATE UNIQUE INDEX feed_blocks_unique ON feed_blocks(feed_id, kind, target);
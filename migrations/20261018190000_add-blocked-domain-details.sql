@@ -0,0 +1,4 @@
+-- Add migration script here
+ALTER TABLE blocked_domains ADD COLUMN severity VARCHAR NOT NULL DEFAULT 'reject';
+ALTER TABLE blocked_domains ADD COLUMN reason TEXT NULL;
+ALTER TABLE blocked_domains ADD COLUMN public_note TEXT NULL;
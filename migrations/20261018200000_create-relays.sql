@@ -0,0 +1,13 @@
+-- Add migration script here
+CREATE TABLE relays (
+  id SERIAL PRIMARY KEY,
+  url VARCHAR NOT NULL,
+  inbox_url VARCHAR NOT NULL,
+  kind VARCHAR NOT NULL,
+  status VARCHAR NOT NULL DEFAULT 'pending',
+  follow_activity_id VARCHAR NULL,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE UNIQUE INDEX relays_url ON relays(url);
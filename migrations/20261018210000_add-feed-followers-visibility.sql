@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE feeds ADD COLUMN followers_visibility VARCHAR NULL;
@@ -0,0 +1,12 @@
+-- Add migration script here
+CREATE TABLE dm_subscribers (
+  id SERIAL PRIMARY KEY,
+  feed_id INTEGER NOT NULL,
+  actor VARCHAR NOT NULL,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+ALTER TABLE dm_subscribers ADD CONSTRAINT dm_subscribers_feed_fk FOREIGN KEY (feed_id) REFERENCES feeds(id) ON DELETE CASCADE;
+
+CREATE UNIQUE INDEX dm_subscribers_unique ON dm_subscribers(feed_id, actor);
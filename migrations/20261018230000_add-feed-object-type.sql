@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE feeds ADD COLUMN object_type VARCHAR NOT NULL DEFAULT 'note';
@@ -0,0 +1,4 @@
+-- Add migration script here
+ALTER TABLE items ADD COLUMN author VARCHAR NULL;
+ALTER TABLE items ADD COLUMN fediverse_creator VARCHAR NULL;
+ALTER TABLE items ADD COLUMN fediverse_creator_url VARCHAR NULL;
@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE feeds ADD COLUMN redirect_items BOOLEAN NOT NULL DEFAULT false;
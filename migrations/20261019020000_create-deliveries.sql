@@ -0,0 +1,41 @@
+-- Add migration script here
+CREATE TABLE deliveries (
+  id SERIAL PRIMARY KEY,
+  feed_id INTEGER NOT NULL,
+  inbox_url VARCHAR NOT NULL,
+  domain VARCHAR NOT NULL,
+  message TEXT NOT NULL,
+  status VARCHAR NOT NULL DEFAULT 'pending',
+  attempts INTEGER NOT NULL DEFAULT 0,
+  last_status_code INTEGER,
+  last_error TEXT,
+  next_attempt_at TIMESTAMP WITH TIME ZONE,
+  retry_until TIMESTAMP WITH TIME ZONE NOT NULL,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+ALTER TABLE deliveries ADD CONSTRAINT deliveries_feed_fk FOREIGN KEY (feed_id) REFERENCES feeds(id) ON DELETE CASCADE;
+
+CREATE INDEX deliveries_status ON deliveries(status, updated_at);
+
+CREATE TABLE delivery_attempts (
+  id SERIAL PRIMARY KEY,
+  delivery_id INTEGER NOT NULL,
+  status_code INTEGER,
+  latency_ms INTEGER NOT NULL,
+  error TEXT,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+ALTER TABLE delivery_attempts ADD CONSTRAINT delivery_attempts_delivery_fk FOREIGN KEY (delivery_id) REFERENCES deliveries(id) ON DELETE CASCADE;
+
+CREATE INDEX delivery_attempts_delivery ON delivery_attempts(delivery_id);
+
+CREATE TABLE delivery_domains (
+  domain VARCHAR PRIMARY KEY,
+  failure_count INTEGER NOT NULL DEFAULT 0,
+  failing_since TIMESTAMP WITH TIME ZONE,
+  paused_until TIMESTAMP WITH TIME ZONE,
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
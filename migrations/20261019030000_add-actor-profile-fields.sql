@@ -0,0 +1,5 @@
+-- Add migration script here
+ALTER TABLE actors ADD COLUMN name VARCHAR;
+ALTER TABLE actors ADD COLUMN icon_url VARCHAR;
+ALTER TABLE actors ADD COLUMN shared_inbox_url VARCHAR;
+ALTER TABLE actors ADD COLUMN manually_approves_followers BOOLEAN NOT NULL DEFAULT false;
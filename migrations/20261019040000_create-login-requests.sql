@@ -0,0 +1,10 @@
+-- Add migration script here
+CREATE TABLE login_requests (
+  id SERIAL PRIMARY KEY,
+  email VARCHAR NOT NULL,
+  ip_address VARCHAR,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX login_requests_email ON login_requests(email, created_at);
+CREATE INDEX login_requests_ip_address ON login_requests(ip_address, created_at);
@@ -0,0 +1,18 @@
+-- Add migration script here
+CREATE TABLE sessions (
+  id SERIAL PRIMARY KEY,
+  user_id INTEGER NOT NULL,
+  token_hash VARCHAR NOT NULL,
+  user_agent VARCHAR,
+  ip_address VARCHAR,
+  last_seen_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+ALTER TABLE sessions ADD CONSTRAINT sessions_user_fk FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
+
+CREATE UNIQUE INDEX sessions_token_hash ON sessions(token_hash);
+CREATE INDEX sessions_user_id ON sessions(user_id);
+
+ALTER TABLE users DROP COLUMN access_token;
+ALTER TABLE users DROP COLUMN access_token_updated_at;
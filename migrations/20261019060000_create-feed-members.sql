@@ -0,0 +1,18 @@
+-- Add migration script here
+CREATE TABLE feed_members (
+  id SERIAL PRIMARY KEY,
+  feed_id INTEGER NOT NULL,
+  user_id INTEGER NOT NULL,
+  role VARCHAR NOT NULL,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+ALTER TABLE feed_members ADD CONSTRAINT feed_members_feed_fk FOREIGN KEY (feed_id) REFERENCES feeds(id) ON DELETE CASCADE;
+ALTER TABLE feed_members ADD CONSTRAINT feed_members_user_fk FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
+
+CREATE UNIQUE INDEX feed_members_feed_id_user_id ON feed_members(feed_id, user_id);
+CREATE INDEX feed_members_user_id ON feed_members(user_id);
+
+INSERT INTO feed_members (feed_id, user_id, role, created_at, updated_at)
+  SELECT id, user_id, 'owner', created_at, created_at FROM feeds;
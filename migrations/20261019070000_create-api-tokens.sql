@@ -0,0 +1,16 @@
+-- Add migration script here
+CREATE TABLE api_tokens (
+  id SERIAL PRIMARY KEY,
+  user_id INTEGER NOT NULL,
+  name VARCHAR NOT NULL,
+  token_hash VARCHAR NOT NULL,
+  last_used_at TIMESTAMP WITH TIME ZONE,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+ALTER TABLE api_tokens ADD CONSTRAINT api_tokens_user_fk FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE;
+
+CREATE UNIQUE INDEX api_tokens_token_hash ON api_tokens(token_hash);
+CREATE INDEX api_tokens_user_id ON api_tokens(user_id);
+
+ALTER TABLE feeds ADD COLUMN paused BOOLEAN NOT NULL DEFAULT false;
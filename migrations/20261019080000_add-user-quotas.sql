@@ -0,0 +1,5 @@
+-- Add migration script here
+ALTER TABLE users ADD COLUMN max_feeds INTEGER;
+ALTER TABLE users ADD COLUMN max_daily_deliveries INTEGER;
+
+CREATE INDEX deliveries_feed ON deliveries(feed_id, created_at);
@@ -0,0 +1,9 @@
+-- Add migration script here
+CREATE TABLE feed_delivery_counts (
+  feed_id INTEGER NOT NULL,
+  day DATE NOT NULL,
+  deliveries BIGINT NOT NULL DEFAULT 0,
+  PRIMARY KEY (feed_id, day)
+);
+
+ALTER TABLE feed_delivery_counts ADD CONSTRAINT feed_delivery_counts_feed_fk FOREIGN KEY (feed_id) REFERENCES feeds(id) ON DELETE CASCADE;
@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE feeds ADD previous_private_key TEXT NULL;
@@ -0,0 +1,8 @@
+-- Add migration script here
+CREATE TABLE fediverse_creators (
+  handle VARCHAR NOT NULL PRIMARY KEY,
+  actor_url VARCHAR NULL,
+  checked_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX fediverse_creators_checked_at ON fediverse_creators(checked_at);
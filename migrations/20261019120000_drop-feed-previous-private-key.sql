@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE feeds DROP COLUMN previous_private_key;
@@ -0,0 +1,109 @@
+#![feature(proc_macro_hygiene)]
+
+use sqlx::postgres::PgPoolOptions;
+use std::env;
+use std::fs;
+
+use rustypub::models::BlockedDomain;
+use rustypub::models::blocked_domain::{REJECT, SILENCE};
+
+use clap::{Parser, Subcommand};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+  #[command(subcommand)]
+  command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+  /// Block a domain
+  Add {
+    /// Domain to block
+    domain: String,
+
+    /// Either reject or silence
+    #[arg(short, long, default_value = REJECT)]
+    severity: String,
+
+    /// Reason for the block, only visible to admins
+    #[arg(short, long)]
+    reason: Option<String>,
+
+    /// Public note about the block
+    #[arg(short, long)]
+    public_note: Option<String>,
+  },
+
+  /// Unblock a domain
+  Remove {
+    /// Domain to unblock
+    domain: String,
+  },
+
+  /// List all blocked domains
+  List,
+
+  /// Import blocks from a CSV file
+  Import {
+    /// Path to the CSV file
+    path: String,
+  },
+
+  /// Print the block list as CSV
+  Export,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), sqlx::Error> {
+  let db_uri = env::var("DATABASE_URL").expect("DATABASE_URL is not set");
+
+  let pool = PgPoolOptions::new()
+    .max_connections(5)
+    .connect(&db_uri)
+    .await
+    .expect("Failed to create pool");
+
+  sqlx::migrate!("./migrations")
+    .run(&pool)
+    .await
+    .ok();
+
+  let args = Args::parse();
+
+  match args.command {
+    Command::Add { domain, severity, reason, public_note } => {
+      if severity != REJECT && severity != SILENCE {
+        println!("severity should be {REJECT} or {SILENCE}");
+        return Ok(());
+      }
+
+      let block = BlockedDomain::block(&domain, &severity, reason, public_note, &pool).await?;
+      println!("Blocked {} ({})", block.name, block.severity);
+    },
+    Command::Remove { domain } => {
+      BlockedDomain::delete(&domain, &pool).await?;
+      println!("Unblocked {domain}");
+    },
+    Command::List => {
+      for block in BlockedDomain::all(&pool).await? {
+        println!("{}\t{}\t{}", block.name, block.severity, block.reason.unwrap_or_default());
+      }
+    },
+    Command::Import { path } => {
+      match fs::read_to_string(&path) {
+        Ok(data) => {
+          let count = BlockedDomain::import_csv(&data, &pool).await?;
+          println!("Imported {count} blocks");
+        },
+        Err(why) => println!("failure! {why:?}")
+      }
+    },
+    Command::Export => {
+      print!("{}", BlockedDomain::to_csv(&pool).await?);
+    }
+  }
+
+  Ok(())
+}
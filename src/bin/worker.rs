@@ -13,7 +13,8 @@ use rustypub::{
   tasks::{
     UpdateStaleFeeds,
     DeleteOldMessages,
-    DeleteBadActors
+    DeleteBadActors,
+    DeleteExpiredFollowRequests
   },
   utils::queue::create_queue
 };
@@ -67,6 +68,12 @@ async fn main() {
     .schedule_task(&cleanup_actors_task as &dyn AsyncRunnable)
     .await
     .unwrap();
+
+  let cleanup_follow_requests_task = DeleteExpiredFollowRequests {};
+  queue
+    .schedule_task(&cleanup_follow_requests_task as &dyn AsyncRunnable)
+    .await
+    .unwrap();
     
   loop {
     sleep(Duration::from_secs(2)).await;
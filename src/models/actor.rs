@@ -3,7 +3,7 @@ use url::Url;
 use sqlx::postgres::PgPool;
 use serde_json::Value;
 
-use chrono::Utc;
+use chrono::{Duration, Utc};
 
 use openssl::{
   hash::MessageDigest,
@@ -14,6 +14,17 @@ use openssl::{
 
 use crate::DeliveryError;
 use crate::models::BlockedDomain;
+use crate::models::Follower;
+use crate::models::Interaction;
+use crate::models::DmSubscriber;
+use crate::tasks::RefreshActor;
+use crate::utils::queue::create_queue;
+
+use fang::AsyncRunnable;
+use fang::asynk::async_queue::AsyncQueueable;
+
+use std::env;
+use std::str::FromStr;
 
 ///
 /// Model for an ActivityPub actor. This could be a remote user who also has
@@ -37,9 +48,59 @@ pub struct Actor {
   pub error: Option<String>,
   pub username: String,
 
-  pub error_count:i32
+  pub error_count:i32,
+
+  pub name: Option<String>,
+  pub icon_url: Option<String>,
+  pub shared_inbox_url: Option<String>,
+  pub manually_approves_followers: bool
+}
+
+///
+/// Profile details we keep for an actor, beyond what we need to
+/// deliver messages to them
+///
+#[derive(Debug, Default, PartialEq)]
+pub struct ActorProfile {
+  pub name: Option<String>,
+  pub icon_url: Option<String>,
+  pub shared_inbox_url: Option<String>,
+  pub manually_approves_followers: bool
+}
+
+impl ActorProfile {
+  pub fn from_json(data: &Value) -> ActorProfile {
+    let icon_url = match &data["icon"] {
+      Value::String(url) => Some(url.to_string()),
+      Value::Object(_) => data["icon"]["url"].as_str().map(|url| url.to_string()),
+      Value::Array(icons) => icons.first().and_then(|icon| icon["url"].as_str()).map(|url| url.to_string()),
+      _ => None
+    };
+
+    ActorProfile {
+      name: data["name"].as_str().map(|name| name.to_string()),
+      icon_url,
+      shared_inbox_url: data["endpoints"]["sharedInbox"].as_str().map(|url| url.to_string()),
+      manually_approves_followers: data["manuallyApprovesFollowers"].as_bool().unwrap_or(false)
+    }
+  }
+}
+
+/// How long cached actor data is good for before we fetch it again, in seconds
+const ACTOR_REFRESH_TTL: i64 = 60 * 60 * 24;
+
+pub fn actor_refresh_ttl() -> i64 {
+  match env::var_os("ACTOR_REFRESH_TTL") {
+    Some(val) => {
+      i64::from_str(&val.into_string().expect("Something went wrong setting the actor refresh TTL")).unwrap()
+    }
+    None => ACTOR_REFRESH_TTL
+  }
 }
 
+/// The least amount of time between refetches when a signature doesn't verify, in seconds
+const ACTOR_REFETCH_INTERVAL: i64 = 60 * 5;
+
 impl PartialEq for Actor {
   fn eq(&self, other: &Self) -> bool {
     self.url == other.url
@@ -51,6 +112,17 @@ impl Actor {
   /// Query the DB for the actor with the given URL. If not found, fetch the data and cache it
   ///
   pub async fn find_or_fetch(url: &str, pool: &PgPool) -> Result<Option<Actor>, DeliveryError> {
+    let mut queue = create_queue().await;
+    queue.connect(fang::NoTls).await.unwrap();
+
+    Actor::find_or_fetch_with_queue(url, pool, &mut queue).await
+  }
+
+  ///
+  /// Same as find_or_fetch, but any background refresh goes into the
+  /// given queue
+  ///
+  pub async fn find_or_fetch_with_queue(url: &str, pool: &PgPool, queue: &mut dyn AsyncQueueable) -> Result<Option<Actor>, DeliveryError> {
     let mut clean_url = Url::parse(url).unwrap();
     clean_url.set_fragment(None);
 
@@ -58,7 +130,7 @@ impl Actor {
     // check if actor is on blocklist. if so, we won't do anything
     //
     let domain = clean_url.host().unwrap();
-    let on_blocklist = BlockedDomain::exists(&domain.to_string(), pool).await?;
+    let on_blocklist = BlockedDomain::rejects(&domain.to_string(), pool).await?;
     if on_blocklist {
       return Ok(None);
     }
@@ -66,11 +138,18 @@ impl Actor {
     let lookup_url = clean_url.as_str().to_string();
 
     //
-    // look for actor in db
+    // look for actor in db. if the data is getting old, we'll use it
+    // for now and refresh it in the background
     //
     let result = Actor::find(url, pool).await?;  
-    if result.is_some() {
-      return Ok(result);
+    if let Some(actor) = result {
+      if actor.is_stale() {
+        if let Err(why) = Actor::queue_refresh(&actor.url, queue).await {
+          log::info!("unable to queue actor refresh: {why:?}");
+        }
+      }
+
+      return Ok(Some(actor));
     }
 
     //
@@ -92,6 +171,53 @@ impl Actor {
     }      
   }
 
+  ///
+  /// Fetch the actor again, even if we have it cached. We do this when a
+  /// signature doesn't verify, in case they've rotated their keys
+  ///
+  pub async fn refetch(url: &str, pool: &PgPool) -> Result<Option<Actor>, DeliveryError> {
+    let mut clean_url = Url::parse(url)?;
+    clean_url.set_fragment(None);
+
+    let domain = clean_url.host().unwrap();
+    let on_blocklist = BlockedDomain::rejects(&domain.to_string(), pool).await?;
+    if on_blocklist {
+      return Ok(None);
+    }
+
+    Actor::fetch(&clean_url.as_str().to_string(), pool).await?;
+
+    Ok(Actor::find(url, pool).await?)
+  }
+
+  ///
+  /// Check if it's been long enough since we fetched this actor that we
+  /// should fetch them again
+  ///
+  pub fn is_stale(&self) -> bool {
+    self.refreshed_at < Utc::now() - Duration::seconds(actor_refresh_ttl())
+  }
+
+  ///
+  /// Check if we can fetch this actor again after a bad signature. This
+  /// keeps badly signed requests from making us fetch the same actor
+  /// over and over
+  ///
+  pub fn can_refetch(&self) -> bool {
+    self.refreshed_at < Utc::now() - Duration::seconds(ACTOR_REFETCH_INTERVAL)
+  }
+
+  ///
+  /// Queue up a background task to fetch the actor again
+  ///
+  pub async fn queue_refresh(url: &str, queue: &mut dyn AsyncQueueable) -> Result<(), DeliveryError> {
+    let task = RefreshActor::new(url.to_string());
+    match queue.insert_task(&task as &dyn AsyncRunnable).await {
+      Ok(_) => Ok(()),
+      Err(why) => Err(DeliveryError::Error(why.to_string()))
+    }
+  }
+
   ///
   /// query the db for this actor
   ///
@@ -105,9 +231,6 @@ impl Actor {
   /// Check if this Actor exists in the database
   ///
   pub async fn exists_by_url(url: &String, pool: &PgPool) -> Result<bool, sqlx::Error> {
-    // look for an actor but exclude old data
-    // let age = Utc::now() - Duration::seconds(3600);
-    //  AND refreshed_at > $2
     let result = sqlx::query!("SELECT count(1) AS tally FROM actors WHERE url = $1 OR inbox_url = $2 OR public_key_id = $3", url, url, url)
       .fetch_one(pool)
       .await;
@@ -152,12 +275,12 @@ impl Actor {
             return Err(DeliveryError::Error(String::from("User has no preferredUsername")))
           };
 
-          let inbox = if data["inbox"].is_string() {
+          let (inbox, profile) = if data["inbox"].is_string() {
             // log::info!("data has inbox key");
-            data["inbox"].as_str().unwrap().to_string()
+            (data["inbox"].as_str().unwrap().to_string(), ActorProfile::from_json(&data))
           } else if data["actor"].is_string() {
             // log::info!("data has actor key");
-            data["actor"].as_str().unwrap().to_string()
+            (data["actor"].as_str().unwrap().to_string(), ActorProfile::from_json(&data))
           } else if data["publicKey"]["owner"].is_string() {
             // log::info!("data has owner key");
 
@@ -183,7 +306,7 @@ impl Actor {
                 log::debug!("ACTOR: {url:} -> {resp:}");
         
                 let data:Value = serde_json::from_str(&resp).unwrap();       
-                data["inbox"].as_str().unwrap().to_string()
+                (data["inbox"].as_str().unwrap().to_string(), ActorProfile::from_json(&data))
               },
               Err(why) => {
                 log::info!("fetch failed: {why:?}");
@@ -205,6 +328,7 @@ impl Actor {
                         &username,
                         pool
           ).await?;
+          Actor::update_profile(data["id"].as_str().unwrap(), &profile, pool).await?;
         } else {
           return Err(DeliveryError::Error(String::from("User not found")))
         }
@@ -248,6 +372,18 @@ impl Actor {
     Ok(())
   }
 
+  ///
+  /// Store the profile details for an actor
+  ///
+  pub async fn update_profile(url: &str, profile: &ActorProfile, pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query!("UPDATE actors SET name = $1, icon_url = $2, shared_inbox_url = $3, manually_approves_followers = $4 WHERE url = $5",
+        profile.name, profile.icon_url, profile.shared_inbox_url, profile.manually_approves_followers, url)
+      .execute(pool)
+      .await?;
+
+    Ok(())
+  }
+
   ///
   /// Delete the specified actor
   ///
@@ -259,6 +395,17 @@ impl Actor {
     Ok(())
   }
 
+  ///
+  /// Remove the actor along with any follows or interactions they've made.
+  /// This happens when a remote account is deleted
+  ///
+  pub async fn purge(url: &str, pool: &PgPool) -> Result<(), sqlx::Error> {
+    Follower::delete_for_actor(url, pool).await?;
+    Interaction::delete_for_actor(url, pool).await?;
+    DmSubscriber::delete_for_actor(url, pool).await?;
+    Actor::delete(&url.to_string(), pool).await
+  }
+
 
   ///
   /// generate a full username address for the actor, ie @username@domain
@@ -287,8 +434,10 @@ mod test {
   use sqlx::postgres::PgPool;
   use std::fs;
 
-  use crate::models::actor::Actor;
-  use crate::utils::test_helpers::real_actor;
+  use chrono::{Duration, Utc};
+
+  use crate::models::actor::{Actor, ActorProfile, actor_refresh_ttl};
+  use crate::utils::test_helpers::{real_actor, test_queue};
 
   #[sqlx::test]
   async fn test_find_or_fetch(pool: PgPool) -> Result<(), String> {
@@ -315,6 +464,39 @@ mod test {
     Ok(())
   }
 
+  #[sqlx::test]
+  async fn test_refetch(pool: PgPool) -> Result<(), String> {
+    let mut server = mockito::Server::new_async().await;
+    let path = "fixtures/muffinista.json";
+    let data = fs::read_to_string(path).unwrap().replace("SERVER_URL", &server.url());
+
+    let m = server.mock("GET", "/users/muffinista")
+      .with_status(200)
+      .with_header("Accept", "application/activity+json")
+      .with_body(data)
+      .create_async()
+      .await;
+
+    let url = format!("{}/users/muffinista", server.url()).to_string();
+
+    // cache a stale key for the actor
+    Actor::create(&url,
+      &"https://botsin.space/users/muffinista/inbox".to_string(),
+      &"https://botsin.space/users/muffinista#main-key".to_string(),
+      &"stale key".to_string(),
+      &"muffinista".to_string(),
+      &pool).await.unwrap();
+
+    let actor = Actor::refetch(&url, &pool).await.unwrap().expect("Failed to load actor");
+
+    m.assert_async().await;
+
+    assert_eq!(actor.url, url);
+    assert!(actor.public_key.contains("BEGIN PUBLIC KEY"));
+
+    Ok(())
+  }
+
   #[sqlx::test]
   async fn test_find(pool: PgPool) -> Result<(), String> {
     let _actor:Actor = real_actor(&pool).await.unwrap();
@@ -352,9 +534,85 @@ mod test {
     let exists = Actor::exists_by_url(&url, &pool).await?;
     assert!(exists);
 
+    let actor = Actor::find(&url, &pool).await?.unwrap();
+    assert_eq!(actor.name, Some("colin mitchell".to_string()));
+    assert_eq!(actor.icon_url, Some("https://files.botsin.space/accounts/avatars/000/000/001/original/9e29b61b4ad8b864.jpg".to_string()));
+    assert_eq!(actor.shared_inbox_url, Some("https://botsin.space/inbox".to_string()));
+    assert!(!actor.manually_approves_followers);
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_stale(pool: PgPool) -> Result<(), sqlx::Error> {
+    let actor:Actor = real_actor(&pool).await?;
+    assert!(!actor.is_stale());
+    assert!(!actor.can_refetch());
+
+    let refreshed_at = Utc::now() - Duration::seconds(actor_refresh_ttl() + 60);
+    sqlx::query!("UPDATE actors SET refreshed_at = $1 WHERE url = $2", refreshed_at, actor.url)
+      .execute(&pool)
+      .await?;
+
+    let actor = Actor::find(&actor.url, &pool).await?.unwrap();
+    assert!(actor.is_stale());
+    assert!(actor.can_refetch());
+
+    let mut queue = test_queue(&pool).await;
+    Actor::queue_refresh(&actor.url, &mut queue).await.unwrap();
+    Actor::queue_refresh(&actor.url, &mut queue).await.unwrap();
+
+    let result = sqlx::query!("SELECT COUNT(1) AS tally FROM fang_tasks WHERE metadata->>'type' = 'RefreshActor' AND metadata->>'url' = $1", actor.url)
+      .fetch_one(&pool)
+      .await?;
+    assert_eq!(result.tally, Some(1));
+
     Ok(())
   }
 
+  #[sqlx::test]
+  async fn test_find_or_fetch_stale(pool: PgPool) -> Result<(), sqlx::Error> {
+    let actor:Actor = real_actor(&pool).await?;
+    let mut queue = test_queue(&pool).await;
+
+    Actor::find_or_fetch_with_queue(&actor.url, &pool, &mut queue).await.unwrap();
+    let result = sqlx::query!("SELECT COUNT(1) AS tally FROM fang_tasks WHERE metadata->>'type' = 'RefreshActor'")
+      .fetch_one(&pool)
+      .await?;
+    assert_eq!(result.tally, Some(0));
+
+    let refreshed_at = Utc::now() - Duration::seconds(actor_refresh_ttl() + 60);
+    sqlx::query!("UPDATE actors SET refreshed_at = $1 WHERE url = $2", refreshed_at, actor.url)
+      .execute(&pool)
+      .await?;
+
+    let found = Actor::find_or_fetch_with_queue(&actor.url, &pool, &mut queue).await.unwrap();
+    assert_eq!(found, Some(actor));
+
+    let result = sqlx::query!("SELECT COUNT(1) AS tally FROM fang_tasks WHERE metadata->>'type' = 'RefreshActor'")
+      .fetch_one(&pool)
+      .await?;
+    assert_eq!(result.tally, Some(1));
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_profile_from_json() {
+    let data = serde_json::json!({
+      "name": "Feed Bot",
+      "icon": [{ "type": "Image", "url": "https://foo.com/icon.png" }],
+      "manuallyApprovesFollowers": true
+    });
+
+    assert_eq!(ActorProfile::from_json(&data), ActorProfile {
+      name: Some("Feed Bot".to_string()),
+      icon_url: Some("https://foo.com/icon.png".to_string()),
+      shared_inbox_url: None,
+      manually_approves_followers: true
+    });
+  }
+
   #[sqlx::test]
   async fn test_fetch_no_inbox(pool: PgPool) -> Result<(), sqlx::Error> {
     let mut server = mockito::Server::new_async().await;
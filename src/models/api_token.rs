@@ -0,0 +1,127 @@
+use sqlx::postgres::PgPool;
+
+use chrono::{Duration, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+
+use crate::models::User;
+use crate::models::session::hash_token;
+
+///
+/// Model for a token that lets a user's own tools call the API. Like
+/// sessions, we only keep a hash of the token, so it's shown to the user
+/// once when it's created and never again
+///
+#[derive(Debug, serde::Serialize)]
+pub struct ApiToken {
+  pub id: i32,
+  pub user_id: i32,
+  pub name: String,
+  #[serde(skip)]
+  pub token_hash: String,
+  pub last_used_at: Option<chrono::DateTime::<Utc>>,
+  pub created_at: chrono::DateTime::<Utc>
+}
+
+impl PartialEq for ApiToken {
+  fn eq(&self, other: &Self) -> bool {
+    self.id == other.id
+  }
+}
+
+/// How often to update last_used_at, in seconds, so we don't write on every request
+const API_TOKEN_TOUCH_INTERVAL: i64 = 60;
+
+impl ApiToken {
+  ///
+  /// Create a token for the user. Returns the token record along with
+  /// the token itself
+  ///
+  pub async fn create(user: &User, name: &str, pool: &PgPool) -> Result<(ApiToken, String), sqlx::Error> {
+    let token: String = rand::thread_rng()
+      .sample_iter(&Alphanumeric)
+      .take(48)
+      .map(char::from)
+      .collect();
+    let now = Utc::now();
+
+    let api_token = sqlx::query_as!(ApiToken, "INSERT INTO api_tokens
+        (user_id, name, token_hash, created_at)
+        VALUES($1, $2, $3, $4)
+        RETURNING *",
+        user.id, name, hash_token(&token), now)
+      .fetch_one(pool)
+      .await?;
+
+    Ok((api_token, token))
+  }
+
+  pub async fn find_by_token(token: &str, pool: &PgPool) -> Result<Option<ApiToken>, sqlx::Error> {
+    sqlx::query_as!(ApiToken, "SELECT * FROM api_tokens WHERE token_hash = $1", hash_token(token))
+      .fetch_optional(pool)
+      .await
+  }
+
+  ///
+  /// Find the user for a token, and note that the token is still in use
+  ///
+  pub async fn user_for_token(token: &str, pool: &PgPool) -> Result<Option<User>, sqlx::Error> {
+    let api_token = match ApiToken::find_by_token(token, pool).await? {
+      Some(api_token) => api_token,
+      None => return Ok(None)
+    };
+
+    let now = Utc::now();
+    if api_token.last_used_at.is_none_or(|last_used_at| last_used_at < now - Duration::seconds(API_TOKEN_TOUCH_INTERVAL)) {
+      sqlx::query!("UPDATE api_tokens SET last_used_at = $1 WHERE id = $2", now, api_token.id)
+        .execute(pool)
+        .await?;
+    }
+
+    Ok(Some(User::find(api_token.user_id, pool).await?))
+  }
+
+  pub async fn for_user(user: &User, pool: &PgPool) -> Result<Vec<ApiToken>, sqlx::Error> {
+    sqlx::query_as!(ApiToken, "SELECT * FROM api_tokens WHERE user_id = $1 ORDER BY id", user.id)
+      .fetch_all(pool)
+      .await
+  }
+
+  ///
+  /// Revoke one of the user's tokens
+  ///
+  pub async fn delete(user: &User, id: i32, pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM api_tokens WHERE id = $1 AND user_id = $2", id, user.id)
+      .execute(pool)
+      .await?;
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use sqlx::postgres::PgPool;
+
+  use crate::models::ApiToken;
+  use crate::utils::test_helpers::real_user;
+
+  #[sqlx::test]
+  async fn test_create_and_revoke(pool: PgPool) -> sqlx::Result<()> {
+    let user = real_user(&pool).await?;
+
+    let (api_token, token) = ApiToken::create(&user, "deploy script", &pool).await?;
+    assert_ne!(api_token.token_hash, token);
+    assert!(api_token.last_used_at.is_none());
+
+    assert_eq!(ApiToken::user_for_token(&token, &pool).await?.map(|found| found.id), Some(user.id));
+    assert!(ApiToken::find_by_token(&token, &pool).await?.unwrap().last_used_at.is_some());
+    assert!(ApiToken::user_for_token("nope", &pool).await?.is_none());
+    assert_eq!(ApiToken::for_user(&user, &pool).await?, vec![api_token]);
+
+    let id = ApiToken::for_user(&user, &pool).await?[0].id;
+    ApiToken::delete(&user, id, &pool).await?;
+    assert!(ApiToken::user_for_token(&token, &pool).await?.is_none());
+
+    Ok(())
+  }
+}
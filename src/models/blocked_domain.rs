@@ -2,17 +2,139 @@ use sqlx::postgres::PgPool;
 
 use chrono::Utc;
 
+use url::Url;
+
 ///
 /// Simple model to track blocked domains. If a domain is blocked,
 /// we won't interact with it
 ///
+#[derive(Debug, serde::Serialize)]
 pub struct BlockedDomain {
   pub name: String,
   pub created_at: chrono::DateTime::<Utc>,
-  pub updated_at: chrono::DateTime::<Utc>
+  pub updated_at: chrono::DateTime::<Utc>,
+  pub severity: String,
+  pub reason: Option<String>,
+  pub public_note: Option<String>
+}
+
+impl PartialEq for BlockedDomain {
+  fn eq(&self, other: &Self) -> bool {
+    self.name == other.name
+  }
+}
+
+/// Refuse everything from the domain, and drop any followers we have there
+pub const REJECT: &str = "reject";
+
+/// Keep delivering to existing followers, but ignore new follows and interactions
+pub const SILENCE: &str = "silence";
+
+/// Header row for the CSV format used by Mastodon and friends
+const CSV_HEADER: &str = "#domain,#severity,#reject_media,#reject_reports,#public_comment,#obfuscate";
+
+///
+/// Turn a domain or a URL into a lowercase hostname
+///
+pub fn normalize_domain(name: &str) -> String {
+  let name = name.trim();
+  match Url::parse(name) {
+    Ok(url) if url.host_str().is_some() => url.host_str().unwrap().to_lowercase(),
+    _ => name.trim_end_matches('.').to_lowercase()
+  }
+}
+
+///
+/// Map a severity from an imported block list to one of ours. Returns
+/// None for severities we don't act on
+///
+fn parse_severity(severity: &str) -> Option<&'static str> {
+  match severity.trim().to_lowercase().as_str() {
+    "" | "suspend" | "reject" => Some(REJECT),
+    "silence" | "limit" => Some(SILENCE),
+    _ => None
+  }
+}
+
+///
+/// Split a line of CSV into fields, handling double-quoted values
+///
+fn parse_csv_line(line: &str) -> Vec<String> {
+  let mut fields = Vec::new();
+  let mut field = String::new();
+  let mut quoted = false;
+  let mut chars = line.chars().peekable();
+
+  while let Some(c) = chars.next() {
+    match c {
+      '"' if quoted && chars.peek() == Some(&'"') => {
+        field.push('"');
+        chars.next();
+      },
+      '"' => quoted = !quoted,
+      ',' if !quoted => fields.push(std::mem::take(&mut field)),
+      _ => field.push(c)
+    }
+  }
+  fields.push(field);
+
+  fields
+}
+
+fn csv_escape(value: &str) -> String {
+  if value.contains(',') || value.contains('"') || value.contains('\n') {
+    format!("\"{}\"", value.replace('"', "\"\""))
+  } else {
+    value.to_string()
+  }
 }
 
 impl BlockedDomain {
+  pub async fn find(name: &str, pool: &PgPool) -> Result<Option<BlockedDomain>, sqlx::Error> {
+    sqlx::query_as!(BlockedDomain, "SELECT * FROM blocked_domains WHERE name = $1", name)
+      .fetch_optional(pool)
+      .await
+  }
+
+  pub async fn all(pool: &PgPool) -> Result<Vec<BlockedDomain>, sqlx::Error> {
+    sqlx::query_as!(BlockedDomain, "SELECT * FROM blocked_domains ORDER BY name")
+      .fetch_all(pool)
+      .await
+  }
+
+  ///
+  /// Find the block that applies to a host, if any. Blocks cover
+  /// subdomains, and if there's more than one match we return the
+  /// most severe
+  ///
+  pub async fn for_host(host: &str, pool: &PgPool) -> Result<Option<BlockedDomain>, sqlx::Error> {
+    let host = normalize_domain(host);
+    sqlx::query_as!(BlockedDomain, "SELECT * FROM blocked_domains
+      WHERE name = $1 OR right($1, length(name) + 1) = '.' || name
+      ORDER BY severity = $2 DESC, length(name) DESC
+      LIMIT 1", host, REJECT)
+      .fetch_optional(pool)
+      .await
+  }
+
+  ///
+  /// Check if we should refuse all contact with the given host
+  ///
+  pub async fn rejects(host: &str, pool: &PgPool) -> Result<bool, sqlx::Error> {
+    let block = BlockedDomain::for_host(host, pool).await?;
+    Ok(block.is_some_and(|block| block.severity == REJECT))
+  }
+
+  ///
+  /// Check if the host of the given actor URL is blocked at all
+  ///
+  pub async fn for_actor(actor: &str, pool: &PgPool) -> Result<Option<BlockedDomain>, sqlx::Error> {
+    match Url::parse(actor).ok().and_then(|url| url.host_str().map(|host| host.to_string())) {
+      Some(host) => BlockedDomain::for_host(&host, pool).await,
+      None => Ok(None)
+    }
+  }
+
   ///
   /// Check if the specfied domain is on the block list
   ///
@@ -44,6 +166,126 @@ impl BlockedDomain {
 
     Ok(())
   }
+
+  ///
+  /// Add or update a block. Rejecting a domain also removes any
+  /// followers and cached actors we have from there
+  ///
+  pub async fn block(name: &str, severity: &str, reason: Option<String>, public_note: Option<String>, pool: &PgPool) -> Result<BlockedDomain, sqlx::Error> {
+    let now = Utc::now();
+    let name = normalize_domain(name);
+    let reason = reason.filter(|reason| !reason.trim().is_empty());
+    let public_note = public_note.filter(|note| !note.trim().is_empty());
+
+    let block = sqlx::query_as!(BlockedDomain, "INSERT INTO blocked_domains
+        (name, severity, reason, public_note, created_at, updated_at)
+        VALUES($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (name) DO UPDATE
+        SET severity = EXCLUDED.severity,
+          reason = EXCLUDED.reason,
+          public_note = EXCLUDED.public_note,
+          updated_at = EXCLUDED.updated_at
+        RETURNING *",
+        name, severity, reason, public_note, now, now)
+      .fetch_one(pool)
+      .await?;
+
+    if block.severity == REJECT {
+      block.purge(pool).await?;
+    }
+
+    Ok(block)
+  }
+
+  ///
+  /// Remove followers, pending follow requests, DM subscribers and cached actors
+  /// from this domain and its subdomains
+  ///
+  pub async fn purge(&self, pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM followers WHERE id IN (
+        SELECT id FROM (SELECT id, lower(substring(actor from '^[a-zA-Z]+://([^/:?#]+)')) AS host FROM followers) f
+        WHERE host = $1 OR right(host, length($1) + 1) = '.' || $1)", self.name)
+      .execute(pool)
+      .await?;
+
+    sqlx::query!("DELETE FROM follow_requests WHERE id IN (
+        SELECT id FROM (SELECT id, lower(substring(actor from '^[a-zA-Z]+://([^/:?#]+)')) AS host FROM follow_requests) f
+        WHERE host = $1 OR right(host, length($1) + 1) = '.' || $1)", self.name)
+      .execute(pool)
+      .await?;
+
+    sqlx::query!("DELETE FROM dm_subscribers WHERE id IN (
+        SELECT id FROM (SELECT id, lower(substring(actor from '^[a-zA-Z]+://([^/:?#]+)')) AS host FROM dm_subscribers) f
+        WHERE host = $1 OR right(host, length($1) + 1) = '.' || $1)", self.name)
+      .execute(pool)
+      .await?;
+
+    sqlx::query!("DELETE FROM actors WHERE url IN (
+        SELECT url FROM (SELECT url, lower(substring(url from '^[a-zA-Z]+://([^/:?#]+)')) AS host FROM actors) a
+        WHERE host = $1 OR right(host, length($1) + 1) = '.' || $1)", self.name)
+      .execute(pool)
+      .await?;
+
+    Ok(())
+  }
+
+  ///
+  /// Export the block list as CSV, in the format Mastodon uses
+  ///
+  pub async fn to_csv(pool: &PgPool) -> Result<String, sqlx::Error> {
+    let mut output = format!("{CSV_HEADER}\n");
+    for block in BlockedDomain::all(pool).await? {
+      let severity = if block.severity == REJECT { "suspend" } else { SILENCE };
+      let note = block.public_note.unwrap_or_default();
+      output.push_str(&format!("{},{},false,false,{},false\n", csv_escape(&block.name), severity, csv_escape(&note)));
+    }
+
+    Ok(output)
+  }
+
+  ///
+  /// Import a CSV block list. We understand the Mastodon format, as well
+  /// as a plain list of domains. Returns the number of blocks added
+  ///
+  pub async fn import_csv(data: &str, pool: &PgPool) -> Result<usize, sqlx::Error> {
+    let mut rows: Vec<Vec<String>> = data.lines()
+      .filter(|line| !line.trim().is_empty())
+      .map(parse_csv_line)
+      .collect();
+
+    // figure out which columns we care about from the header, if there is one
+    let mut columns: Vec<String> = vec!["domain".to_string(), "severity".to_string()];
+    if rows.first().is_some_and(|row| row[0].starts_with('#') || row[0].trim() == "domain") {
+      columns = rows.remove(0).iter().map(|name| name.trim().trim_start_matches('#').to_string()).collect();
+    }
+
+    let domain_index = columns.iter().position(|column| column == "domain").unwrap_or(0);
+    let severity_index = columns.iter().position(|column| column == "severity");
+    let note_index = columns.iter().position(|column| column == "public_comment");
+
+    let mut blocks: Vec<(String, &str, Option<String>)> = Vec::new();
+    for fields in rows {
+      let name = match fields.get(domain_index) {
+        Some(name) if !name.trim().is_empty() => name.clone(),
+        _ => continue
+      };
+
+      let severity = severity_index.and_then(|index| fields.get(index)).cloned().unwrap_or_default();
+      let severity = match parse_severity(&severity) {
+        Some(severity) => severity,
+        None => continue
+      };
+
+      let note = note_index.and_then(|index| fields.get(index)).cloned();
+      blocks.push((name, severity, note));
+    }
+
+    for (name, severity, note) in &blocks {
+      BlockedDomain::block(name, severity, None, note.clone(), pool).await?;
+    }
+
+    Ok(blocks.len())
+  }
 }
 
 
@@ -51,6 +293,8 @@ impl BlockedDomain {
 mod test {
   use sqlx::postgres::PgPool;
   use crate::models::BlockedDomain;
+  use crate::models::blocked_domain::{REJECT, SILENCE};
+  use crate::utils::test_helpers::real_feed;
 
 
   #[sqlx::test]
@@ -67,4 +311,70 @@ mod test {
 
     Ok(())
   }
+
+  #[sqlx::test]
+  async fn test_for_host(pool: PgPool) -> Result<(), sqlx::Error> {
+    BlockedDomain::block("Bad.Place", SILENCE, None, None, &pool).await?;
+    BlockedDomain::block("https://worse.bad.place/users/someone", REJECT, Some("spam".to_string()), None, &pool).await?;
+
+    let block = BlockedDomain::for_host("sub.bad.place", &pool).await?.unwrap();
+    assert_eq!(block.name, "bad.place");
+    assert_eq!(block.severity, SILENCE);
+
+    let block = BlockedDomain::for_host("worse.bad.place", &pool).await?.unwrap();
+    assert_eq!(block.severity, REJECT);
+    assert_eq!(block.reason, Some("spam".to_string()));
+
+    assert!(BlockedDomain::for_host("notbad.place", &pool).await?.is_none());
+    assert!(BlockedDomain::rejects("a.worse.bad.place", &pool).await?);
+    assert!(!BlockedDomain::rejects("bad.place", &pool).await?);
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_block_purges(pool: PgPool) -> Result<(), sqlx::Error> {
+    let feed = real_feed(&pool).await?;
+    for actor in ["https://bad.place/users/one", "https://ok.place/users/two"] {
+      sqlx::query!("INSERT INTO followers (feed_id, actor, created_at, updated_at) VALUES($1, $2, $3, $4)", feed.id, actor, chrono::Utc::now(), chrono::Utc::now())
+        .execute(&pool)
+        .await?;
+    }
+
+    // silencing leaves existing followers alone
+    BlockedDomain::block("bad.place", SILENCE, None, None, &pool).await?;
+    assert_eq!(feed.follower_count(&pool).await?, 2);
+
+    BlockedDomain::block("bad.place", REJECT, None, None, &pool).await?;
+    assert_eq!(feed.follower_count(&pool).await?, 1);
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_csv(pool: PgPool) -> Result<(), sqlx::Error> {
+    let data = "#domain,#severity,#reject_media,#reject_reports,#public_comment,#obfuscate
+bad.place,suspend,false,false,\"spam, mostly\",false
+meh.place,silence,false,false,,false
+fine.place,noop,false,false,,false
+";
+
+    assert_eq!(BlockedDomain::import_csv(data, &pool).await?, 2);
+
+    let block = BlockedDomain::find("bad.place", &pool).await?.unwrap();
+    assert_eq!(block.severity, REJECT);
+    assert_eq!(block.public_note, Some("spam, mostly".to_string()));
+    assert_eq!(BlockedDomain::find("meh.place", &pool).await?.unwrap().severity, SILENCE);
+    assert!(BlockedDomain::find("fine.place", &pool).await?.is_none());
+
+    let output = BlockedDomain::to_csv(&pool).await?;
+    assert!(output.contains("bad.place,suspend,false,false,\"spam, mostly\",false"));
+    assert!(output.contains("meh.place,silence,false,false,,false"));
+
+    // a plain list of domains works too
+    assert_eq!(BlockedDomain::import_csv("other.place\nanother.place\n", &pool).await?, 2);
+    assert!(BlockedDomain::rejects("other.place", &pool).await?);
+
+    Ok(())
+  }
 }
@@ -0,0 +1,281 @@
+use sqlx::postgres::PgPool;
+
+use chrono::{Duration, Utc};
+
+use url::Url;
+
+use std::env;
+use std::str::FromStr;
+
+///
+/// Model for a message we're delivering to a remote inbox. Each try is
+/// logged as a DeliveryAttempt. Deliveries that fail for too long, or
+/// that the remote server refuses outright, end up dead, and admins can
+/// look them over and replay them
+///
+#[derive(Debug, serde::Serialize)]
+pub struct Delivery {
+  pub id: i32,
+  pub feed_id: i32,
+  pub inbox_url: String,
+  pub domain: String,
+  pub message: String,
+  pub status: String,
+  pub attempts: i32,
+  pub last_status_code: Option<i32>,
+  pub last_error: Option<String>,
+  pub next_attempt_at: Option<chrono::DateTime::<Utc>>,
+  pub retry_until: chrono::DateTime::<Utc>,
+  pub created_at: chrono::DateTime::<Utc>,
+  pub updated_at: chrono::DateTime::<Utc>
+}
+
+impl PartialEq for Delivery {
+  fn eq(&self, other: &Self) -> bool {
+    self.id == other.id
+  }
+}
+
+///
+/// A single try at delivering a message
+///
+#[derive(Debug, serde::Serialize)]
+pub struct DeliveryAttempt {
+  pub id: i32,
+  pub delivery_id: i32,
+  pub status_code: Option<i32>,
+  pub latency_ms: i32,
+  pub error: Option<String>,
+  pub created_at: chrono::DateTime::<Utc>
+}
+
+pub const PENDING: &str = "pending";
+pub const DELIVERED: &str = "delivered";
+pub const DEAD: &str = "dead";
+
+/// How long to wait before the first retry, in seconds. This doubles
+/// with every attempt
+const DELIVERY_RETRY_DELAY: i64 = 60;
+
+/// The longest we'll wait between retries, in seconds
+const DELIVERY_MAX_RETRY_DELAY: i64 = 60 * 60 * 6;
+
+/// How long to keep retrying a delivery before giving up, in seconds
+const DELIVERY_RETRY_WINDOW: i64 = 60 * 60 * 48;
+
+pub fn delivery_retry_window() -> i64 {
+  match env::var_os("DELIVERY_RETRY_WINDOW") {
+    Some(val) => {
+      i64::from_str(&val.into_string().expect("Something went wrong setting the delivery retry window")).unwrap()
+    }
+    None => DELIVERY_RETRY_WINDOW
+  }
+}
+
+///
+/// How long to wait before trying a delivery again, given the number of
+/// attempts so far
+///
+pub fn retry_delay(attempts: i32) -> Duration {
+  let exponent = attempts.clamp(1, 20) as u32 - 1;
+  let delay = DELIVERY_RETRY_DELAY.saturating_mul(2_i64.pow(exponent));
+
+  Duration::seconds(delay.min(DELIVERY_MAX_RETRY_DELAY))
+}
+
+impl Delivery {
+  pub async fn find(id: i32, pool: &PgPool) -> Result<Option<Delivery>, sqlx::Error> {
+    sqlx::query_as!(Delivery, "SELECT * FROM deliveries WHERE id = $1", id)
+      .fetch_optional(pool)
+      .await
+  }
+
+  ///
+  /// Get the deliveries we've given up on, most recent first
+  ///
+  pub async fn dead(limit: i64, pool: &PgPool) -> Result<Vec<Delivery>, sqlx::Error> {
+    sqlx::query_as!(Delivery, "SELECT * FROM deliveries WHERE status = $1 ORDER BY updated_at DESC LIMIT $2", DEAD, limit)
+      .fetch_all(pool)
+      .await
+  }
+
+  pub async fn dead_count(pool: &PgPool) -> Result<i64, sqlx::Error> {
+    let result = sqlx::query!("SELECT COUNT(1) AS tally FROM deliveries WHERE status = $1", DEAD)
+      .fetch_one(pool)
+      .await?;
+
+    Ok(result.tally.unwrap_or(0))
+  }
+
+  pub async fn create(feed_id: i32, inbox_url: &str, message: &str, pool: &PgPool) -> Result<Delivery, sqlx::Error> {
+    let now = Utc::now();
+    let retry_until = now + Duration::seconds(delivery_retry_window());
+    let domain = Url::parse(inbox_url)
+      .ok()
+      .and_then(|url| url.host_str().map(|host| host.to_string()))
+      .unwrap_or_default();
+
+    sqlx::query_as!(Delivery, "INSERT INTO deliveries
+        (feed_id, inbox_url, domain, message, status, retry_until, created_at, updated_at)
+        VALUES($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING *",
+        feed_id, inbox_url, domain, message, PENDING, retry_until, now, now)
+      .fetch_one(pool)
+      .await
+  }
+
+  ///
+  /// Log an attempt to deliver the message
+  ///
+  pub async fn record_attempt(&mut self, status_code: Option<i32>, latency_ms: i32, error: Option<String>, pool: &PgPool) -> Result<(), sqlx::Error> {
+    let now = Utc::now();
+
+    sqlx::query!("INSERT INTO delivery_attempts (delivery_id, status_code, latency_ms, error, created_at)
+        VALUES($1, $2, $3, $4, $5)",
+        self.id, status_code, latency_ms, error, now)
+      .execute(pool)
+      .await?;
+
+    sqlx::query!("UPDATE deliveries SET attempts = attempts + 1, last_status_code = $1, last_error = $2, updated_at = $3 WHERE id = $4",
+        status_code, error, now, self.id)
+      .execute(pool)
+      .await?;
+
+    self.attempts += 1;
+    self.last_status_code = status_code;
+    self.last_error = error;
+
+    Ok(())
+  }
+
+  pub async fn attempt_log(&self, pool: &PgPool) -> Result<Vec<DeliveryAttempt>, sqlx::Error> {
+    sqlx::query_as!(DeliveryAttempt, "SELECT * FROM delivery_attempts WHERE delivery_id = $1 ORDER BY id", self.id)
+      .fetch_all(pool)
+      .await
+  }
+
+  async fn update_status(&mut self, status: &str, next_attempt_at: Option<chrono::DateTime::<Utc>>, pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query!("UPDATE deliveries SET status = $1, next_attempt_at = $2, updated_at = $3 WHERE id = $4",
+        status, next_attempt_at, Utc::now(), self.id)
+      .execute(pool)
+      .await?;
+
+    self.status = status.to_string();
+    self.next_attempt_at = next_attempt_at;
+
+    Ok(())
+  }
+
+  pub async fn mark_delivered(&mut self, pool: &PgPool) -> Result<(), sqlx::Error> {
+    self.update_status(DELIVERED, None, pool).await
+  }
+
+  pub async fn mark_dead(&mut self, pool: &PgPool) -> Result<(), sqlx::Error> {
+    self.update_status(DEAD, None, pool).await
+  }
+
+  pub async fn mark_retry(&mut self, next_attempt_at: chrono::DateTime::<Utc>, pool: &PgPool) -> Result<(), sqlx::Error> {
+    self.update_status(PENDING, Some(next_attempt_at), pool).await
+  }
+
+  ///
+  /// Check if we're still willing to try delivering at the given time
+  ///
+  pub fn retryable_at(&self, when: chrono::DateTime::<Utc>) -> bool {
+    when <= self.retry_until
+  }
+
+  ///
+  /// Put a dead delivery back in line, with a fresh retry window
+  ///
+  pub async fn replay(&mut self, pool: &PgPool) -> Result<(), sqlx::Error> {
+    let now = Utc::now();
+    let retry_until = now + Duration::seconds(delivery_retry_window());
+
+    sqlx::query!("UPDATE deliveries SET status = $1, next_attempt_at = $2, retry_until = $3, updated_at = $4 WHERE id = $5",
+        PENDING, now, retry_until, now, self.id)
+      .execute(pool)
+      .await?;
+
+    self.status = PENDING.to_string();
+    self.next_attempt_at = Some(now);
+    self.retry_until = retry_until;
+
+    Ok(())
+  }
+
+  pub async fn delete(&self, pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM deliveries WHERE id = $1", self.id)
+      .execute(pool)
+      .await?;
+
+    Ok(())
+  }
+
+  ///
+  /// Remove finished deliveries older than the given age, in seconds.
+  /// Dead deliveries stick around until an admin deals with them
+  ///
+  pub async fn cleanup(pool: &PgPool, age: i64, limit: i64) -> Result<(), sqlx::Error> {
+    let age = Utc::now() - Duration::seconds(age);
+
+    sqlx::query!("DELETE FROM deliveries WHERE id IN (SELECT id FROM deliveries WHERE status = $1 AND updated_at <= $2 ORDER BY updated_at LIMIT $3)",
+        DELIVERED, age, limit)
+      .execute(pool)
+      .await?;
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use sqlx::postgres::PgPool;
+  use chrono::{Duration, Utc};
+
+  use crate::models::Delivery;
+  use crate::models::delivery::{retry_delay, DEAD, PENDING};
+  use crate::utils::test_helpers::real_feed;
+
+  #[sqlx::test]
+  async fn test_retry_delay(_pool: PgPool) -> sqlx::Result<()> {
+    assert_eq!(retry_delay(1), Duration::seconds(60));
+    assert_eq!(retry_delay(2), Duration::seconds(120));
+    assert_eq!(retry_delay(5), Duration::seconds(960));
+    assert_eq!(retry_delay(100), Duration::seconds(60 * 60 * 6));
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_attempts_and_replay(pool: PgPool) -> sqlx::Result<()> {
+    let feed = real_feed(&pool).await?;
+    let mut delivery = Delivery::create(feed.id, "https://activitypub.pizza/users/colin/inbox", "{}", &pool).await?;
+    assert_eq!(delivery.domain, "activitypub.pizza");
+    assert_eq!(delivery.status, PENDING);
+    assert!(delivery.retryable_at(Utc::now()));
+
+    delivery.record_attempt(Some(503), 120, Some("Service Unavailable".to_string()), &pool).await?;
+    delivery.record_attempt(None, 30000, Some("timed out".to_string()), &pool).await?;
+    delivery.mark_dead(&pool).await?;
+
+    let delivery = Delivery::find(delivery.id, &pool).await?.unwrap();
+    assert_eq!(delivery.attempts, 2);
+    assert_eq!(delivery.status, DEAD);
+    assert_eq!(delivery.last_error, Some("timed out".to_string()));
+
+    let log = delivery.attempt_log(&pool).await?;
+    assert_eq!(log.len(), 2);
+    assert_eq!(log[0].status_code, Some(503));
+
+    assert_eq!(Delivery::dead(10, &pool).await?, vec![delivery]);
+    assert_eq!(Delivery::dead_count(&pool).await?, 1);
+
+    let mut delivery = Delivery::dead(10, &pool).await?.pop().unwrap();
+    delivery.replay(&pool).await?;
+    assert_eq!(delivery.status, PENDING);
+    assert_eq!(Delivery::dead_count(&pool).await?, 0);
+
+    Ok(())
+  }
+}
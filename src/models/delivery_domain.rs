@@ -0,0 +1,192 @@
+use sqlx::postgres::PgPool;
+
+use chrono::{Duration, Utc};
+
+use std::env;
+use std::str::FromStr;
+
+///
+/// Tracks delivery failures for a remote domain. Once a domain fails
+/// enough deliveries in a row, we stop sending to it for a while so a
+/// dead server doesn't tie up the queue. The longer it has been
+/// failing, the longer the pause
+///
+#[derive(Debug, serde::Serialize)]
+pub struct DeliveryDomain {
+  pub domain: String,
+  pub failure_count: i32,
+  pub failing_since: Option<chrono::DateTime::<Utc>>,
+  pub paused_until: Option<chrono::DateTime::<Utc>>,
+  pub updated_at: chrono::DateTime::<Utc>
+}
+
+/// How many failures in a row before we pause deliveries to a domain
+const CIRCUIT_BREAKER_THRESHOLD: i32 = 5;
+
+/// How long to pause deliveries to a failing domain, in seconds
+const CIRCUIT_BREAKER_PAUSE: i64 = 60 * 15;
+
+/// The longest we'll pause deliveries to a domain, in seconds
+const CIRCUIT_BREAKER_MAX_PAUSE: i64 = 60 * 60 * 24;
+
+pub fn circuit_breaker_threshold() -> i32 {
+  match env::var_os("CIRCUIT_BREAKER_THRESHOLD") {
+    Some(val) => {
+      i32::from_str(&val.into_string().expect("Something went wrong setting the circuit breaker threshold")).unwrap()
+    }
+    None => CIRCUIT_BREAKER_THRESHOLD
+  }
+}
+
+pub fn circuit_breaker_pause() -> i64 {
+  match env::var_os("CIRCUIT_BREAKER_PAUSE") {
+    Some(val) => {
+      i64::from_str(&val.into_string().expect("Something went wrong setting the circuit breaker pause")).unwrap()
+    }
+    None => CIRCUIT_BREAKER_PAUSE
+  }
+}
+
+///
+/// How long to pause a domain that has been failing since the given
+/// time. A server that has been down for hours gets left alone for
+/// about as long, rather than being checked every few minutes
+///
+pub fn pause_length(failing_since: Option<chrono::DateTime::<Utc>>, now: chrono::DateTime::<Utc>) -> Duration {
+  let pause = Duration::seconds(circuit_breaker_pause());
+  let failing_for = failing_since.map(|since| now - since).unwrap_or(pause);
+
+  failing_for.clamp(pause, Duration::seconds(CIRCUIT_BREAKER_MAX_PAUSE.max(circuit_breaker_pause())))
+}
+
+impl DeliveryDomain {
+  pub async fn find(domain: &str, pool: &PgPool) -> Result<Option<DeliveryDomain>, sqlx::Error> {
+    sqlx::query_as!(DeliveryDomain, "SELECT * FROM delivery_domains WHERE domain = $1", domain)
+      .fetch_optional(pool)
+      .await
+  }
+
+  ///
+  /// Get the domains we've stopped delivering to for now
+  ///
+  pub async fn paused(pool: &PgPool) -> Result<Vec<DeliveryDomain>, sqlx::Error> {
+    sqlx::query_as!(DeliveryDomain, "SELECT * FROM delivery_domains WHERE paused_until > $1 ORDER BY domain", Utc::now())
+      .fetch_all(pool)
+      .await
+  }
+
+  ///
+  /// If deliveries to the domain are paused, return when they can start again
+  ///
+  pub async fn paused_until(domain: &str, pool: &PgPool) -> Result<Option<chrono::DateTime::<Utc>>, sqlx::Error> {
+    let result = DeliveryDomain::find(domain, pool).await?;
+
+    Ok(result
+      .and_then(|d| d.paused_until)
+      .filter(|paused_until| *paused_until > Utc::now()))
+  }
+
+  ///
+  /// The domain accepted a delivery, so close the circuit
+  ///
+  pub async fn record_success(domain: &str, pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM delivery_domains WHERE domain = $1", domain)
+      .execute(pool)
+      .await?;
+
+    Ok(())
+  }
+
+  ///
+  /// Count a failed delivery to the domain, and pause deliveries once
+  /// there have been too many in a row
+  ///
+  pub async fn record_failure(domain: &str, pool: &PgPool) -> Result<DeliveryDomain, sqlx::Error> {
+    let now = Utc::now();
+
+    let mut result = sqlx::query_as!(DeliveryDomain, "INSERT INTO delivery_domains
+        (domain, failure_count, failing_since, updated_at)
+        VALUES($1, 1, $2, $3)
+        ON CONFLICT (domain) DO UPDATE
+        SET failure_count = delivery_domains.failure_count + 1,
+          updated_at = EXCLUDED.updated_at
+        RETURNING *",
+        domain, now, now)
+      .fetch_one(pool)
+      .await?;
+
+    if result.failure_count >= circuit_breaker_threshold() {
+      let paused_until = now + pause_length(result.failing_since, now);
+
+      sqlx::query!("UPDATE delivery_domains SET failure_count = 0, paused_until = $1 WHERE domain = $2",
+          paused_until, domain)
+        .execute(pool)
+        .await?;
+
+      result.failure_count = 0;
+      result.paused_until = Some(paused_until);
+    }
+
+    Ok(result)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use sqlx::postgres::PgPool;
+
+  use chrono::{Duration, Utc};
+
+  use crate::models::DeliveryDomain;
+  use crate::models::delivery_domain::{circuit_breaker_pause, circuit_breaker_threshold, pause_length};
+
+  #[test]
+  fn test_pause_length() {
+    let now = Utc::now();
+    let pause = Duration::seconds(circuit_breaker_pause());
+
+    assert_eq!(pause_length(None, now), pause);
+    assert_eq!(pause_length(Some(now), now), pause);
+    assert_eq!(pause_length(Some(now - Duration::hours(3)), now), Duration::hours(3));
+    assert_eq!(pause_length(Some(now - Duration::days(7)), now), Duration::days(1));
+  }
+
+  #[sqlx::test]
+  async fn test_circuit_breaker(pool: PgPool) -> sqlx::Result<()> {
+    let domain = "broken.place";
+
+    for _ in 1..circuit_breaker_threshold() {
+      DeliveryDomain::record_failure(domain, &pool).await?;
+    }
+    assert!(DeliveryDomain::paused_until(domain, &pool).await?.is_none());
+
+    DeliveryDomain::record_failure(domain, &pool).await?;
+    assert!(DeliveryDomain::paused_until(domain, &pool).await?.is_some());
+    assert_eq!(DeliveryDomain::paused(&pool).await?.len(), 1);
+
+    DeliveryDomain::record_success(domain, &pool).await?;
+    assert!(DeliveryDomain::paused_until(domain, &pool).await?.is_none());
+    assert!(DeliveryDomain::find(domain, &pool).await?.is_none());
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_long_failure(pool: PgPool) -> sqlx::Result<()> {
+    let domain = "broken.place";
+
+    DeliveryDomain::record_failure(domain, &pool).await?;
+    sqlx::query!("UPDATE delivery_domains SET failing_since = $1 WHERE domain = $2", Utc::now() - Duration::hours(3), domain)
+      .execute(&pool)
+      .await?;
+
+    for _ in 1..circuit_breaker_threshold() {
+      DeliveryDomain::record_failure(domain, &pool).await?;
+    }
+
+    let paused_until = DeliveryDomain::paused_until(domain, &pool).await?.unwrap();
+    assert!(paused_until > Utc::now() + Duration::minutes(170));
+
+    Ok(())
+  }
+}
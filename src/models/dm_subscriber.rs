@@ -0,0 +1,101 @@
+use sqlx::postgres::PgPool;
+
+use chrono::Utc;
+
+use crate::models::Feed;
+
+///
+/// Model for someone who gets a feed's items as direct messages. These
+/// are kept apart from followers, since the subscriber might be on a
+/// server that hides posts from bots
+///
+#[derive(Debug, serde::Serialize)]
+pub struct DmSubscriber {
+  pub id: i32,
+  pub feed_id: i32,
+  pub actor: String,
+  pub created_at: chrono::DateTime::<Utc>,
+  pub updated_at: chrono::DateTime::<Utc>
+}
+
+impl PartialEq for DmSubscriber {
+  fn eq(&self, other: &Self) -> bool {
+    self.id == other.id
+  }
+}
+
+impl DmSubscriber {
+  pub async fn for_feed(feed: &Feed, pool: &PgPool) -> Result<Vec<DmSubscriber>, sqlx::Error> {
+    sqlx::query_as!(DmSubscriber, "SELECT * FROM dm_subscribers WHERE feed_id = $1 ORDER BY id", feed.id)
+      .fetch_all(pool)
+      .await
+  }
+
+  pub async fn count_for_feed(feed: &Feed, pool: &PgPool) -> Result<i32, sqlx::Error> {
+    let result = sqlx::query!("SELECT COUNT(1) AS tally FROM dm_subscribers WHERE feed_id = $1", feed.id)
+      .fetch_one(pool)
+      .await?;
+
+    Ok(result.tally.unwrap() as i32)
+  }
+
+  ///
+  /// Subscribe the actor to the feed. Subscribing twice is harmless
+  ///
+  pub async fn create(feed: &Feed, actor: &str, pool: &PgPool) -> Result<(), sqlx::Error> {
+    let now = Utc::now();
+
+    sqlx::query!("INSERT INTO dm_subscribers (feed_id, actor, created_at, updated_at)
+        VALUES($1, $2, $3, $4)
+        ON CONFLICT (feed_id, actor) DO UPDATE SET updated_at = EXCLUDED.updated_at",
+        feed.id, actor, now, now)
+      .execute(pool)
+      .await?;
+
+    Ok(())
+  }
+
+  pub async fn delete(feed: &Feed, actor: &str, pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM dm_subscribers WHERE feed_id = $1 AND actor = $2", feed.id, actor)
+      .execute(pool)
+      .await?;
+
+    Ok(())
+  }
+
+  ///
+  /// Remove the actor's subscriptions to every feed
+  ///
+  pub async fn delete_for_actor(actor: &str, pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM dm_subscribers WHERE actor = $1", actor)
+      .execute(pool)
+      .await?;
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use sqlx::postgres::PgPool;
+
+  use crate::models::Feed;
+  use crate::models::DmSubscriber;
+  use crate::utils::test_helpers::real_feed;
+
+  #[sqlx::test]
+  async fn test_create_and_delete(pool: PgPool) -> sqlx::Result<()> {
+    let feed: Feed = real_feed(&pool).await?;
+    let actor = "https://activitypub.pizza/users/colin";
+
+    DmSubscriber::create(&feed, actor, &pool).await?;
+    DmSubscriber::create(&feed, actor, &pool).await?;
+    assert_eq!(DmSubscriber::count_for_feed(&feed, &pool).await?, 1);
+    assert_eq!(DmSubscriber::for_feed(&feed, &pool).await?[0].actor, actor);
+
+    DmSubscriber::delete(&feed, actor, &pool).await?;
+    assert_eq!(DmSubscriber::count_for_feed(&feed, &pool).await?, 0);
+
+    Ok(())
+  }
+}
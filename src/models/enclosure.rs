@@ -9,6 +9,7 @@ use crate::models::Item;
 ///
 /// Model for enclosures on RSS feeds. We'll attach enclosures to messages
 ///
+#[derive(Debug, serde::Serialize)]
 pub struct Enclosure {
   pub id: i32,
   pub item_id: i32,
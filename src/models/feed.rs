@@ -19,6 +19,7 @@ use activitystreams::{
   collection::{OrderedCollection, OrderedCollectionPage},
   link::Mention,
   object::*,
+  public,
   time::OffsetDateTime
 };
 
@@ -38,7 +39,15 @@ use std::{
 
 use md5::{Md5, Digest};
 
+use openssl::{
+  hash::MessageDigest,
+  pkey::PKey,
+  rsa::Rsa,
+  sign,
+};
+
 use fang::AsyncQueueable;
+use fang::AsyncRunnable;
 
 use sanitize_html::sanitize_str;
 use sanitize_html::rules::predefined::DEFAULT;
@@ -49,14 +58,27 @@ use crate::models::Actor;
 use crate::models::User;
 use crate::models::Item;
 use crate::models::Follower;
+use crate::models::FollowRequest;
+use crate::models::FeedBlock;
+use crate::models::BlockedDomain;
+use crate::models::Relay;
+use crate::models::DmSubscriber;
+use crate::models::FeedMember;
+use crate::models::feed_member::{roles_at_least, LAST_OWNER_ERROR, OWNER};
+use crate::models::relay::{ACCEPTED, REJECTED};
 use crate::models::SensitiveNote;
+use crate::models::Setting;
 use crate::models::FeedError;
+use crate::models::Interaction;
+use crate::models::interaction::{LIKE, ANNOUNCE, REPLY};
 
 use crate::utils::keys::*;
 use crate::utils::path_to_url;
 use crate::utils::http::*;
 
-use crate::services::mailer::*;
+use crate::services::fediverse_creator::resolve_handle;
+
+use crate::tasks::{DeliverItem, DeliverMessage, UpdateProfile};
 
 use crate::traits::property_value::{
   schema_property_context,
@@ -109,7 +131,27 @@ pub struct Feed {
   pub language: Option<String>,
 
   pub error: Option<String>,
-  pub error_count: i32
+  pub error_count: i32,
+
+  pub profile_updated_at: Option<chrono::DateTime::<Utc>>,
+
+  pub also_known_as: Option<String>,
+  pub moved_to: Option<String>,
+
+  pub previous_public_key: Option<String>,
+  pub key_rotated_at: Option<chrono::DateTime::<Utc>>,
+
+  pub secure_mode: bool,
+
+  pub manually_approves_followers: bool,
+
+  pub followers_visibility: Option<String>,
+
+  pub object_type: String,
+
+  pub redirect_items: bool,
+
+  pub paused: bool
 }
 
 impl PartialEq for Feed {
@@ -139,6 +181,53 @@ pub enum AcceptedTypes {
 
 pub type AcceptedActivity = ActorAndObject<AcceptedTypes>;
 
+///
+/// Some activities (Undo, for example) wrap another activity. If
+/// that's the case, pull it out so we can check what it was
+///
+fn embedded_activity(activity: &AcceptedActivity) -> Option<AcceptedActivity> {
+  let obj = activity.object_unchecked().as_one()?;
+  obj.clone().extend().unwrap_or(None)
+}
+
+///
+/// Check if a note is only addressed to specific actors, and not to the
+/// public or to the sender's followers
+///
+fn is_direct(note: &Note) -> bool {
+  let public = public().to_string();
+
+  note.to().into_iter().chain(note.cc())
+    .flat_map(|addresses| addresses.iter())
+    .filter_map(|address| address.id())
+    .all(|address| {
+      let address = address.as_str();
+      address != public && address != "as:Public" && !address.ends_with("/followers")
+    })
+}
+
+///
+/// Check a DM for a subscribe/unsubscribe command. The command has to be
+/// the first thing after any mentions. Returns whether the sender wants
+/// to subscribe, and the name of the feed if they gave one
+///
+fn subscription_command(content: &str) -> Option<(bool, Option<String>)> {
+  let text = scraper::Html::parse_fragment(content).root_element().text().collect::<String>();
+  let mut words = text.split_whitespace().skip_while(|word| word.starts_with('@'));
+
+  let subscribe = match words.next()?.trim_end_matches(['.', '!', ',']).to_lowercase().as_str() {
+    "subscribe" => true,
+    "unsubscribe" => false,
+    _ => return None
+  };
+
+  let target = words.next()
+    .map(|word| word.trim_start_matches('@').split('@').next().unwrap_or("").trim_end_matches(['.', '!', ',']).to_string())
+    .filter(|name| !name.is_empty());
+
+  Some((subscribe, target))
+}
+
 ///
 /// Extend Service with a public key
 ///
@@ -146,6 +235,43 @@ pub type ExtendedService = Ext1<ApActor<Service>, PublicKey>;
 
 const MAX_FEED_ERROR_COUNT: i32 = 10;
 
+/// Anyone can page through the followers of the feed
+pub const FOLLOWERS_PUBLIC: &str = "public";
+
+/// The followers collection only has a count
+pub const FOLLOWERS_COUNT: &str = "count";
+
+/// The followers collection has no count or pages
+pub const FOLLOWERS_HIDDEN: &str = "hidden";
+
+///
+/// A position in a paged collection. max_id asks for the entries older
+/// than the given one, and min_id asks for the ones newer than it
+///
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct PageCursor {
+  pub max_id: Option<i32>,
+  pub min_id: Option<i32>
+}
+
+impl PageCursor {
+  ///
+  /// check if we're paging towards newer entries
+  ///
+  pub fn is_newer(&self) -> bool {
+    self.max_id.is_none() && self.min_id.is_some()
+  }
+}
+
+/// Items are sent as Notes
+pub const OBJECT_TYPE_NOTE: &str = "note";
+
+/// Items are sent as Articles, with a title and an excerpt
+pub const OBJECT_TYPE_ARTICLE: &str = "article";
+
+/// Items are sent as Articles once they are long enough
+pub const OBJECT_TYPE_AUTO: &str = "auto";
+
 pub fn feed_max_error_count() -> i32 {
   match env::var_os("FEED_ERROR_COUNT") {
     Some(val) => {
@@ -155,6 +281,71 @@ pub fn feed_max_error_count() -> i32 {
   }
 }
 
+///
+/// activitystreams doesn't give Move a target, so we'll build our own
+///
+pub type MoveWithTarget = ActorAndObjectOptOriginAndTarget<activitystreams::activity::kind::MoveType>;
+
+///
+/// JSON-LD definitions for the actor properties which activitystreams
+/// doesn't know about
+///
+fn actor_context() -> Result<AnyBase, serde_json::Error> {
+  AnyBase::from_arbitrary_json(serde_json::json!({
+    "manuallyApprovesFollowers": "as:manuallyApprovesFollowers",
+    "alsoKnownAs": {
+      "@id": "as:alsoKnownAs",
+      "@type": "@id"
+    },
+    "movedTo": {
+      "@id": "as:movedTo",
+      "@type": "@id"
+    }
+  }))
+}
+
+const PROFILE_UPDATE_DELAY: i64 = 300;
+const PROFILE_UPDATE_INTERVAL: i64 = 21600;
+
+///
+/// How long to wait after a profile change before telling followers. Any
+/// other changes in this window get rolled into the same Update
+///
+pub fn profile_update_delay() -> i64 {
+  match env::var_os("PROFILE_UPDATE_DELAY") {
+    Some(val) => {
+      i64::from_str(&val.into_string().expect("Something went wrong setting the profile update delay")).unwrap()
+    }
+    None => PROFILE_UPDATE_DELAY
+  }
+}
+
+///
+/// Minimum number of seconds between profile Updates for a feed
+///
+pub fn profile_update_interval() -> i64 {
+  match env::var_os("PROFILE_UPDATE_INTERVAL") {
+    Some(val) => {
+      i64::from_str(&val.into_string().expect("Something went wrong setting the profile update interval")).unwrap()
+    }
+    None => PROFILE_UPDATE_INTERVAL
+  }
+}
+
+const KEY_ROTATION_GRACE_PERIOD: i64 = 604800;
+
+///
+/// How long we'll keep accepting the previous key after a key rotation
+///
+pub fn key_rotation_grace_period() -> i64 {
+  match env::var_os("KEY_ROTATION_GRACE_PERIOD") {
+    Some(val) => {
+      i64::from_str(&val.into_string().expect("Something went wrong setting the key rotation grace period")).unwrap()
+    }
+    None => KEY_ROTATION_GRACE_PERIOD
+  }
+}
+
 impl Feed {
   pub async fn find(id: i32, pool: &PgPool) -> Result<Feed, sqlx::Error> {
     sqlx::query_as!(Feed, "SELECT * FROM feeds WHERE id = $1", id)
@@ -181,12 +372,15 @@ impl Feed {
   }
 
   ///
-  /// Return a page of feeds for a given user
+  /// Return a page of feeds the given user is a member of
   ///
   pub async fn paged_for_user(user: &User, page: i32, pool: &PgPool) -> Result<Vec<Feed>, sqlx::Error> {
     let offset:i64 = ((page - 1) * PER_PAGE) as i64;
 
-    sqlx::query_as!(Feed, "SELECT * FROM feeds WHERE user_id = $1 ORDER BY id DESC LIMIT $2 OFFSET $3", user.id, PER_PAGE as i64, offset )
+    sqlx::query_as!(Feed, "SELECT feeds.* FROM feeds
+      INNER JOIN feed_members ON feed_members.feed_id = feeds.id
+      WHERE feed_members.user_id = $1
+      ORDER BY feeds.id DESC LIMIT $2 OFFSET $3", user.id, PER_PAGE as i64, offset )
       .fetch_all(pool)
       .await
   }
@@ -206,10 +400,10 @@ impl Feed {
   }
   
   ///
-  /// Get a count of how many items we have for this feed
+  /// Get a count of how many feeds the given user is a member of
   ///
   pub async fn count_for_user(user: &User, pool: &PgPool)  -> Result<i32, sqlx::Error> {
-    let result = sqlx::query!("SELECT COUNT(1) AS tally FROM feeds WHERE user_id = $1", user.id)
+    let result = sqlx::query!("SELECT COUNT(1) AS tally FROM feed_members WHERE user_id = $1", user.id)
       .fetch_one(pool)
       .await;
 
@@ -221,20 +415,37 @@ impl Feed {
   
 
   ///
-  /// Query the db for a feed owned by this user with the given name
+  /// Get a count of the feeds the user created, which is what counts
+  /// against their quota
+  ///
+  pub async fn count_created_by(user: &User, pool: &PgPool) -> Result<i64, sqlx::Error> {
+    let result = sqlx::query!("SELECT COUNT(1) AS tally FROM feeds WHERE user_id = $1", user.id)
+      .fetch_one(pool)
+      .await?;
+
+    Ok(result.tally.unwrap_or(0))
+  }
+
+  ///
+  /// Query the db for a feed with the given name, where the user is a
+  /// member with at least the given role
   ///
-  pub async fn find_by_user_and_name(user: &User, name: &String, pool: &PgPool) -> Result<Option<Feed>, sqlx::Error> {
-    sqlx::query_as!(Feed, "SELECT * FROM feeds WHERE name = $1 AND user_id = $2", name, user.id)
+  pub async fn find_by_user_and_name(user: &User, name: &String, role: &str, pool: &PgPool) -> Result<Option<Feed>, sqlx::Error> {
+    sqlx::query_as!(Feed, "SELECT feeds.* FROM feeds
+      INNER JOIN feed_members ON feed_members.feed_id = feeds.id
+      WHERE feeds.name = $1 AND feed_members.user_id = $2 AND feed_members.role = ANY($3)",
+      name, user.id, &roles_at_least(role))
       .fetch_optional(pool)
       .await
   }
 
   ///
-  /// Query the db for a maximum of _limit_ feeds older than _age_ seconds
+  /// Query the db for a maximum of _limit_ feeds older than _age_ seconds.
+  /// Paused feeds are left alone
   ///
   pub async fn stale(pool: &PgPool, age:i64, limit: i64) -> Result<Vec<Feed>, sqlx::Error> {
     let age = Utc::now() - Duration::seconds(age);
-    sqlx::query_as!(Feed, "SELECT * FROM feeds WHERE admin = false AND refreshed_at < $1 ORDER BY refreshed_at LIMIT $2", age, limit)
+    sqlx::query_as!(Feed, "SELECT * FROM feeds WHERE admin = false AND paused = false AND moved_to IS NULL AND refreshed_at < $1 ORDER BY refreshed_at LIMIT $2", age, limit)
     .fetch_all(pool)
     .await
   }
@@ -250,20 +461,47 @@ impl Feed {
   }
 
   ///
-  /// Find all the feeds for the given user
+  /// Find all the feeds the given user is a member of
   ///
   pub async fn for_user(user: &User, pool: &PgPool) -> Result<Vec<Feed>, sqlx::Error> {
-    sqlx::query_as!(Feed, "SELECT * FROM feeds WHERE user_id = $1", user.id)
+    sqlx::query_as!(Feed, "SELECT feeds.* FROM feeds
+      INNER JOIN feed_members ON feed_members.feed_id = feeds.id
+      WHERE feed_members.user_id = $1", user.id)
     .fetch_all(pool)
     .await
   }
     
+  ///
+  /// Find a local feed by its ActivityPub URL. Any fragment (ie, #main-key)
+  /// is ignored
+  ///
+  pub async fn find_by_ap_url(url: &str, pool: &PgPool) -> Result<Option<Feed>, sqlx::Error> {
+    let instance_domain = env::var("DOMAIN_NAME").expect("DOMAIN_NAME is not set");
+    let prefix = format!("https://{instance_domain}/feed/");
+
+    let url = url.split('#').next().unwrap_or(url);
+    match url.strip_prefix(&prefix) {
+      Some(name) if !name.is_empty() && !name.contains('/') => Feed::find_by_name(&name.to_string(), pool).await,
+      _ => Ok(None)
+    }
+  }
+
   pub async fn find_by_name(name: &String, pool: &PgPool) -> Result<Option<Feed>, sqlx::Error> {
     sqlx::query_as!(Feed, "SELECT * FROM feeds WHERE name = $1", name)
       .fetch_optional(pool)
       .await
   }
 
+  ///
+  /// Find a feed by name, ignoring case. Webfinger and DM commands come
+  /// from people typing the name in by hand
+  ///
+  pub async fn find_by_name_case_insensitive(name: &str, pool: &PgPool) -> Result<Option<Feed>, sqlx::Error> {
+    sqlx::query_as!(Feed, "SELECT * FROM feeds WHERE LOWER(name) = LOWER($1) ORDER BY name = $1 DESC LIMIT 1", name)
+      .fetch_optional(pool)
+      .await
+  }
+
   pub async fn load_by_name(name: &String, pool: &PgPool) -> Result<Feed, sqlx::Error> {
     sqlx::query_as!(Feed, "SELECT * FROM feeds WHERE name = $1", name)
       .fetch_one(pool)
@@ -307,8 +545,11 @@ impl Feed {
       .fetch_one(pool)
       .await?
       .id;
-    
-    Feed::find(feed_id, pool).await
+
+    let feed = Feed::find(feed_id, pool).await?;
+    FeedMember::add(&feed, user, OWNER, pool).await?;
+
+    Ok(feed)
   }
 
   ///
@@ -342,8 +583,14 @@ impl Feed {
           listed = $16,
           error_count = $17,
           tweaked_profile_data = $18,
-          language = $19
-      WHERE id = $20",
+          language = $19,
+          also_known_as = $20,
+          secure_mode = $21,
+          manually_approves_followers = $22,
+          followers_visibility = $23,
+          object_type = $24,
+          redirect_items = $25
+      WHERE id = $26",
       self.url,
       self.name,
       self.private_key,
@@ -363,6 +610,12 @@ impl Feed {
       self.error_count,
       self.tweaked_profile_data,
       self.language,
+      self.also_known_as,
+      self.secure_mode,
+      self.manually_approves_followers,
+      self.followers_visibility,
+      self.object_type,
+      self.redirect_items,
       self.id
     ).execute(pool)
       .await?;
@@ -373,7 +626,8 @@ impl Feed {
   pub async fn delete(user: &User, id: i32, pool: &PgPool) -> Result<Feed, sqlx::Error> {
     let old_feed = Feed::find(id, pool).await;
     
-    sqlx::query!("DELETE FROM feeds WHERE user_id = $1 AND id = $2", user.id, id)
+    sqlx::query!("DELETE FROM feeds WHERE id = $2
+        AND id IN (SELECT feed_id FROM feed_members WHERE user_id = $1 AND role = $3)", user.id, id, OWNER)
       .execute(pool)
       .await?;
     
@@ -408,6 +662,14 @@ impl Feed {
     self.admin
   }
 
+  ///
+  /// Should posts from this feed be forwarded to relays? We only do this
+  /// for public posts from listed feeds
+  ///
+  pub fn sends_to_relays(&self) -> bool {
+    self.listed && self.status_publicity.as_deref() == Some("public")
+  }
+
 
   ///
   /// Is this feed throwing an error?
@@ -447,6 +709,19 @@ impl Feed {
     }
   }
 
+  ///
+  /// Stop or start refreshing the feed on a schedule
+  ///
+  pub async fn set_paused(&mut self, paused: bool, pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query!("UPDATE feeds SET paused = $1, updated_at = $2 WHERE id = $3", paused, Utc::now(), self.id)
+      .execute(pool)
+      .await?;
+
+    self.paused = paused;
+
+    Ok(())
+  }
+
   pub async fn mark_error(&self, err: &String, pool: &PgPool) -> Result<(), sqlx::Error> {
     let result = sqlx::query!("UPDATE feeds SET error = $1, error_count = error_count + 1 WHERE id = $2", Some(err), self.id)
       .execute(pool)
@@ -482,6 +757,15 @@ impl Feed {
     }
   }
 
+  pub async fn mark_profile_updated(&self, pool: &PgPool) -> Result<(), sqlx::Error> {
+    let now = Utc::now();
+    sqlx::query!("UPDATE feeds SET profile_updated_at = $1 WHERE id = $2", now, self.id)
+      .execute(pool)
+      .await?;
+
+    Ok(())
+  }
+
   ///
   /// Get a count of how many items we have for this feed
   ///
@@ -547,6 +831,10 @@ impl Feed {
             Ok(item) => result.push(item),
             Err(why) => return Err(why)
           };
+        } else {
+          // but keep track of any edits to existing ones
+          let item = Item::find_by_guid(&entry.id, self, pool).await?;
+          item.update_from_entry(entry, pool).await?;
         }
       }
     }
@@ -575,19 +863,33 @@ impl Feed {
       return Ok(())
     }
   
+    // a feed that has moved doesn't post anymore
+    if self.moved_to.is_some() {
+      log::info!("Feed {} has moved to {:?}, skipping", self.id, self.moved_to);
+      return Ok(())
+    }
+
     if self.error_count > feed_max_error_count() {
       log::info!("Feed {} {} has too many errors {}, skipping", self.id, self.url, self.error_count);
       Ok(())
     } else {
 
+      let profile = self.profile_snapshot();
       let items = self.parse(pool).await;
       match items {
         Ok(items) => {
           if !items.is_empty() {
-            log::info!("delivering {} items", items.len());
+            log::info!("queueing {} items", items.len());
             for item in items {
-              item.deliver(self, pool, queue).await?;
-            }  
+              let task = DeliverItem::new(self.id, item.id);
+              if let Err(why) = queue.insert_task(&task as &dyn AsyncRunnable).await {
+                return Err(DeliveryError::Error(why.to_string()))
+              }
+            }
+          }
+
+          if self.profile_snapshot() != profile {
+            self.queue_profile_update(queue).await?;
           }
   
           self.mark_valid(pool).await?;
@@ -694,80 +996,448 @@ impl Feed {
 
 
   ///
-  /// Return URL to use in ActivityPub output for this feed
+  /// The fields which end up on our actor profile. If any of these
+  /// change, we'll send an Update to followers
   ///
-  pub fn ap_url(&self) -> String {
-    path_to_url(&uri!(render_feed(&self.name)))
+  pub fn profile_snapshot(&self) -> Vec<Option<String>> {
+    vec![
+      self.title.clone(),
+      self.description.clone(),
+      self.icon_url.clone(),
+      self.image_url.clone(),
+      self.also_known_as.clone(),
+      self.moved_to.clone(),
+      Some(self.manually_approves_followers.to_string())
+    ]
   }
 
   ///
-  /// Return URL to use in HTML output for this feed
+  /// Schedule an actor Update for this feed. Updates are debounced -- we
+  /// wait a little while in case anything else changes, and we won't send
+  /// them more often than PROFILE_UPDATE_INTERVAL
   ///
-  pub fn permalink_url(&self) -> String {
-    path_to_url(&uri!(show_feed(&self.name, None::<i32>)))
+  pub async fn queue_profile_update(&self, queue: &mut dyn AsyncQueueable) -> Result<(), DeliveryError> {
+    let mut run_at = Utc::now() + Duration::seconds(profile_update_delay());
+    if let Some(last_update) = self.profile_updated_at {
+      let earliest = last_update + Duration::seconds(profile_update_interval());
+      if earliest > run_at {
+        run_at = earliest;
+      }
+    }
+
+    let task = UpdateProfile::new(self.id, run_at);
+    let result = queue
+      .schedule_task(&task as &dyn AsyncRunnable)
+      .await;
+
+    match result {
+      Ok(_result) => Ok(()),
+      Err(why) => Err(DeliveryError::Error(why.to_string()))
+    }
   }
 
   ///
-  /// URL for the followers route
+  /// Generate an Update activity with the current actor data for this feed
   ///
-  pub fn followers_url(&self) -> String {
-    path_to_url(&uri!(render_feed_followers(&self.name, None::<i32>)))
+  pub async fn to_update_activity(&self, pool: &PgPool) -> Result<ApObject<Update>, DeliveryError> {
+    let feed_url = self.ap_url();
+    let actor: serde_json::Value = serde_json::from_str(&self.to_activity_pub(pool).await?)?;
+    let ts = OffsetDateTime::now_utc();
+
+    let mut action: ApObject<Update> = ApObject::new(
+      Update::new(
+        iri!(feed_url),
+        AnyBase::from_arbitrary_json(actor)?
+      )
+    );
+
+    action
+      .set_context(context())
+      .add_context(security())
+      .set_id(iri!(format!("{feed_url}#updates/{}", ts.unix_timestamp())))
+      .set_to(iri!("https://www.w3.org/ns/activitystreams#Public"))
+      .add_cc(iri!(self.followers_url()))
+      .set_published(ts);
+
+    Ok(action)
   }
 
   ///
-  /// return the email-style address for this feed
+  /// Send an Update with our current profile to all followers
   ///
-  pub fn address(&self) -> String {
-    let instance_domain = env::var("DOMAIN_NAME").expect("DOMAIN_NAME is not set");
-    format!("@{}@{}", self.name, instance_domain)
+  pub async fn broadcast_profile_update(&self, pool: &PgPool, queue: &mut dyn AsyncQueueable) -> Result<(), DeliveryError> {
+    let message = self.to_update_activity(pool).await?;
+    let msg = serde_json::to_string(&message).unwrap();
+
+    self.deliver_to_followers(&msg, pool, queue).await?;
+    self.mark_profile_updated(pool).await?;
+
+    Ok(())
   }
 
-  pub fn display_name(&self) -> &String {
-    if self.title.is_some() {
-      self.title.as_ref().unwrap()
+  ///
+  /// Queue up delivery of the given message to the inbox of every follower.
+  /// These are profile updates and moves, which followers need to stay in
+  /// sync, so they don't count against the daily delivery quota
+  ///
+  pub async fn deliver_to_followers(&self, msg: &str, pool: &PgPool, queue: &mut dyn AsyncQueueable) -> Result<(), DeliveryError> {
+    let followers = self.followers_list(pool).await?;
+    for follower in followers {
+      let inbox = follower.find_inbox(pool).await;
+      match inbox {
+        Ok(Some(inbox)) => {
+          let task = DeliverMessage::new(self.id, inbox, msg.to_string());
+          queue
+            .insert_task(&task as &dyn AsyncRunnable)
+            .await
+            .map_err(|why| DeliveryError::Error(why.to_string()))?;
+        },
+        Ok(None) => {},
+        Err(why) => log::info!("lookup failure! {why:?}")
+      }
+    }
+
+    Ok(())
+  }
+
+  ///
+  /// Other actors which are allowed to migrate to this feed. The owner
+  /// enters these one per line
+  ///
+  pub fn aliases(&self) -> Vec<String> {
+    match &self.also_known_as {
+      Some(also_known_as) => also_known_as
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect(),
+      None => vec![]
+    }
+  }
+
+  ///
+  /// Clean up a list of aliases entered by the owner, one per line. Only
+  /// http(s) URLs are kept
+  ///
+  pub fn clean_aliases(also_known_as: &str) -> Option<String> {
+    let aliases: Vec<String> = also_known_as
+      .lines()
+      .filter_map(|line| Url::parse(line.trim()).ok())
+      .filter(|url| url.scheme() == "https" || url.scheme() == "http")
+      .map(|url| url.to_string())
+      .collect();
+
+    if aliases.is_empty() {
+      None
     } else {
-      &self.name
+      Some(aliases.join("\n"))
     }
   }
 
+  ///
+  /// Add an actor to the list of aliases for this feed
+  ///
+  pub async fn add_alias(&mut self, url: &str, pool: &PgPool) -> Result<(), sqlx::Error> {
+    let mut aliases = self.aliases();
+    if !aliases.iter().any(|alias| alias == url) {
+      aliases.push(url.to_string());
+    }
 
-  pub async fn properties(&self, pool: &PgPool) -> Result<Vec<AnyBase>, DeliveryError> {
-    let mut results: Vec<AnyBase> = Vec::new();
-    let instance_domain = env::var("DOMAIN_NAME").expect("DOMAIN_NAME is not set");
-    let user = User::find(self.user_id, pool).await;
+    self.also_known_as = Some(aliases.join("\n"));
+    sqlx::query!("UPDATE feeds SET also_known_as = $1 WHERE id = $2", self.also_known_as, self.id)
+      .execute(pool)
+      .await?;
 
-    if user.is_err() {
-      return Ok(results);
+    Ok(())
+  }
+
+  pub async fn mark_moved(&mut self, target: &str, pool: &PgPool) -> Result<(), sqlx::Error> {
+    self.moved_to = Some(target.to_string());
+    sqlx::query!("UPDATE feeds SET moved_to = $1 WHERE id = $2", self.moved_to, self.id)
+      .execute(pool)
+      .await?;
+
+    Ok(())
+  }
+
+  ///
+  /// Generate a new keypair for this feed. The old public key is kept
+  /// around for a grace period, and followers get an Update with the new key
+  ///
+  pub async fn rotate_keys(&mut self, pool: &PgPool, queue: &mut dyn AsyncQueueable) -> Result<(), DeliveryError> {
+    let (private_key, public_key) = generate_key();
+    let now = Utc::now();
+
+    self.previous_public_key = Some(self.public_key.clone());
+    self.private_key = private_key;
+    self.public_key = public_key;
+    self.key_rotated_at = Some(now);
+
+    sqlx::query!("UPDATE feeds
+      SET private_key = $1,
+        public_key = $2,
+        previous_public_key = $3,
+        key_rotated_at = $4,
+        updated_at = $5
+      WHERE id = $6",
+      self.private_key, self.public_key, self.previous_public_key, self.key_rotated_at, now, self.id)
+      .execute(pool)
+      .await?;
+
+    self.broadcast_profile_update(pool, queue).await
+  }
+
+  ///
+  /// Public keys which are valid for this feed -- the current key, plus
+  /// the previous one if we're still in the grace period after a rotation
+  ///
+  pub fn verification_keys(&self) -> Vec<String> {
+    let mut keys = vec![self.public_key.clone()];
+
+    if let Some(previous) = &self.previous_public_key {
+      if self.in_key_grace_period() {
+        keys.push(previous.clone());
+      }
     }
 
-    let user = user.unwrap();
-    let full_username = user.full_username();
+    keys
+  }
 
-    if self.site_url.is_some() {
-      let guts = self.site_url.clone().unwrap();
-      let value = to_profile_value_link(guts.clone(), guts);
-      results.push(PropertyValue::new("Homepage", &value).into_any_base().unwrap());
+  ///
+  /// Check if the keys were rotated recently enough that the previous
+  /// key is still good
+  ///
+  pub fn in_key_grace_period(&self) -> bool {
+    match self.key_rotated_at {
+      Some(rotated_at) => rotated_at + Duration::seconds(key_rotation_grace_period()) > Utc::now(),
+      None => false
     }
+  }
 
-    let actor_url = user.actor_url;
-    if full_username.is_some() && actor_url.is_some() {
-      let value = to_profile_value_link(actor_url.unwrap(), full_username.unwrap());
-      results.push(PropertyValue::new("Generated by", &value).into_any_base().unwrap());
+  ///
+  /// Check a signature against our verification keys
+  ///
+  pub fn verify_signature(&self, payload: &str, signature: &[u8]) -> Result<bool, DeliveryError> {
+    for key in self.verification_keys() {
+      let key = PKey::from_rsa(Rsa::public_key_from_pem(key.as_bytes())?)?;
+      let mut verifier = sign::Verifier::new(MessageDigest::sha256(), &key)?;
+      verifier.update(payload.as_bytes())?;
+      if verifier.verify(signature).unwrap_or(false) {
+        return Ok(true)
+      }
     }
 
+    Ok(false)
+  }
 
-    let value = to_profile_value_link(format!("https://{instance_domain:}/"), instance_domain);
-    results.push(PropertyValue::new("Powered by", &value).into_any_base().unwrap());
+  ///
+  /// Figure out the actor URL for a migration target. This can be the
+  /// name or URL of another feed the same user owns, or the URL of a
+  /// remote actor which lists this feed in alsoKnownAs
+  ///
+  pub async fn resolve_migration_target(&self, user: &User, target: &str, pool: &PgPool) -> Result<String, DeliveryError> {
+    let instance_domain = env::var("DOMAIN_NAME").expect("DOMAIN_NAME is not set");
+    let target = target.trim();
+    let local_prefix = format!("https://{instance_domain}/feed/");
 
-    Ok(results)
+    let remote_url = match Url::parse(target) {
+      Ok(url) if !target.starts_with(&local_prefix) => Some(url),
+      _ => None
+    };
+
+    if let Some(remote_url) = remote_url {
+      let data = crate::services::mailer::admin_fetch_object(remote_url.as_str(), pool).await?;
+      let data: serde_json::Value = match data {
+        Some(data) => serde_json::from_str(&data)?,
+        None => return Err(DeliveryError::Error(String::from("Target not found")))
+      };
+
+      let our_url = self.ap_url();
+      let listed = match &data["alsoKnownAs"] {
+        serde_json::Value::String(alias) => *alias == our_url,
+        serde_json::Value::Array(aliases) => aliases.iter().any(|alias| alias.as_str() == Some(&our_url)),
+        _ => false
+      };
+
+      if !listed {
+        return Err(DeliveryError::Error(format!("{target} does not list {our_url} in alsoKnownAs")))
+      }
+
+      // make sure we got the actor we asked for, and not something
+      // else that happens to list us
+      let mut requested = remote_url.clone();
+      requested.set_fragment(None);
+
+      return match data["id"].as_str().map(Url::parse) {
+        Some(Ok(id)) if id == requested => Ok(id.to_string()),
+        Some(_) => Err(DeliveryError::Error(format!("{target} doesn't match the id of the actor it points to"))),
+        None => Err(DeliveryError::Error(String::from("Target has no id")))
+      }
+    }
+
+    // strip down to the name of a local feed
+    let name = target.trim_start_matches(&local_prefix).trim_start_matches('@');
+    let name = name.strip_suffix(&format!("@{instance_domain}")).unwrap_or(name);
+
+    if name == self.name {
+      return Err(DeliveryError::Error(String::from("Can't migrate a feed to itself")))
+    }
+
+    match Feed::find_by_user_and_name(user, &name.to_string(), OWNER, pool).await? {
+      Some(mut dest) => {
+        dest.add_alias(&self.ap_url(), pool).await?;
+        Ok(dest.ap_url())
+      },
+      None => Err(DeliveryError::Error(format!("{name} not found")))
+    }
   }
-  
 
   ///
-  /// Generate valid ActivityPub data for this feed
+  /// Generate a Move activity pointing our followers at the target actor
   ///
-  pub async fn to_activity_pub(&self, pool: &PgPool) -> Result<String, DeliveryError> {
-    let instance_domain = env::var("DOMAIN_NAME").expect("DOMAIN_NAME is not set");
+  pub fn to_move_activity(&self, target: &str) -> Result<ApObject<MoveWithTarget>, DeliveryError> {
+    let feed_url = self.ap_url();
+    let ts = OffsetDateTime::now_utc();
+    let target = IriString::try_from(target)?;
+
+    let mut action: ApObject<MoveWithTarget> = ApObject::new(
+      MoveWithTarget::new(
+        iri!(feed_url),
+        iri!(feed_url)
+      )
+    );
+
+    action
+      .set_context(context())
+      .set_id(iri!(format!("{feed_url}#moves/{}", ts.unix_timestamp())))
+      .set_target(target)
+      .set_to(iri!(self.followers_url()))
+      .set_published(ts);
+
+    Ok(action)
+  }
+
+  ///
+  /// Migrate this feed to another actor. We mark the feed with movedTo and
+  /// send a Move to all our followers, who should then follow the target
+  ///
+  pub async fn migrate_to(&mut self, user: &User, target: &str, pool: &PgPool, queue: &mut dyn AsyncQueueable) -> Result<String, DeliveryError> {
+    let target = self.resolve_migration_target(user, target, pool).await?;
+
+    // build the activity first, so a bad target doesn't leave us marked as moved
+    let message = self.to_move_activity(&target)?;
+    let msg = serde_json::to_string(&message).unwrap();
+
+    self.mark_moved(&target, pool).await?;
+    self.deliver_to_followers(&msg, pool, queue).await?;
+
+    Ok(target)
+  }
+
+  ///
+  /// Check if ActivityPub requests for this feed need a valid signature.
+  /// This can be set per-feed or for the whole instance. The admin feed is
+  /// always public, since other servers need its key to verify our requests
+  ///
+  pub async fn requires_signed_fetch(&self, pool: &PgPool) -> Result<bool, sqlx::Error> {
+    if self.is_admin() {
+      return Ok(false)
+    }
+
+    if self.secure_mode {
+      return Ok(true)
+    }
+
+    let secure_mode = Setting::value_or(&"secure_mode".to_string(), &"false".to_string(), pool).await?;
+    Ok(secure_mode == "true")
+  }
+
+  ///
+  /// Figure out how much of the followers collection we should share.
+  /// If the feed doesn't have a setting, use the instance default
+  ///
+  pub async fn followers_visibility(&self, pool: &PgPool) -> Result<String, sqlx::Error> {
+    match &self.followers_visibility {
+      Some(visibility) if !visibility.is_empty() => Ok(visibility.to_string()),
+      _ => Setting::value_or(&"followers_visibility".to_string(), &FOLLOWERS_PUBLIC.to_string(), pool).await
+    }
+  }
+
+  ///
+  /// Return URL to use in ActivityPub output for this feed
+  ///
+  pub fn ap_url(&self) -> String {
+    path_to_url(&uri!(render_feed(&self.name)))
+  }
+
+  ///
+  /// Return URL to use in HTML output for this feed
+  ///
+  pub fn permalink_url(&self) -> String {
+    path_to_url(&uri!(show_feed(&self.name, None::<i32>)))
+  }
+
+  ///
+  /// URL for the followers route
+  ///
+  pub fn followers_url(&self) -> String {
+    path_to_url(&uri!(render_feed_followers(&self.name, None::<bool>, None::<i32>, None::<i32>)))
+  }
+
+  ///
+  /// return the email-style address for this feed
+  ///
+  pub fn address(&self) -> String {
+    let instance_domain = env::var("DOMAIN_NAME").expect("DOMAIN_NAME is not set");
+    format!("@{}@{}", self.name, instance_domain)
+  }
+
+  pub fn display_name(&self) -> &String {
+    if self.title.is_some() {
+      self.title.as_ref().unwrap()
+    } else {
+      &self.name
+    }
+  }
+
+
+  pub async fn properties(&self, pool: &PgPool) -> Result<Vec<AnyBase>, DeliveryError> {
+    let mut results: Vec<AnyBase> = Vec::new();
+    let instance_domain = env::var("DOMAIN_NAME").expect("DOMAIN_NAME is not set");
+    let user = User::find(self.user_id, pool).await;
+
+    if user.is_err() {
+      return Ok(results);
+    }
+
+    let user = user.unwrap();
+    let full_username = user.full_username();
+
+    if self.site_url.is_some() {
+      let guts = self.site_url.clone().unwrap();
+      let value = to_profile_value_link(guts.clone(), guts);
+      results.push(PropertyValue::new("Homepage", &value).into_any_base().unwrap());
+    }
+
+    let actor_url = user.actor_url;
+    if full_username.is_some() && actor_url.is_some() {
+      let value = to_profile_value_link(actor_url.unwrap(), full_username.unwrap());
+      results.push(PropertyValue::new("Generated by", &value).into_any_base().unwrap());
+    }
+
+
+    let value = to_profile_value_link(format!("https://{instance_domain:}/"), instance_domain);
+    results.push(PropertyValue::new("Powered by", &value).into_any_base().unwrap());
+
+    Ok(results)
+  }
+  
+
+  ///
+  /// Generate valid ActivityPub data for this feed
+  ///
+  pub async fn to_activity_pub(&self, pool: &PgPool) -> Result<String, DeliveryError> {
+    let instance_domain = env::var("DOMAIN_NAME").expect("DOMAIN_NAME is not set");
     let feed_url = self.ap_url();
     let mut svc = Ext1::new(
       ApActor::new(
@@ -787,11 +1457,12 @@ impl Feed {
       .set_context(context())
       .add_context(security())
       .add_context(schema_property_context()?)
+      .add_context(actor_context()?)
       .set_id(iri!(path_to_url(&uri!(render_feed(&self.name)))))
       .set_name(self.display_name().clone())
       .set_preferred_username(self.name.clone())
       .set_inbox(iri!(path_to_url(&uri!(user_inbox(&self.name)))))
-      .set_outbox(iri!(path_to_url(&uri!(render_feed_outbox(&self.name, None::<i32>)))))
+      .set_outbox(iri!(self.outbox_url()))
       .set_followers(iri!(self.followers_url()))
       .set_many_attachments(self.properties(pool).await?);
     
@@ -836,8 +1507,52 @@ impl Feed {
     // manipulate it if needed but i had trouble getting that to work because of
     // assorted traits throwing issues when calling into_any_base()
 
+    // alsoKnownAs/movedTo/manuallyApprovesFollowers aren't supported by
+    // activitystreams, so we'll tack them onto the JSON
+    let mut output = serde_json::to_value(&svc)?;
+    output["manuallyApprovesFollowers"] = serde_json::json!(self.manually_approves_followers);
+
+    let aliases = self.aliases();
+    if !aliases.is_empty() {
+      output["alsoKnownAs"] = serde_json::json!(aliases);
+    }
+
+    if let Some(moved_to) = &self.moved_to {
+      output["movedTo"] = serde_json::json!(moved_to);
+    }
+
     // generate JSON and return
-    Ok(serde_json::to_string(&svc).unwrap())   
+    Ok(serde_json::to_string(&output).unwrap())   
+  }
+
+  ///
+  /// Generate the bare minimum actor data for this feed -- enough for
+  /// another server to verify our signatures, but nothing else. This
+  /// is what unsigned requests get when the feed is in secure mode
+  ///
+  pub fn to_minimal_activity_pub(&self) -> Result<String, DeliveryError> {
+    let feed_url = self.ap_url();
+    let mut svc = Ext1::new(
+      ApActor::new(
+        iri!(path_to_url(&uri!(user_inbox(&self.name)))),
+        Service::new(),
+      ),
+      PublicKey {
+        public_key: PublicKeyInner {
+          id: iri!(format!("{feed_url}#main-key")),
+          owner: iri!(feed_url),
+          public_key_pem: self.public_key.to_owned(),
+        },
+      },
+    );
+
+    svc
+      .set_context(context())
+      .add_context(security())
+      .set_id(iri!(feed_url))
+      .set_preferred_username(self.name.clone());
+
+    Ok(serde_json::to_string(&svc)?)
   }
 
   ///
@@ -861,42 +1576,153 @@ impl Feed {
   ///
   /// handle an actor following the feed by adding them to the db and sending an Accept message back
   ///
-  pub async fn follow(&self, pool: &PgPool, actor: &str, activity: &AcceptedActivity) -> Result<(), DeliveryError> {
+  pub async fn follow(&self, pool: &PgPool, queue: &mut dyn AsyncQueueable, actor: &str, activity: &AcceptedActivity) -> Result<(), DeliveryError> {
+    // reconstruct original follow activity
+    let (_actor, _object, original_follow) = activity.clone().into_parts();
+    let follow_id: &IriString = original_follow.id_unchecked().unwrap();
+
+    // the owner has blocked this actor or their domain
+    if FeedBlock::is_blocked(self, actor, pool).await? {
+      log::info!("refusing follow from blocked actor {actor}");
+      return self.queue_follow_response(queue, actor, Some(follow_id.as_str()), false).await
+    }
+
+    // if the owner wants to approve followers, hang onto the request
+    // and we'll send an Accept or Reject later
+    if self.manually_approves_followers {
+      FollowRequest::create(self, actor, follow_id.as_str(), pool).await?;
+      return Ok(())
+    }
+
     // store follower in the db
     self.add_follower(pool, actor).await?;
 
-    // now let's deliver an Accept message
+    // now let's queue up an Accept message
+    self.queue_follow_response(queue, actor, Some(follow_id.as_str()), true).await
+  }
+
+  ///
+  /// Queue up an Accept or Reject for a Follow from the given actor, so
+  /// it gets retried if their server is having trouble. We don't keep
+  /// the id of the Follow for existing followers, so it's optional
+  ///
+  pub async fn queue_follow_response(&self, queue: &mut dyn AsyncQueueable, actor: &str, follow_id: Option<&str>, accepted: bool) -> Result<(), DeliveryError> {
+    let inbox = format!("{actor}/inbox");
+    let message = self.follow_response(actor, follow_id, accepted)?;
 
-    // reconstruct original follow activity
-    let (_actor, _object, original_follow) = activity.clone().into_parts();
+    self.queue_delivery(&inbox, &message, queue).await
+  }
 
+  ///
+  /// Generate the Accept or Reject for a Follow from the given actor
+  ///
+  fn follow_response(&self, actor: &str, follow_id: Option<&str>, accepted: bool) -> Result<serde_json::Value, DeliveryError> {
     let mut follow = Follow::new(actor, self.ap_url());
+    if let Some(follow_id) = follow_id {
+      follow.set_id(iri!(follow_id));
+    }
 
-    let inbox = format!("{actor}/inbox");
-    let follow_id: &IriString = original_follow.id_unchecked().unwrap();
-    follow.set_id(follow_id.clone());
+    let message = if accepted {
+      let mut accept = Accept::new(self.ap_url(), follow.into_any_base()?);
+      if let Some(follow_id) = follow_id {
+        accept.set_id(iri!(follow_id));
+      }
+      accept.set_context(context());
+
+      serde_json::to_value(accept)?
+    } else {
+      let ts = OffsetDateTime::now_utc();
+      let mut reject = Reject::new(self.ap_url(), follow.into_any_base()?);
+      reject.set_id(iri!(format!("{}#rejects/{}", self.ap_url(), ts.unix_timestamp_nanos())));
+      reject.set_context(context());
+
+      serde_json::to_value(reject)?
+    };
+
+    Ok(message)
+  }
+
+  ///
+  /// Queue up delivery of a message to a single inbox
+  ///
+  pub async fn queue_delivery<T: serde::Serialize>(&self, inbox: &str, message: &T, queue: &mut dyn AsyncQueueable) -> Result<(), DeliveryError> {
+    let task = DeliverMessage::new(self.id, inbox.to_string(), serde_json::to_string(message)?);
+    let result = queue
+      .insert_task(&task as &dyn AsyncRunnable)
+      .await;
+
+    match result {
+      Ok(_result) => Ok(()),
+      Err(why) => Err(DeliveryError::Error(why.to_string()))
+    }
+  }
+
+  ///
+  /// Approve a pending follow request. The actor becomes a follower and
+  /// we send them an Accept
+  ///
+  pub async fn approve_follow_request(&self, pool: &PgPool, queue: &mut dyn AsyncQueueable, request: &FollowRequest) -> Result<(), DeliveryError> {
+    self.add_follower(pool, &request.actor).await?;
+    request.delete(pool).await?;
+
+    self.queue_follow_response(queue, &request.actor, Some(&request.activity_id), true).await
+  }
+
+  ///
+  /// Reject a pending follow request
+  ///
+  pub async fn reject_follow_request(&self, pool: &PgPool, queue: &mut dyn AsyncQueueable, request: &FollowRequest) -> Result<(), DeliveryError> {
+    request.delete(pool).await?;
+
+    self.queue_follow_response(queue, &request.actor, Some(&request.activity_id), false).await
+  }
+
+  ///
+  /// Remove a follower, and send them a Reject so their server knows
+  /// they aren't following us anymore
+  ///
+  pub async fn remove_follower(&self, pool: &PgPool, queue: &mut dyn AsyncQueueable, follower: &Follower) -> Result<(), DeliveryError> {
+    follower.delete(pool).await?;
 
-    // generate accept message for follow activity
-    let mut accept = Accept::new(self.ap_url(), follow.into_any_base()?);
-    accept.set_id(follow_id.clone());
-    accept.set_context(context());
+    self.queue_follow_response(queue, &follower.actor, None, false).await
+  }
 
-    // deliver to the user
-    let result = deliver_to_inbox(&Url::parse(&inbox)?, &self.ap_url(), &self.private_key, &accept).await;
+  ///
+  /// Block an actor or domain from following this feed. Any existing
+  /// followers or pending requests which match are removed
+  ///
+  pub async fn block(&self, pool: &PgPool, queue: &mut dyn AsyncQueueable, kind: &str, target: &str) -> Result<FeedBlock, DeliveryError> {
+    let block = FeedBlock::create(self, kind, target, pool).await?;
+
+    for follower in self.followers_list(pool).await? {
+      if block.matches(&follower.actor) {
+        let result = self.remove_follower(pool, queue, &follower).await;
+        if let Err(why) = result {
+          log::info!("couldn't notify {} of removal: {why:?}", follower.actor);
+        }
+      }
+    }
 
-    if result.is_err() {
-      Actor::log_error(&inbox, pool).await?;
+    for request in FollowRequest::for_feed(self, pool).await? {
+      if block.matches(&request.actor) {
+        request.delete(pool).await?;
+      }
     }
 
-    result
+    for subscriber in DmSubscriber::for_feed(self, pool).await? {
+      if block.matches(&subscriber.actor) {
+        DmSubscriber::delete(self, &subscriber.actor, pool).await?;
+      }
+    }
 
+    Ok(block)
   }
 
   ///
   /// handle an incoming message. we mostly ignore these except a user can message the admin
   /// feed to login to the site to add/manage feeds
   ///
-  pub async fn incoming_message(&self, pool: &PgPool, actor_url: &str, activity: &AcceptedActivity) -> Result<(), DeliveryError> {
+  pub async fn incoming_message(&self, pool: &PgPool, queue: &mut dyn AsyncQueueable, actor_url: &str, activity: &AcceptedActivity) -> Result<(), DeliveryError> {
 
     // pull the Note out of the Activity
     let obj = activity.object();
@@ -917,9 +1743,27 @@ impl Feed {
     }
     let content = content.unwrap();
     let message = content.as_single_xsd_string();
+    if message.is_none() {
+      return Ok(())
+    }
+
+    // anyone can subscribe to DMs by messaging the feed, or the admin feed. replies
+    // that other people can see don't count
+    let command = if is_direct(&note) { subscription_command(message.unwrap()) } else { None };
+    if let Some((subscribe, target)) = command {
+      if !self.is_admin() {
+        return self.handle_subscription_command(pool, queue, actor_url, self, subscribe).await
+      }
+
+      if let Some(target) = target {
+        if let Some(feed) = Feed::find_by_name_case_insensitive(&target, pool).await? {
+          return self.handle_subscription_command(pool, queue, actor_url, &feed, subscribe).await
+        }
+      }
+    }
 
     // ignore messages that aren't to admin feed
-    if ! self.is_admin() || message.is_none() {
+    if ! self.is_admin() {
       return Ok(())
     }
 
@@ -946,20 +1790,9 @@ impl Feed {
         let message = self.generate_login_message(Some(activity), &dest_actor, pool).await?;
         let msg = serde_json::to_string(&message).unwrap();
         log::debug!("{msg}");
-    
-        let my_url = self.ap_url();
 
         // send the message!
-        let result = deliver_to_inbox(&Url::parse(&dest_actor.inbox_url)?, &my_url, &self.private_key, &message).await;
-    
-        if result.is_err() {
-          Actor::log_error(&dest_actor.inbox_url, pool).await?;
-        }
-    
-        match result {
-          Ok(result) => log::debug!("sent! {result:?}"),
-          Err(why) => log::debug!("failure! {why:?}")
-        }    
+        self.queue_delivery(&dest_actor.inbox_url, &message, queue).await?;
       },
       Err(why) => {
         log::debug!("couldnt find actor: {why:?}");
@@ -969,6 +1802,80 @@ impl Feed {
     Ok(())
   }
 
+  ///
+  /// Subscribe or unsubscribe the actor from DMs of the given feed, and
+  /// let them know it worked
+  ///
+  pub async fn handle_subscription_command(&self, pool: &PgPool, queue: &mut dyn AsyncQueueable, actor_url: &str, feed: &Feed, subscribe: bool) -> Result<(), DeliveryError> {
+    if subscribe {
+      if feed.is_admin() || FeedBlock::is_blocked(feed, actor_url, pool).await? {
+        return Ok(())
+      }
+
+      // a DM subscription would skip the owner's approval, so they'll
+      // need to follow the feed instead
+      if !feed.manually_approves_followers {
+        DmSubscriber::create(feed, actor_url, pool).await?;
+      }
+    } else {
+      DmSubscriber::delete(feed, actor_url, pool).await?;
+    }
+
+    let dest_actor = Actor::find_or_fetch(actor_url, pool).await?;
+    if let Some(dest_actor) = dest_actor {
+      let message = self.subscription_status_message(&dest_actor, feed, subscribe).await?;
+      self.queue_delivery(&dest_actor.inbox_url, &message, queue).await?;
+    }
+
+    Ok(())
+  }
+
+  ///
+  /// generate a DM confirming a change to someone's subscription to a feed,
+  /// or explaining that the feed needs them to follow it instead
+  ///
+  pub async fn subscription_status_message(&self, actor: &Actor, feed: &Feed, subscribed: bool) -> Result<ApObject<Create>, DeliveryError> {
+    let mut reply = Note::new();
+    let my_url = self.ap_url();
+
+    let random_id: String = thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(30)
+        .map(char::from)
+        .collect();
+
+    let mut mention = Mention::new();
+    mention
+      .set_href(iri!(&actor.url))
+      .set_name(actor.full_username());
+
+    let mut template_context = Context::new();
+    template_context.insert("link", &feed.permalink_url());
+    template_context.insert("address", &feed.address());
+    template_context.insert("subscribed", &subscribed);
+    template_context.insert("needs_approval", &(subscribed && feed.manually_approves_followers));
+
+    let body = render("email/send-subscription-status", &template_context).unwrap();
+
+    reply
+      .set_attributed_to(iri!(my_url))
+      .set_content(body)
+      .set_id(iri!(format!("{my_url}/{random_id}")))
+      .set_to(iri!(&actor.url))
+      .add_tag(mention.into_any_base()?);
+
+    let mut action: ApObject<Create> = ApObject::new(
+      Create::new(
+        iri!(my_url),
+        reply.into_any_base()?
+      )
+    );
+
+    action.set_context(context());
+
+    Ok(action)
+  }
+
   ///
   /// generate a login message to send to the user
   ///
@@ -1004,7 +1911,9 @@ impl Feed {
     // update with actor information
     user.apply_actor(dest_actor, pool).await.unwrap();
 
-    let auth_url = path_to_url(&uri!(attempt_login(&user.login_token)));
+    // login links expire, so hand out a fresh one
+    let login_token = user.reset_login_token(pool).await?;
+    let auth_url = path_to_url(&uri!(attempt_login(&login_token)));
 
     let mut mention = Mention::new();
     mention
@@ -1051,37 +1960,309 @@ impl Feed {
     Ok(action) 
   }
 
-  
   ///
-  /// handle unfollow activity
+  /// Add the fediverse account with the given handle or actor URL as a
+  /// member of this feed, and send them a login link as a DM from the
+  /// admin account
   ///
-  pub async fn unfollow(&self, pool: &PgPool, actor: &str) -> Result<(), DeliveryError>  {
-    sqlx::query!("DELETE FROM followers WHERE feed_id = $1 AND actor = $2",
+  pub async fn invite_member(&self, handle: &str, role: &str, pool: &PgPool, queue: &mut dyn AsyncQueueable) -> Result<FeedMember, DeliveryError> {
+    let handle = handle.trim();
+    let actor_url = match Url::parse(handle) {
+      Ok(url) => Some(url.to_string()),
+      Err(_) => resolve_handle(handle).await?
+    };
+
+    let dest_actor = match actor_url {
+      Some(actor_url) => Actor::find_or_fetch(&actor_url, pool).await?,
+      None => None
+    };
+
+    let dest_actor = match dest_actor {
+      Some(dest_actor) => dest_actor,
+      None => return Err(DeliveryError::Error(format!("{handle} not found")))
+    };
+
+    let admin_feed = match Feed::for_admin(pool).await? {
+      Some(admin_feed) => admin_feed,
+      None => return Err(DeliveryError::Error(String::from("No admin feed to send the invite from")))
+    };
+
+    let user = User::find_or_create_by_actor_url(&dest_actor.url, pool).await?;
+    user.apply_actor(&dest_actor, pool).await?;
+
+    // inviting an existing member changes their role, but the feed
+    // can't be left without an owner
+    let existing_role = FeedMember::role_for(self, &user, pool).await?;
+    if existing_role.as_deref() == Some(OWNER) && role != OWNER && FeedMember::owner_count(self, pool).await? <= 1 {
+      return Err(DeliveryError::Error(LAST_OWNER_ERROR.to_string()))
+    }
+
+    let member = FeedMember::add(self, &user, role, pool).await?;
+
+    let message = admin_feed.generate_invite_message(self, role, &user, &dest_actor, existing_role.is_none(), pool).await?;
+    admin_feed.queue_delivery(&dest_actor.inbox_url, &message, queue).await?;
+
+    Ok(member)
+  }
+
+  ///
+  /// generate a DM letting someone know they've been added to a feed.
+  /// New members get a login link so they can get to it, while existing
+  /// members just hear about their new role, so we don't cancel any
+  /// login link they're already using
+  ///
+  pub async fn generate_invite_message(&self, feed: &Feed, role: &str, user: &User, dest_actor: &Actor, new_member: bool, pool: &PgPool) -> Result<ApObject<Create>, DeliveryError> {
+    let mut reply: SensitiveNote = SensitiveNote::new();
+    let my_url = self.ap_url();
+
+    let random_id: String = thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(30)
+        .map(char::from)
+        .collect();
+
+    let mut mention = Mention::new();
+    mention
+      .set_href(iri!(dest_actor.url.to_string()))
+      .set_name(dest_actor.full_username());
+
+    let mut template_context = Context::new();
+    if new_member {
+      let login_token = user.reset_login_token(pool).await?;
+      template_context.insert("link", &path_to_url(&uri!(attempt_login(&login_token))));
+    }
+    template_context.insert("feed_link", &feed.permalink_url());
+    template_context.insert("address", &feed.address());
+    template_context.insert("role", role);
+
+    let body = render("email/send-feed-invite", &template_context).unwrap();
+    let ts = OffsetDateTime::now_utc();
+
+    reply
+      .set_sensitive(true)
+      .set_attributed_to(iri!(my_url))
+      .set_content(body)
+      .set_url(iri!(my_url))
+      .set_id(iri!(format!("{my_url}/{random_id}")))
+      .set_to(iri!(dest_actor.url))
+      .set_tag(mention.into_any_base()?)
+      .set_published(ts);
+
+    let mut action: ApObject<Create> = ApObject::new(
+      Create::new(
+        iri!(my_url),
+        reply.into_any_base()?
+      )
+    );
+
+    action
+      .set_context(context())
+      .add_context(security())
+      .add_context("as:sensitive".to_string())
+      .set_id(iri!(format!("{my_url}/{random_id}")))
+      .set_to(iri!(dest_actor.url))
+      .set_published(ts);
+
+    Ok(action)
+  }
+
+  ///
+  /// handle unfollow activity
+  ///
+  pub async fn unfollow(&self, pool: &PgPool, actor: &str) -> Result<(), DeliveryError>  {
+    sqlx::query!("DELETE FROM followers WHERE feed_id = $1 AND actor = $2",
                  self.id, actor)
       .execute(pool)
       .await?;
+
+    // they might still be waiting for approval
+    FollowRequest::delete_for_actor(self, actor, pool).await?;
     
     Ok(())
   }
 
+  ///
+  /// handle an Undo activity. The object of the Undo is the activity
+  /// being reverted, so we check what that was before doing anything
+  ///
+  pub async fn handle_undo(&self, pool: &PgPool, actor: &str, activity: &AcceptedActivity) -> Result<(), DeliveryError> {
+    let original = embedded_activity(activity);
+    if original.is_none() {
+      log::debug!("Undo from {actor} without an embedded activity, ignoring");
+      return Ok(())
+    }
+    let original = original.unwrap();
+
+    // actors can only undo their own activities
+    let original_actor = original.actor_unchecked().as_single_id();
+    if original_actor.is_some() && original_actor.unwrap().as_str() != actor {
+      log::debug!("Undo from {actor} for someone else's activity, ignoring");
+      return Ok(())
+    }
+
+    let original_id = original.id_unchecked().map(|id| id.to_string());
+    let object_id = original.object_unchecked().as_single_id().map(|id| id.to_string());
+
+    match original.kind() {
+      Some(AcceptedTypes::Follow) => self.unfollow(pool, actor).await,
+      Some(AcceptedTypes::Like) => Ok(Interaction::undo(self, actor, original_id, object_id, LIKE, pool).await?),
+      Some(AcceptedTypes::Announce) => Ok(Interaction::undo(self, actor, original_id, object_id, ANNOUNCE, pool).await?),
+      _ => Ok(())
+    }
+  }
+
+  ///
+  /// handle a relay's response to the admin feed following it
+  ///
+  pub async fn handle_relay_response(&self, pool: &PgPool, actor: &str, activity: &AcceptedActivity, status: &str) -> Result<(), DeliveryError> {
+    if !self.is_admin() {
+      return Ok(())
+    }
+
+    let follow_id = match embedded_activity(activity) {
+      Some(original) => original.id_unchecked().map(|id| id.to_string()),
+      None => activity.object_unchecked().as_single_id().map(|id| id.to_string())
+    };
+
+    if let Some(mut relay) = Relay::find_for_response(actor, follow_id, pool).await? {
+      log::info!("relay {} is now {status}", relay.url);
+      relay.mark_status(status, pool).await?;
+    }
+
+    Ok(())
+  }
+
+  ///
+  /// handle a Delete activity. If the actor is deleting themselves, we'll
+  /// forget about them entirely. Otherwise they're deleting a post, which
+  /// might be a reply we've stored
+  ///
+  pub async fn handle_delete(&self, pool: &PgPool, actor: &str, activity: &AcceptedActivity) -> Result<(), DeliveryError> {
+    let object_id = activity.object_unchecked().as_single_id();
+    if object_id.is_none() {
+      return Ok(())
+    }
+    let object_id = object_id.unwrap().to_string();
+
+    if object_id == actor {
+      Actor::purge(actor, pool).await?;
+    } else {
+      Interaction::delete_by_object(actor, &object_id, pool).await?;
+    }
+
+    Ok(())
+  }
+
+  ///
+  /// an actor has updated their profile, so refresh our copy of it. We
+  /// fetch it from their server rather than trusting the embedded object
+  ///
+  pub async fn handle_update(&self, pool: &PgPool, actor: &str, activity: &AcceptedActivity) -> Result<(), DeliveryError> {
+    let object_id = activity.object_unchecked().as_single_id();
+    if object_id.is_none() || object_id.unwrap().as_str() != actor {
+      return Ok(())
+    }
+
+    // no need to cache actors we haven't seen before
+    if Actor::find(actor, pool).await?.is_some() {
+      Actor::refetch(actor, pool).await?;
+    }
+
+    Ok(())
+  }
+
+  ///
+  /// record a Like or Announce of one of our items
+  ///
+  pub async fn record_interaction(&self, pool: &PgPool, actor: &str, activity: &AcceptedActivity, kind: &str) -> Result<(), DeliveryError> {
+    let object_id = activity.object_unchecked().as_single_id();
+    if object_id.is_none() {
+      return Ok(())
+    }
+    let object_id = object_id.unwrap().to_string();
+
+    // ignore interactions with anything that isn't one of our items
+    let item = Item::find_by_feed_and_ap_url(self, &object_id, pool).await?;
+    if item.is_none() {
+      return Ok(())
+    }
+
+    let activity_id = activity.id_unchecked().map(|id| id.to_string());
+    Interaction::create(self, item.as_ref(), actor, activity_id, &object_id, kind, None, pool).await?;
+
+    Ok(())
+  }
+
+  ///
+  /// if an incoming Note is a reply to one of our items, hang onto it
+  ///
+  pub async fn record_reply(&self, pool: &PgPool, actor: &str, activity: &AcceptedActivity) -> Result<(), DeliveryError> {
+    let obj = activity.object_unchecked().as_one();
+    if obj.is_none() {
+      return Ok(())
+    }
+
+    let note: Option<Note> = obj.unwrap().clone().extend().unwrap_or(None);
+    if note.is_none() {
+      return Ok(())
+    }
+    let note = note.unwrap();
+
+    let in_reply_to = note.in_reply_to().and_then(|reply| reply.as_single_id());
+    let note_id = note.id_unchecked();
+    if in_reply_to.is_none() || note_id.is_none() {
+      return Ok(())
+    }
+
+    let item = Item::find_by_feed_and_ap_url(self, in_reply_to.unwrap().as_str(), pool).await?;
+    if item.is_none() {
+      return Ok(())
+    }
+
+    let content = note
+      .content()
+      .and_then(|content| content.as_single_xsd_string().map(|content| sanitize_str(&DEFAULT, content).unwrap().trim().to_string()));
+
+    let activity_id = activity.id_unchecked().map(|id| id.to_string());
+    Interaction::create(self, item.as_ref(), actor, activity_id, note_id.unwrap().as_str(), REPLY, content, pool).await?;
+
+    Ok(())
+  }
+
   ///
   /// handle any incoming events
   ///
-  pub async fn handle_activity(&self, pool: &PgPool, activity: &AcceptedActivity)  -> Result<(), DeliveryError> {
+  pub async fn handle_activity(&self, pool: &PgPool, queue: &mut dyn AsyncQueueable, activity: &AcceptedActivity)  -> Result<(), DeliveryError> {
     let s = serde_json::to_string(&activity).unwrap();
     log::debug!("{s:}");
 
     let (actor, _object, act) = activity.clone().into_parts();
 
     let actor_id = actor.as_single_id().unwrap().to_string();
-    
+
+    // silenced domains can still unfollow or clean up after themselves,
+    // but we won't take new follows or interactions from them
+    if let Some(block) = BlockedDomain::for_actor(&actor_id, pool).await? {
+      if !matches!(act.kind(), Some(AcceptedTypes::Undo) | Some(AcceptedTypes::Delete)) {
+        log::info!("ignoring activity from blocked domain {} ({})", block.name, block.severity);
+        return Ok(())
+      }
+    }
+
     match act.kind() {
-      Some(AcceptedTypes::Follow) => self.follow(pool, &actor_id, activity).await,
-      Some(AcceptedTypes::Undo) => self.unfollow(pool, &actor_id).await,
-      Some(AcceptedTypes::Delete) => self.unfollow(pool, &actor_id).await,
-      Some(AcceptedTypes::Create) => self.incoming_message(pool, &actor_id, activity).await,
-      // we don't need to handle this but if we receive it, just move on
-      Some(AcceptedTypes::Accept) => Ok(()),
+      Some(AcceptedTypes::Follow) => self.follow(pool, queue, &actor_id, activity).await,
+      Some(AcceptedTypes::Undo) => self.handle_undo(pool, &actor_id, activity).await,
+      Some(AcceptedTypes::Delete) => self.handle_delete(pool, &actor_id, activity).await,
+      Some(AcceptedTypes::Update) => self.handle_update(pool, &actor_id, activity).await,
+      Some(AcceptedTypes::Like) => self.record_interaction(pool, &actor_id, activity, LIKE).await,
+      Some(AcceptedTypes::Announce) => self.record_interaction(pool, &actor_id, activity, ANNOUNCE).await,
+      Some(AcceptedTypes::Create) => {
+        self.record_reply(pool, &actor_id, activity).await?;
+        self.incoming_message(pool, queue, &actor_id, activity).await
+      },
+      // the only follows we send are from the admin feed to relays
+      Some(AcceptedTypes::Accept) => self.handle_relay_response(pool, &actor_id, activity, ACCEPTED).await,
+      Some(AcceptedTypes::Reject) => self.handle_relay_response(pool, &actor_id, activity, REJECTED).await,
       None => Ok(()),
 
       // unknown activity type, just ignore quietly
@@ -1169,81 +2350,97 @@ impl Feed {
       .await
   }
   
+  ///
+  /// URL for a page of followers
+  ///
+  pub fn followers_page_url(&self, cursor: PageCursor) -> String {
+    path_to_url(&uri!(render_feed_followers(&self.name, Some(true), cursor.max_id, cursor.min_id)))
+  }
+
   ///
   /// generate AP data to represent follower information
   ///
   pub async fn followers(&self, pool: &PgPool)  -> Result<ApObject<OrderedCollection>, DeliveryError> {
+    let visibility = self.followers_visibility(pool).await?;
     let count = self.follower_count(pool).await?;
-    let total_pages = (count / PER_PAGE) + 1;
 
     let mut collection: ApObject<OrderedCollection> = ApObject::new(OrderedCollection::new());
 
     // The first, next, prev, last, and current properties are used
     // to reference other CollectionPage instances that contain 
     // additional subsets of items from the parent collection. 
-
-    
-    // in theory we can drop the first page of data in here
-    // however, it's not required (mastodon doesn't do it)
-    // and activitystreams might not be wired for it
     collection
       .set_context(context())
-      .set_id(iri!(path_to_url(&uri!(render_feed(&self.name)))))
-      .set_summary("A list of followers".to_string())
-      .set_total_items(count as u64)
-      .set_first(iri!(path_to_url(&uri!(render_feed_followers(&self.name, Some(1))))))
-      .set_last(iri!(path_to_url(&uri!(render_feed_followers(&self.name, Some(total_pages))))));
+      .set_id(iri!(self.followers_url()))
+      .set_summary("A list of followers".to_string());
+
+    if visibility != FOLLOWERS_HIDDEN {
+      collection.set_total_items(count as u64);
+    }
+
+    if visibility == FOLLOWERS_PUBLIC {
+      let first = self.build_followers_page(&PageCursor::default(), pool).await?;
+      collection
+        .set_first(first.into_any_base()?)
+        .set_last(iri!(self.followers_page_url(PageCursor { max_id: None, min_id: Some(0) })));
+    }
 
     Ok(collection)
   }
 
   ///
-  /// generate actual AP page of followes 
+  /// generate actual AP page of followers
   ///
-  pub async fn followers_paged(&self, page: i32, pool: &PgPool)  -> Result<ApObject<OrderedCollectionPage>, DeliveryError> {
-    let count = self.follower_count(pool).await?;
-    let total_pages:i32 = (count / PER_PAGE) + 1;
+  pub async fn followers_paged(&self, cursor: &PageCursor, pool: &PgPool)  -> Result<ApObject<OrderedCollectionPage>, DeliveryError> {
+    let mut collection = self.build_followers_page(cursor, pool).await?;
+    collection.set_context(context());
+
+    Ok(collection)
+  }
+
+  async fn build_followers_page(&self, cursor: &PageCursor, pool: &PgPool)  -> Result<ApObject<OrderedCollectionPage>, DeliveryError> {
     let mut collection: ApObject<OrderedCollectionPage> = ApObject::new(OrderedCollectionPage::new());
 
     collection
-      .set_context(context())
       .set_summary("A list of followers".to_string())
-      .set_part_of(iri!(path_to_url(&uri!(render_feed(&self.name)))))
-      .set_first(iri!(path_to_url(&uri!(render_feed_followers(&self.name, Some(1))))))
-      .set_last(iri!(path_to_url(&uri!(render_feed_followers(&self.name, Some(total_pages))))))
-      .set_current(iri!(path_to_url(&uri!(render_feed_followers(&self.name, Some(page))))));
-
-    if page > 1 {
-      collection.set_prev(iri!(path_to_url(&uri!(render_feed_followers(&self.name, Some(page - 1))))));
+      .set_part_of(iri!(self.followers_url()));
+
+    // grab an extra follower to see if there's another page
+    let mut followers = Follower::page_for_feed(self, cursor.max_id, cursor.min_id, (PER_PAGE + 1) as i64, pool).await?;
+    let more = followers.len() > PER_PAGE as usize;
+    if more {
+      if cursor.is_newer() {
+        followers.remove(0);
+      } else {
+        followers.pop();
+      }
     }
 
-    if page < total_pages {
-      collection.set_next(iri!(path_to_url(&uri!(render_feed_followers(&self.name, Some(page + 1))))));
-    }
+    let ids: Vec<i32> = followers.iter().map(|follower| follower.id).collect();
+    set_page_links(&mut collection, cursor, &ids, more, |cursor| self.followers_page_url(cursor))?;
 
-    // return empty collection for invalid pages
-    if page == 0 || page > total_pages {
-      return Ok(collection)
-    }
-    
-    let offset:i64 = ((page - 1) * PER_PAGE) as i64;
-    let result = sqlx::query_as!(Follower, "SELECT * FROM followers WHERE feed_id = $1 LIMIT $2 OFFSET $3", self.id, PER_PAGE as i64, offset )
-      .fetch_all(pool)
-      .await;
-  
-    match result {
-      Ok(result) => {
-        let v: Vec<String> = result
-          .into_iter()
-          .map(|follower| follower.actor)
-          .collect();
-        
-        collection.set_many_items(v);
-        
-        Ok(collection)
-      },
-      Err(why) => Err(why.into())
-    }
+    let v: Vec<String> = followers
+      .into_iter()
+      .map(|follower| follower.actor)
+      .collect();
+
+    collection.set_many_ordered_items(v);
+
+    Ok(collection)
+  }
+
+  ///
+  /// URL for the outbox route
+  ///
+  pub fn outbox_url(&self) -> String {
+    path_to_url(&uri!(render_feed_outbox(&self.name, None::<bool>, None::<i32>, None::<i32>)))
+  }
+
+  ///
+  /// URL for a page of the outbox
+  ///
+  pub fn outbox_page_url(&self, cursor: PageCursor) -> String {
+    path_to_url(&uri!(render_feed_outbox(&self.name, Some(true), cursor.max_id, cursor.min_id)))
   }
 
   ///
@@ -1256,25 +2453,23 @@ impl Feed {
       0
     };
 
-    let total_pages = (count / PER_PAGE) + 1;
-
     let mut collection: ApObject<OrderedCollection> = ApObject::new(OrderedCollection::new());
 
     // The first, next, prev, last, and current properties are used
     // to reference other CollectionPage instances that contain 
     // additional subsets of items from the parent collection. 
+    //
+    // the first page is included outright, since that's what remote
+    // servers use to fill in a profile when someone looks it up
+    let first = self.build_outbox_page(&PageCursor::default(), pool).await?;
 
-    
-    // in theory we can drop the first page of data in here
-    // however, it's not required (mastodon doesn't do it)
-    // and activitystreams might not be wired for it
     collection
       .set_context(context())
-      .set_id(iri!(path_to_url(&uri!(render_feed(&self.name)))))
+      .set_id(iri!(self.outbox_url()))
       .set_summary("A list of outbox items".to_string())
       .set_total_items(count as u64)
-      .set_first(iri!(path_to_url(&uri!(render_feed_outbox(&self.name, Some(1))))))
-      .set_last(iri!(path_to_url(&uri!(render_feed_outbox(&self.name, Some(total_pages))))));
+      .set_first(first.into_any_base()?)
+      .set_last(iri!(self.outbox_page_url(PageCursor { max_id: None, min_id: Some(0) })));
 
     Ok(collection)
   }
@@ -1284,68 +2479,87 @@ impl Feed {
   }
 
   ///
-  /// generate actual AP page of follows 
+  /// generate actual AP page of outbox items
   ///
-  pub async fn outbox_paged(&self, page: i32, pool: &PgPool)  -> Result<ApObject<OrderedCollectionPage>, DeliveryError>{
-    let count = if self.show_statuses_in_outbox() {
-      self.entries_count(pool).await?
-    } else {
-      0
-    };
+  pub async fn outbox_paged(&self, cursor: &PageCursor, pool: &PgPool)  -> Result<ApObject<OrderedCollectionPage>, DeliveryError>{
+    let mut collection = self.build_outbox_page(cursor, pool).await?;
+    collection.set_context(context());
 
-    let total_pages = (count / PER_PAGE) + 1;
+    Ok(collection)
+  }
+
+  async fn build_outbox_page(&self, cursor: &PageCursor, pool: &PgPool)  -> Result<ApObject<OrderedCollectionPage>, DeliveryError>{
     let mut collection: ApObject<OrderedCollectionPage> = ApObject::new(OrderedCollectionPage::new());
 
     collection
-      .set_context(context())
       .set_summary("A list of outbox items".to_string())
-      .set_part_of(iri!(path_to_url(&uri!(render_feed(&self.name)))))
-      .set_first(iri!(path_to_url(&uri!(render_feed_outbox(&self.name, Some(1))))))
-      .set_last(iri!(path_to_url(&uri!(render_feed_outbox(&self.name, Some(total_pages))))))
-      .set_current(iri!(path_to_url(&uri!(render_feed_outbox(&self.name, Some(page))))));
+      .set_part_of(iri!(self.outbox_url()));
 
-    if page > 1 {
-      collection.set_prev(iri!(path_to_url(&uri!(render_feed_outbox(&self.name, Some(page - 1))))));
+    if !self.show_statuses_in_outbox() {
+      collection.set_id(iri!(self.outbox_page_url(*cursor)));
+      collection.set_many_ordered_items(Vec::<AnyBase>::new());
+      return Ok(collection)
     }
 
-    if page < total_pages {
-      collection.set_next(iri!(path_to_url(&uri!(render_feed_outbox(&self.name, Some(page + 1))))));
+    // grab an extra item to see if there's another page
+    let mut items = Item::page_for_feed(self, cursor.max_id, cursor.min_id, (PER_PAGE + 1) as i64, pool).await?;
+    let more = items.len() > PER_PAGE as usize;
+    if more {
+      if cursor.is_newer() {
+        items.remove(0);
+      } else {
+        items.pop();
+      }
     }
 
-    // return empty collection for invalid pages
-    if page == 0 || page > total_pages {
-      return Ok(collection)
+    let ids: Vec<i32> = items.iter().map(|item| item.id).collect();
+    set_page_links(&mut collection, cursor, &ids, more, |cursor| self.outbox_page_url(cursor))?;
+
+    let mut output: Vec<AnyBase> = Vec::new();
+    for item in items {
+      output.push(item.to_activity_pub(self, pool).await?.into_any_base()?);
     }
+    collection.set_many_ordered_items(output);
 
-    if self.show_statuses_in_outbox() {
-      let offset = (page - 1) * PER_PAGE;
-      let result = sqlx::query_as!(Item, "SELECT * FROM items WHERE feed_id = $1 LIMIT $2 OFFSET $3",
-        self.id as i32, PER_PAGE as i32, offset as i32)
-        .fetch_all(pool)
-        .await;
+    Ok(collection)
+  }
+}
 
-      match result {
-        Ok(result) => {
-          for item in result {
-            let output = item.to_activity_pub(self, pool).await.unwrap();
-            collection.add_item(output.into_any_base()?);    
-          }
+///
+/// Set the id, next and prev links for a page of a collection. ids are
+/// the ids of the entries on the page, newest first. more is true if
+/// there are more entries past this page, in the direction the cursor
+/// was heading
+///
+fn set_page_links<F>(collection: &mut ApObject<OrderedCollectionPage>, cursor: &PageCursor, ids: &[i32], more: bool, page_url: F) -> Result<(), DeliveryError>
+where F: Fn(PageCursor) -> String {
+  collection.set_id(iri!(page_url(*cursor)));
+
+  let (newest, oldest) = match (ids.first(), ids.last()) {
+    (Some(newest), Some(oldest)) => (*newest, *oldest),
+    _ => return Ok(())
+  };
+
+  // when paging towards newer entries, the cursor itself is older
+  // than anything on this page, unless we started from the very end
+  let has_older = if cursor.is_newer() { cursor.min_id.unwrap_or(0) > 0 } else { more };
+  let has_newer = if cursor.is_newer() { more } else { cursor.max_id.is_some() };
+
+  if has_older {
+    collection.set_next(iri!(page_url(PageCursor { max_id: Some(oldest), min_id: None })));
+  }
 
-          Ok(collection)
-        },
-        Err(why) => Err(why.into())
-      }
-    } else {
-      Ok(collection)
-    }
+  if has_newer {
+    collection.set_prev(iri!(page_url(PageCursor { max_id: None, min_id: Some(newest) })));
   }
+
+  Ok(())
 }
 
 #[cfg(test)]
 mod test {
   use std::fs;
   use sqlx::postgres::PgPool;
-  use rocket::uri;
   use feed_rs::parser;
   use chrono::Utc;
 
@@ -1355,13 +2569,16 @@ mod test {
   use crate::models::Item;
   use crate::models::Enclosure;
   use crate::models::Actor;
+  use crate::models::Interaction;
+  use crate::models::User;
+  use crate::models::FollowRequest;
+  use crate::models::FeedBlock;
+  use crate::models::BlockedDomain;
+  use crate::models::DmSubscriber;
+  use crate::models::feed_block::{ACTOR, DOMAIN};
+  use crate::models::interaction::{LIKE, ANNOUNCE, REPLY};
 
-  use crate::utils::test_helpers::{fake_user, fake_feed, real_feed, real_user, real_item, real_actor};
-  use crate::utils::path_to_url;
-
-  use crate::routes::feeds::*;
-  use crate::routes::ap::outbox::*;
-
+  use crate::utils::test_helpers::{fake_user, fake_feed, real_feed, real_user, real_item, real_actor, test_queue, queued_deliveries};
 
   #[sqlx::test]
   async fn test_create(pool: PgPool) -> sqlx::Result<()> {
@@ -1418,7 +2635,7 @@ mod test {
 
   #[sqlx::test]
   async fn test_stale(pool: PgPool) -> sqlx::Result<()> {
-    let _feed: Feed = real_feed(&pool).await?;
+    let mut feed: Feed = real_feed(&pool).await?;
     let feed2: Feed = real_feed(&pool).await?;
     
 
@@ -1435,6 +2652,11 @@ mod test {
     let stale3 = Feed::stale(&pool, 100, 100).await?;
     assert_eq!(stale3.len(), 2);
 
+    // feeds that have moved aren't refreshed anymore
+    feed.mark_moved("https://new.server/users/new", &pool).await?;
+    let stale4 = Feed::stale(&pool, 100, 100).await?;
+    assert_eq!(stale4.len(), 1);
+
     Ok(())
   }
 
@@ -1641,108 +2863,769 @@ mod test {
     Ok(())
   }
 
+  #[test]
+  fn test_feed_to_minimal_activity_pub() {
+    use serde_json::Value;
+    let feed:Feed = fake_feed();
+
+    let output = feed.to_minimal_activity_pub().unwrap();
+
+    let v: Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(v["id"], feed.ap_url());
+    assert_eq!(v["inbox"], format!("{}/inbox", feed.ap_url()));
+    assert_eq!(v["publicKey"]["id"], format!("{}#main-key", feed.ap_url()));
+    assert_eq!(v["publicKey"]["publicKeyPem"], feed.public_key);
+    assert!(v.get("outbox").is_none());
+    assert!(v.get("followers").is_none());
+    assert!(v.get("name").is_none());
+  }
+
+  #[sqlx::test]
+  fn test_admin_feed_to_activity_pub(pool: PgPool) -> Result<(), String> {
+    use std::env;
+    let instance_domain = env::var("DOMAIN_NAME").expect("DOMAIN_NAME is not set");
+
+    use serde_json::Value;
+    let tmpfeed:Feed = real_feed(&pool).await.unwrap();
+    tmpfeed.mark_admin(&pool).await.unwrap();
+
+    let feed = Feed::find(tmpfeed.id, &pool).await.unwrap();
+    let output = feed.to_activity_pub(&pool).await.unwrap();
+
+    let v: Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(v["summary"], format!("Admin account for {}", instance_domain));
+    assert_eq!(v["image"]["url"], format!("https://{}/assets/image.png", instance_domain));
+    assert_eq!(v["icon"]["url"], format!("https://{}/assets/icon.png", instance_domain));
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_to_update_activity(pool: PgPool) -> Result<(), String> {
+    use serde_json::Value;
+    let feed:Feed = real_feed(&pool).await.unwrap();
+
+    let output = feed.to_update_activity(&pool).await.unwrap();
+
+    let v: Value = serde_json::to_value(&output).unwrap();
+    assert_eq!(v["type"], "Update");
+    assert_eq!(v["actor"], feed.ap_url());
+    assert_eq!(v["object"]["type"], "Service");
+    assert_eq!(v["object"]["id"], feed.ap_url());
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_queue_profile_update(pool: PgPool) -> Result<(), String> {
+    let feed:Feed = real_feed(&pool).await.unwrap();
+    let mut queue = test_queue(&pool).await;
+
+    assert!(feed.queue_profile_update(&mut queue).await.is_ok());
+
+    // a second change while the first update is pending is a no-op
+    assert!(feed.queue_profile_update(&mut queue).await.is_ok());
+
+    let tasks = sqlx::query!("SELECT metadata, scheduled_at FROM fang_tasks WHERE metadata->>'type' = 'UpdateProfile'")
+      .fetch_all(&pool)
+      .await
+      .unwrap();
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].metadata["feed_id"], feed.id);
+    assert!(tasks[0].scheduled_at > Utc::now());
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_broadcast_profile_update(pool: PgPool) -> Result<(), String> {
+    use crate::utils::queue::create_queue;
+    use fang::NoTls;
+
+    let feed:Feed = real_feed(&pool).await.unwrap();
+    let actor:Actor = real_actor(&pool).await.unwrap();
+    add_follower(&feed, &actor.url, &pool).await;
+
+    assert!(feed.profile_updated_at.is_none());
+
+    let mut queue = create_queue().await;
+    queue.connect(NoTls).await.unwrap();
+
+    assert!(feed.broadcast_profile_update(&pool, &mut queue).await.is_ok());
+
+    let feed = Feed::find(feed.id, &pool).await.unwrap();
+    assert!(feed.profile_updated_at.is_some());
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_feed_to_activity_pub_migration(pool: PgPool) -> Result<(), String> {
+    use serde_json::Value;
+    let mut feed:Feed = fake_feed();
+    feed.also_known_as = Some("https://old.server/users/old\n\n  https://other.server/users/older ".to_string());
+    feed.moved_to = Some("https://new.server/users/new".to_string());
+
+    let output = feed.to_activity_pub(&pool).await.unwrap();
+
+    let v: Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(v["alsoKnownAs"][0], "https://old.server/users/old");
+    assert_eq!(v["alsoKnownAs"][1], "https://other.server/users/older");
+    assert_eq!(v["movedTo"], "https://new.server/users/new");
+    assert_eq!(v["manuallyApprovesFollowers"], false);
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_migrate_to_local(pool: PgPool) -> Result<(), String> {
+    use crate::utils::queue::create_queue;
+    use fang::NoTls;
+
+    let user = real_user(&pool).await.unwrap();
+    let mut feed = Feed::create(&user, &"https://foo.com/rss.xml".to_string(), &"oldfeed".to_string(), &pool).await.unwrap();
+    let dest = Feed::create(&user, &"https://foo.com/rss.xml".to_string(), &"newfeed".to_string(), &pool).await.unwrap();
+
+    let mut queue = create_queue().await;
+    queue.connect(NoTls).await.unwrap();
+
+    // can't move to yourself
+    assert!(feed.migrate_to(&user, "oldfeed", &pool, &mut queue).await.is_err());
+
+    let target = feed.migrate_to(&user, "@newfeed", &pool, &mut queue).await.unwrap();
+    assert_eq!(target, dest.ap_url());
+
+    let feed = Feed::find(feed.id, &pool).await.unwrap();
+    assert_eq!(feed.moved_to, Some(dest.ap_url()));
+
+    let dest = Feed::find(dest.id, &pool).await.unwrap();
+    assert_eq!(dest.aliases(), vec![feed.ap_url()]);
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_migrate_to_someone_elses_feed(pool: PgPool) -> Result<(), String> {
+    use crate::utils::queue::create_queue;
+    use fang::NoTls;
+
+    let user = real_user(&pool).await.unwrap();
+    let mut feed = Feed::create(&user, &"https://foo.com/rss.xml".to_string(), &"oldfeed".to_string(), &pool).await.unwrap();
+    let other_user = User::find_or_create_by_actor_url(&"https://muffin.pizza/users/other".to_string(), &pool).await.unwrap();
+    let dest = Feed::create(&other_user, &"https://foo.com/rss.xml".to_string(), &"newfeed".to_string(), &pool).await.unwrap();
+
+    let mut queue = create_queue().await;
+    queue.connect(NoTls).await.unwrap();
+
+    assert!(feed.migrate_to(&user, &dest.name, &pool, &mut queue).await.is_err());
+
+    let feed = Feed::find(feed.id, &pool).await.unwrap();
+    assert!(feed.moved_to.is_none());
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_migrate_to_remote(pool: PgPool) -> Result<(), String> {
+    use crate::utils::queue::create_queue;
+    use fang::NoTls;
+
+    let mut server = mockito::Server::new_async().await;
+    let mut feed:Feed = real_feed(&pool).await.unwrap();
+    let user = feed.user(&pool).await.unwrap();
+
+    let target = format!("{}/users/new", server.url());
+    let body = format!(r#"{{"id":"{}","type":"Person","alsoKnownAs":["{}"]}}"#, target, feed.ap_url());
+    let _m = server.mock("GET", "/users/new")
+      .with_status(200)
+      .with_body(body)
+      .create_async()
+      .await;
+
+    let unlisted = format!("{}/users/stranger", server.url());
+    let _m2 = server.mock("GET", "/users/stranger")
+      .with_status(200)
+      .with_body(format!(r#"{{"id":"{}","type":"Person"}}"#, unlisted))
+      .create_async()
+      .await;
+
+    // this one lists us, but claims to be someone else
+    let impostor = format!("{}/users/impostor", server.url());
+    let _m3 = server.mock("GET", "/users/impostor")
+      .with_status(200)
+      .with_body(format!(r#"{{"id":"{}","type":"Person","alsoKnownAs":["{}"]}}"#, target, feed.ap_url()))
+      .create_async()
+      .await;
+
+    let mut queue = create_queue().await;
+    queue.connect(NoTls).await.unwrap();
+
+    assert!(feed.migrate_to(&user, &unlisted, &pool, &mut queue).await.is_err());
+    assert!(feed.migrate_to(&user, &impostor, &pool, &mut queue).await.is_err());
+    assert!(Feed::find(feed.id, &pool).await.unwrap().moved_to.is_none());
+    assert_eq!(feed.migrate_to(&user, &target, &pool, &mut queue).await.unwrap(), target);
+
+    let feed = Feed::find(feed.id, &pool).await.unwrap();
+    assert_eq!(feed.moved_to, Some(target));
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_to_move_activity(_pool: PgPool) -> Result<(), String> {
+    use serde_json::Value;
+    let feed:Feed = fake_feed();
+
+    let output = feed.to_move_activity("https://new.server/users/new").unwrap();
+
+    let v: Value = serde_json::to_value(&output).unwrap();
+    assert_eq!(v["type"], "Move");
+    assert_eq!(v["actor"], feed.ap_url());
+    assert_eq!(v["object"], feed.ap_url());
+    assert_eq!(v["target"], "https://new.server/users/new");
+
+    assert!(feed.to_move_activity("not a url at all").is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_clean_aliases() {
+    assert_eq!(Feed::clean_aliases("https://old.server/users/old\n\n  javascript:alert(1)\nnonsense\n"), Some("https://old.server/users/old".to_string()));
+    assert_eq!(Feed::clean_aliases("nonsense"), None);
+  }
+
+  fn sign_payload(private_key: &str, payload: &str) -> Vec<u8> {
+    use openssl::{hash::MessageDigest, pkey::PKey, sign::Signer};
+
+    let key = PKey::private_key_from_pem(private_key.as_bytes()).unwrap();
+    let mut signer = Signer::new(MessageDigest::sha256(), &key).unwrap();
+    signer.update(payload.as_bytes()).unwrap();
+    signer.sign_to_vec().unwrap()
+  }
+
+  #[sqlx::test]
+  async fn test_rotate_keys(pool: PgPool) -> Result<(), String> {
+    use crate::utils::queue::create_queue;
+    use fang::NoTls;
+
+    let mut feed:Feed = real_feed(&pool).await.unwrap();
+    let old_private_key = feed.private_key.clone();
+    let old_public_key = feed.public_key.clone();
+
+    let mut queue = create_queue().await;
+    queue.connect(NoTls).await.unwrap();
+
+    feed.rotate_keys(&pool, &mut queue).await.unwrap();
+
+    let mut feed = Feed::find(feed.id, &pool).await.unwrap();
+    assert_ne!(feed.public_key, old_public_key);
+    assert_eq!(feed.previous_public_key, Some(old_public_key));
+    assert!(feed.key_rotated_at.is_some());
+    assert!(feed.profile_updated_at.is_some());
+
+    let payload = "date: Tue, 07 Mar 2023 17:54:08 GMT";
+    let new_signature = sign_payload(&feed.private_key, payload);
+    let old_signature = sign_payload(&old_private_key, payload);
+
+    assert!(feed.verify_signature(payload, &new_signature).unwrap());
+    assert!(feed.verify_signature(payload, &old_signature).unwrap());
+    assert!(!feed.verify_signature("something else", &new_signature).unwrap());
+
+    // once the grace period is over, the old key is no good
+    feed.key_rotated_at = Some(Utc::now() - chrono::Duration::days(30));
+    assert_eq!(feed.verification_keys().len(), 1);
+    assert!(!feed.verify_signature(payload, &old_signature).unwrap());
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_requires_signed_fetch(pool: PgPool) -> Result<(), String> {
+    use crate::models::Setting;
+
+    let mut feed:Feed = real_feed(&pool).await.unwrap();
+    assert!(!feed.requires_signed_fetch(&pool).await.unwrap());
+
+    feed.secure_mode = true;
+    assert!(feed.requires_signed_fetch(&pool).await.unwrap());
+
+    feed.secure_mode = false;
+    Setting::update(&"secure_mode".to_string(), &"true".to_string(), &pool).await.unwrap();
+    assert!(feed.requires_signed_fetch(&pool).await.unwrap());
+
+    // the admin feed is always public
+    feed.admin = true;
+    assert!(!feed.requires_signed_fetch(&pool).await.unwrap());
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_find_by_ap_url(pool: PgPool) -> Result<(), String> {
+    let feed:Feed = real_feed(&pool).await.unwrap();
+
+    let result = Feed::find_by_ap_url(&format!("{}#main-key", feed.ap_url()), &pool).await.unwrap();
+    assert_eq!(result.unwrap().id, feed.id);
+
+    let result = Feed::find_by_ap_url(&format!("{}/items/1", feed.ap_url()), &pool).await.unwrap();
+    assert!(result.is_none());
+
+    let result = Feed::find_by_ap_url("https://activitypub.pizza/users/colin", &pool).await.unwrap();
+    assert!(result.is_none());
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_follow(pool: PgPool) -> Result<(), String> {
+    let mut server = mockito::Server::new_async().await;
+    let actor = format!("{}/users/colin", &server.url());
+
+    let json = format!(r#"{{"id": "{}/1/2/3", "actor":"{}","object":{{ "id": "{}" }} ,"type":"Follow"}}"#, &server.url(), actor, actor).to_string();
+    let act:AcceptedActivity = serde_json::from_str(&json).unwrap();
+
+
+    let _m = server.mock("GET", "/users/colin")
+      .with_status(200)
+      .with_header("Accept", "application/ld+json")
+      .create_async()
+      .await;
+
+    let _m2 = server.mock("POST", "/users/colin/inbox")
+      .with_status(202)
+      .create_async()
+      .await;
+
+
+    let feed:Feed = real_feed(&pool).await.unwrap();
+
+    let result = sqlx::query!("SELECT COUNT(1) AS tally FROM followers WHERE feed_id = $1 AND actor = $2", feed.id, actor)
+      .fetch_one(&pool)
+      .await
+      .unwrap();
+
+    assert!(result.tally.unwrap() == 0);
+
+    let mut queue = test_queue(&pool).await;
+    let activity_result = feed.handle_activity(&pool, &mut queue, &act).await;
+    match activity_result {
+      Ok(_result) => {
+
+        let result2 = sqlx::query!("SELECT COUNT(1) AS tally FROM followers WHERE feed_id = $1 AND actor = $2", feed.id, actor)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+  
+        assert!(result2.tally.unwrap() > 0);
+
+        Ok(())
+      },
+
+      Err(why) => Err(why.to_string())
+    }
+  }
+
+ 
+  #[sqlx::test]
+  async fn test_follow_manually_approved(pool: PgPool) -> Result<(), String> {
+    let actor = "https://activitypub.pizza/users/colin";
+    let mut feed:Feed = real_feed(&pool).await.unwrap();
+    feed.manually_approves_followers = true;
+    feed.save(&pool).await.unwrap();
+
+    let json = format!(r#"{{"id": "{}/follows/1", "actor":"{}","object":"{}","type":"Follow"}}"#, actor, actor, feed.ap_url()).to_string();
+    let act:AcceptedActivity = serde_json::from_str(&json).unwrap();
+
+    // no Accept gets sent here, so nothing to mock
+    let mut queue = test_queue(&pool).await;
+    feed.handle_activity(&pool, &mut queue, &act).await.unwrap();
+
+    assert_eq!(follower_tally(&feed, actor, &pool).await, 0);
+
+    let requests = FollowRequest::for_feed(&feed, &pool).await.unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].actor, actor);
+    assert_eq!(requests[0].activity_id, format!("{actor}/follows/1"));
+
+    // they can change their mind before we get to it
+    feed.unfollow(&pool, actor).await.unwrap();
+    assert!(FollowRequest::for_feed(&feed, &pool).await.unwrap().is_empty());
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_approve_follow_request(pool: PgPool) -> Result<(), String> {
+    let server = mockito::Server::new_async().await;
+    let actor = format!("{}/users/colin", server.url());
+
+    let feed:Feed = real_feed(&pool).await.unwrap();
+    FollowRequest::create(&feed, &actor, &format!("{actor}/follows/1"), &pool).await.unwrap();
+    let request = FollowRequest::for_feed(&feed, &pool).await.unwrap().pop().unwrap();
+
+    let mut queue = test_queue(&pool).await;
+    feed.approve_follow_request(&pool, &mut queue, &request).await.unwrap();
+
+    let deliveries = queued_deliveries(&format!("{actor}/inbox"), &pool).await;
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0]["type"], "Accept");
+    assert_eq!(follower_tally(&feed, &actor, &pool).await, 1);
+    assert!(FollowRequest::for_feed(&feed, &pool).await.unwrap().is_empty());
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_reject_follow_request(pool: PgPool) -> Result<(), String> {
+    let server = mockito::Server::new_async().await;
+    let actor = format!("{}/users/colin", server.url());
+
+    let feed:Feed = real_feed(&pool).await.unwrap();
+    FollowRequest::create(&feed, &actor, &format!("{actor}/follows/1"), &pool).await.unwrap();
+    let request = FollowRequest::for_feed(&feed, &pool).await.unwrap().pop().unwrap();
+
+    let mut queue = test_queue(&pool).await;
+    feed.reject_follow_request(&pool, &mut queue, &request).await.unwrap();
+
+    let deliveries = queued_deliveries(&format!("{actor}/inbox"), &pool).await;
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0]["type"], "Reject");
+    assert_eq!(follower_tally(&feed, &actor, &pool).await, 0);
+    assert!(FollowRequest::for_feed(&feed, &pool).await.unwrap().is_empty());
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_follow_blocked(pool: PgPool) -> Result<(), String> {
+    let server = mockito::Server::new_async().await;
+    let actor = format!("{}/users/colin", server.url());
+
+    let feed:Feed = real_feed(&pool).await.unwrap();
+    FeedBlock::create(&feed, ACTOR, &actor, &pool).await.unwrap();
+
+    let json = format!(r#"{{"id": "{}/follows/1", "actor":"{}","object":"{}","type":"Follow"}}"#, actor, actor, feed.ap_url()).to_string();
+    let act:AcceptedActivity = serde_json::from_str(&json).unwrap();
+
+    let mut queue = test_queue(&pool).await;
+    feed.handle_activity(&pool, &mut queue, &act).await.unwrap();
+
+    // the Reject is queued up for delivery
+    let deliveries = queued_deliveries(&format!("{actor}/inbox"), &pool).await;
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0]["type"], "Reject");
+    assert_eq!(follower_tally(&feed, &actor, &pool).await, 0);
+    assert!(FollowRequest::for_feed(&feed, &pool).await.unwrap().is_empty());
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_sends_to_relays(_pool: PgPool) -> Result<(), String> {
+    let mut feed:Feed = fake_feed();
+    assert!(!feed.sends_to_relays());
+
+    feed.listed = true;
+    feed.status_publicity = Some("unlisted".to_string());
+    assert!(!feed.sends_to_relays());
+
+    feed.status_publicity = Some("public".to_string());
+    assert!(feed.sends_to_relays());
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_handle_relay_response(pool: PgPool) -> Result<(), String> {
+    use crate::models::Relay;
+
+    let feed:Feed = real_feed(&pool).await.unwrap();
+    feed.mark_admin(&pool).await.unwrap();
+    let feed = Feed::find(feed.id, &pool).await.unwrap();
+
+    let relay = Relay::create("https://relay.place/inbox", &pool).await.unwrap();
+    let follow_id = format!("{}#relays/{}/1", feed.ap_url(), relay.id);
+    sqlx::query!("UPDATE relays SET follow_activity_id = $1 WHERE id = $2", follow_id, relay.id)
+      .execute(&pool)
+      .await
+      .unwrap();
+
+    let json = format!(r#"{{"id": "https://relay.place/activities/1", "actor":"https://relay.place/actor","object":{{"id":"{}","type":"Follow","actor":"{}","object":"https://www.w3.org/ns/activitystreams#Public"}},"type":"Accept"}}"#, follow_id, feed.ap_url());
+    let act:AcceptedActivity = serde_json::from_str(&json).unwrap();
+
+    let mut queue = test_queue(&pool).await;
+    feed.handle_activity(&pool, &mut queue, &act).await.unwrap();
+
+    let relay = Relay::find(relay.id, &pool).await.unwrap().unwrap();
+    assert_eq!(relay.status, "accepted");
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_follow_silenced_domain(pool: PgPool) -> Result<(), String> {
+    let actor = "https://silenced.place/users/colin";
+    let feed:Feed = real_feed(&pool).await.unwrap();
+    BlockedDomain::block("silenced.place", crate::models::blocked_domain::SILENCE, None, None, &pool).await.unwrap();
+
+    let json = format!(r#"{{"id": "{}/follows/1", "actor":"{}","object":"{}","type":"Follow"}}"#, actor, actor, feed.ap_url()).to_string();
+    let act:AcceptedActivity = serde_json::from_str(&json).unwrap();
+
+    // the follow is quietly ignored, so nothing to mock here
+    let mut queue = test_queue(&pool).await;
+    feed.handle_activity(&pool, &mut queue, &act).await.unwrap();
+    assert_eq!(follower_tally(&feed, actor, &pool).await, 0);
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_block_removes_followers(pool: PgPool) -> Result<(), String> {
+    let server = mockito::Server::new_async().await;
+    let actor = format!("{}/users/colin", server.url());
+    let domain = url::Url::parse(&server.url()).unwrap().host_str().unwrap().to_string();
+
+    let feed:Feed = real_feed(&pool).await.unwrap();
+    let other = "https://activitypub.pizza/users/other";
+    sqlx::query!("INSERT INTO followers (feed_id, actor, created_at, updated_at) VALUES($1, $2, $3, $4)", feed.id, actor, Utc::now(), Utc::now())
+      .execute(&pool)
+      .await
+      .unwrap();
+    sqlx::query!("INSERT INTO followers (feed_id, actor, created_at, updated_at) VALUES($1, $2, $3, $4)", feed.id, other, Utc::now(), Utc::now())
+      .execute(&pool)
+      .await
+      .unwrap();
+
+    let mut queue = test_queue(&pool).await;
+    feed.block(&pool, &mut queue, DOMAIN, &domain).await.unwrap();
+
+    let deliveries = queued_deliveries(&format!("{actor}/inbox"), &pool).await;
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0]["type"], "Reject");
+    assert_eq!(follower_tally(&feed, &actor, &pool).await, 0);
+    assert_eq!(follower_tally(&feed, other, &pool).await, 1);
+    assert!(FeedBlock::is_blocked(&feed, &actor, &pool).await.unwrap());
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_unfollow(pool: PgPool) -> Result<(), String> {
+    let actor = "https://activitypub.pizza/users/colin".to_string();
+    let feed:Feed = real_feed(&pool).await.unwrap();
+
+    let json = format!(r#"{{"actor":"{}","object":{{"id":"{}/follows/1","type":"Follow","actor":"{}","object":"{}"}},"type":"Undo"}}"#, actor, actor, actor, feed.ap_url()).to_string();
+    let act:AcceptedActivity = serde_json::from_str(&json).unwrap();
+    
+    let now = Utc::now();
+
+    sqlx::query!("INSERT INTO followers (feed_id, actor, created_at, updated_at) VALUES($1, $2, $3, $4)", feed.id, actor, now, now)
+      .execute(&pool)
+      .await
+      .unwrap();
+
+    let result = sqlx::query!("SELECT COUNT(1) AS tally FROM followers WHERE feed_id = $1 AND actor = $2", feed.id, actor)
+      .fetch_one(&pool)
+      .await
+      .unwrap();
+
+    assert!(result.tally.unwrap() == 1);
+
+    let mut queue = test_queue(&pool).await;
+    feed.handle_activity(&pool, &mut queue, &act).await.unwrap();
+
+    let post_result = sqlx::query!("SELECT COUNT(1) AS tally FROM followers WHERE feed_id = $1 AND actor = $2", feed.id, actor)
+      .fetch_one(&pool)
+      .await
+      .unwrap();
+
+    assert!(post_result.tally.unwrap() == 0);
+      
+    Ok(())
+  }
+
+
+  async fn add_follower(feed: &Feed, actor: &str, pool: &PgPool) {
+    let now = Utc::now();
+    sqlx::query!("INSERT INTO followers (feed_id, actor, created_at, updated_at) VALUES($1, $2, $3, $4)", feed.id, actor, now, now)
+      .execute(pool)
+      .await
+      .unwrap();
+  }
+
+  async fn follower_tally(feed: &Feed, actor: &str, pool: &PgPool) -> i64 {
+    sqlx::query!("SELECT COUNT(1) AS tally FROM followers WHERE feed_id = $1 AND actor = $2", feed.id, actor)
+      .fetch_one(pool)
+      .await
+      .unwrap()
+      .tally
+      .unwrap()
+  }
+
+  #[sqlx::test]
+  async fn test_undo_like(pool: PgPool) -> Result<(), String> {
+    let actor = "https://activitypub.pizza/users/colin";
+    let feed:Feed = real_feed(&pool).await.unwrap();
+    let item:Item = real_item(&feed, &pool).await.unwrap();
+    let item_url = format!("{}/items/{}", feed.ap_url(), item.id);
+
+    add_follower(&feed, actor, &pool).await;
+
+    let json = format!(r#"{{"id":"{actor}/likes/1","actor":"{actor}","object":"{item_url}","type":"Like"}}"#);
+    let act:AcceptedActivity = serde_json::from_str(&json).unwrap();
+    let mut queue = test_queue(&pool).await;
+    feed.handle_activity(&pool, &mut queue, &act).await.unwrap();
+
+    assert_eq!(Interaction::for_item(&item, LIKE, &pool).await.unwrap().len(), 1);
+
+    let json = format!(r#"{{"id":"{actor}/likes/1/undo","actor":"{actor}","object":{{"id":"{actor}/likes/1","type":"Like","actor":"{actor}","object":"{item_url}"}},"type":"Undo"}}"#);
+    let act:AcceptedActivity = serde_json::from_str(&json).unwrap();
+    feed.handle_activity(&pool, &mut queue, &act).await.unwrap();
+
+    assert_eq!(Interaction::for_item(&item, LIKE, &pool).await.unwrap().len(), 0);
+
+    // undoing a like shouldn't touch the follow
+    assert_eq!(follower_tally(&feed, actor, &pool).await, 1);
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_undo_announce(pool: PgPool) -> Result<(), String> {
+    let actor = "https://activitypub.pizza/users/colin";
+    let feed:Feed = real_feed(&pool).await.unwrap();
+    let item:Item = real_item(&feed, &pool).await.unwrap();
+    let item_url = format!("{}/items/{}", feed.ap_url(), item.id);
+
+    add_follower(&feed, actor, &pool).await;
+
+    let json = format!(r#"{{"id":"{actor}/statuses/1/activity","actor":"{actor}","object":"{item_url}","type":"Announce"}}"#);
+    let act:AcceptedActivity = serde_json::from_str(&json).unwrap();
+    let mut queue = test_queue(&pool).await;
+    feed.handle_activity(&pool, &mut queue, &act).await.unwrap();
+
+    assert_eq!(Interaction::for_item(&item, ANNOUNCE, &pool).await.unwrap().len(), 1);
+
+    let json = format!(r#"{{"id":"{actor}/statuses/1#undo","actor":"{actor}","object":{{"id":"{actor}/statuses/1/activity","type":"Announce","actor":"{actor}","object":"{item_url}"}},"type":"Undo"}}"#);
+    let act:AcceptedActivity = serde_json::from_str(&json).unwrap();
+    feed.handle_activity(&pool, &mut queue, &act).await.unwrap();
+
+    assert_eq!(Interaction::for_item(&item, ANNOUNCE, &pool).await.unwrap().len(), 0);
+    assert_eq!(follower_tally(&feed, actor, &pool).await, 1);
+
+    Ok(())
+  }
+
   #[sqlx::test]
-  fn test_admin_feed_to_activity_pub(pool: PgPool) -> Result<(), String> {
-    use std::env;
-    let instance_domain = env::var("DOMAIN_NAME").expect("DOMAIN_NAME is not set");
+  async fn test_undo_someone_elses_follow(pool: PgPool) -> Result<(), String> {
+    let actor = "https://activitypub.pizza/users/colin";
+    let other = "https://activitypub.pizza/users/other";
+    let feed:Feed = real_feed(&pool).await.unwrap();
 
-    use serde_json::Value;
-    let tmpfeed:Feed = real_feed(&pool).await.unwrap();
-    tmpfeed.mark_admin(&pool).await.unwrap();
+    add_follower(&feed, other, &pool).await;
 
-    let feed = Feed::find(tmpfeed.id, &pool).await.unwrap();
-    let output = feed.to_activity_pub(&pool).await.unwrap();
+    let json = format!(r#"{{"actor":"{actor}","object":{{"id":"{other}/follows/1","type":"Follow","actor":"{other}","object":"{}"}},"type":"Undo"}}"#, feed.ap_url());
+    let act:AcceptedActivity = serde_json::from_str(&json).unwrap();
+    let mut queue = test_queue(&pool).await;
+    feed.handle_activity(&pool, &mut queue, &act).await.unwrap();
 
-    let v: Value = serde_json::from_str(&output).unwrap();
-    assert_eq!(v["summary"], format!("Admin account for {}", instance_domain));
-    assert_eq!(v["image"]["url"], format!("https://{}/assets/image.png", instance_domain));
-    assert_eq!(v["icon"]["url"], format!("https://{}/assets/icon.png", instance_domain));
+    assert_eq!(follower_tally(&feed, other, &pool).await, 1);
 
     Ok(())
   }
 
   #[sqlx::test]
-  async fn test_follow(pool: PgPool) -> Result<(), String> {
-    let mut server = mockito::Server::new_async().await;
-    let actor = format!("{}/users/colin", &server.url());
+  async fn test_delete_actor(pool: PgPool) -> Result<(), String> {
+    let actor:Actor = real_actor(&pool).await.unwrap();
+    let feed:Feed = real_feed(&pool).await.unwrap();
+    let feed2:Feed = real_feed(&pool).await.unwrap();
 
-    let json = format!(r#"{{"id": "{}/1/2/3", "actor":"{}","object":{{ "id": "{}" }} ,"type":"Follow"}}"#, &server.url(), actor, actor).to_string();
+    add_follower(&feed, &actor.url, &pool).await;
+    add_follower(&feed2, &actor.url, &pool).await;
+
+    let json = format!(r#"{{"id":"{}#delete","actor":"{}","object":"{}","type":"Delete"}}"#, actor.url, actor.url, actor.url);
     let act:AcceptedActivity = serde_json::from_str(&json).unwrap();
+    let mut queue = test_queue(&pool).await;
+    feed.handle_activity(&pool, &mut queue, &act).await.unwrap();
 
+    assert_eq!(follower_tally(&feed, &actor.url, &pool).await, 0);
+    assert_eq!(follower_tally(&feed2, &actor.url, &pool).await, 0);
+    assert!(Actor::find(&actor.url, &pool).await.unwrap().is_none());
 
-    let _m = server.mock("GET", "/users/colin")
-      .with_status(200)
-      .with_header("Accept", "application/ld+json")
-      .create_async()
-      .await;
+    Ok(())
+  }
 
-    let _m2 = server.mock("POST", "/users/colin/inbox")
-      .with_status(202)
+  #[sqlx::test]
+  async fn test_update_actor(pool: PgPool) -> Result<(), String> {
+    let mut server = mockito::Server::new_async().await;
+    let data = fs::read_to_string("fixtures/muffinista.json").unwrap().replace("SERVER_URL", &server.url());
+
+    let m = server.mock("GET", "/users/muffinista")
+      .with_status(200)
+      .with_body(data)
       .create_async()
       .await;
 
-
     let feed:Feed = real_feed(&pool).await.unwrap();
+    let actor = format!("{}/users/muffinista", server.url());
+    Actor::create(&actor, &format!("{actor}/inbox"), &format!("{actor}#main-key"), &"old key".to_string(), &"muffinista".to_string(), &pool).await.unwrap();
 
-    let result = sqlx::query!("SELECT COUNT(1) AS tally FROM followers WHERE feed_id = $1 AND actor = $2", feed.id, actor)
-      .fetch_one(&pool)
-      .await
-      .unwrap();
-
-    assert!(result.tally.unwrap() == 0);
-
-    let activity_result = feed.handle_activity(&pool, &act).await;
-    match activity_result {
-      Ok(_result) => {
+    let json = format!(r#"{{"id":"{actor}#updates/1","actor":"{actor}","object":{{"id":"{actor}","type":"Person","name":"new name"}},"type":"Update"}}"#);
+    let act:AcceptedActivity = serde_json::from_str(&json).unwrap();
+    let mut queue = test_queue(&pool).await;
+    feed.handle_activity(&pool, &mut queue, &act).await.unwrap();
 
-        let result2 = sqlx::query!("SELECT COUNT(1) AS tally FROM followers WHERE feed_id = $1 AND actor = $2", feed.id, actor)
-        .fetch_one(&pool)
-        .await
-        .unwrap();
-  
-        assert!(result2.tally.unwrap() > 0);
+    m.assert_async().await;
 
-        Ok(())
-      },
+    let actor = Actor::find(&actor, &pool).await.unwrap().unwrap();
+    assert_eq!(actor.name, Some("colin mitchell".to_string()));
+    assert_eq!(actor.shared_inbox_url, Some("https://botsin.space/inbox".to_string()));
+    assert!(actor.public_key.contains("BEGIN PUBLIC KEY"));
 
-      Err(why) => Err(why.to_string())
-    }
+    Ok(())
   }
 
- 
   #[sqlx::test]
-  async fn test_unfollow(pool: PgPool) -> Result<(), String> {
-    let actor = "https://activitypub.pizza/users/colin".to_string();
-    let json = format!(r#"{{"actor":"{}","object":"{}/feed","type":"Undo"}}"#, actor, actor).to_string();
-    let act:AcceptedActivity = serde_json::from_str(&json).unwrap();
-    
+  async fn test_delete_note(pool: PgPool) -> Result<(), String> {
+    let actor = "https://activitypub.pizza/users/colin";
     let feed:Feed = real_feed(&pool).await.unwrap();
-    let now = Utc::now();
+    let item:Item = real_item(&feed, &pool).await.unwrap();
+    let item_url = format!("{}/items/{}", feed.ap_url(), item.id);
 
-    sqlx::query!("INSERT INTO followers (feed_id, actor, created_at, updated_at) VALUES($1, $2, $3, $4)", feed.id, actor, now, now)
-      .execute(&pool)
-      .await
-      .unwrap();
+    add_follower(&feed, actor, &pool).await;
 
-    let result = sqlx::query!("SELECT COUNT(1) AS tally FROM followers WHERE feed_id = $1 AND actor = $2", feed.id, actor)
-      .fetch_one(&pool)
-      .await
-      .unwrap();
+    let json = format!(r#"{{
+      "id": "{actor}/statuses/1/activity",
+      "actor": "{actor}",
+      "type": "Create",
+      "object": {{
+        "id": "{actor}/statuses/1",
+        "type": "Note",
+        "attributedTo": "{actor}",
+        "inReplyTo": "{item_url}",
+        "content": "<p>nice post!</p>"
+      }}
+    }}"#);
+    let act:AcceptedActivity = serde_json::from_str(&json).unwrap();
+    let mut queue = test_queue(&pool).await;
+    feed.handle_activity(&pool, &mut queue, &act).await.unwrap();
 
-    assert!(result.tally.unwrap() == 1);
+    let replies = Interaction::for_item(&item, REPLY, &pool).await.unwrap();
+    assert_eq!(replies.len(), 1);
+    assert_eq!(replies[0].content, Some("nice post!".to_string()));
 
-    feed.handle_activity(&pool, &act).await.unwrap();
+    let json = format!(r#"{{"id":"{actor}/statuses/1#delete","actor":"{actor}","object":{{"id":"{actor}/statuses/1","type":"Tombstone"}},"type":"Delete"}}"#);
+    let act:AcceptedActivity = serde_json::from_str(&json).unwrap();
+    feed.handle_activity(&pool, &mut queue, &act).await.unwrap();
 
-    let post_result = sqlx::query!("SELECT COUNT(1) AS tally FROM followers WHERE feed_id = $1 AND actor = $2", feed.id, actor)
-      .fetch_one(&pool)
-      .await
-      .unwrap();
+    assert_eq!(Interaction::for_item(&item, REPLY, &pool).await.unwrap().len(), 0);
+
+    // deleting a post shouldn't unfollow
+    assert_eq!(follower_tally(&feed, actor, &pool).await, 1);
 
-    assert!(post_result.tally.unwrap() == 0);
-      
     Ok(())
   }
-
   
   #[sqlx::test]
   async fn test_help(pool: PgPool) -> Result<(), String> {
@@ -1842,18 +3725,15 @@ mod test {
       .create_async()
       .await;
 
-    let m2 = server.mock("POST", "/users/muffinista/inbox")
-      .with_status(202)
-      .create_async()
-      .await;
-
-
     let feed:Feed = Feed::find(admin_feed.id, &pool).await.unwrap();
 
-    let activity_result = feed.handle_activity(&pool, &act).await;
+    let mut queue = test_queue(&pool).await;
+    let activity_result = feed.handle_activity(&pool, &mut queue, &act).await;
     match activity_result {
       Ok(_result) => {
-        m2.assert();
+        let deliveries = queued_deliveries(&format!("{actor}/inbox"), &pool).await;
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0]["type"], "Create");
         Ok(())
       },
 
@@ -1862,6 +3742,159 @@ mod test {
   }
 
 
+  fn public_reply(actor: &str, to: &str, content: &str) -> AcceptedActivity {
+    let json = format!(r#"{{
+      "@context": "https://www.w3.org/ns/activitystreams",
+      "id": "{actor}/statuses/1/activity",
+      "type": "Create",
+      "actor": "{actor}",
+      "to": ["https://www.w3.org/ns/activitystreams#Public"],
+      "cc": ["{actor}/followers", "{to}"],
+      "object": {{
+        "id": "{actor}/statuses/1",
+        "type": "Note",
+        "attributedTo": "{actor}",
+        "to": ["https://www.w3.org/ns/activitystreams#Public"],
+        "cc": ["{actor}/followers", "{to}"],
+        "content": "{content}"
+      }}
+    }}"#);
+
+    serde_json::from_str(&json).unwrap()
+  }
+
+  fn direct_message(actor: &str, to: &str, content: &str) -> AcceptedActivity {
+    let json = format!(r#"{{
+      "@context": "https://www.w3.org/ns/activitystreams",
+      "id": "{actor}/statuses/1/activity",
+      "type": "Create",
+      "actor": "{actor}",
+      "to": ["{to}"],
+      "object": {{
+        "id": "{actor}/statuses/1",
+        "type": "Note",
+        "attributedTo": "{actor}",
+        "to": ["{to}"],
+        "content": "{content}"
+      }}
+    }}"#);
+
+    serde_json::from_str(&json).unwrap()
+  }
+
+  #[test]
+  fn test_subscription_command() {
+    assert_eq!(super::subscription_command("<p>subscribe</p>"), Some((true, None)));
+    assert_eq!(super::subscription_command("<p><span><a href=\"https://local/feed\">@<span>feed</span></a></span> Unsubscribe!</p>"), Some((false, None)));
+    assert_eq!(super::subscription_command("@admin subscribe @testfeed@feedsin.space"), Some((true, Some("testfeed".to_string()))));
+    assert_eq!(super::subscription_command("please subscribe me"), None);
+  }
+
+  #[sqlx::test]
+  async fn test_subscribe_by_dm(pool: PgPool) -> Result<(), String> {
+    let mut server = mockito::Server::new_async().await;
+    let actor = format!("{}/users/muffinista", server.url());
+
+    let feed:Feed = real_feed(&pool).await.unwrap();
+
+    let data = fs::read_to_string("fixtures/helper.json").unwrap().replace("SERVER_URL", &server.url());
+    let _m = server.mock("GET", "/users/muffinista")
+      .with_status(200)
+      .with_body(data)
+      .create_async()
+      .await;
+
+    let act = direct_message(&actor, &feed.ap_url(), "subscribe");
+    let mut queue = test_queue(&pool).await;
+    feed.handle_activity(&pool, &mut queue, &act).await.unwrap();
+
+    let subscribers = DmSubscriber::for_feed(&feed, &pool).await.unwrap();
+    assert_eq!(subscribers.len(), 1);
+    assert_eq!(subscribers[0].actor, actor);
+
+    let act = direct_message(&actor, &feed.ap_url(), "unsubscribe");
+    feed.handle_activity(&pool, &mut queue, &act).await.unwrap();
+
+    assert_eq!(DmSubscriber::count_for_feed(&feed, &pool).await.unwrap(), 0);
+    assert_eq!(queued_deliveries(&format!("{actor}/inbox"), &pool).await.len(), 2);
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_subscribe_by_dm_needs_approval(pool: PgPool) -> Result<(), String> {
+    let mut server = mockito::Server::new_async().await;
+    let actor = format!("{}/users/muffinista", server.url());
+
+    let mut feed:Feed = real_feed(&pool).await.unwrap();
+    feed.manually_approves_followers = true;
+    feed.save(&pool).await.unwrap();
+
+    let data = fs::read_to_string("fixtures/helper.json").unwrap().replace("SERVER_URL", &server.url());
+    let _m = server.mock("GET", "/users/muffinista")
+      .with_status(200)
+      .with_body(data)
+      .create_async()
+      .await;
+
+    let act = direct_message(&actor, &feed.ap_url(), "subscribe");
+    let mut queue = test_queue(&pool).await;
+    feed.handle_activity(&pool, &mut queue, &act).await.unwrap();
+
+    assert_eq!(DmSubscriber::count_for_feed(&feed, &pool).await.unwrap(), 0);
+
+    let deliveries = queued_deliveries(&format!("{actor}/inbox"), &pool).await;
+    assert_eq!(deliveries.len(), 1);
+    assert!(deliveries[0]["object"]["content"].as_str().unwrap().contains("approves its followers"));
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_subscribe_by_public_reply(pool: PgPool) -> Result<(), String> {
+    let actor = "https://activitypub.pizza/users/colin";
+    let feed:Feed = real_feed(&pool).await.unwrap();
+
+    let act = public_reply(actor, &feed.ap_url(), "subscribe");
+    let mut queue = test_queue(&pool).await;
+    feed.handle_activity(&pool, &mut queue, &act).await.unwrap();
+
+    assert_eq!(DmSubscriber::count_for_feed(&feed, &pool).await.unwrap(), 0);
+    assert_eq!(queued_deliveries(&format!("{actor}/inbox"), &pool).await.len(), 0);
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_subscribe_by_dm_to_admin(pool: PgPool) -> Result<(), String> {
+    let mut server = mockito::Server::new_async().await;
+    let actor = format!("{}/users/muffinista", server.url());
+
+    let admin_feed:Feed = real_feed(&pool).await.unwrap();
+    admin_feed.mark_admin(&pool).await.unwrap();
+    let admin_feed = Feed::find(admin_feed.id, &pool).await.unwrap();
+
+    let feed:Feed = real_feed(&pool).await.unwrap();
+
+    let data = fs::read_to_string("fixtures/helper.json").unwrap().replace("SERVER_URL", &server.url());
+    let _m = server.mock("GET", "/users/muffinista")
+      .with_status(200)
+      .with_body(data)
+      .create_async()
+      .await;
+
+    let content = format!("@{} subscribe @{}", admin_feed.name, feed.name);
+    let act = direct_message(&actor, &admin_feed.ap_url(), &content);
+    let mut queue = test_queue(&pool).await;
+    admin_feed.handle_activity(&pool, &mut queue, &act).await.unwrap();
+
+    assert_eq!(queued_deliveries(&format!("{actor}/inbox"), &pool).await.len(), 1);
+    assert_eq!(DmSubscriber::count_for_feed(&feed, &pool).await.unwrap(), 1);
+    assert_eq!(DmSubscriber::count_for_feed(&admin_feed, &pool).await.unwrap(), 0);
+
+    Ok(())
+  }
+
   #[sqlx::test]
   async fn test_generate_login_message(pool: PgPool) -> Result<(), String> {
     let server = mockito::Server::new_async().await;
@@ -1897,6 +3930,95 @@ mod test {
     Ok(())
   }
 
+  #[sqlx::test]
+  async fn test_invite_member(pool: PgPool) -> Result<(), String> {
+    use crate::models::FeedMember;
+    use crate::models::feed_member::EDITOR;
+
+    let mut server = mockito::Server::new_async().await;
+    let actor = format!("{}/users/muffinista", server.url());
+
+    let admin_feed:Feed = real_feed(&pool).await.unwrap();
+    admin_feed.mark_admin(&pool).await.unwrap();
+
+    let feed:Feed = real_feed(&pool).await.unwrap();
+
+    let data = fs::read_to_string("fixtures/helper.json").unwrap().replace("SERVER_URL", &server.url());
+    let _m = server.mock("GET", "/users/muffinista")
+      .with_status(200)
+      .with_body(data)
+      .create_async()
+      .await;
+
+    let mut queue = test_queue(&pool).await;
+    let member = feed.invite_member(&actor, EDITOR, &pool, &mut queue).await.unwrap();
+    assert_eq!(member.role, EDITOR);
+
+    let user = User::find_by_actor_url(&actor, &pool).await.unwrap().unwrap();
+    assert_eq!(FeedMember::role_for(&feed, &user, &pool).await.unwrap(), Some(EDITOR.to_string()));
+    assert_eq!(Feed::for_user(&user, &pool).await.unwrap().len(), 1);
+
+    let deliveries = queued_deliveries(&format!("{actor}/inbox"), &pool).await;
+    assert_eq!(deliveries.len(), 1);
+    assert!(deliveries[0]["object"]["content"].as_str().unwrap().contains(&feed.address()));
+    assert_eq!(deliveries[0]["actor"], admin_feed.ap_url());
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_invite_existing_member(pool: PgPool) -> Result<(), String> {
+    use crate::models::FeedMember;
+    use crate::models::feed_member::{LAST_OWNER_ERROR, OWNER, VIEWER};
+
+    let mut server = mockito::Server::new_async().await;
+    let actor = format!("{}/users/muffinista", server.url());
+
+    let admin_feed:Feed = real_feed(&pool).await.unwrap();
+    admin_feed.mark_admin(&pool).await.unwrap();
+
+    let feed:Feed = real_feed(&pool).await.unwrap();
+
+    let data = fs::read_to_string("fixtures/helper.json").unwrap().replace("SERVER_URL", &server.url());
+    let _m = server.mock("GET", "/users/muffinista")
+      .with_status(200)
+      .with_body(data)
+      .create_async()
+      .await;
+
+    let mut queue = test_queue(&pool).await;
+    feed.invite_member(&actor, OWNER, &pool, &mut queue).await.unwrap();
+    let user = User::find_by_actor_url(&actor, &pool).await.unwrap().unwrap();
+
+    // changing an existing member's role leaves their login link alone
+    feed.invite_member(&actor, VIEWER, &pool, &mut queue).await.unwrap();
+    assert_eq!(User::find(user.id, &pool).await.unwrap().login_token, user.login_token);
+    let deliveries = queued_deliveries(&format!("{actor}/inbox"), &pool).await;
+    assert_eq!(deliveries.len(), 2);
+    assert!(!deliveries[1]["object"]["content"].as_str().unwrap().contains("login link"));
+
+    // once they're the only owner, they can't be demoted
+    feed.invite_member(&actor, OWNER, &pool, &mut queue).await.unwrap();
+    let creator = FeedMember::details_for_feed(&feed, &pool).await.unwrap().remove(0);
+    FeedMember::find(&feed, creator.id, &pool).await.unwrap().unwrap().delete(&pool).await.unwrap();
+
+    let result = feed.invite_member(&actor, VIEWER, &pool, &mut queue).await;
+    assert!(matches!(result, Err(DeliveryError::Error(why)) if why == LAST_OWNER_ERROR));
+    assert_eq!(FeedMember::role_for(&feed, &user, &pool).await.unwrap(), Some(OWNER.to_string()));
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_invite_member_not_found(pool: PgPool) -> Result<(), String> {
+    let feed:Feed = real_feed(&pool).await.unwrap();
+    let mut queue = test_queue(&pool).await;
+
+    assert!(feed.invite_member("not a handle", "editor", &pool, &mut queue).await.is_err());
+
+    Ok(())
+  }
+
   #[sqlx::test]
   async fn test_link_to_feed_message(pool: PgPool) -> Result<(), String> {
     let actor = real_actor(&pool).await.unwrap();
@@ -1941,8 +4063,45 @@ mod test {
     }
   }
 
+  #[sqlx::test]
+  async fn test_followers_visibility(pool: PgPool) -> Result<(), String> {
+    use serde_json::Value;
+    use crate::models::Setting;
+    use crate::models::feed::{FOLLOWERS_COUNT, FOLLOWERS_HIDDEN, FOLLOWERS_PUBLIC};
+
+    let mut feed:Feed = real_feed(&pool).await.unwrap();
+    add_follower(&feed, "https://activitypub.pizza/users/colin", &pool).await;
+
+    let v: Value = serde_json::to_value(feed.followers(&pool).await.unwrap()).unwrap();
+    assert_eq!(v["totalItems"], 1);
+    assert_eq!(v["first"]["orderedItems"][0], "https://activitypub.pizza/users/colin");
+    assert!(v["last"].is_string());
+
+    feed.followers_visibility = Some(FOLLOWERS_COUNT.to_string());
+    let v: Value = serde_json::to_value(feed.followers(&pool).await.unwrap()).unwrap();
+    assert_eq!(v["totalItems"], 1);
+    assert!(v["first"].is_null());
+
+    feed.followers_visibility = Some(FOLLOWERS_HIDDEN.to_string());
+    let v: Value = serde_json::to_value(feed.followers(&pool).await.unwrap()).unwrap();
+    assert!(v["totalItems"].is_null());
+    assert!(v["first"].is_null());
+
+    // feeds without a setting use the instance default
+    feed.followers_visibility = None;
+    Setting::update(&"followers_visibility".to_string(), &FOLLOWERS_HIDDEN.to_string(), &pool).await.unwrap();
+    assert_eq!(feed.followers_visibility(&pool).await.unwrap(), FOLLOWERS_HIDDEN);
+
+    feed.followers_visibility = Some(FOLLOWERS_PUBLIC.to_string());
+    assert_eq!(feed.followers_visibility(&pool).await.unwrap(), FOLLOWERS_PUBLIC);
+
+    Ok(())
+  }
+
   #[sqlx::test]
   async fn test_followers_paged(pool: PgPool) -> Result<(), String> {
+    use crate::models::feed::PageCursor;
+
     let feed:Feed = fake_feed();
     let now = Utc::now();
 
@@ -1954,21 +4113,19 @@ mod test {
         .unwrap();
     }
 
-    let result = feed.followers_paged(2, &pool).await;
+    let result = feed.followers_paged(&PageCursor { max_id: Some(25), min_id: None }, &pool).await;
     match result {
       Ok(result) => {
         let s = serde_json::to_string(&result).unwrap();
         // println!("{:?}", s);
-        
+
         assert!(s.contains("OrderedCollectionPage"));
-        assert!(s.contains("/colin11"));
-        assert!(s.contains("/colin12"));
-        assert!(s.contains("/colin13"));
-        assert!(s.contains(&format!(r#"first":"{}"#, path_to_url(&uri!(render_feed_followers(feed.name.clone(), Some(1)))))));
-        assert!(s.contains(&format!(r#"prev":"{}"#, path_to_url(&uri!(render_feed_followers(feed.name.clone(), Some(1)))))));      
-        assert!(s.contains(&format!(r#"next":"{}"#, path_to_url(&uri!(render_feed_followers(feed.name.clone(), Some(3)))))));
-        assert!(s.contains(&format!(r#"last":"{}"#, path_to_url(&uri!(render_feed_followers(feed.name.clone(), Some(4)))))));
-        assert!(s.contains(&format!(r#"current":"{}"#, path_to_url(&uri!(render_feed_followers(feed.name.clone(), Some(2)))))));
+        assert!(s.contains(r#""orderedItems":["https://activitypub.pizza/users/colin24","#));
+        assert!(s.contains(r#""https://activitypub.pizza/users/colin15"]"#));
+        assert!(s.contains(&format!(r#"id":"{}"#, feed.followers_page_url(PageCursor { max_id: Some(25), min_id: None }))));
+        assert!(s.contains(&format!(r#"partOf":"{}"#, feed.followers_url())));
+        assert!(s.contains(&format!(r#"prev":"{}"#, feed.followers_page_url(PageCursor { max_id: None, min_id: Some(24) }))));
+        assert!(s.contains(&format!(r#"next":"{}"#, feed.followers_page_url(PageCursor { max_id: Some(15), min_id: None }))));
 
         Ok(())
       },
@@ -2050,36 +4207,68 @@ mod test {
 
   #[sqlx::test]
   async fn test_outbox_paged(pool: PgPool) -> Result<(), DeliveryError> {
+    use crate::models::feed::PageCursor;
+
     let feed:Feed = real_feed(&pool).await?;
 
     for _i in 1..35 {
       real_item(&feed, &pool).await?;
     }
 
-    let result = feed.outbox_paged(2, &pool).await;
-    match result {
-      Ok(result) => {
-        let s = serde_json::to_string(&result).unwrap();
+    let cursor = PageCursor { max_id: Some(25), min_id: None };
+    let v = serde_json::to_value(feed.outbox_paged(&cursor, &pool).await?).unwrap();
+    let items = v["orderedItems"].as_array().unwrap();
 
-        assert!(s.contains("OrderedCollectionPage"));
-        assert!(s.contains("/items/15"));
-        assert!(s.contains("/items/16"));
-        assert!(s.contains("/items/17"));
-        assert!(s.contains(&format!(r#"first":"{}"#, path_to_url(&uri!(render_feed_outbox(feed.name.clone(), Some(1)))))));
-        assert!(s.contains(&format!(r#"prev":"{}"#, path_to_url(&uri!(render_feed_outbox(feed.name.clone(), Some(1)))))));      
-        assert!(s.contains(&format!(r#"next":"{}"#, path_to_url(&uri!(render_feed_outbox(feed.name.clone(), Some(3)))))));
-        assert!(s.contains(&format!(r#"last":"{}"#, path_to_url(&uri!(render_feed_outbox(feed.name.clone(), Some(4)))))));
-        assert!(s.contains(&format!(r#"current":"{}"#, path_to_url(&uri!(render_feed_outbox(feed.name.clone(), Some(2)))))));
+    // newest items come first
+    assert_eq!(v["type"], "OrderedCollectionPage");
+    assert_eq!(items.len(), 10);
+    assert!(items[0]["id"].as_str().unwrap().contains("/items/24"));
+    assert!(items[9]["id"].as_str().unwrap().contains("/items/15"));
+    assert_eq!(v["id"], feed.outbox_page_url(cursor));
+    assert_eq!(v["partOf"], feed.outbox_url());
+    assert_eq!(v["prev"], feed.outbox_page_url(PageCursor { max_id: None, min_id: Some(24) }));
+    assert_eq!(v["next"], feed.outbox_page_url(PageCursor { max_id: Some(15), min_id: None }));
 
-        Ok(())
-      },
-      Err(why) => Err(why)
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_outbox_paged_min_id(pool: PgPool) -> Result<(), DeliveryError> {
+    use crate::models::feed::PageCursor;
+
+    let feed:Feed = real_feed(&pool).await?;
+
+    for _i in 1..35 {
+      real_item(&feed, &pool).await?;
     }
+
+    // paging back towards the newest items
+    let v = serde_json::to_value(feed.outbox_paged(&PageCursor { max_id: None, min_id: Some(24) }, &pool).await?).unwrap();
+    let items = v["orderedItems"].as_array().unwrap();
+
+    assert_eq!(items.len(), 10);
+    assert!(items[0]["id"].as_str().unwrap().contains("/items/34"));
+    assert!(items[9]["id"].as_str().unwrap().contains("/items/25"));
+    assert!(v["prev"].is_null());
+    assert_eq!(v["next"], feed.outbox_page_url(PageCursor { max_id: Some(25), min_id: None }));
+
+    // the last page holds the oldest items
+    let v = serde_json::to_value(feed.outbox_paged(&PageCursor { max_id: None, min_id: Some(0) }, &pool).await?).unwrap();
+    let items = v["orderedItems"].as_array().unwrap();
+
+    assert_eq!(items.len(), 10);
+    assert!(items[0]["id"].as_str().unwrap().contains("/items/10"));
+    assert!(items[9]["id"].as_str().unwrap().ends_with("/items/1"));
+    assert!(v["next"].is_null());
+    assert_eq!(v["prev"], feed.outbox_page_url(PageCursor { max_id: None, min_id: Some(10) }));
+
+    Ok(())
   }
-  
-  
+
   #[sqlx::test]
   async fn test_outbox_paged_direct_status(pool: PgPool) -> Result<(), DeliveryError> {
+    use crate::models::feed::PageCursor;
+
     let mut feed:Feed = real_feed(&pool).await?;
     feed.status_publicity = Some("direct".to_string());
 
@@ -2087,24 +4276,21 @@ mod test {
       real_item(&feed, &pool).await?;
     }
 
-    let result = feed.outbox_paged(2, &pool).await;
+    let result = feed.outbox_paged(&PageCursor::default(), &pool).await;
     match result {
       Ok(result) => {
         let s = serde_json::to_string(&result).unwrap();
 
         assert!(s.contains("OrderedCollectionPage"));
         assert!(!s.contains("/items/15"));
-        assert!(!s.contains("/items/16"));
-        assert!(!s.contains("/items/17"));
-        assert!(s.contains(&format!(r#"first":"{}"#, path_to_url(&uri!(render_feed_outbox(feed.name.clone(), Some(1)))))));
-        assert!(s.contains(&format!(r#"prev":"{}"#, path_to_url(&uri!(render_feed_outbox(feed.name.clone(), Some(1)))))));      
-        assert!(s.contains(&format!(r#"last":"{}"#, path_to_url(&uri!(render_feed_outbox(feed.name.clone(), Some(1)))))));
-        assert!(s.contains(&format!(r#"current":"{}"#, path_to_url(&uri!(render_feed_outbox(feed.name.clone(), Some(2)))))));
+        assert!(!s.contains("/items/34"));
+        assert!(!s.contains(r#""next""#));
+        assert!(s.contains(&format!(r#"partOf":"{}"#, feed.outbox_url())));
 
         Ok(())
       },
       Err(why) => Err(why)
     }
   }
- 
+
 }
@@ -0,0 +1,160 @@
+use sqlx::postgres::PgPool;
+
+use chrono::Utc;
+
+use url::Url;
+
+use crate::models::Feed;
+
+///
+/// Model for an actor or domain which is blocked from following a
+/// specific feed. Instance-wide blocks live in BlockedDomain
+///
+#[derive(Debug, serde::Serialize)]
+pub struct FeedBlock {
+  pub id: i32,
+  pub feed_id: i32,
+  pub kind: String,
+  pub target: String,
+  pub created_at: chrono::DateTime::<Utc>,
+  pub updated_at: chrono::DateTime::<Utc>
+}
+
+impl PartialEq for FeedBlock {
+  fn eq(&self, other: &Self) -> bool {
+    self.id == other.id
+  }
+}
+
+pub const ACTOR: &str = "actor";
+pub const DOMAIN: &str = "domain";
+
+///
+/// Get the domain for an actor URL, if it has one
+///
+fn actor_domain(actor: &str) -> Option<String> {
+  Url::parse(actor)
+    .ok()
+    .and_then(|url| url.host_str().map(|host| host.to_lowercase()))
+}
+
+impl FeedBlock {
+  pub async fn find_for_feed(feed: &Feed, id: i32, pool: &PgPool) -> Result<Option<FeedBlock>, sqlx::Error> {
+    sqlx::query_as!(FeedBlock, "SELECT * FROM feed_blocks WHERE feed_id = $1 AND id = $2", feed.id, id)
+      .fetch_optional(pool)
+      .await
+  }
+
+  pub async fn for_feed(feed: &Feed, pool: &PgPool) -> Result<Vec<FeedBlock>, sqlx::Error> {
+    sqlx::query_as!(FeedBlock, "SELECT * FROM feed_blocks WHERE feed_id = $1 ORDER BY kind, target", feed.id)
+      .fetch_all(pool)
+      .await
+  }
+
+  ///
+  /// Block an actor URL or a domain for the given feed. If we get a
+  /// URL for a domain block, we'll block the host of that URL
+  ///
+  pub async fn create(feed: &Feed, kind: &str, target: &str, pool: &PgPool) -> Result<FeedBlock, sqlx::Error> {
+    let now = Utc::now();
+    let target = if kind == DOMAIN {
+      actor_domain(target.trim()).unwrap_or_else(|| target.trim().to_lowercase())
+    } else {
+      target.trim().to_string()
+    };
+
+    sqlx::query_as!(FeedBlock, "INSERT INTO feed_blocks
+        (feed_id, kind, target, created_at, updated_at)
+        VALUES($1, $2, $3, $4, $5)
+        ON CONFLICT (feed_id, kind, target) DO UPDATE
+        SET updated_at = EXCLUDED.updated_at
+        RETURNING *",
+        feed.id, kind, target, now, now)
+      .fetch_one(pool)
+      .await
+  }
+
+  pub async fn delete(&self, pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM feed_blocks WHERE id = $1", self.id)
+      .execute(pool)
+      .await?;
+
+    Ok(())
+  }
+
+  ///
+  /// Check if this block applies to the given actor
+  ///
+  pub fn matches(&self, actor: &str) -> bool {
+    match self.kind.as_str() {
+      ACTOR => self.target == actor,
+      DOMAIN => actor_domain(actor).is_some_and(|domain| domain == self.target),
+      _ => false
+    }
+  }
+
+  ///
+  /// Check if the actor is blocked from following the feed
+  ///
+  pub async fn is_blocked(feed: &Feed, actor: &str, pool: &PgPool) -> Result<bool, sqlx::Error> {
+    let domain = actor_domain(actor);
+    let result = sqlx::query!("SELECT COUNT(1) AS tally FROM feed_blocks
+      WHERE feed_id = $1 AND ((kind = $2 AND target = $3) OR (kind = $4 AND target = $5))",
+      feed.id, ACTOR, actor, DOMAIN, domain)
+      .fetch_one(pool)
+      .await?;
+
+    Ok(result.tally.unwrap() > 0)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use sqlx::postgres::PgPool;
+
+  use crate::models::Feed;
+  use crate::models::FeedBlock;
+  use crate::models::feed_block::{ACTOR, DOMAIN};
+  use crate::utils::test_helpers::real_feed;
+
+  #[sqlx::test]
+  async fn test_is_blocked(pool: PgPool) -> sqlx::Result<()> {
+    let feed: Feed = real_feed(&pool).await?;
+    let other: Feed = real_feed(&pool).await?;
+
+    assert!(!FeedBlock::is_blocked(&feed, "https://activitypub.pizza/users/colin", &pool).await?);
+
+    FeedBlock::create(&feed, ACTOR, "https://activitypub.pizza/users/colin", &pool).await?;
+    FeedBlock::create(&feed, DOMAIN, " Bad.Place ", &pool).await?;
+
+    assert!(FeedBlock::is_blocked(&feed, "https://activitypub.pizza/users/colin", &pool).await?);
+    assert!(!FeedBlock::is_blocked(&feed, "https://activitypub.pizza/users/other", &pool).await?);
+    assert!(FeedBlock::is_blocked(&feed, "https://bad.place/users/anyone", &pool).await?);
+
+    // blocks only apply to the one feed
+    assert!(!FeedBlock::is_blocked(&other, "https://bad.place/users/anyone", &pool).await?);
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_matches(pool: PgPool) -> sqlx::Result<()> {
+    let feed: Feed = real_feed(&pool).await?;
+    let block = FeedBlock::create(&feed, DOMAIN, "bad.place", &pool).await?;
+
+    assert!(block.matches("https://bad.place/users/anyone"));
+    assert!(!block.matches("https://good.place/users/anyone"));
+
+    let block = FeedBlock::create(&feed, DOMAIN, "https://Other.Place/users/someone", &pool).await?;
+    assert_eq!(block.target, "other.place");
+    block.delete(&pool).await?;
+
+    let block = FeedBlock::for_feed(&feed, &pool).await?.pop().unwrap();
+    assert!(!block.matches("https://good.place/users/anyone"));
+
+    block.delete(&pool).await?;
+    assert!(FeedBlock::for_feed(&feed, &pool).await?.is_empty());
+
+    Ok(())
+  }
+}
@@ -0,0 +1,184 @@
+use sqlx::postgres::PgPool;
+
+use chrono::Utc;
+
+use crate::models::Feed;
+use crate::models::User;
+
+///
+/// Model for someone who helps look after a feed. Owners can do
+/// anything, including managing the other members. Editors can change
+/// settings and deal with followers, and viewers can look but not touch
+///
+#[derive(Debug, serde::Serialize)]
+pub struct FeedMember {
+  pub id: i32,
+  pub feed_id: i32,
+  pub user_id: i32,
+  pub role: String,
+  pub created_at: chrono::DateTime::<Utc>,
+  pub updated_at: chrono::DateTime::<Utc>
+}
+
+impl PartialEq for FeedMember {
+  fn eq(&self, other: &Self) -> bool {
+    self.id == other.id
+  }
+}
+
+///
+/// A member along with their fediverse name, for the feed page
+///
+#[derive(Debug, serde::Serialize)]
+pub struct FeedMemberDetails {
+  pub id: i32,
+  pub user_id: i32,
+  pub role: String,
+  pub username: Option<String>,
+  pub actor_url: Option<String>,
+  pub created_at: chrono::DateTime::<Utc>
+}
+
+pub const OWNER: &str = "owner";
+pub const EDITOR: &str = "editor";
+pub const VIEWER: &str = "viewer";
+
+/// What we tell someone who tries to leave a feed without an owner
+pub const LAST_OWNER_ERROR: &str = "A feed needs at least one owner!";
+
+/// Roles from most to least powerful
+pub const ROLES: [&str; 3] = [OWNER, EDITOR, VIEWER];
+
+///
+/// Get the roles that are allowed to do anything the given role can do
+///
+pub fn roles_at_least(role: &str) -> Vec<String> {
+  let position = ROLES.iter().position(|r| *r == role).unwrap_or(0);
+  ROLES[..=position].iter().map(|r| r.to_string()).collect()
+}
+
+///
+/// Check if someone with the given role can do things that need the
+/// required role
+///
+pub fn role_allows(role: &str, required: &str) -> bool {
+  roles_at_least(required).iter().any(|r| r == role)
+}
+
+impl FeedMember {
+  pub async fn find(feed: &Feed, id: i32, pool: &PgPool) -> Result<Option<FeedMember>, sqlx::Error> {
+    sqlx::query_as!(FeedMember, "SELECT * FROM feed_members WHERE feed_id = $1 AND id = $2", feed.id, id)
+      .fetch_optional(pool)
+      .await
+  }
+
+  ///
+  /// Get the user's role on the feed, if they have one
+  ///
+  pub async fn role_for(feed: &Feed, user: &User, pool: &PgPool) -> Result<Option<String>, sqlx::Error> {
+    let result = sqlx::query!("SELECT role FROM feed_members WHERE feed_id = $1 AND user_id = $2", feed.id, user.id)
+      .fetch_optional(pool)
+      .await?;
+
+    Ok(result.map(|r| r.role))
+  }
+
+  pub async fn details_for_feed(feed: &Feed, pool: &PgPool) -> Result<Vec<FeedMemberDetails>, sqlx::Error> {
+    sqlx::query_as!(FeedMemberDetails, "SELECT feed_members.id, feed_members.user_id, feed_members.role,
+        users.username, users.actor_url, feed_members.created_at
+      FROM feed_members
+      INNER JOIN users ON feed_members.user_id = users.id
+      WHERE feed_members.feed_id = $1
+      ORDER BY feed_members.id", feed.id)
+      .fetch_all(pool)
+      .await
+  }
+
+  pub async fn owner_count(feed: &Feed, pool: &PgPool) -> Result<i64, sqlx::Error> {
+    let result = sqlx::query!("SELECT COUNT(1) AS tally FROM feed_members WHERE feed_id = $1 AND role = $2", feed.id, OWNER)
+      .fetch_one(pool)
+      .await?;
+
+    Ok(result.tally.unwrap_or(0))
+  }
+
+  ///
+  /// Add the user to the feed with the given role. If they're already a
+  /// member, their role is updated instead
+  ///
+  pub async fn add(feed: &Feed, user: &User, role: &str, pool: &PgPool) -> Result<FeedMember, sqlx::Error> {
+    let now = Utc::now();
+
+    sqlx::query_as!(FeedMember, "INSERT INTO feed_members (feed_id, user_id, role, created_at, updated_at)
+        VALUES($1, $2, $3, $4, $5)
+        ON CONFLICT (feed_id, user_id) DO UPDATE SET role = EXCLUDED.role, updated_at = EXCLUDED.updated_at
+        RETURNING *",
+        feed.id, user.id, role, now, now)
+      .fetch_one(pool)
+      .await
+  }
+
+  ///
+  /// Remove the member from the feed. If they created the feed, it's
+  /// handed over to the longest-serving owner who is left
+  ///
+  pub async fn delete(&self, pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM feed_members WHERE id = $1", self.id)
+      .execute(pool)
+      .await?;
+
+    sqlx::query!("UPDATE feeds SET user_id = (SELECT user_id FROM feed_members WHERE feed_id = $1 AND role = $2 ORDER BY id LIMIT 1)
+        WHERE id = $1 AND user_id = $3
+        AND EXISTS (SELECT 1 FROM feed_members WHERE feed_id = $1 AND role = $2)",
+        self.feed_id, OWNER, self.user_id)
+      .execute(pool)
+      .await?;
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use sqlx::postgres::PgPool;
+
+  use crate::models::{Feed, FeedMember, User};
+  use crate::models::feed_member::{role_allows, EDITOR, OWNER, VIEWER};
+  use crate::utils::test_helpers::real_feed;
+
+  #[test]
+  fn test_role_allows() {
+    assert!(role_allows(OWNER, EDITOR));
+    assert!(role_allows(EDITOR, EDITOR));
+    assert!(role_allows(EDITOR, VIEWER));
+    assert!(!role_allows(VIEWER, EDITOR));
+    assert!(!role_allows(EDITOR, OWNER));
+    assert!(!role_allows("intruder", VIEWER));
+  }
+
+  #[sqlx::test]
+  async fn test_add_and_delete(pool: PgPool) -> sqlx::Result<()> {
+    let feed: Feed = real_feed(&pool).await?;
+    let owner = feed.user(&pool).await?;
+    assert_eq!(FeedMember::role_for(&feed, &owner, &pool).await?, Some(OWNER.to_string()));
+
+    let other = User::find_or_create_by_actor_url(&"https://activitypub.pizza/users/colin".to_string(), &pool).await?;
+    assert_eq!(FeedMember::role_for(&feed, &other, &pool).await?, None);
+
+    FeedMember::add(&feed, &other, VIEWER, &pool).await?;
+    let member = FeedMember::add(&feed, &other, OWNER, &pool).await?;
+    assert_eq!(FeedMember::role_for(&feed, &other, &pool).await?, Some(OWNER.to_string()));
+    assert_eq!(FeedMember::details_for_feed(&feed, &pool).await?.len(), 2);
+    assert_eq!(FeedMember::owner_count(&feed, &pool).await?, 2);
+
+    // removing the creator hands the feed to the remaining owner
+    let creator = FeedMember::details_for_feed(&feed, &pool).await?.remove(0);
+    FeedMember::find(&feed, creator.id, &pool).await?.unwrap().delete(&pool).await?;
+
+    let feed = Feed::find(feed.id, &pool).await?;
+    assert_eq!(feed.user_id, other.id);
+    assert_eq!(FeedMember::find(&feed, member.id, &pool).await?, Some(member));
+
+    Ok(())
+  }
+}
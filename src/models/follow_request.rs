@@ -0,0 +1,168 @@
+use sqlx::postgres::PgPool;
+
+use chrono::{Duration, Utc};
+
+use std::{
+  env,
+  str::FromStr
+};
+
+use crate::models::Feed;
+
+///
+/// Model for a pending follow of a feed which requires approval from
+/// the owner
+///
+#[derive(Debug, serde::Serialize)]
+pub struct FollowRequest {
+  pub id: i32,
+  pub feed_id: i32,
+  pub actor: String,
+  pub activity_id: String,
+  pub created_at: chrono::DateTime::<Utc>,
+  pub updated_at: chrono::DateTime::<Utc>
+}
+
+impl PartialEq for FollowRequest {
+  fn eq(&self, other: &Self) -> bool {
+    self.id == other.id
+  }
+}
+
+const FOLLOW_REQUEST_EXPIRY: i64 = 1209600;
+
+///
+/// How many seconds a follow request can sit around before we drop it
+///
+pub fn follow_request_expiry() -> i64 {
+  match env::var_os("FOLLOW_REQUEST_EXPIRY") {
+    Some(val) => {
+      i64::from_str(&val.into_string().expect("Something went wrong setting the follow request expiry")).unwrap()
+    }
+    None => FOLLOW_REQUEST_EXPIRY
+  }
+}
+
+impl FollowRequest {
+  pub async fn find(id: i32, pool: &PgPool) -> Result<Option<FollowRequest>, sqlx::Error> {
+    sqlx::query_as!(FollowRequest, "SELECT * FROM follow_requests WHERE id = $1", id)
+      .fetch_optional(pool)
+      .await
+  }
+
+  pub async fn find_for_feed(feed: &Feed, id: i32, pool: &PgPool) -> Result<Option<FollowRequest>, sqlx::Error> {
+    sqlx::query_as!(FollowRequest, "SELECT * FROM follow_requests WHERE feed_id = $1 AND id = $2", feed.id, id)
+      .fetch_optional(pool)
+      .await
+  }
+
+  ///
+  /// Get all the pending follow requests for a feed, oldest first
+  ///
+  pub async fn for_feed(feed: &Feed, pool: &PgPool) -> Result<Vec<FollowRequest>, sqlx::Error> {
+    sqlx::query_as!(FollowRequest, "SELECT * FROM follow_requests WHERE feed_id = $1 ORDER BY id", feed.id)
+      .fetch_all(pool)
+      .await
+  }
+
+  ///
+  /// Store a follow request. If the actor asks again, we'll hang onto
+  /// the latest activity id and reset the expiry
+  ///
+  pub async fn create(feed: &Feed, actor: &str, activity_id: &str, pool: &PgPool) -> Result<(), sqlx::Error> {
+    let now = Utc::now();
+
+    sqlx::query!("INSERT INTO follow_requests
+        (feed_id, actor, activity_id, created_at, updated_at)
+        VALUES($1, $2, $3, $4, $5)
+        ON CONFLICT (feed_id, actor) DO UPDATE
+        SET activity_id = EXCLUDED.activity_id,
+          created_at = EXCLUDED.created_at,
+          updated_at = EXCLUDED.updated_at",
+        feed.id, actor, activity_id, now, now)
+      .execute(pool)
+      .await?;
+
+    Ok(())
+  }
+
+  pub async fn delete(&self, pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM follow_requests WHERE id = $1", self.id)
+      .execute(pool)
+      .await?;
+
+    Ok(())
+  }
+
+  ///
+  /// Remove a pending request, if the actor has one
+  ///
+  pub async fn delete_for_actor(feed: &Feed, actor: &str, pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM follow_requests WHERE feed_id = $1 AND actor = $2", feed.id, actor)
+      .execute(pool)
+      .await?;
+
+    Ok(())
+  }
+
+  ///
+  /// Drop any requests older than the given number of seconds
+  ///
+  pub async fn cleanup(pool: &PgPool, age: i64) -> Result<(), sqlx::Error> {
+    let age = Utc::now() - Duration::seconds(age);
+
+    sqlx::query!("DELETE FROM follow_requests WHERE created_at <= $1", age)
+      .execute(pool)
+      .await?;
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use sqlx::postgres::PgPool;
+  use chrono::{Duration, Utc};
+
+  use crate::models::Feed;
+  use crate::models::FollowRequest;
+  use crate::utils::test_helpers::real_feed;
+
+  #[sqlx::test]
+  async fn test_create(pool: PgPool) -> sqlx::Result<()> {
+    let feed: Feed = real_feed(&pool).await?;
+    let actor = "https://activitypub.pizza/users/colin";
+
+    FollowRequest::create(&feed, actor, "https://activitypub.pizza/follows/1", &pool).await?;
+    FollowRequest::create(&feed, actor, "https://activitypub.pizza/follows/2", &pool).await?;
+
+    let requests = FollowRequest::for_feed(&feed, &pool).await?;
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].activity_id, "https://activitypub.pizza/follows/2");
+
+    FollowRequest::delete_for_actor(&feed, actor, &pool).await?;
+    assert!(FollowRequest::for_feed(&feed, &pool).await?.is_empty());
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_cleanup(pool: PgPool) -> sqlx::Result<()> {
+    let feed: Feed = real_feed(&pool).await?;
+    let old = Utc::now() - Duration::seconds(10000);
+
+    FollowRequest::create(&feed, "https://activitypub.pizza/users/new", "https://activitypub.pizza/follows/1", &pool).await?;
+    sqlx::query!("INSERT INTO follow_requests (feed_id, actor, activity_id, created_at, updated_at) VALUES($1, $2, $3, $4, $5)",
+      feed.id, "https://activitypub.pizza/users/old", "https://activitypub.pizza/follows/2", old, old)
+      .execute(&pool)
+      .await?;
+
+    FollowRequest::cleanup(&pool, 5000).await?;
+
+    let requests = FollowRequest::for_feed(&feed, &pool).await?;
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].actor, "https://activitypub.pizza/users/new");
+
+    Ok(())
+  }
+}
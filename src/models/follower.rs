@@ -1,7 +1,9 @@
 use sqlx::postgres::PgPool;
 
 use crate::models::Actor;
+use crate::models::Feed;
 use crate::DeliveryError;
+use crate::PER_PAGE;
 
 use chrono::Utc;
 
@@ -17,6 +19,18 @@ pub struct Follower {
   pub updated_at: chrono::DateTime::<Utc>
 }
 
+///
+/// Follower data for the owner's followers page, including the username
+/// from the actor cache if we have it
+///
+#[derive(Debug, serde::Serialize)]
+pub struct FollowerDetails {
+  pub id: i32,
+  pub actor: String,
+  pub username: Option<String>,
+  pub created_at: chrono::DateTime::<Utc>
+}
+
 impl PartialEq for Follower {
   fn eq(&self, other: &Self) -> bool {
     self.id == other.id
@@ -30,6 +44,71 @@ impl Follower {
       .await
   }
 
+  pub async fn find_for_feed(feed: &Feed, id: i32, pool: &PgPool) -> Result<Option<Follower>, sqlx::Error> {
+    sqlx::query_as!(Follower, "SELECT * FROM followers WHERE feed_id = $1 AND id = $2", feed.id, id)
+      .fetch_optional(pool)
+      .await
+  }
+
+  ///
+  /// Get a page of followers for a feed, newest first
+  ///
+  pub async fn details_for_feed(feed: &Feed, page: i32, pool: &PgPool) -> Result<Vec<FollowerDetails>, sqlx::Error> {
+    let offset:i64 = ((page - 1) * PER_PAGE) as i64;
+
+    sqlx::query_as!(FollowerDetails, r#"SELECT followers.id, followers.actor, actors.username AS "username?", followers.created_at
+      FROM followers
+      LEFT JOIN actors ON followers.actor = actors.url
+      WHERE followers.feed_id = $1
+      ORDER BY followers.id DESC
+      LIMIT $2 OFFSET $3"#, feed.id, PER_PAGE as i64, offset)
+      .fetch_all(pool)
+      .await
+  }
+
+  ///
+  /// Get a page of a feed's followers, newest first. max_id gets the
+  /// followers older than the given id, min_id the ones newer than it
+  ///
+  pub async fn page_for_feed(feed: &Feed, max_id: Option<i32>, min_id: Option<i32>, limit: i64, pool: &PgPool) -> Result<Vec<Follower>, sqlx::Error> {
+    if let (None, Some(min_id)) = (max_id, min_id) {
+      let mut followers = sqlx::query_as!(Follower, "SELECT * FROM followers WHERE feed_id = $1 AND id > $2 ORDER BY id ASC LIMIT $3",
+        feed.id, min_id, limit)
+        .fetch_all(pool)
+        .await?;
+
+      followers.reverse();
+      return Ok(followers)
+    }
+
+    sqlx::query_as!(Follower, "SELECT * FROM followers WHERE feed_id = $1 AND ($2::int IS NULL OR id < $2) ORDER BY id DESC LIMIT $3",
+      feed.id, max_id, limit)
+      .fetch_all(pool)
+      .await
+  }
+
+  ///
+  /// Remove the follower from their feed
+  ///
+  pub async fn delete(&self, pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM followers WHERE feed_id = $1 AND id = $2", self.feed_id, self.id)
+      .execute(pool)
+      .await?;
+
+    Ok(())
+  }
+
+  ///
+  /// Remove the given actor from the followers of every feed
+  ///
+  pub async fn delete_for_actor(actor: &str, pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM followers WHERE actor = $1", actor)
+      .execute(pool)
+      .await?;
+
+    Ok(())
+  }
+
   ///
   /// Ping the actor's profile data to get their inbox
   ///
@@ -55,7 +134,23 @@ mod test {
   use sqlx::postgres::PgPool;
   use crate::models::Feed;
   use crate::models::Follower;
-  use crate::utils::test_helpers::{fake_feed, fake_follower};
+  use crate::utils::test_helpers::{fake_feed, fake_follower, real_feed};
+
+  #[sqlx::test]
+  async fn test_delete(pool: PgPool) -> Result<(), String> {
+    let feed: Feed = real_feed(&pool).await.unwrap();
+    feed.add_follower(&pool, "https://activitypub.pizza/users/colin").await.unwrap();
+    feed.add_follower(&pool, "https://activitypub.pizza/users/muffin").await.unwrap();
+
+    let followers = feed.followers_list(&pool).await.unwrap();
+    followers[0].delete(&pool).await.unwrap();
+
+    let remaining = feed.followers_list(&pool).await.unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0], followers[1]);
+
+    Ok(())
+  }
 
   #[sqlx::test]
   async fn test_find_inbox(pool: PgPool) -> Result<(), String> {
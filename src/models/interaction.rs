@@ -0,0 +1,183 @@
+use sqlx::postgres::PgPool;
+
+use chrono::Utc;
+
+use crate::models::Feed;
+use crate::models::Item;
+
+///
+/// Model for a remote interaction with a feed -- a like, a boost or a reply.
+/// We hold onto these so that we can remove them again if the remote actor
+/// sends an Undo or a Delete
+///
+#[derive(Debug)]
+pub struct Interaction {
+  pub id: i32,
+  pub feed_id: i32,
+  pub item_id: Option<i32>,
+  pub actor: String,
+  pub activity_id: Option<String>,
+  pub object_id: String,
+  pub kind: String,
+  pub content: Option<String>,
+  pub created_at: chrono::DateTime::<Utc>,
+  pub updated_at: chrono::DateTime::<Utc>
+}
+
+impl PartialEq for Interaction {
+  fn eq(&self, other: &Self) -> bool {
+    self.id == other.id
+  }
+}
+
+pub const LIKE: &str = "like";
+pub const ANNOUNCE: &str = "announce";
+pub const REPLY: &str = "reply";
+
+impl Interaction {
+  pub async fn find(id: i32, pool: &PgPool) -> Result<Option<Interaction>, sqlx::Error> {
+    sqlx::query_as!(Interaction, "SELECT * FROM interactions WHERE id = $1", id)
+      .fetch_optional(pool)
+      .await
+  }
+
+  ///
+  /// Get all the interactions of the given kind for an item
+  ///
+  pub async fn for_item(item: &Item, kind: &str, pool: &PgPool) -> Result<Vec<Interaction>, sqlx::Error> {
+    sqlx::query_as!(Interaction, "SELECT * FROM interactions WHERE item_id = $1 AND kind = $2 ORDER BY id", item.id, kind)
+      .fetch_all(pool)
+      .await
+  }
+
+  ///
+  /// Get a count of interactions of the given kind for this feed
+  ///
+  pub async fn count_for_feed(feed: &Feed, kind: &str, pool: &PgPool) -> Result<i32, sqlx::Error> {
+    let result = sqlx::query!("SELECT COUNT(1) AS tally FROM interactions WHERE feed_id = $1 AND kind = $2", feed.id, kind)
+      .fetch_one(pool)
+      .await;
+
+    match result {
+      Ok(result) => Ok(result.tally.unwrap() as i32),
+      Err(why) => Err(why)
+    }
+  }
+
+  ///
+  /// Store an interaction. If we've already seen it, just bump the timestamp
+  ///
+  #[allow(clippy::too_many_arguments)]
+  pub async fn create(feed: &Feed,
+    item: Option<&Item>,
+    actor: &str,
+    activity_id: Option<String>,
+    object_id: &str,
+    kind: &str,
+    content: Option<String>,
+    pool: &PgPool) -> Result<(), sqlx::Error> {
+
+    let now = Utc::now();
+    let item_id = item.map(|item| item.id);
+
+    sqlx::query!("INSERT INTO interactions
+        (feed_id, item_id, actor, activity_id, object_id, kind, content, created_at, updated_at)
+        VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        ON CONFLICT (feed_id, actor, object_id, kind) DO UPDATE
+        SET activity_id = EXCLUDED.activity_id,
+          content = EXCLUDED.content,
+          updated_at = EXCLUDED.updated_at",
+        feed.id, item_id, actor, activity_id, object_id, kind, content, now, now)
+      .execute(pool)
+      .await?;
+
+    Ok(())
+  }
+
+  ///
+  /// Remove an interaction in response to an Undo. We match on the
+  /// id of the original activity if we have it, otherwise on the object
+  ///
+  pub async fn undo(feed: &Feed, actor: &str, activity_id: Option<String>, object_id: Option<String>, kind: &str, pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM interactions
+      WHERE feed_id = $1 AND actor = $2 AND kind = $3 AND (activity_id = $4 OR object_id = $5)",
+      feed.id, actor, kind, activity_id, object_id)
+      .execute(pool)
+      .await?;
+
+    Ok(())
+  }
+
+  ///
+  /// Remove a reply that the actor has deleted
+  ///
+  pub async fn delete_by_object(actor: &str, object_id: &str, pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM interactions WHERE actor = $1 AND object_id = $2 AND kind = $3", actor, object_id, REPLY)
+      .execute(pool)
+      .await?;
+
+    Ok(())
+  }
+
+  ///
+  /// Remove everything we know about the given actor
+  ///
+  pub async fn delete_for_actor(actor: &str, pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM interactions WHERE actor = $1", actor)
+      .execute(pool)
+      .await?;
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use sqlx::postgres::PgPool;
+
+  use crate::models::Feed;
+  use crate::models::Item;
+  use crate::models::Interaction;
+  use crate::models::interaction::{LIKE, REPLY};
+  use crate::utils::test_helpers::{real_feed, real_item};
+
+  #[sqlx::test]
+  async fn test_create_and_undo(pool: PgPool) -> sqlx::Result<()> {
+    let feed: Feed = real_feed(&pool).await?;
+    let item: Item = real_item(&feed, &pool).await?;
+    let actor = "https://activitypub.pizza/users/colin";
+    let object_id = format!("{}/items/{}", feed.ap_url(), item.id);
+    let like_id = "https://activitypub.pizza/likes/1".to_string();
+
+    Interaction::create(&feed, Some(&item), actor, Some(like_id.clone()), &object_id, LIKE, None, &pool).await?;
+
+    // storing the same like twice shouldn't duplicate it
+    Interaction::create(&feed, Some(&item), actor, Some(like_id.clone()), &object_id, LIKE, None, &pool).await?;
+    assert_eq!(Interaction::for_item(&item, LIKE, &pool).await?.len(), 1);
+
+    Interaction::undo(&feed, actor, Some(like_id), None, LIKE, &pool).await?;
+    assert_eq!(Interaction::for_item(&item, LIKE, &pool).await?.len(), 0);
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_delete_by_object(pool: PgPool) -> sqlx::Result<()> {
+    let feed: Feed = real_feed(&pool).await?;
+    let item: Item = real_item(&feed, &pool).await?;
+    let actor = "https://activitypub.pizza/users/colin";
+    let note_id = "https://activitypub.pizza/notes/1";
+
+    Interaction::create(&feed, Some(&item), actor, None, note_id, REPLY, Some("hi!".to_string()), &pool).await?;
+    assert_eq!(Interaction::count_for_feed(&feed, REPLY, &pool).await?, 1);
+
+    // only the author can delete their reply
+    Interaction::delete_by_object("https://activitypub.pizza/users/other", note_id, &pool).await?;
+    assert_eq!(Interaction::count_for_feed(&feed, REPLY, &pool).await?, 1);
+
+    Interaction::delete_by_object(actor, note_id, &pool).await?;
+    assert_eq!(Interaction::count_for_feed(&feed, REPLY, &pool).await?, 0);
+
+    Ok(())
+  }
+}
@@ -3,12 +3,17 @@ use serde::Serialize;
 use feed_rs::model::Entry;
 
 use crate::models::Actor;
+use crate::models::DmSubscriber;
 use crate::models::Enclosure;
 use crate::models::Feed;
+use crate::models::feed::{OBJECT_TYPE_ARTICLE, OBJECT_TYPE_AUTO};
+use crate::models::quota::DeliveryBudget;
+use crate::models::Relay;
 use crate::traits::content_map::*;
 
 use crate::routes::enclosures::*;
 
+use crate::services::fediverse_creator::{fetch_fediverse_creator, find_creator_url};
 use crate::utils::path_to_url;
 use crate::DeliveryError;
 
@@ -19,8 +24,10 @@ use activitystreams::iri;
 use activitystreams::base::BaseExt;
 use activitystreams::base::ExtendsExt;
 use activitystreams::object::ObjectExt;
-use activitystreams::link::Mention;
+use activitystreams::link::{Link, Mention};
 use activitystreams::link::LinkExt;
+use activitystreams::link::kind::LinkType;
+use activitystreams::actor::Person;
 use activitystreams::time::OffsetDateTime;
 
 use crate::activitystreams::Hashtag;
@@ -44,9 +51,28 @@ use chrono::{Duration, Utc};
 use rocket::uri;
 
 use std::env;
+use std::str::FromStr;
 
 use activitystreams::mime::Mime;
 
+const ARTICLE_LENGTH_THRESHOLD: usize = 1500;
+
+/// How many characters of text to include in an Article's summary
+const EXCERPT_LENGTH: usize = 400;
+
+///
+/// Number of characters of text an item needs before feeds in auto mode
+/// send it as an Article
+///
+pub fn article_length_threshold() -> usize {
+  match env::var_os("ARTICLE_LENGTH_THRESHOLD") {
+    Some(val) => {
+      usize::from_str(&val.into_string().expect("Something went wrong setting the article length threshold")).unwrap()
+    }
+    None => ARTICLE_LENGTH_THRESHOLD
+  }
+}
+
 
 ///
 /// Model for an item, which is the equivalent of an entry in an rss feed
@@ -60,11 +86,36 @@ pub struct Item {
   pub content: Option<String>,
   pub url: Option<String>,
   pub language: Option<String>,
+
+  pub author: Option<String>,
+  pub fediverse_creator: Option<String>,
+  pub fediverse_creator_url: Option<String>,
   
   pub created_at: chrono::DateTime::<Utc>,
   pub updated_at: chrono::DateTime::<Utc>
 }
 
+fn entry_title(entry: &Entry) -> Option<String> {
+  entry.title.as_ref().map(|title| title.content.clone())
+}
+
+fn entry_body(entry: &Entry) -> Option<String> {
+  // default to summary if we have it
+  if let Some(summary) = &entry.summary {
+    Some(sanitize_string(&summary.content))
+  } else {
+    entry.content.as_ref()
+      .and_then(|content| content.body.as_ref())
+      .map(|body| sanitize_string(body))
+  }
+}
+
+fn entry_author(entry: &Entry) -> Option<String> {
+  entry.authors.first()
+    .map(|author| author.name.trim().to_string())
+    .filter(|name| !name.is_empty())
+}
+
 // NOTE: mastodon is going to allow: del, pre, blockquote, code, b, strong, u, i, em, ul, ol, li
 fn sanitize_string(input: &str) -> String {
   // relaxed or basic makes sense here probably
@@ -91,6 +142,64 @@ impl Item {
     .await
   }
 
+  ///
+  /// Given the ActivityPub id of an item, ie https://domain/feed/name/items/123,
+  /// find the matching item in this feed
+  ///
+  pub async fn find_by_feed_and_ap_url(feed: &Feed, url: &str, pool: &PgPool) -> Result<Option<Item>, sqlx::Error> {
+    let prefix = format!("{}/items/", feed.ap_url());
+    let id = url
+      .strip_prefix(&prefix)
+      .and_then(|id| id.parse::<i32>().ok());
+
+    match id {
+      Some(id) => Item::find_by_feed_and_id(feed, id, pool).await,
+      None => Ok(None)
+    }
+  }
+
+  ///
+  /// Get a page of a feed's items, newest first. max_id gets the items
+  /// older than the given one, min_id the ones newer than it. Items are
+  /// sorted by when they were published, with the id breaking ties, so
+  /// pages stay put as new items come in
+  ///
+  pub async fn page_for_feed(feed: &Feed, max_id: Option<i32>, min_id: Option<i32>, limit: i64, pool: &PgPool) -> Result<Vec<Item>, sqlx::Error> {
+    if let (None, Some(min_id)) = (max_id, min_id) {
+      // if the cursor is gone, start from the oldest item
+      let cursor = Item::find_by_feed_and_id(feed, min_id, pool).await?;
+      let (created_at, id) = match &cursor {
+        Some(cursor) => (Some(cursor.created_at), cursor.id),
+        None => (None, 0)
+      };
+
+      let mut items = sqlx::query_as!(Item, "SELECT * FROM items
+        WHERE feed_id = $1 AND ($2::timestamptz IS NULL OR (created_at, id) > ($2, $3))
+        ORDER BY created_at ASC, id ASC LIMIT $4",
+        feed.id, created_at, id, limit)
+        .fetch_all(pool)
+        .await?;
+
+      items.reverse();
+      return Ok(items)
+    }
+
+    let (created_at, id) = match max_id {
+      Some(max_id) => match Item::find_by_feed_and_id(feed, max_id, pool).await? {
+        Some(cursor) => (Some(cursor.created_at), cursor.id),
+        None => return Ok(Vec::new())
+      },
+      None => (None, 0)
+    };
+
+    sqlx::query_as!(Item, "SELECT * FROM items
+      WHERE feed_id = $1 AND ($2::timestamptz IS NULL OR (created_at, id) < ($2, $3))
+      ORDER BY created_at DESC, id DESC LIMIT $4",
+      feed.id, created_at, id, limit)
+      .fetch_all(pool)
+      .await
+  }
+
   pub async fn find_by_guid(guid: &String, feed: &Feed, pool: &PgPool) -> Result<Item, sqlx::Error> {
     sqlx::query_as!(Item, "SELECT * FROM items WHERE feed_id = $1 AND guid = $2", feed.id, guid)
     .fetch_one(pool)
@@ -115,25 +224,9 @@ impl Item {
   }
 
   pub async fn create_from_entry(entry: &Entry, feed: &Feed, pool: &PgPool) -> Result<Item, sqlx::Error> {
-    let title = if entry.title.is_some() {
-      Some(&entry.title.as_ref().unwrap().content)
-    } else {
-      None
-    };
-
-    let clean_body;
-
-    // default to summary if we have it
-    let body = if entry.summary.is_some() {
-      clean_body = sanitize_string(&entry.summary.as_ref().unwrap().content);
-      Some(&clean_body)
-    }
-    else if entry.content.is_some() {
-      clean_body = sanitize_string(entry.content.as_ref().unwrap().body.as_ref().unwrap());
-      Some(&clean_body)
-    } else {
-      None
-    };
+    let title = entry_title(entry);
+    let body = entry_body(entry);
+    let author = entry_author(entry);
 
     let item_url = if !entry.links.is_empty() {
       Some(&entry.links[0].href)
@@ -151,10 +244,16 @@ impl Item {
       now
     };
 
+    // updated_at tracks changes to the entry, so it only moves past
+    // created_at if the feed says the entry was edited
+    let updated_at = match entry.updated {
+      Some(updated) if updated > published_at => updated,
+      _ => published_at
+    };
 
     let item_id = sqlx::query!("INSERT INTO items 
-                                (feed_id, guid, title, content, url, language, created_at, updated_at)
-                                VALUES($1, $2, $3, $4, $5, $6, $7, $8)
+                                (feed_id, guid, title, content, url, language, author, created_at, updated_at)
+                                VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9)
                                 RETURNING id",
                                feed.id,
                                entry.id,
@@ -162,8 +261,9 @@ impl Item {
                                body,
                                item_url,
                                entry.language,
+                               author,
                                published_at,
-                               now
+                               updated_at
     )
       .fetch_one(pool)
       .await?
@@ -214,6 +314,55 @@ impl Item {
     Item::find(item_id, pool).await
   }
 
+  ///
+  /// Update the item if the entry has been edited since we last saw it.
+  /// This only changes our copy, followers aren't sent an Update
+  ///
+  pub async fn update_from_entry(&self, entry: &Entry, pool: &PgPool) -> Result<Option<Item>, sqlx::Error> {
+    let updated_at = match entry.updated {
+      Some(updated) if updated > self.updated_at => updated,
+      _ => return Ok(None)
+    };
+
+    sqlx::query!("UPDATE items SET title = $1, content = $2, author = $3, updated_at = $4 WHERE id = $5",
+      entry_title(entry), entry_body(entry), entry_author(entry), updated_at, self.id)
+      .execute(pool)
+      .await?;
+
+    Ok(Some(Item::find(self.id, pool).await?))
+  }
+
+  ///
+  /// Check the original page for a fediverse:creator tag, and if there is
+  /// one, figure out which actor it belongs to so we can mention them
+  ///
+  pub async fn load_fediverse_creator(&mut self, pool: &PgPool) -> Result<(), DeliveryError> {
+    let url = match &self.url {
+      Some(url) if url.starts_with("http") => url.clone(),
+      _ => return Ok(())
+    };
+
+    let handle = match fetch_fediverse_creator(&url).await? {
+      Some(handle) => handle,
+      None => return Ok(())
+    };
+
+    let actor_url = find_creator_url(&handle, pool).await?;
+    if actor_url.is_none() {
+      return Ok(())
+    }
+
+    sqlx::query!("UPDATE items SET fediverse_creator = $1, fediverse_creator_url = $2 WHERE id = $3",
+      handle, actor_url, self.id)
+      .execute(pool)
+      .await?;
+
+    self.fediverse_creator = Some(handle);
+    self.fediverse_creator_url = actor_url;
+
+    Ok(())
+  }
+
 
   ///
   /// generate an HTML-ish version of this item suitable
@@ -242,6 +391,49 @@ impl Item {
     render("ap/feed-item", &context).unwrap()
   }
 
+  ///
+  /// the text of the item, without any markup
+  ///
+  pub fn text_content(&self) -> String {
+    match &self.content {
+      Some(content) => {
+        let text = scraper::Html::parse_fragment(content).root_element().text().collect::<Vec<_>>().join(" ");
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+      },
+      None => String::new()
+    }
+  }
+
+  ///
+  /// a plain text preview of the item, cut off at a word boundary
+  ///
+  pub fn excerpt(&self, length: usize) -> String {
+    let text = self.text_content();
+    if text.chars().count() <= length {
+      return text;
+    }
+
+    let cutoff = text.char_indices().nth(length).map(|(index, _)| index).unwrap_or(text.len());
+    let truncated = &text[..cutoff];
+    let truncated = match truncated.rfind(' ') {
+      Some(index) => &truncated[..index],
+      None => truncated
+    };
+
+    format!("{}…", truncated.trim_end_matches([',', '.', ';', ':']))
+  }
+
+  ///
+  /// check if this item should be sent as an Article instead of a Note
+  ///
+  pub fn publishes_as_article(&self, feed: &Feed) -> bool {
+    match feed.object_type.as_str() {
+      OBJECT_TYPE_ARTICLE => true,
+      OBJECT_TYPE_AUTO => self.text_content().chars().count() > article_length_threshold(),
+      _ => false
+    }
+  }
+
   pub fn language(&self, feed: &Feed) -> String {
     match &self.language {
       Some(l) => l.to_string(),
@@ -250,25 +442,51 @@ impl Item {
   }
 
   ///
-  /// generate an AP version of this item
+  /// fill in the parts of the object that are the same whether it's
+  /// being sent as a Note or an Article
   ///
-  pub async fn to_activity_pub(&self, feed: &Feed, pool: &PgPool) -> Result<ApObject<Create>, DeliveryError> {    
-
+  async fn populate_object<O, I>(&self, object: &mut O, feed: &Feed, pool: &PgPool) -> Result<(), DeliveryError>
+  where O: BaseExt + ObjectExt + AsContentMapExt<I> {
     let feed_url = feed.ap_url();
     let item_url = format!("{}/items/{}", feed_url, self.id);
     let ts = OffsetDateTime::from_unix_timestamp(self.created_at.timestamp()).unwrap();
 
     let content = self.to_html(feed.hashtag.clone()).await;
 
-    let mut note: ContentMapNote = ContentMapNote::new();
-
-    note
+    object
       .set_attributed_to(iri!(feed_url))
       .set_content_language_and_value(self.language(feed), content)
-      .set_url(iri!(feed_url))
       .set_id(iri!(item_url))
       .set_published(ts);
 
+    //
+    // link to the original post, as well as our own copy of it
+    //
+    if let Some(url) = &self.url {
+      let mut link: Link<LinkType> = Link::new();
+      link
+        .set_href(iri!(url))
+        .set_media_type("text/html".parse::<Mime>().unwrap());
+
+      object.add_url(link.into_any_base()?);
+    }
+    object.add_url(iri!(item_url));
+
+    if self.updated_at.timestamp() > self.created_at.timestamp() {
+      object.set_updated(OffsetDateTime::from_unix_timestamp(self.updated_at.timestamp()).unwrap());
+    }
+
+    //
+    // the feed is responsible for the post, but we'll include the
+    // original author as a hint for clients that want to show it
+    //
+    if let Some(author) = &self.author {
+      let mut person = Person::new();
+      person.set_name(author.to_string());
+
+      object.add_attributed_to(person.into_any_base()?);
+    }
+
     let item_publicity = match &feed.status_publicity {
       Some(value) => value.as_str(),
       None => "unlisted"
@@ -281,18 +499,18 @@ impl Item {
       // public items are sent _to_ activitystreams#Public
       // and cc'd to followers
       "public" => { 
-        note
+        object
           .set_to(iri!("https://www.w3.org/ns/activitystreams#Public")) 
           .add_cc(iri!(feed.followers_url()))
       },
 
       // we'll handle some DM logic outside of message generation here
-      "direct" => { &mut note },
-      "followers" => { note.set_to(iri!(feed.followers_url())) },
+      "direct" => { &mut *object },
+      "followers" => { object.set_to(iri!(feed.followers_url())) },
 
       // unlisted/fallback
       _ => { 
-        note
+        object
           .set_to(iri!(feed.followers_url()))
           .add_cc(iri!("https://www.w3.org/ns/activitystreams#Public"))
       },
@@ -303,7 +521,7 @@ impl Item {
     // 
     if feed.content_warning.is_some() {
       let summary = feed.content_warning.as_ref().unwrap();
-      note.set_summary(summary.to_string());
+      object.set_summary(summary.to_string());
     }
 
     //
@@ -323,9 +541,25 @@ impl Item {
         .set_href(iri!(hashtag_url))
         .set_name(output);
   
-      note.add_tag(hashtag.into_any_base()?);  
+      object.add_tag(hashtag.into_any_base()?);  
     }
 
+    //
+    // mention the author if their page told us who they are on the
+    // fediverse. this is skipped for DMs so we don't notify a stranger
+    //
+    if let (Some(handle), Some(creator_url)) = (&self.fediverse_creator, &self.fediverse_creator_url) {
+      if item_publicity != "direct" {
+        let mut mention = Mention::new();
+        mention
+          .set_href(iri!(creator_url))
+          .set_name(handle.to_string());
+
+        object
+          .add_tag(mention.into_any_base()?)
+          .add_cc(iri!(creator_url));
+      }
+    }
 
 
     //
@@ -350,13 +584,49 @@ impl Item {
         attachment.set_summary(enclosure.description.unwrap());
       }
 
-      note.add_attachment(attachment.into_any_base()?);
+      object.add_attachment(attachment.into_any_base()?);
     }
-    
+
+    Ok(())
+  }
+
+  ///
+  /// generate an AP version of this item
+  ///
+  pub async fn to_activity_pub(&self, feed: &Feed, pool: &PgPool) -> Result<ApObject<Create>, DeliveryError> {    
+
+    let feed_url = feed.ap_url();
+    let item_url = format!("{}/items/{}", feed_url, self.id);
+    let ts = OffsetDateTime::from_unix_timestamp(self.created_at.timestamp()).unwrap();
+
+    let object = if self.publishes_as_article(feed) {
+      let mut article: ContentMapArticle = ContentMapArticle::new();
+      self.populate_object(&mut article, feed, pool).await?;
+
+      //
+      // articles get a title and an excerpt for clients that don't show the whole thing. the content warning
+      // takes priority over the excerpt, since it also uses the summary
+      //
+      if let Some(title) = &self.title {
+        article.set_name(title.to_string());
+      }
+
+      if feed.content_warning.is_none() {
+        article.set_summary(self.excerpt(EXCERPT_LENGTH));
+      }
+
+      article.into_any_base()?
+    } else {
+      let mut note: ContentMapNote = ContentMapNote::new();
+      self.populate_object(&mut note, feed, pool).await?;
+
+      note.into_any_base()?
+    };
+
     let mut action: ApObject<Create> = ApObject::new(
       Create::new(
         iri!(feed_url),
-        note.into_any_base()?
+        object
       )
     );
 
@@ -367,6 +637,10 @@ impl Item {
       .set_id(iri!(item_url))
       .set_published(ts);
 
+    let item_publicity = match &feed.status_publicity {
+      Some(value) => value.as_str(),
+      None => "unlisted"
+    };
 
     //
     // set destination according to desired publicity level
@@ -416,7 +690,79 @@ impl Item {
   ///
   /// deliver this item to any followers of the parent feed
   ///
-  pub async fn deliver(&self, feed: &Feed, pool: &PgPool, queue: &mut dyn AsyncQueueable) -> Result<(), DeliveryError> {
+  ///
+  /// Generate a copy of this item addressed only to the given actor, with
+  /// a mention so that it shows up as a direct message
+  ///
+  pub async fn to_direct_message(&self, feed: &Feed, actor: &Actor, pool: &PgPool) -> Result<String, DeliveryError> {
+    let mut message = self.to_activity_pub(feed, pool).await?;
+    message.set_to(iri!(actor.url));
+    message.delete_cc();
+
+    let mut mention = Mention::new();
+    mention
+      .set_href(iri!(actor.url))
+      .set_name(format!("@{}", actor.username));
+
+    let mention = mention.into_any_base()?;
+    message.add_tag(mention.clone());
+
+    //
+    // the note itself needs the same addressing and mention, otherwise
+    // servers will treat it like a regular post
+    //
+    let mut json = serde_json::to_value(&message)?;
+    if let Some(object) = json.get_mut("object").and_then(|object| object.as_object_mut()) {
+      object.insert("to".to_string(), serde_json::json!([actor.url]));
+      object.remove("cc");
+
+      let mention = serde_json::to_value(&mention)?;
+      match object.get_mut("tag") {
+        Some(serde_json::Value::Array(tags)) => tags.push(mention),
+        Some(tag) => *tag = serde_json::json!([tag.take(), mention]),
+        None => { object.insert("tag".to_string(), serde_json::json!([mention])); }
+      }
+    }
+
+    Ok(serde_json::to_string(&json)?)
+  }
+
+  ///
+  /// Send this item to anyone who subscribed to the feed by DM
+  ///
+  pub async fn deliver_to_subscribers(&self, feed: &Feed, budget: &DeliveryBudget, pool: &PgPool, queue: &mut dyn AsyncQueueable) -> Result<(), DeliveryError> {
+    for subscriber in DmSubscriber::for_feed(feed, pool).await? {
+      match Actor::find_or_fetch(&subscriber.actor, pool).await {
+        Ok(Some(actor)) => {
+          if !budget.spend(pool).await? {
+            log::info!("{} is over its daily delivery quota, not sending item {} to subscribers", feed.name, self.id);
+            return Ok(());
+          }
+
+          let msg = self.to_direct_message(feed, &actor, pool).await?;
+          log::debug!("DM {msg}");
+
+          let task = DeliverMessage::new(feed.id, actor.inbox_url, msg);
+          queue
+            .insert_task(&task as &dyn AsyncRunnable)
+            .await
+            .map_err(|why| DeliveryError::Error(why.to_string()))?;
+        },
+        Ok(None) => {},
+        Err(why) => log::info!("couldnt find subscriber {}: {why:?}", subscriber.actor)
+      }
+    }
+
+    Ok(())
+  }
+
+  ///
+  /// Send this item out to the feed's followers, and any relays. Each
+  /// message comes out of the feed's daily delivery budget
+  ///
+  pub async fn deliver(&self, feed: &Feed, budget: &DeliveryBudget, pool: &PgPool, queue: &mut dyn AsyncQueueable) -> Result<(), DeliveryError> {
+    self.deliver_to_subscribers(feed, budget, pool, queue).await?;
+
     let message = self.to_activity_pub(feed, pool).await.unwrap();
     let item_publicity = match &feed.status_publicity {
       Some(value) => value.as_str(),
@@ -457,11 +803,11 @@ impl Item {
           let msg = serde_json::to_string(&targeted).unwrap();
           log::debug!("DM {msg}");
     
-          let task = DeliverMessage { feed_id: feed.id, actor_url: dest_url, message: msg };
-          let _result = queue
+          let task = DeliverMessage::new(feed.id, dest_url, msg);
+          queue
             .insert_task(&task as &dyn AsyncRunnable)
             .await
-            .unwrap();
+            .map_err(|why| DeliveryError::Error(why.to_string()))?;
         },
         Err(why) => {
           log::debug!("couldnt find actor: {why:?}");
@@ -484,14 +830,20 @@ impl Item {
               let mut targeted = message.clone();
               targeted.set_many_tos(vec![iri!(inbox)]);
                 
+              // stop sending once the feed has used up its deliveries for the day
+              if !budget.spend(pool).await? {
+                log::info!("{} is over its daily delivery quota, not sending item {}", feed.name, self.id);
+                return Ok(());
+              }
+
               let msg = serde_json::to_string(&targeted).unwrap();
               log::debug!("{msg}");     
       
-              let task = DeliverMessage { feed_id: feed.id, actor_url: inbox, message: msg };
-              let _result = queue
+              let task = DeliverMessage::new(feed.id, inbox, msg);
+              queue
                 .insert_task(&task as &dyn AsyncRunnable)
                 .await
-                .unwrap();      
+                .map_err(|why| DeliveryError::Error(why.to_string()))?;
             }
           },
           Err(why) => {
@@ -500,6 +852,24 @@ impl Item {
           }
         }      
       }
+
+      // public posts from listed feeds get sent along to any relays too
+      if feed.sends_to_relays() {
+        let msg = serde_json::to_string(&message).unwrap();
+        for relay in Relay::accepted(pool).await? {
+          if !budget.spend(pool).await? {
+            log::info!("{} is over its daily delivery quota, not relaying item {}", feed.name, self.id);
+            break;
+          }
+
+          let task = DeliverMessage::new(feed.id, relay.inbox_url, msg.clone());
+          queue
+            .insert_task(&task as &dyn AsyncRunnable)
+            .await
+            .map_err(|why| DeliveryError::Error(why.to_string()))?;
+        }
+      }
+
       Ok(())
     }
   }
@@ -520,11 +890,15 @@ impl Item {
 mod test {
   use sqlx::postgres::PgPool;
   use fang::NoTls;
+  use chrono::Duration;
   
   use crate::models::Feed;
   use crate::models::Item;
   use crate::models::Actor;
-  use crate::utils::test_helpers::{real_item, real_feed, fake_item, real_item_with_enclosure};
+  use crate::models::{DmSubscriber, Quota, Setting};
+  use crate::models::quota::MAX_DAILY_DELIVERIES_PER_FEED;
+  use crate::utils::test_helpers::{queued_deliveries, test_queue};
+  use crate::utils::test_helpers::{real_item, real_feed, fake_item, real_item_with_enclosure, real_actor};
 
   use crate::utils::queue::create_queue;
 
@@ -628,7 +1002,99 @@ mod test {
     }
   }
 
-  
+
+  #[sqlx::test]
+  async fn test_to_activity_pub_source_metadata(pool: PgPool) -> Result<(), String> {
+    let mut feed: Feed = real_feed(&pool).await.unwrap();
+    feed.status_publicity = Some("public".to_string());
+
+    let mut item: Item = fake_item();
+    item.updated_at = item.created_at + Duration::hours(1);
+    item.author = Some("Colin".to_string());
+    item.fediverse_creator = Some("@colin@muffin.industries".to_string());
+    item.fediverse_creator_url = Some("https://muffin.industries/users/colin".to_string());
+
+    let result = item.to_activity_pub(&feed, &pool).await.unwrap();
+    let v: Value = serde_json::to_value(&result).unwrap();
+    let object = &v["object"];
+
+    assert_eq!(object["url"][0]["type"], "Link");
+    assert_eq!(object["url"][0]["href"], "http://google.com");
+    assert_eq!(object["url"][0]["mediaType"], "text/html");
+    assert_eq!(object["url"][1], format!("{}/items/1", feed.ap_url()));
+
+    assert!(object["updated"].is_string());
+
+    assert_eq!(object["attributedTo"][0], feed.ap_url());
+    assert_eq!(object["attributedTo"][1]["name"], "Colin");
+
+    let tag = &object["tag"][0];
+    assert_eq!(tag["type"], "Mention");
+    assert_eq!(tag["name"], "@colin@muffin.industries");
+    assert!(object["cc"].as_array().unwrap().contains(&serde_json::json!("https://muffin.industries/users/colin")));
+
+    // nothing extra for a plain item
+    let item: Item = fake_item();
+    let v: Value = serde_json::to_value(item.to_activity_pub(&feed, &pool).await.unwrap()).unwrap();
+    assert!(v["object"].get("updated").is_none());
+    assert_eq!(v["object"]["attributedTo"], feed.ap_url());
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_to_activity_pub_as_article(pool: PgPool) -> Result<(), String> {
+    let mut feed: Feed = real_feed(&pool).await.unwrap();
+    let mut item: Item = fake_item();
+    item.content = Some(format!("<p>{}</p>", "words ".repeat(200)));
+
+    feed.object_type = "article".to_string();
+    let result = item.to_activity_pub(&feed, &pool).await.unwrap();
+    let v: Value = serde_json::to_value(&result).unwrap();
+
+    assert_eq!(v["object"]["type"], "Article");
+    assert_eq!(v["object"]["name"], "Hello!");
+    assert_eq!(v["object"]["url"][0]["href"], "http://google.com");
+    assert!(v["object"]["summary"].as_str().unwrap().ends_with("words…"));
+    assert!(v["object"]["contentMap"]["en"].as_str().unwrap().contains("words words"));
+
+    // the content warning takes the place of the excerpt
+    feed.content_warning = Some("long post".to_string());
+    let result = item.to_activity_pub(&feed, &pool).await.unwrap();
+    let v: Value = serde_json::to_value(&result).unwrap();
+    assert_eq!(v["object"]["summary"], "long post");
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_publishes_as_article(pool: PgPool) -> Result<(), String> {
+    let mut feed: Feed = real_feed(&pool).await.unwrap();
+    let mut item: Item = fake_item();
+
+    assert!(!item.publishes_as_article(&feed));
+
+    feed.object_type = "auto".to_string();
+    assert!(!item.publishes_as_article(&feed));
+
+    item.content = Some(format!("<p>{}</p>", "words ".repeat(1000)));
+    assert!(item.publishes_as_article(&feed));
+
+    let v: Value = serde_json::to_value(item.to_activity_pub(&feed, &pool).await.unwrap()).unwrap();
+    assert_eq!(v["object"]["type"], "Article");
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_excerpt() {
+    let mut item: Item = fake_item();
+    item.content = Some("<p>The quick brown fox</p><p>jumps over the lazy dog.</p>".to_string());
+
+    assert_eq!(item.excerpt(100), "The quick brown fox jumps over the lazy dog.");
+    assert_eq!(item.excerpt(12), "The quick…");
+  }
+
   #[sqlx::test]
   async fn test_to_activity_pub_publicity_public(pool: PgPool) -> Result<(), String> {
     let mut feed: Feed = real_feed(&pool).await.unwrap();
@@ -790,15 +1256,86 @@ mod test {
     queue.connect(NoTls).await.unwrap();
 
 
+    let budget = Quota::load(&pool).await.unwrap().delivery_budget(&feed, &pool).await.unwrap();
+
     feed.status_publicity = Some("unlisted".to_string());
-    assert!(item.deliver(&feed, &pool, &mut queue).await.is_ok());
+    assert!(item.deliver(&feed, &budget, &pool, &mut queue).await.is_ok());
 
     feed.status_publicity = Some("public".to_string());
-    assert!(item.deliver(&feed, &pool, &mut queue).await.is_ok());
+    assert!(item.deliver(&feed, &budget, &pool, &mut queue).await.is_ok());
 
     // skip for now @todo fix this
     // feed.status_publicity = Some("direct".to_string());
-    // assert!(item.deliver(&feed, &pool, &mut queue).await.is_ok());
+    // assert!(item.deliver(&feed, &budget, &pool, &mut queue).await.is_ok());
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_deliver_stops_at_quota(pool: PgPool) -> sqlx::Result<()> {
+    let mut feed: Feed = real_feed(&pool).await?;
+    feed.status_publicity = Some("unlisted".to_string());
+
+    for name in ["colin", "muffin", "cupcake"] {
+      let actor = format!("https://foo.com/users/{name}");
+      Actor::create(&actor, &format!("{actor}/inbox"), &format!("{actor}#main-key"), &"public_key".to_string(), &name.to_string(), &pool).await?;
+      feed.add_follower(&pool, &actor).await?;
+    }
+
+    // DMs to subscribers count too
+    let subscriber = "https://foo.com/users/sprinkles";
+    Actor::create(&subscriber.to_string(), &format!("{subscriber}/inbox"), &format!("{subscriber}#main-key"), &"public_key".to_string(), &"sprinkles".to_string(), &pool).await?;
+    DmSubscriber::create(&feed, subscriber, &pool).await?;
+
+    Setting::update(&MAX_DAILY_DELIVERIES_PER_FEED.to_string(), &"5".to_string(), &pool).await?;
+    let budget = Quota::load(&pool).await?.delivery_budget(&feed, &pool).await?;
+    let mut queue = test_queue(&pool).await;
+
+    // the second item only has room for its DM, even though none of
+    // the first batch have been sent yet
+    let first = real_item(&feed, &pool).await?;
+    first.deliver(&feed, &budget, &pool, &mut queue).await.unwrap();
+    let second = real_item(&feed, &pool).await?;
+    second.deliver(&feed, &budget, &pool, &mut queue).await.unwrap();
+
+    let result = sqlx::query!("SELECT COUNT(1) AS tally FROM fang_tasks WHERE metadata->>'type' = 'DeliverMessage'")
+      .fetch_one(&pool)
+      .await?;
+    assert_eq!(result.tally, Some(5));
+    assert_eq!(budget.remaining(&pool).await?, Some(0));
+    assert_eq!(queued_deliveries(&format!("{subscriber}/inbox"), &pool).await.len(), 2);
+
+    // profile updates and moves still go out
+    feed.deliver_to_followers("{}", &pool, &mut queue).await.unwrap();
+    let result = sqlx::query!("SELECT COUNT(1) AS tally FROM fang_tasks WHERE metadata->>'type' = 'DeliverMessage'")
+      .fetch_one(&pool)
+      .await?;
+    assert_eq!(result.tally, Some(8));
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_to_direct_message(pool: PgPool) -> Result<(), String> {
+    let mut feed: Feed = real_feed(&pool).await.unwrap();
+    feed.status_publicity = Some("public".to_string());
+    feed.hashtag = Some("hashtag".to_string());
+
+    let item: Item = real_item(&feed, &pool).await.unwrap();
+    let actor = real_actor(&pool).await.unwrap();
+
+    let msg = item.to_direct_message(&feed, &actor, &pool).await.unwrap();
+    let v: Value = serde_json::from_str(&msg).unwrap();
+
+    assert_eq!(v["to"], actor.url);
+    assert!(v.get("cc").is_none());
+    assert_eq!(v["object"]["to"], serde_json::json!([actor.url]));
+    assert!(v["object"].get("cc").is_none());
+
+    let tags = v["object"]["tag"].as_array().unwrap();
+    assert_eq!(tags.len(), 2);
+    assert_eq!(tags[1]["type"], "Mention");
+    assert_eq!(tags[1]["href"], actor.url);
 
     Ok(())
   }
@@ -825,6 +1362,44 @@ mod test {
     Ok(())
   }
 
+  #[sqlx::test]
+  async fn test_update_from_entry(pool: PgPool) -> Result<(), String> {
+    use feed_rs::parser;
+
+    let feed: Feed = real_feed(&pool).await.unwrap();
+
+    let atom = |title: &str, updated: &str| format!(r#"<?xml version="1.0" encoding="utf-8"?>
+      <feed xmlns="http://www.w3.org/2005/Atom">
+        <title>Blog</title>
+        <id>https://blog.muffin.industries/</id>
+        <updated>{updated}</updated>
+        <entry>
+          <title>{title}</title>
+          <id>https://blog.muffin.industries/post</id>
+          <link href="https://blog.muffin.industries/post" />
+          <author><name>Colin</name></author>
+          <published>2024-01-01T12:00:00Z</published>
+          <updated>{updated}</updated>
+          <content type="html">Hello there</content>
+        </entry>
+      </feed>"#);
+
+    let data = parser::parse(atom("First", "2024-01-01T12:00:00Z").as_bytes()).unwrap();
+    let item:Item = Item::create_from_entry(&data.entries[0], &feed, &pool).await.unwrap();
+
+    assert_eq!(item.author, Some("Colin".to_string()));
+    assert_eq!(item.updated_at, item.created_at);
+    assert!(item.update_from_entry(&data.entries[0], &pool).await.unwrap().is_none());
+
+    let data = parser::parse(atom("Second", "2024-01-02T12:00:00Z").as_bytes()).unwrap();
+    let updated = item.update_from_entry(&data.entries[0], &pool).await.unwrap().unwrap();
+
+    assert_eq!(updated.title, Some("Second".to_string()));
+    assert!(updated.updated_at > updated.created_at);
+
+    Ok(())
+  }
+
   #[sqlx::test]
   async fn test_create_from_entry_link_in_guid(pool: PgPool) -> Result<(), String> {
     use std::fs;
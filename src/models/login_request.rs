@@ -0,0 +1,136 @@
+use sqlx::postgres::PgPool;
+
+use chrono::{Duration, Utc};
+
+use std::env;
+use std::str::FromStr;
+
+///
+/// Model for a request to email a login link. We keep track of these
+/// so that nobody can use the login form to flood an inbox, or to send
+/// mail to every address they can think of
+///
+#[derive(Debug)]
+pub struct LoginRequest {
+  pub id: i32,
+  pub email: String,
+  pub ip_address: Option<String>,
+  pub created_at: chrono::DateTime::<Utc>
+}
+
+/// How long the rate limit window is, in seconds
+const LOGIN_RATE_LIMIT_WINDOW: i64 = 60 * 60;
+
+/// How many login emails can go to one address in the window
+const LOGIN_EMAIL_LIMIT: i64 = 5;
+
+/// How many login emails can be requested from one IP address in the window
+const LOGIN_IP_LIMIT: i64 = 20;
+
+pub fn login_email_limit() -> i64 {
+  match env::var_os("LOGIN_EMAIL_LIMIT") {
+    Some(val) => {
+      i64::from_str(&val.into_string().expect("Something went wrong setting the login email limit")).unwrap()
+    }
+    None => LOGIN_EMAIL_LIMIT
+  }
+}
+
+pub fn login_ip_limit() -> i64 {
+  match env::var_os("LOGIN_IP_LIMIT") {
+    Some(val) => {
+      i64::from_str(&val.into_string().expect("Something went wrong setting the login IP limit")).unwrap()
+    }
+    None => LOGIN_IP_LIMIT
+  }
+}
+
+impl LoginRequest {
+  ///
+  /// Check if another login email can be sent to this address, and
+  /// requested from this IP
+  ///
+  pub async fn allowed(email: &str, ip_address: Option<&str>, pool: &PgPool) -> Result<bool, sqlx::Error> {
+    let email = email.to_lowercase();
+    let since = Utc::now() - Duration::seconds(LOGIN_RATE_LIMIT_WINDOW);
+
+    let by_email = sqlx::query!("SELECT COUNT(1) AS tally FROM login_requests WHERE email = $1 AND created_at > $2", email, since)
+      .fetch_one(pool)
+      .await?;
+
+    if by_email.tally.unwrap_or(0) >= login_email_limit() {
+      return Ok(false)
+    }
+
+    if let Some(ip_address) = ip_address {
+      let by_ip = sqlx::query!("SELECT COUNT(1) AS tally FROM login_requests WHERE ip_address = $1 AND created_at > $2", ip_address, since)
+        .fetch_one(pool)
+        .await?;
+
+      if by_ip.tally.unwrap_or(0) >= login_ip_limit() {
+        return Ok(false)
+      }
+    }
+
+    Ok(true)
+  }
+
+  pub async fn create(email: &str, ip_address: Option<&str>, pool: &PgPool) -> Result<(), sqlx::Error> {
+    let email = email.to_lowercase();
+    sqlx::query!("INSERT INTO login_requests (email, ip_address, created_at) VALUES($1, $2, $3)",
+        email, ip_address, Utc::now())
+      .execute(pool)
+      .await?;
+
+    Ok(())
+  }
+
+  ///
+  /// Remove requests that are too old to count towards any limits
+  ///
+  pub async fn cleanup(pool: &PgPool) -> Result<(), sqlx::Error> {
+    let age = Utc::now() - Duration::seconds(LOGIN_RATE_LIMIT_WINDOW);
+
+    sqlx::query!("DELETE FROM login_requests WHERE created_at <= $1", age)
+      .execute(pool)
+      .await?;
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use sqlx::postgres::PgPool;
+
+  use crate::models::LoginRequest;
+  use crate::models::login_request::{login_email_limit, login_ip_limit};
+
+  #[sqlx::test]
+  async fn test_email_limit(pool: PgPool) -> sqlx::Result<()> {
+    let email = "foo@bar.com";
+
+    for _ in 0..login_email_limit() {
+      assert!(LoginRequest::allowed(email, Some("127.0.0.1"), &pool).await?);
+      LoginRequest::create(email, Some("127.0.0.1"), &pool).await?;
+    }
+
+    assert!(!LoginRequest::allowed(email, Some("127.0.0.2"), &pool).await?);
+    assert!(!LoginRequest::allowed("Foo@Bar.com", Some("127.0.0.2"), &pool).await?);
+    assert!(LoginRequest::allowed("other@bar.com", Some("127.0.0.2"), &pool).await?);
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_ip_limit(pool: PgPool) -> sqlx::Result<()> {
+    for i in 0..login_ip_limit() {
+      LoginRequest::create(&format!("foo{i}@bar.com"), Some("127.0.0.1"), &pool).await?;
+    }
+
+    assert!(!LoginRequest::allowed("new@bar.com", Some("127.0.0.1"), &pool).await?);
+    assert!(LoginRequest::allowed("new@bar.com", Some("127.0.0.2"), &pool).await?);
+
+    Ok(())
+  }
+}
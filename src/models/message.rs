@@ -30,16 +30,30 @@ impl Message {
       .await
   }
 
-  pub async fn log(username: &String, text: &String, actor: Option<String>, error: Option<String>,  handled: bool, pool: &PgPool) -> Result<(), sqlx::Error> {
+  pub async fn log(username: &String, text: &String, actor: Option<String>, error: Option<String>,  handled: bool, pool: &PgPool) -> Result<Message, sqlx::Error> {
     let now = Utc::now();
 
-    sqlx::query!("INSERT INTO messages 
+    sqlx::query_as!(Message, "INSERT INTO messages 
       (username, text, actor, error, handled, created_at, updated_at)
-      VALUES ($1, $2, $3, $4, $5, $6, $7)",
+      VALUES ($1, $2, $3, $4, $5, $6, $7)
+      RETURNING *",
       username, text, actor, error, handled, now, now)
+      .fetch_one(pool)
+      .await
+  }
+
+  ///
+  /// Record the outcome of handling the message
+  ///
+  pub async fn mark_handled(&mut self, handled: bool, error: Option<String>, pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query!("UPDATE messages SET handled = $1, error = $2, updated_at = $3 WHERE id = $4",
+      handled, error, Utc::now(), self.id)
       .execute(pool)
       .await?;
 
+    self.handled = handled;
+    self.error = error;
+
     Ok(())
   }
 
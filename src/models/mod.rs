@@ -10,6 +10,18 @@ pub mod setting;
 pub mod message;
 pub mod sensitive_note;
 pub mod nodeinfo;
+pub mod interaction;
+pub mod follow_request;
+pub mod feed_block;
+pub mod relay;
+pub mod dm_subscriber;
+pub mod delivery;
+pub mod delivery_domain;
+pub mod login_request;
+pub mod session;
+pub mod feed_member;
+pub mod api_token;
+pub mod quota;
 
 pub use actor::Actor;
 pub use user::User;
@@ -22,4 +34,16 @@ pub use blocked_domain::BlockedDomain;
 pub use setting::Setting;
 pub use message::Message;
 pub use sensitive_note::SensitiveNote;
-pub use nodeinfo::NodeInfo;
\ No newline at end of file
+pub use nodeinfo::NodeInfo;
+pub use interaction::Interaction;
+pub use follow_request::FollowRequest;
+pub use feed_block::FeedBlock;
+pub use relay::Relay;
+pub use dm_subscriber::DmSubscriber;
+pub use delivery::Delivery;
+pub use delivery_domain::DeliveryDomain;
+pub use login_request::LoginRequest;
+pub use session::Session;
+pub use feed_member::FeedMember;
+pub use api_token::ApiToken;
+pub use quota::Quota;
\ No newline at end of file
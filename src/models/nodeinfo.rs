@@ -1,29 +1,95 @@
 use serde::Serialize;
 use sqlx::postgres::PgPool;
 
+use chrono::{Duration, Utc};
+
+use crate::models::Setting;
 
 #[derive(Debug, Serialize)]
 pub struct NodeInfo {
   pub users: i64,
+  pub active_month: i64,
+  pub active_halfyear: i64,
   pub posts: i64,
+  pub open_registrations: bool,
+  pub node_name: String,
+  pub node_description: String,
+  pub contact: String
 }
 
 impl NodeInfo {
   pub async fn current(pool: &PgPool) -> Result<NodeInfo, sqlx::Error> {
-    let user_query = sqlx::query!("SELECT COUNT(1) AS tally FROM users")
+    let user_count = sqlx::query!("SELECT COUNT(1) AS tally FROM feeds")
       .fetch_one(pool)
-      .await;
+      .await?
+      .tally
+      .unwrap();
 
-    let post_query = sqlx::query!("SELECT COUNT(1) AS tally FROM items")
+    let post_count = sqlx::query!("SELECT COUNT(1) AS tally FROM items")
       .fetch_one(pool)
-      .await;
-    let user_count = user_query.unwrap().tally.unwrap();
-    let post_count = post_query.unwrap().tally.unwrap();
+      .await?
+      .tally
+      .unwrap();
+
+    let signups_enabled = Setting::value_or(&"signups_enabled".to_string(), &"true".to_string(), pool).await?;
+
     let output = NodeInfo{
       users: user_count,
-      posts: post_count
+      active_month: NodeInfo::active_since(Duration::days(30), pool).await?,
+      active_halfyear: NodeInfo::active_since(Duration::days(180), pool).await?,
+      posts: post_count,
+      open_registrations: signups_enabled == "true",
+      node_name: Setting::value_or(&"nodeinfo_name".to_string(), &String::new(), pool).await?,
+      node_description: Setting::value_or(&"nodeinfo_description".to_string(), &String::new(), pool).await?,
+      contact: Setting::value_or(&"nodeinfo_contact".to_string(), &String::new(), pool).await?
     };
 
     Ok(output)
   }
-}
\ No newline at end of file
+
+  ///
+  /// Count the feeds which have posted something recently
+  ///
+  pub async fn active_since(window: Duration, pool: &PgPool) -> Result<i64, sqlx::Error> {
+    let since = Utc::now() - window;
+    let result = sqlx::query!("SELECT COUNT(1) AS tally FROM feeds WHERE last_post_at >= $1", since)
+      .fetch_one(pool)
+      .await?;
+
+    Ok(result.tally.unwrap())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use sqlx::postgres::PgPool;
+  use chrono::{Duration, Utc};
+
+  use crate::models::NodeInfo;
+  use crate::models::Setting;
+  use crate::utils::test_helpers::real_feed;
+
+  #[sqlx::test]
+  async fn test_current(pool: PgPool) -> sqlx::Result<()> {
+    let recent = real_feed(&pool).await?;
+    recent.update_last_post_at(Utc::now() - Duration::days(2), &pool).await?;
+
+    let older = real_feed(&pool).await?;
+    older.update_last_post_at(Utc::now() - Duration::days(60), &pool).await?;
+
+    real_feed(&pool).await?;
+
+    Setting::update(&"signups_enabled".to_string(), &"false".to_string(), &pool).await?;
+    Setting::update(&"nodeinfo_name".to_string(), &"Feeds".to_string(), &pool).await?;
+
+    let info = NodeInfo::current(&pool).await?;
+    assert_eq!(info.users, 3);
+    assert_eq!(info.active_month, 1);
+    assert_eq!(info.active_halfyear, 2);
+    assert!(!info.open_registrations);
+    assert_eq!(info.node_name, "Feeds");
+    assert_eq!(info.contact, "");
+
+    Ok(())
+  }
+}
@@ -0,0 +1,331 @@
+use sqlx::postgres::PgPool;
+
+use chrono::{NaiveDate, Utc};
+
+use crate::models::Feed;
+use crate::models::Setting;
+use crate::models::User;
+
+pub const MAX_FEEDS_PER_USER: &str = "max_feeds_per_user";
+pub const MAX_DAILY_DELIVERIES_PER_FEED: &str = "max_daily_deliveries_per_feed";
+pub const MAX_FEEDS: &str = "max_feeds";
+
+/// The settings that hold the instance-wide quotas
+pub const QUOTA_SETTINGS: [&str; 3] = [MAX_FEEDS_PER_USER, MAX_DAILY_DELIVERIES_PER_FEED, MAX_FEEDS];
+
+///
+/// The limits on how much a user can ask of the instance. Admins set
+/// these on the admin page and can override the per-user limits for
+/// anyone. A limit of None means there isn't one, and admins themselves
+/// are never limited
+///
+#[derive(Debug, serde::Serialize)]
+pub struct Quota {
+  pub max_feeds_per_user: Option<i64>,
+  pub max_daily_deliveries_per_feed: Option<i64>,
+  pub max_feeds: Option<i64>
+}
+
+///
+/// How many feeds a user has created, for the admin page
+///
+#[derive(Debug, serde::Serialize)]
+pub struct UserUsage {
+  pub id: i32,
+  pub email: Option<String>,
+  pub username: Option<String>,
+  pub actor_url: Option<String>,
+  pub admin: bool,
+  pub max_feeds: Option<i32>,
+  pub max_daily_deliveries: Option<i32>,
+  pub feed_count: i64,
+  pub feed_limit: Option<i64>
+}
+
+///
+/// How many messages a feed has sent today, for the admin page
+///
+#[derive(Debug, serde::Serialize)]
+pub struct FeedUsage {
+  pub id: i32,
+  pub name: String,
+  pub deliveries: i64,
+  pub delivery_limit: Option<i64>
+}
+
+///
+/// Turn a setting or form value into a limit. Blank or nonsense values
+/// mean no limit
+///
+pub fn parse_limit(value: &str) -> Option<i64> {
+  value.trim().parse::<i64>().ok().filter(|limit| *limit >= 0)
+}
+
+impl Quota {
+  pub async fn load(pool: &PgPool) -> Result<Quota, sqlx::Error> {
+    let mut limits = Vec::new();
+    for name in QUOTA_SETTINGS {
+      let value = Setting::value_or(&name.to_string(), &String::new(), pool).await?;
+      limits.push(parse_limit(&value));
+    }
+
+    Ok(Quota {
+      max_feeds_per_user: limits[0],
+      max_daily_deliveries_per_feed: limits[1],
+      max_feeds: limits[2]
+    })
+  }
+
+  ///
+  /// How many feeds the user can create, if there's a limit
+  ///
+  pub fn feeds_allowed(&self, user: &User) -> Option<i64> {
+    if user.is_admin() {
+      return None
+    }
+
+    user.max_feeds.map(i64::from).or(self.max_feeds_per_user)
+  }
+
+  ///
+  /// How many messages each of the user's feeds can send in a day, if
+  /// there's a limit
+  ///
+  pub fn deliveries_allowed(&self, user: &User) -> Option<i64> {
+    if user.is_admin() {
+      return None
+    }
+
+    user.max_daily_deliveries.map(i64::from).or(self.max_daily_deliveries_per_feed)
+  }
+
+  ///
+  /// Check if the instance has room for another feed from the user
+  ///
+  pub async fn instance_full(&self, user: &User, pool: &PgPool) -> Result<bool, sqlx::Error> {
+    match self.max_feeds {
+      Some(max_feeds) if !user.is_admin() => Ok(i64::from(Feed::count(pool).await?) >= max_feeds),
+      _ => Ok(false)
+    }
+  }
+
+  ///
+  /// Check if the user has used up their feeds. Returns their limit if so
+  ///
+  pub async fn user_full(&self, user: &User, pool: &PgPool) -> Result<Option<i64>, sqlx::Error> {
+    match self.feeds_allowed(user) {
+      Some(limit) if Feed::count_created_by(user, pool).await? >= limit => Ok(Some(limit)),
+      _ => Ok(None)
+    }
+  }
+
+  ///
+  /// Get the budget for messages to the feed's followers today
+  ///
+  pub async fn delivery_budget(&self, feed: &Feed, pool: &PgPool) -> Result<DeliveryBudget, sqlx::Error> {
+    let owner = feed.user(pool).await?;
+
+    Ok(DeliveryBudget {
+      feed_id: feed.id,
+      limit: self.deliveries_allowed(&owner)
+    })
+  }
+
+  ///
+  /// The users who have created feeds or have their own limits, busiest first
+  ///
+  pub async fn user_usage(&self, limit: i64, pool: &PgPool) -> Result<Vec<UserUsage>, sqlx::Error> {
+    let rows = sqlx::query!("SELECT users.id, users.email, users.username, users.actor_url, users.admin,
+        users.max_feeds, users.max_daily_deliveries, COUNT(feeds.id) AS feed_count
+      FROM users
+      LEFT JOIN feeds ON feeds.user_id = users.id
+      GROUP BY users.id
+      HAVING COUNT(feeds.id) > 0 OR users.max_feeds IS NOT NULL OR users.max_daily_deliveries IS NOT NULL
+      ORDER BY feed_count DESC, users.id
+      LIMIT $1", limit)
+      .fetch_all(pool)
+      .await?;
+
+    Ok(rows.into_iter().map(|row| {
+      let feed_limit = if row.admin { None } else { row.max_feeds.map(i64::from).or(self.max_feeds_per_user) };
+
+      UserUsage {
+        id: row.id,
+        email: row.email,
+        username: row.username,
+        actor_url: row.actor_url,
+        admin: row.admin,
+        max_feeds: row.max_feeds,
+        max_daily_deliveries: row.max_daily_deliveries,
+        feed_count: row.feed_count.unwrap_or(0),
+        feed_limit
+      }
+    }).collect())
+  }
+
+  ///
+  /// The feeds that have sent the most messages today
+  ///
+  pub async fn feed_usage(&self, limit: i64, pool: &PgPool) -> Result<Vec<FeedUsage>, sqlx::Error> {
+    let rows = sqlx::query!("SELECT feeds.id, feeds.name, users.admin, users.max_daily_deliveries,
+        feed_delivery_counts.deliveries
+      FROM feeds
+      INNER JOIN users ON feeds.user_id = users.id
+      INNER JOIN feed_delivery_counts ON feed_delivery_counts.feed_id = feeds.id
+      WHERE feed_delivery_counts.day = $1
+      ORDER BY feed_delivery_counts.deliveries DESC, feeds.id
+      LIMIT $2", today(), limit)
+      .fetch_all(pool)
+      .await?;
+
+    Ok(rows.into_iter().map(|row| {
+      let delivery_limit = if row.admin { None } else { row.max_daily_deliveries.map(i64::from).or(self.max_daily_deliveries_per_feed) };
+
+      FeedUsage {
+        id: row.id,
+        name: row.name,
+        deliveries: row.deliveries,
+        delivery_limit
+      }
+    }).collect())
+  }
+}
+
+///
+/// Tracks how many messages a feed has queued up for its followers
+/// today. Messages are counted as they go into the queue, so a feed
+/// can't get past its limit by queueing lots of them before any are sent
+///
+#[derive(Debug)]
+pub struct DeliveryBudget {
+  pub feed_id: i32,
+  pub limit: Option<i64>
+}
+
+fn today() -> NaiveDate {
+  Utc::now().date_naive()
+}
+
+impl DeliveryBudget {
+  ///
+  /// Count a message against the budget. Returns false, without
+  /// counting it, if the feed has used up its messages for the day
+  ///
+  pub async fn spend(&self, pool: &PgPool) -> Result<bool, sqlx::Error> {
+    if self.limit == Some(0) {
+      return Ok(false)
+    }
+
+    let result = sqlx::query!("INSERT INTO feed_delivery_counts (feed_id, day, deliveries)
+        VALUES($1, $2, 1)
+        ON CONFLICT (feed_id, day) DO UPDATE SET deliveries = feed_delivery_counts.deliveries + 1
+        WHERE feed_delivery_counts.deliveries < $3
+        RETURNING deliveries",
+        self.feed_id, today(), self.limit.unwrap_or(i64::MAX))
+      .fetch_optional(pool)
+      .await?;
+
+    Ok(result.is_some())
+  }
+
+  ///
+  /// How many more messages the feed can send today, if there's a limit
+  ///
+  pub async fn remaining(&self, pool: &PgPool) -> Result<Option<i64>, sqlx::Error> {
+    let limit = match self.limit {
+      Some(limit) => limit,
+      None => return Ok(None)
+    };
+
+    let result = sqlx::query!("SELECT deliveries FROM feed_delivery_counts WHERE feed_id = $1 AND day = $2", self.feed_id, today())
+      .fetch_optional(pool)
+      .await?;
+    let sent = result.map(|r| r.deliveries).unwrap_or(0);
+
+    Ok(Some((limit - sent).max(0)))
+  }
+
+  ///
+  /// Remove counts from days gone by
+  ///
+  pub async fn cleanup(pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM feed_delivery_counts WHERE day < $1", today())
+      .execute(pool)
+      .await?;
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use sqlx::postgres::PgPool;
+
+  use crate::models::{Feed, Setting, User};
+  use crate::models::quota::{parse_limit, Quota, MAX_DAILY_DELIVERIES_PER_FEED, MAX_FEEDS, MAX_FEEDS_PER_USER};
+  use crate::utils::test_helpers::{real_feed, real_user};
+
+  #[test]
+  fn test_parse_limit() {
+    assert_eq!(parse_limit("10"), Some(10));
+    assert_eq!(parse_limit(" 0 "), Some(0));
+    assert_eq!(parse_limit(""), None);
+    assert_eq!(parse_limit("-1"), None);
+    assert_eq!(parse_limit("lots"), None);
+  }
+
+  #[sqlx::test]
+  async fn test_feed_limits(pool: PgPool) -> sqlx::Result<()> {
+    let mut user = real_user(&pool).await?;
+    let quota = Quota::load(&pool).await?;
+    assert_eq!(quota.feeds_allowed(&user), None);
+    assert!(!quota.instance_full(&user, &pool).await?);
+
+    Setting::update(&MAX_FEEDS_PER_USER.to_string(), &"1".to_string(), &pool).await?;
+    Setting::update(&MAX_FEEDS.to_string(), &"2".to_string(), &pool).await?;
+    let quota = Quota::load(&pool).await?;
+    assert_eq!(quota.user_full(&user, &pool).await?, None);
+
+    Feed::create(&user, &"https://foo.com/rss.xml".to_string(), &"muffins".to_string(), &pool).await?;
+    assert_eq!(quota.user_full(&user, &pool).await?, Some(1));
+    assert!(!quota.instance_full(&user, &pool).await?);
+
+    user.set_quota(Some(5), None, &pool).await?;
+    let user = User::find(user.id, &pool).await?;
+    assert_eq!(quota.feeds_allowed(&user), Some(5));
+    assert_eq!(quota.user_full(&user, &pool).await?, None);
+
+    Feed::create(&user, &"https://foo.com/rss.xml".to_string(), &"cupcakes".to_string(), &pool).await?;
+    assert!(quota.instance_full(&user, &pool).await?);
+
+    let usage = quota.user_usage(10, &pool).await?;
+    assert_eq!(usage[0].feed_count, 2);
+    assert_eq!(usage[0].feed_limit, Some(5));
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_delivery_budget(pool: PgPool) -> sqlx::Result<()> {
+    let feed = real_feed(&pool).await?;
+    let budget = Quota::load(&pool).await?.delivery_budget(&feed, &pool).await?;
+    assert_eq!(budget.remaining(&pool).await?, None);
+    assert!(budget.spend(&pool).await?);
+
+    Setting::update(&MAX_DAILY_DELIVERIES_PER_FEED.to_string(), &"3".to_string(), &pool).await?;
+    let quota = Quota::load(&pool).await?;
+    let budget = quota.delivery_budget(&feed, &pool).await?;
+    assert_eq!(budget.remaining(&pool).await?, Some(2));
+
+    assert!(budget.spend(&pool).await?);
+    assert!(budget.spend(&pool).await?);
+    assert!(!budget.spend(&pool).await?);
+    assert_eq!(budget.remaining(&pool).await?, Some(0));
+
+    let usage = quota.feed_usage(10, &pool).await?;
+    assert_eq!(usage[0].deliveries, 3);
+    assert_eq!(usage[0].delivery_limit, Some(3));
+
+    Ok(())
+  }
+}
@@ -0,0 +1,306 @@
+use sqlx::postgres::PgPool;
+
+use chrono::Utc;
+
+use url::Url;
+
+use activitystreams::{
+  activity::*,
+  base::{BaseExt, ExtendsExt},
+  context,
+  iri,
+  public,
+  time::OffsetDateTime
+};
+
+use crate::DeliveryError;
+use crate::models::Actor;
+use crate::models::Feed;
+use crate::services::mailer::deliver_to_inbox;
+
+///
+/// Model for an ActivityPub relay. The admin feed follows the relay,
+/// and once the relay accepts, public posts are sent along to it so
+/// they can reach servers where nobody follows the feed yet
+///
+#[derive(Debug, serde::Serialize)]
+pub struct Relay {
+  pub id: i32,
+  pub url: String,
+  pub inbox_url: String,
+  pub kind: String,
+  pub status: String,
+  pub follow_activity_id: Option<String>,
+  pub created_at: chrono::DateTime::<Utc>,
+  pub updated_at: chrono::DateTime::<Utc>
+}
+
+impl PartialEq for Relay {
+  fn eq(&self, other: &Self) -> bool {
+    self.id == other.id
+  }
+}
+
+/// Mastodon-style relays are addressed by inbox, and we follow the public collection
+pub const MASTODON: &str = "mastodon";
+
+/// LitePub relays are regular actors, and we follow them directly
+pub const LITEPUB: &str = "litepub";
+
+pub const PENDING: &str = "pending";
+pub const ACCEPTED: &str = "accepted";
+pub const REJECTED: &str = "rejected";
+
+impl Relay {
+  pub async fn find(id: i32, pool: &PgPool) -> Result<Option<Relay>, sqlx::Error> {
+    sqlx::query_as!(Relay, "SELECT * FROM relays WHERE id = $1", id)
+      .fetch_optional(pool)
+      .await
+  }
+
+  pub async fn all(pool: &PgPool) -> Result<Vec<Relay>, sqlx::Error> {
+    sqlx::query_as!(Relay, "SELECT * FROM relays ORDER BY url")
+      .fetch_all(pool)
+      .await
+  }
+
+  ///
+  /// Get the relays which have accepted our follow
+  ///
+  pub async fn accepted(pool: &PgPool) -> Result<Vec<Relay>, sqlx::Error> {
+    sqlx::query_as!(Relay, "SELECT * FROM relays WHERE status = $1 ORDER BY url", ACCEPTED)
+      .fetch_all(pool)
+      .await
+  }
+
+  ///
+  /// Find the relay that an Accept or Reject is responding to. We match on
+  /// the id of our Follow if we have it. Otherwise we fall back to the
+  /// relay's domain, since Mastodon-style relays reply from an actor we
+  /// never saw when subscribing
+  ///
+  pub async fn find_for_response(actor: &str, follow_id: Option<String>, pool: &PgPool) -> Result<Option<Relay>, sqlx::Error> {
+    if let Some(follow_id) = follow_id {
+      let relay = sqlx::query_as!(Relay, "SELECT * FROM relays WHERE follow_activity_id = $1", follow_id)
+        .fetch_optional(pool)
+        .await?;
+
+      if relay.is_some() {
+        return Ok(relay);
+      }
+    }
+
+    let host = Url::parse(actor).ok().and_then(|url| url.host_str().map(|host| host.to_string()));
+    if host.is_none() {
+      return Ok(None);
+    }
+    let host = host.unwrap();
+
+    let relays = Relay::all(pool).await?;
+    Ok(relays.into_iter().find(|relay| relay.url == actor || Url::parse(&relay.url).is_ok_and(|url| url.host_str() == Some(host.as_str()))))
+  }
+
+  ///
+  /// Add a relay. A URL ending in /inbox is treated as a Mastodon-style
+  /// relay, anything else is assumed to be a LitePub relay actor
+  ///
+  pub async fn create(url: &str, pool: &PgPool) -> Result<Relay, DeliveryError> {
+    let url = Url::parse(url.trim())?.to_string();
+
+    let (kind, inbox_url) = if url.ends_with("/inbox") {
+      (MASTODON, url.clone())
+    } else {
+      match Actor::find_or_fetch(&url, pool).await? {
+        Some(actor) => (LITEPUB, actor.inbox_url),
+        None => return Err(DeliveryError::Error(format!("Unable to find relay actor {url}")))
+      }
+    };
+
+    let now = Utc::now();
+    let relay = sqlx::query_as!(Relay, "INSERT INTO relays
+        (url, inbox_url, kind, status, created_at, updated_at)
+        VALUES($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (url) DO UPDATE
+        SET inbox_url = EXCLUDED.inbox_url,
+          kind = EXCLUDED.kind,
+          updated_at = EXCLUDED.updated_at
+        RETURNING *",
+        url, inbox_url, kind, PENDING, now, now)
+      .fetch_one(pool)
+      .await?;
+
+    Ok(relay)
+  }
+
+  ///
+  /// Build the Follow that the admin feed sends to the relay
+  ///
+  fn to_follow(&self, admin: &Feed, follow_id: &str) -> Result<Follow, DeliveryError> {
+    let object = if self.kind == MASTODON {
+      public().to_string()
+    } else {
+      self.url.clone()
+    };
+
+    let mut follow = Follow::new(admin.ap_url(), object);
+    follow.set_id(iri!(follow_id));
+    follow.set_context(context());
+
+    Ok(follow)
+  }
+
+  ///
+  /// Send a Follow to the relay from the admin feed. The relay will be
+  /// pending until it sends back an Accept
+  ///
+  pub async fn subscribe(&mut self, pool: &PgPool) -> Result<(), DeliveryError> {
+    let admin = Feed::for_admin(pool).await?;
+    if admin.is_none() {
+      return Err(DeliveryError::Error("No admin feed to follow the relay with".to_string()));
+    }
+    let admin = admin.unwrap();
+
+    let ts = OffsetDateTime::now_utc();
+    let follow_id = format!("{}#relays/{}/{}", admin.ap_url(), self.id, ts.unix_timestamp_nanos());
+    let follow = self.to_follow(&admin, &follow_id)?;
+
+    sqlx::query!("UPDATE relays SET status = $1, follow_activity_id = $2, updated_at = $3 WHERE id = $4",
+      PENDING, follow_id, Utc::now(), self.id)
+      .execute(pool)
+      .await?;
+
+    self.status = PENDING.to_string();
+    self.follow_activity_id = Some(follow_id);
+
+    deliver_to_inbox(&Url::parse(&self.inbox_url)?, &admin.ap_url(), &admin.private_key, &follow).await
+  }
+
+  ///
+  /// Undo our Follow and forget about the relay
+  ///
+  pub async fn unsubscribe(&self, pool: &PgPool) -> Result<(), DeliveryError> {
+    sqlx::query!("DELETE FROM relays WHERE id = $1", self.id)
+      .execute(pool)
+      .await?;
+
+    let admin = Feed::for_admin(pool).await?;
+    match (admin, &self.follow_activity_id) {
+      (Some(admin), Some(follow_id)) => {
+        let follow = self.to_follow(&admin, follow_id)?;
+
+        let mut undo = Undo::new(admin.ap_url(), follow.into_any_base()?);
+        undo.set_id(iri!(format!("{follow_id}/undo")));
+        undo.set_context(context());
+
+        deliver_to_inbox(&Url::parse(&self.inbox_url)?, &admin.ap_url(), &admin.private_key, &undo).await
+      },
+      _ => Ok(())
+    }
+  }
+
+  ///
+  /// Record the relay's response to our Follow
+  ///
+  pub async fn mark_status(&mut self, status: &str, pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query!("UPDATE relays SET status = $1, updated_at = $2 WHERE id = $3", status, Utc::now(), self.id)
+      .execute(pool)
+      .await?;
+
+    self.status = status.to_string();
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use sqlx::postgres::PgPool;
+
+  use crate::models::Feed;
+  use crate::models::Relay;
+  use crate::models::relay::{ACCEPTED, LITEPUB, MASTODON, PENDING};
+  use crate::utils::test_helpers::real_feed;
+
+  async fn admin_feed(pool: &PgPool) -> Feed {
+    let feed = real_feed(pool).await.unwrap();
+    feed.mark_admin(pool).await.unwrap();
+    feed
+  }
+
+  #[sqlx::test]
+  async fn test_subscribe_mastodon(pool: PgPool) -> Result<(), String> {
+    let mut server = mockito::Server::new_async().await;
+    let admin = admin_feed(&pool).await;
+
+    let m = server.mock("POST", "/inbox")
+      .with_status(202)
+      .match_body(mockito::Matcher::PartialJsonString(r#"{"type":"Follow","object":"https://www.w3.org/ns/activitystreams#Public"}"#.to_string()))
+      .create_async()
+      .await;
+
+    let mut relay = Relay::create(&format!("{}/inbox", server.url()), &pool).await.unwrap();
+    assert_eq!(relay.kind, MASTODON);
+    assert_eq!(relay.inbox_url, format!("{}/inbox", server.url()));
+
+    relay.subscribe(&pool).await.unwrap();
+    m.assert_async().await;
+
+    assert_eq!(relay.status, PENDING);
+    assert!(relay.follow_activity_id.as_ref().unwrap().starts_with(&admin.ap_url()));
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_subscribe_litepub(pool: PgPool) -> Result<(), String> {
+    let mut server = mockito::Server::new_async().await;
+    admin_feed(&pool).await;
+
+    let actor = format!("{}/actor", server.url());
+    let body = format!(r#"{{"id":"{}","type":"Application","preferredUsername":"relay","inbox":"{}/relay-inbox","publicKey":{{"id":"{}#main-key","owner":"{}","publicKeyPem":"key"}}}}"#,
+      actor, server.url(), actor, actor);
+
+    let _m = server.mock("GET", "/actor")
+      .with_status(200)
+      .with_body(body)
+      .create_async()
+      .await;
+
+    let m2 = server.mock("POST", "/relay-inbox")
+      .with_status(202)
+      .match_body(mockito::Matcher::PartialJsonString(format!(r#"{{"type":"Follow","object":"{actor}"}}"#)))
+      .create_async()
+      .await;
+
+    let mut relay = Relay::create(&actor, &pool).await.unwrap();
+    assert_eq!(relay.kind, LITEPUB);
+
+    relay.subscribe(&pool).await.unwrap();
+    m2.assert_async().await;
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_find_for_response(pool: PgPool) -> Result<(), String> {
+    let mut relay = Relay::create("https://relay.place/inbox", &pool).await.unwrap();
+    sqlx::query!("UPDATE relays SET follow_activity_id = $1 WHERE id = $2", "https://local/follows/1", relay.id)
+      .execute(&pool)
+      .await
+      .unwrap();
+
+    let found = Relay::find_for_response("https://relay.place/actor", Some("https://local/follows/1".to_string()), &pool).await.unwrap();
+    assert_eq!(found, Some(Relay::find(relay.id, &pool).await.unwrap().unwrap()));
+
+    let found = Relay::find_for_response("https://relay.place/actor", None, &pool).await.unwrap();
+    assert!(found.is_some());
+
+    let found = Relay::find_for_response("https://other.place/actor", None, &pool).await.unwrap();
+    assert!(found.is_none());
+
+    relay.mark_status(ACCEPTED, &pool).await.unwrap();
+    assert_eq!(Relay::accepted(&pool).await.unwrap().len(), 1);
+
+    Ok(())
+  }
+}
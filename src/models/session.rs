@@ -0,0 +1,221 @@
+use sqlx::postgres::PgPool;
+
+use chrono::{Duration, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use sha2::{Digest, Sha256};
+
+use std::env;
+use std::str::FromStr;
+
+use crate::models::User;
+
+///
+/// Model for a logged in browser. The cookie holds a random token, and
+/// we only store a hash of it, so a leaked database doesn't hand out
+/// working sessions
+///
+#[derive(Debug, serde::Serialize)]
+pub struct Session {
+  pub id: i32,
+  pub user_id: i32,
+  #[serde(skip)]
+  pub token_hash: String,
+  pub user_agent: Option<String>,
+  pub ip_address: Option<String>,
+  pub last_seen_at: chrono::DateTime::<Utc>,
+  pub created_at: chrono::DateTime::<Utc>
+}
+
+impl PartialEq for Session {
+  fn eq(&self, other: &Self) -> bool {
+    self.id == other.id
+  }
+}
+
+/// How long a session can go unused before it expires, in seconds
+const SESSION_MAX_AGE: i64 = 60 * 60 * 24 * 30;
+
+/// How often to update last_seen_at, in seconds, so we don't write on every request
+const SESSION_TOUCH_INTERVAL: i64 = 60 * 5;
+
+pub fn session_max_age() -> i64 {
+  match env::var_os("SESSION_MAX_AGE") {
+    Some(val) => {
+      i64::from_str(&val.into_string().expect("Something went wrong setting the session max age")).unwrap()
+    }
+    None => SESSION_MAX_AGE
+  }
+}
+
+pub fn hash_token(token: &str) -> String {
+  format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+impl Session {
+  ///
+  /// Start a new session for the user. Returns the session along with
+  /// the token to put in the cookie
+  ///
+  pub async fn create(user: &User, user_agent: Option<&str>, ip_address: Option<&str>, pool: &PgPool) -> Result<(Session, String), sqlx::Error> {
+    let token: String = rand::thread_rng()
+      .sample_iter(&Alphanumeric)
+      .take(40)
+      .map(char::from)
+      .collect();
+    let now = Utc::now();
+
+    let session = sqlx::query_as!(Session, "INSERT INTO sessions
+        (user_id, token_hash, user_agent, ip_address, last_seen_at, created_at)
+        VALUES($1, $2, $3, $4, $5, $6)
+        RETURNING *",
+        user.id, hash_token(&token), user_agent, ip_address, now, now)
+      .fetch_one(pool)
+      .await?;
+
+    Ok((session, token))
+  }
+
+  pub async fn find_by_token(token: &str, pool: &PgPool) -> Result<Option<Session>, sqlx::Error> {
+    let expires = Utc::now() - Duration::seconds(session_max_age());
+
+    sqlx::query_as!(Session, "SELECT * FROM sessions WHERE token_hash = $1 AND last_seen_at > $2", hash_token(token), expires)
+      .fetch_optional(pool)
+      .await
+  }
+
+  ///
+  /// Find the user for a session token, and note that the session is
+  /// still in use
+  ///
+  pub async fn user_for_token(token: &str, ip_address: Option<&str>, pool: &PgPool) -> Result<Option<User>, sqlx::Error> {
+    let session = Session::find_by_token(token, pool).await?;
+    if session.is_none() {
+      return Ok(None)
+    }
+    let session = session.unwrap();
+
+    let now = Utc::now();
+    if session.last_seen_at < now - Duration::seconds(SESSION_TOUCH_INTERVAL) {
+      sqlx::query!("UPDATE sessions SET last_seen_at = $1, ip_address = COALESCE($2, ip_address) WHERE id = $3", now, ip_address, session.id)
+        .execute(pool)
+        .await?;
+    }
+
+    Ok(Some(User::find(session.user_id, pool).await?))
+  }
+
+  pub async fn for_user(user: &User, pool: &PgPool) -> Result<Vec<Session>, sqlx::Error> {
+    let expires = Utc::now() - Duration::seconds(session_max_age());
+
+    sqlx::query_as!(Session, "SELECT * FROM sessions WHERE user_id = $1 AND last_seen_at > $2 ORDER BY last_seen_at DESC", user.id, expires)
+      .fetch_all(pool)
+      .await
+  }
+
+  ///
+  /// Check if this session belongs to the given cookie token
+  ///
+  pub fn matches_token(&self, token: &str) -> bool {
+    self.token_hash == hash_token(token)
+  }
+
+  ///
+  /// Revoke one of the user's sessions
+  ///
+  pub async fn delete(user: &User, id: i32, pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM sessions WHERE id = $1 AND user_id = $2", id, user.id)
+      .execute(pool)
+      .await?;
+
+    Ok(())
+  }
+
+  pub async fn delete_by_token(token: &str, pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM sessions WHERE token_hash = $1", hash_token(token))
+      .execute(pool)
+      .await?;
+
+    Ok(())
+  }
+
+  ///
+  /// Log the user out everywhere
+  ///
+  pub async fn delete_for_user(user: &User, pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM sessions WHERE user_id = $1", user.id)
+      .execute(pool)
+      .await?;
+
+    Ok(())
+  }
+
+  ///
+  /// Remove sessions that have expired
+  ///
+  pub async fn cleanup(pool: &PgPool) -> Result<(), sqlx::Error> {
+    let expires = Utc::now() - Duration::seconds(session_max_age());
+
+    sqlx::query!("DELETE FROM sessions WHERE last_seen_at <= $1", expires)
+      .execute(pool)
+      .await?;
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use sqlx::postgres::PgPool;
+  use chrono::{Duration, Utc};
+
+  use crate::models::Session;
+  use crate::models::session::session_max_age;
+  use crate::utils::test_helpers::real_user;
+
+  #[sqlx::test]
+  async fn test_create_and_find(pool: PgPool) -> sqlx::Result<()> {
+    let user = real_user(&pool).await?;
+    let (session, token) = Session::create(&user, Some("Firefox"), Some("127.0.0.1"), &pool).await?;
+
+    assert_ne!(session.token_hash, token);
+    assert!(session.matches_token(&token));
+    assert_eq!(Session::user_for_token(&token, None, &pool).await?, Some(user));
+    assert!(Session::user_for_token("nope", None, &pool).await?.is_none());
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_expiry(pool: PgPool) -> sqlx::Result<()> {
+    let user = real_user(&pool).await?;
+    let (session, token) = Session::create(&user, None, None, &pool).await?;
+
+    let old = Utc::now() - Duration::seconds(session_max_age() + 60);
+    sqlx::query!("UPDATE sessions SET last_seen_at = $1 WHERE id = $2", old, session.id)
+      .execute(&pool)
+      .await?;
+
+    assert!(Session::user_for_token(&token, None, &pool).await?.is_none());
+    assert!(Session::for_user(&user, &pool).await?.is_empty());
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_revoke(pool: PgPool) -> sqlx::Result<()> {
+    let user = real_user(&pool).await?;
+    let (session, _token) = Session::create(&user, None, None, &pool).await?;
+    let (_session2, token2) = Session::create(&user, None, None, &pool).await?;
+    let (_session3, _token3) = Session::create(&user, None, None, &pool).await?;
+    assert_eq!(Session::for_user(&user, &pool).await?.len(), 3);
+
+    Session::delete(&user, session.id, &pool).await?;
+    Session::delete_by_token(&token2, &pool).await?;
+    assert_eq!(Session::for_user(&user, &pool).await?.len(), 1);
+
+    Session::delete_for_user(&user, &pool).await?;
+    assert!(Session::for_user(&user, &pool).await?.is_empty());
+
+    Ok(())
+  }
+}
@@ -8,26 +8,34 @@ use crate:: {
     Actor,
     Feed
   },
+  services::email::Mailer,
   services::mailer::deliver_to_inbox,
+  utils::templates::Context,
+  utils::urls::path_to_url,
   DeliveryError
 };
 
+use rocket::uri;
+
 use url::Url;
 
+use std::env;
+use std::str::FromStr;
+
 #[derive(Debug)]
 pub struct User {
   pub id: i32,
   pub email: Option<String>,
   pub actor_url: Option<String>,
   pub login_token: String,
-  pub access_token: Option<String>,
   pub username: Option<String>,
   pub admin: bool,
+  pub max_feeds: Option<i32>,
+  pub max_daily_deliveries: Option<i32>,
 
   pub created_at: chrono::DateTime::<Utc>,
   pub updated_at: chrono::DateTime::<Utc>,
   pub login_token_updated_at: chrono::DateTime::<Utc>,
-  pub access_token_updated_at: chrono::DateTime::<Utc>,
 }
 
 impl PartialEq for User {
@@ -36,6 +44,18 @@ impl PartialEq for User {
   }
 }
 
+/// How long a login link is good for, in seconds
+const LOGIN_TOKEN_TTL: i64 = 60 * 15;
+
+pub fn login_token_ttl() -> i64 {
+  match env::var_os("LOGIN_TOKEN_TTL") {
+    Some(val) => {
+      i64::from_str(&val.into_string().expect("Something went wrong setting the login token TTL")).unwrap()
+    }
+    None => LOGIN_TOKEN_TTL
+  }
+}
+
 impl User {
   ///
   /// Find user by ID. This assumes that the user exists!
@@ -80,17 +100,25 @@ impl User {
   /// Find user by login
   ///
   pub async fn find_by_login(token: &String, pool: &PgPool) -> Result<Option<User>, sqlx::Error> {
-    sqlx::query_as!(User, "SELECT * FROM users WHERE login_token = $1", token)
+    let expires = Utc::now() - Duration::seconds(login_token_ttl());
+    sqlx::query_as!(User, "SELECT * FROM users WHERE login_token = $1 AND login_token_updated_at > $2", token, expires)
       .fetch_optional(pool)
       .await
   }
   
   ///
-  /// Find user by access token
+  /// Log in with a login token. Tokens only work once, so this swaps in
+  /// a new one and expires it in the same step. That way two requests
+  /// with the same link can't both succeed
   ///
-  pub async fn find_by_access(token: &String, pool: &PgPool) -> Result<Option<User>, sqlx::Error> {
-    log::debug!("Find user: {token:}");
-    sqlx::query_as!(User, "SELECT * FROM users WHERE access_token = $1", token)
+  pub async fn redeem_login_token(token: &String, pool: &PgPool) -> Result<Option<User>, sqlx::Error> {
+    let expires = Utc::now() - Duration::seconds(login_token_ttl());
+    let replacement = User::generate_login_token();
+
+    sqlx::query_as!(User, "UPDATE users SET login_token = $1, login_token_updated_at = 'epoch'
+        WHERE login_token = $2 AND login_token_updated_at > $3
+        RETURNING *",
+        replacement, token, expires)
       .fetch_optional(pool)
       .await
   }
@@ -98,7 +126,7 @@ impl User {
   pub async fn reset_login_token(&self, pool: &PgPool) -> Result<String, sqlx::Error> {
     let token = User::generate_login_token();
     let query_check = sqlx::query!(
-      "UPDATE users SET login_token = $1 WHERE id = $2", token, self.id)
+      "UPDATE users SET login_token = $1, login_token_updated_at = NOW() WHERE id = $2", token, self.id)
       .execute(pool)
       .await;
       
@@ -108,40 +136,6 @@ impl User {
     }
   }
   
-  pub fn needs_new_access_token(&self) -> bool {
-    if self.access_token.is_none() {
-      return true;
-    }
-
-    let now = Utc::now();
-    let diff = now.signed_duration_since(self.access_token_updated_at);
-    println!("Total time: {}", diff.num_minutes());
-
-    diff > Duration::hours(24)
-  }
-
-  ///
-  /// generate and apply access token to the current object
-  ///
-  pub async fn apply_access_token(&self, pool: &PgPool) -> Result<String, sqlx::Error> {
-    if self.needs_new_access_token() {
-      let token = User::generate_access_token();
-      // log::info!("generate token: {token:}");
-  
-      let query_check = sqlx::query!(
-        "UPDATE users SET access_token = $1, access_token_updated_at = NOW() WHERE id = $2", token, self.id)
-        .execute(pool)
-        .await;
-        
-      match query_check {
-        Ok(_q) => Ok(token),
-        Err(why) => Err(why)
-      }  
-    } else {
-      Ok(self.access_token.clone().unwrap())
-    }
-  }
-
   ///
   /// create a user with the given email address
   ///
@@ -214,18 +208,26 @@ impl User {
     .collect()
   }
   
-  pub fn generate_access_token() -> String {
-    rand::thread_rng()
-    .sample_iter(&Alphanumeric)
-    .take(40)
-    .map(char::from)
-    .collect()    
-  }
-
   pub fn is_admin(&self) -> bool {
     self.admin
   }
 
+  ///
+  /// Override the instance quotas for this user. None means they get
+  /// the instance-wide limit
+  ///
+  pub async fn set_quota(&mut self, max_feeds: Option<i32>, max_daily_deliveries: Option<i32>, pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query!("UPDATE users SET max_feeds = $1, max_daily_deliveries = $2, updated_at = $3 WHERE id = $4",
+        max_feeds, max_daily_deliveries, Utc::now(), self.id)
+      .execute(pool)
+      .await?;
+
+    self.max_feeds = max_feeds;
+    self.max_daily_deliveries = max_daily_deliveries;
+
+    Ok(())
+  }
+
   ///
   /// update user record with a few things from their actor
   ///
@@ -253,6 +255,24 @@ impl User {
   }
 
 
+  ///
+  /// Email the user a fresh login link
+  ///
+  pub async fn send_login_email(&self, mailer: &Mailer, pool: &PgPool) -> Result<(), DeliveryError> {
+    let email = match &self.email {
+      Some(email) => email,
+      None => return Err(DeliveryError::Error(String::from("User has no email address")))
+    };
+
+    let token = self.reset_login_token(pool).await?;
+    let link = path_to_url(&uri!(crate::routes::login::attempt_login(&token)));
+
+    let mut context = Context::new();
+    context.insert("link", &link);
+
+    mailer.send(email, "Your login link", "email/send-login-email", &context).await
+  }
+
   pub async fn send_link_to_feed(&self, feed: &Feed, pool: &PgPool) -> Result<(), DeliveryError> {
     let dest_actor = Actor::find_or_fetch(self.actor_url.as_ref().expect("No actor url!"), pool).await;
 
@@ -297,7 +317,10 @@ impl User {
 #[cfg(test)]
 mod test {
   use sqlx::postgres::PgPool;
+  use chrono::{Duration, Utc};
+
   use crate::models::User;
+  use crate::models::user::login_token_ttl;
 
   #[sqlx::test]
   async fn test_find_or_create_by_email(pool: PgPool) -> sqlx::Result<()> {
@@ -321,32 +344,56 @@ mod test {
   }
 
   #[sqlx::test]
-  async fn test_find_by_access(pool: PgPool) -> sqlx::Result<()> {
+  async fn test_login_token_expires(pool: PgPool) -> sqlx::Result<()> {
     let email:String = "foo@bar.com".to_string();
     let user = User::find_or_create_by_email(&email, &pool).await?;
-    let access_token = user.apply_access_token(&pool).await.unwrap().to_string();
 
-    let user_find = User::find_by_access(&access_token, &pool).await?.unwrap();
-    assert_eq!(user, user_find);
-    
-    let bad_token = format!("dfdfs{}fdsdf", access_token);
-    let no_user = User::find_by_access(&bad_token, &pool).await;
-    assert_eq!(false, no_user.unwrap().is_some());
-    
+    let expired = Utc::now() - Duration::seconds(login_token_ttl() + 60);
+    sqlx::query!("UPDATE users SET login_token_updated_at = $1 WHERE id = $2", expired, user.id)
+      .execute(&pool)
+      .await?;
+    assert!(User::find_by_login(&user.login_token, &pool).await?.is_none());
+
+    let token = user.reset_login_token(&pool).await?;
+    assert!(User::find_by_login(&token, &pool).await?.is_some());
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_send_login_email(pool: PgPool) -> Result<(), String> {
+    use lettre::{AsyncFileTransport, Tokio1Executor};
+    use crate::services::email::{Mailer, MailTransport};
+
+    let dir = std::env::temp_dir().join(format!("mail-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mailer = Mailer::new("noreply@feeds.place", MailTransport::File(AsyncFileTransport::<Tokio1Executor>::new(&dir))).unwrap();
+
+    let user = User::find_or_create_by_email(&"foo@bar.com".to_string(), &pool).await.unwrap();
+    user.send_login_email(&mailer, &pool).await.unwrap();
+
+    let user = User::find(user.id, &pool).await.unwrap();
+    let file = std::fs::read_dir(&dir).unwrap().next().unwrap().unwrap();
+    let body = std::fs::read_to_string(file.path()).unwrap();
+    assert!(body.contains(&format!("/user/auth/{}", user.login_token)));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+
     Ok(())
   }
 
   #[sqlx::test]
-  async fn test_doesnt_regenerate_access_token_too_soon(pool: PgPool) -> sqlx::Result<()> {
+  async fn test_redeem_login_token(pool: PgPool) -> sqlx::Result<()> {
     let email:String = "foo@bar.com".to_string();
     let user = User::find_or_create_by_email(&email, &pool).await?;
-    let access_token = user.apply_access_token(&pool).await.unwrap().to_string();
 
-    let user_2 = User::find_or_create_by_email(&email, &pool).await?;
-    let access_token_2 = user_2.apply_access_token(&pool).await.unwrap().to_string();
+    let redeemed = User::redeem_login_token(&user.login_token, &pool).await?;
+    assert_eq!(redeemed.unwrap(), user);
+
+    // the link only works once
+    assert!(User::redeem_login_token(&user.login_token, &pool).await?.is_none());
+    assert!(User::find_by_login(&user.login_token, &pool).await?.is_none());
 
-    assert_eq!(access_token, access_token_2);
-    
     Ok(())
   }
 }
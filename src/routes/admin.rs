@@ -1,12 +1,13 @@
 use std::env;
 use rocket_dyn_templates::{Template, context};
 
-use rocket::{FromForm, get, put, delete};
+use rocket::{FromForm, get, post, put, delete};
 use rocket::form::Form;
 use rocket::State;
 use rocket::uri;
+use rocket::request::FlashMessage;
 use rocket::response::{Flash, Redirect};
-use rocket::http::Status;
+use rocket::http::{ContentType, Status};
 
 use sqlx::postgres::PgPool;
 
@@ -14,13 +15,66 @@ use crate::models::User;
 use crate::models::Feed;
 use crate::models::Item;
 use crate::models::Setting;
+use crate::models::BlockedDomain;
+use crate::models::Relay;
+use crate::models::Delivery;
+use crate::models::DeliveryDomain;
+use crate::models::Quota;
+use crate::models::quota::{parse_limit, QUOTA_SETTINGS};
+use crate::models::delivery::DEAD;
+use crate::models::feed::{FOLLOWERS_PUBLIC, FOLLOWERS_COUNT, FOLLOWERS_HIDDEN};
+use crate::models::blocked_domain::{REJECT, SILENCE};
+
+use crate::tasks::DeliverMessage;
+use crate::utils::queue::create_queue;
+
+use fang::AsyncRunnable;
+use fang::asynk::async_queue::AsyncQueueable;
+
+use chrono::Utc;
 
 use crate::PER_PAGE;
 
 #[derive(FromForm, serde::Deserialize)]
 #[serde(crate = "rocket::serde")]
 pub struct AdminSettingsForm {
-  signups_enabled: String
+  signups_enabled: String,
+  secure_mode: Option<String>,
+  followers_visibility: Option<String>,
+  nodeinfo_name: Option<String>,
+  nodeinfo_description: Option<String>,
+  nodeinfo_contact: Option<String>,
+  max_feeds_per_user: Option<String>,
+  max_daily_deliveries_per_feed: Option<String>,
+  max_feeds: Option<String>
+}
+
+#[derive(FromForm, serde::Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct UserQuotaForm {
+  max_feeds: Option<String>,
+  max_daily_deliveries: Option<String>
+}
+
+#[derive(FromForm, serde::Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct DomainBlockForm {
+  domain: String,
+  severity: String,
+  reason: Option<String>,
+  public_note: Option<String>
+}
+
+#[derive(FromForm, serde::Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct DomainBlockImportForm {
+  csv: String
+}
+
+#[derive(FromForm, serde::Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct RelayForm {
+  url: String
 }
 
 
@@ -35,6 +89,15 @@ pub async fn index_admin(user: User, page: Option<i32>, db: &State<PgPool>) -> R
 
   let feeds = Feed::paged(page, db).await.unwrap();
   let signups_enabled = Setting::value_or(&"signups_enabled".to_string(), &"true".to_string(), db).await.unwrap();
+  let secure_mode = Setting::value_or(&"secure_mode".to_string(), &"false".to_string(), db).await.unwrap();
+  let followers_visibility = Setting::value_or(&"followers_visibility".to_string(), &FOLLOWERS_PUBLIC.to_string(), db).await.unwrap();
+  let nodeinfo_name = Setting::value_or(&"nodeinfo_name".to_string(), &String::new(), db).await.unwrap();
+  let nodeinfo_description = Setting::value_or(&"nodeinfo_description".to_string(), &String::new(), db).await.unwrap();
+  let nodeinfo_contact = Setting::value_or(&"nodeinfo_contact".to_string(), &String::new(), db).await.unwrap();
+  let relays = Relay::all(db).await.unwrap();
+  let quota = Quota::load(db).await.unwrap();
+  let user_usage = quota.user_usage(i64::from(PER_PAGE), db).await.unwrap();
+  let feed_usage = quota.feed_usage(i64::from(PER_PAGE), db).await.unwrap();
 
   let count = Feed::count(db).await.unwrap();
   let total_pages:i32 = (count / PER_PAGE) + 1;
@@ -46,6 +109,15 @@ pub async fn index_admin(user: User, page: Option<i32>, db: &State<PgPool>) -> R
     total_pages: total_pages,
     total: count,
     signups_enabled: signups_enabled,
+    secure_mode: secure_mode,
+    followers_visibility: followers_visibility,
+    nodeinfo_name: nodeinfo_name,
+    nodeinfo_description: nodeinfo_description,
+    nodeinfo_contact: nodeinfo_contact,
+    relays: relays,
+    quota: quota,
+    user_usage: user_usage,
+    feed_usage: feed_usage,
     instance_domain: instance_domain,
     feed_link_prefix: "/admin"
   }))
@@ -58,7 +130,48 @@ pub async fn update_settings_admin(user: User, db: &State<PgPool>, form: Form<Ad
     return Err(Status::NotFound)
   }
 
-  let result = Setting::update(&"signups_enabled".to_string(), &form.signups_enabled, db).await;
+  let mut result = Setting::update(&"signups_enabled".to_string(), &form.signups_enabled, db).await;
+  if result.is_ok() {
+    if let Some(secure_mode) = &form.secure_mode {
+      result = Setting::update(&"secure_mode".to_string(), secure_mode, db).await;
+    }
+  }
+  if result.is_ok() {
+    if let Some(followers_visibility) = &form.followers_visibility {
+      if [FOLLOWERS_PUBLIC, FOLLOWERS_COUNT, FOLLOWERS_HIDDEN].contains(&followers_visibility.as_str()) {
+        result = Setting::update(&"followers_visibility".to_string(), followers_visibility, db).await;
+      }
+    }
+  }
+
+  // details about the server that get published in nodeinfo
+  for (name, value) in [
+    ("nodeinfo_name", &form.nodeinfo_name),
+    ("nodeinfo_description", &form.nodeinfo_description),
+    ("nodeinfo_contact", &form.nodeinfo_contact)
+  ] {
+    if result.is_ok() {
+      if let Some(value) = value {
+        result = Setting::update(&name.to_string(), &value.trim().to_string(), db).await;
+      }
+    }
+  }
+
+  // quotas, where blank means no limit
+  for (name, value) in QUOTA_SETTINGS.iter().zip([
+    &form.max_feeds_per_user,
+    &form.max_daily_deliveries_per_feed,
+    &form.max_feeds
+  ]) {
+    if result.is_ok() {
+      if let Some(value) = value {
+        let value = value.trim();
+        if value.is_empty() || parse_limit(value).is_some() {
+          result = Setting::update(&name.to_string(), &value.to_string(), db).await;
+        }
+      }
+    }
+  }
 
   let dest = uri!(index_admin(Some(1)));
 
@@ -69,6 +182,233 @@ pub async fn update_settings_admin(user: User, db: &State<PgPool>, form: Form<Ad
 }
 
 
+///
+/// Give a user their own quotas instead of the instance-wide ones.
+/// Leaving a field blank puts them back on the instance limit
+///
+#[put("/admin/users/<id>/quota", data = "<form>")]
+pub async fn update_user_quota_admin(user: User, id: i32, db: &State<PgPool>, form: Form<UserQuotaForm>) -> Result<Flash<Redirect>, Status> {
+  if ! user.is_admin() {
+    return Err(Status::NotFound)
+  }
+
+  let mut target = match User::find(id, db).await {
+    Ok(target) => target,
+    Err(_why) => return Err(Status::NotFound)
+  };
+
+  let limit = |value: &Option<String>| value.as_deref().and_then(|value| value.trim().parse::<i32>().ok()).filter(|limit| *limit >= 0);
+  let dest = uri!(index_admin(Some(1)));
+
+  match target.set_quota(limit(&form.max_feeds), limit(&form.max_daily_deliveries), db).await {
+    Ok(_result) => Ok(Flash::success(Redirect::to(dest), "Quota updated!")),
+    Err(_why) => Ok(Flash::error(Redirect::to(dest), "Sorry, something went wrong!"))
+  }
+}
+
+#[get("/admin/domain-blocks")]
+pub async fn index_domain_blocks_admin(user: User, flash: Option<FlashMessage<'_>>, db: &State<PgPool>) -> Result<Template, Status> {
+  if ! user.is_admin() {
+    return Err(Status::NotFound)
+  }
+
+  match BlockedDomain::all(db).await {
+    Ok(blocks) => Ok(Template::render("domain-blocks", context! {
+      flash: flash,
+      blocks: blocks,
+      logged_in: true,
+      username: user.full_username()
+    })),
+    Err(_why) => Err(Status::InternalServerError)
+  }
+}
+
+#[post("/admin/domain-blocks", data = "<form>")]
+pub async fn create_domain_block_admin(user: User, db: &State<PgPool>, form: Form<DomainBlockForm>) -> Result<Flash<Redirect>, Status> {
+  if ! user.is_admin() {
+    return Err(Status::NotFound)
+  }
+
+  let dest = uri!(index_domain_blocks_admin());
+  if (form.severity != REJECT && form.severity != SILENCE) || form.domain.trim().is_empty() {
+    return Ok(Flash::error(Redirect::to(dest), "Sorry, something went wrong!"))
+  }
+
+  let result = BlockedDomain::block(&form.domain, &form.severity, form.reason.clone(), form.public_note.clone(), db).await;
+  match result {
+    Ok(_result) => Ok(Flash::success(Redirect::to(dest), "Domain blocked!")),
+    Err(_why) => Ok(Flash::error(Redirect::to(dest), "Sorry, something went wrong!"))
+  }
+}
+
+#[delete("/admin/domain-blocks/<name>")]
+pub async fn delete_domain_block_admin(user: User, name: &str, db: &State<PgPool>) -> Result<Flash<Redirect>, Status> {
+  if ! user.is_admin() {
+    return Err(Status::NotFound)
+  }
+
+  let dest = uri!(index_domain_blocks_admin());
+  match BlockedDomain::delete(&name.to_string(), db).await {
+    Ok(_result) => Ok(Flash::success(Redirect::to(dest), "Domain unblocked!")),
+    Err(_why) => Ok(Flash::error(Redirect::to(dest), "Sorry, something went wrong!"))
+  }
+}
+
+#[get("/admin/domain-blocks/export")]
+pub async fn export_domain_blocks_admin(user: User, db: &State<PgPool>) -> Result<(ContentType, String), Status> {
+  if ! user.is_admin() {
+    return Err(Status::NotFound)
+  }
+
+  match BlockedDomain::to_csv(db).await {
+    Ok(csv) => Ok((ContentType::CSV, csv)),
+    Err(_why) => Err(Status::InternalServerError)
+  }
+}
+
+#[post("/admin/domain-blocks/import", data = "<form>")]
+pub async fn import_domain_blocks_admin(user: User, db: &State<PgPool>, form: Form<DomainBlockImportForm>) -> Result<Flash<Redirect>, Status> {
+  if ! user.is_admin() {
+    return Err(Status::NotFound)
+  }
+
+  let dest = uri!(index_domain_blocks_admin());
+  match BlockedDomain::import_csv(&form.csv, db).await {
+    Ok(count) => Ok(Flash::success(Redirect::to(dest), format!("Imported {count} blocks!"))),
+    Err(_why) => Ok(Flash::error(Redirect::to(dest), "Sorry, something went wrong!"))
+  }
+}
+
+#[get("/admin/deliveries")]
+pub async fn index_deliveries_admin(user: User, db: &State<PgPool>) -> Result<Template, Status> {
+  if ! user.is_admin() {
+    return Err(Status::NotFound)
+  }
+
+  let deliveries = Delivery::dead(100, db).await;
+  let paused_domains = DeliveryDomain::paused(db).await;
+  let total = Delivery::dead_count(db).await;
+
+  match (deliveries, paused_domains, total) {
+    (Ok(deliveries), Ok(paused_domains), Ok(total)) => Ok(Template::render("deliveries", context! {
+      deliveries: deliveries,
+      paused_domains: paused_domains,
+      total: total,
+      logged_in: true,
+      username: user.full_username()
+    })),
+    _ => Err(Status::InternalServerError)
+  }
+}
+
+#[post("/admin/deliveries/<id>/replay")]
+pub async fn replay_delivery_admin(user: User, id: i32, db: &State<PgPool>) -> Result<Flash<Redirect>, Status> {
+  if ! user.is_admin() {
+    return Err(Status::NotFound)
+  }
+
+  let mut delivery = match Delivery::find(id, db).await {
+    Ok(Some(delivery)) if delivery.status == DEAD => delivery,
+    _ => return Err(Status::NotFound)
+  };
+
+  let dest = uri!(index_deliveries_admin());
+  if delivery.replay(db).await.is_err() {
+    return Ok(Flash::error(Redirect::to(dest), "Sorry, something went wrong!"))
+  }
+
+  let mut queue = create_queue().await;
+  queue.connect(fang::NoTls).await.unwrap();
+
+  let task = DeliverMessage::retry(&delivery, Utc::now());
+  match queue.schedule_task(&task as &dyn AsyncRunnable).await {
+    Ok(_result) => Ok(Flash::success(Redirect::to(dest), "Delivery queued!")),
+    Err(_why) => Ok(Flash::error(Redirect::to(dest), "Sorry, something went wrong!"))
+  }
+}
+
+#[delete("/admin/deliveries/<id>")]
+pub async fn delete_delivery_admin(user: User, id: i32, db: &State<PgPool>) -> Result<Flash<Redirect>, Status> {
+  if ! user.is_admin() {
+    return Err(Status::NotFound)
+  }
+
+  let delivery = match Delivery::find(id, db).await {
+    Ok(Some(delivery)) => delivery,
+    _ => return Err(Status::NotFound)
+  };
+
+  let dest = uri!(index_deliveries_admin());
+  match delivery.delete(db).await {
+    Ok(_result) => Ok(Flash::success(Redirect::to(dest), "Delivery discarded!")),
+    Err(_why) => Ok(Flash::error(Redirect::to(dest), "Sorry, something went wrong!"))
+  }
+}
+
+#[post("/admin/relays", data = "<form>")]
+pub async fn create_relay_admin(user: User, db: &State<PgPool>, form: Form<RelayForm>) -> Result<Flash<Redirect>, Status> {
+  if ! user.is_admin() {
+    return Err(Status::NotFound)
+  }
+
+  let dest = uri!(index_admin(Some(1)));
+  let relay = Relay::create(&form.url, db).await;
+  match relay {
+    Ok(mut relay) => {
+      match relay.subscribe(db).await {
+        Ok(_result) => Ok(Flash::success(Redirect::to(dest), "Relay added!")),
+        Err(why) => {
+          log::info!("relay subscription failed: {why}");
+          Ok(Flash::error(Redirect::to(dest), "The relay was added, but we couldn't send it a follow"))
+        }
+      }
+    },
+    Err(why) => {
+      log::info!("relay creation failed: {why}");
+      Ok(Flash::error(Redirect::to(dest), "Sorry, something went wrong!"))
+    }
+  }
+}
+
+#[post("/admin/relays/<id>/subscribe")]
+pub async fn subscribe_relay_admin(user: User, id: i32, db: &State<PgPool>) -> Result<Flash<Redirect>, Status> {
+  if ! user.is_admin() {
+    return Err(Status::NotFound)
+  }
+
+  let mut relay = match Relay::find(id, db).await {
+    Ok(Some(relay)) => relay,
+    _ => return Err(Status::NotFound)
+  };
+
+  let dest = uri!(index_admin(Some(1)));
+  match relay.subscribe(db).await {
+    Ok(_result) => Ok(Flash::success(Redirect::to(dest), "Follow sent!")),
+    Err(_why) => Ok(Flash::error(Redirect::to(dest), "Sorry, something went wrong!"))
+  }
+}
+
+#[delete("/admin/relays/<id>")]
+pub async fn delete_relay_admin(user: User, id: i32, db: &State<PgPool>) -> Result<Flash<Redirect>, Status> {
+  if ! user.is_admin() {
+    return Err(Status::NotFound)
+  }
+
+  let relay = match Relay::find(id, db).await {
+    Ok(Some(relay)) => relay,
+    _ => return Err(Status::NotFound)
+  };
+
+  let dest = uri!(index_admin(Some(1)));
+  match relay.unsubscribe(db).await {
+    Ok(_result) => Ok(Flash::success(Redirect::to(dest), "Relay removed!")),
+    Err(why) => {
+      log::info!("relay unsubscribe failed: {why}");
+      Ok(Flash::error(Redirect::to(dest), "The relay was removed, but we couldn't notify it"))
+    }
+  }
+}
+
 #[get("/admin/feed/<username>", format = "text/html", rank = 2)]
 pub async fn show_feed_admin(user: User, username: &str, db: &State<PgPool>) -> Result<Template, Status> {
   if ! user.is_admin() {
@@ -110,6 +450,31 @@ pub async fn show_feed_admin(user: User, username: &str, db: &State<PgPool>) ->
   }
 }
 
+#[post("/admin/feed/<id>/rotate-key")]
+pub async fn rotate_feed_key_admin(user: User, id: i32, db: &State<PgPool>) -> Result<Flash<Redirect>, Status> {
+  if ! user.is_admin() {
+    return Err(Status::NotFound)
+  }
+
+  let feed = Feed::find(id, db).await;
+
+  match feed {
+    Ok(mut feed) => {
+      let dest = uri!(show_feed_admin(&feed.name));
+
+      let mut queue = create_queue().await;
+      queue.connect(fang::NoTls).await.unwrap();
+
+      let result = feed.rotate_keys(db, &mut queue).await;
+      match result {
+        Ok(_result) => Ok(Flash::success(Redirect::to(dest), "Signing key updated!")),
+        Err(_why) => Ok(Flash::error(Redirect::to(dest), "Sorry, something went wrong!"))
+      }
+    },
+    Err(_why) => Err(Status::NotFound)
+  }
+}
+
 #[delete("/admin/feed/<id>/delete")]
 pub async fn delete_feed_admin(user: User, id: i32, db: &State<PgPool>) -> Result<Redirect, Status> {
   if ! user.is_admin() {
@@ -185,4 +550,211 @@ mod test {
       None => panic!()
     }
   }
+
+  #[sqlx::test]
+  async fn update_settings_admin(pool: PgPool) -> sqlx::Result<()> {
+    use rocket::http::ContentType;
+    use crate::models::Setting;
+
+    let user = real_admin_user(&pool).await.unwrap();
+
+    let server: Rocket<Build> = build_test_server(pool.clone()).await;
+    let client = Client::tracked(server).await.unwrap();
+
+    crate::utils::test_helpers::login_user(&client, &user).await;
+
+    let response = client.put(uri!(super::update_settings_admin()))
+      .header(ContentType::Form)
+      .body("signups_enabled=false&nodeinfo_name=Feeds&nodeinfo_description=&nodeinfo_contact=+admin%40feeds.place+")
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::SeeOther);
+
+    assert_eq!(Setting::value_or(&"signups_enabled".to_string(), &"true".to_string(), &pool).await?, "false");
+    assert_eq!(Setting::value_or(&"nodeinfo_name".to_string(), &String::new(), &pool).await?, "Feeds");
+    assert_eq!(Setting::value_or(&"nodeinfo_contact".to_string(), &String::new(), &pool).await?, "admin@feeds.place");
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn quotas_admin(pool: PgPool) -> sqlx::Result<()> {
+    use rocket::http::ContentType;
+    use crate::models::{Feed, Quota, User};
+
+    let user = real_admin_user(&pool).await.unwrap();
+    let member = User::find_or_create_by_email(&"member@feeds.place".to_string(), &pool).await?;
+    Feed::create(&member, &"https://foo.com/rss.xml".to_string(), &"muffins".to_string(), &pool).await?;
+
+    let server: Rocket<Build> = build_test_server(pool.clone()).await;
+    let client = Client::tracked(server).await.unwrap();
+
+    crate::utils::test_helpers::login_user(&client, &user).await;
+
+    let response = client.put(uri!(super::update_settings_admin()))
+      .header(ContentType::Form)
+      .body("signups_enabled=true&max_feeds_per_user=3&max_daily_deliveries_per_feed=lots&max_feeds=")
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::SeeOther);
+
+    let quota = Quota::load(&pool).await?;
+    assert_eq!(quota.max_feeds_per_user, Some(3));
+    assert_eq!(quota.max_daily_deliveries_per_feed, None);
+    assert_eq!(quota.max_feeds, None);
+
+    let body = client.get(uri!(super::index_admin(Some(1)))).dispatch().await.into_string().await.unwrap();
+    assert!(body.contains("1 of 3 feeds"));
+
+    let response = client.put(uri!(super::update_user_quota_admin(member.id)))
+      .header(ContentType::Form)
+      .body("max_feeds=5&max_daily_deliveries=")
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::SeeOther);
+
+    let member = User::find(member.id, &pool).await?;
+    assert_eq!(member.max_feeds, Some(5));
+    assert_eq!(member.max_daily_deliveries, None);
+
+    let body = client.get(uri!(super::index_admin(Some(1)))).dispatch().await.into_string().await.unwrap();
+    assert!(body.contains("1 of 5 feeds"));
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn domain_blocks_admin(pool: PgPool) -> sqlx::Result<()> {
+    use rocket::http::ContentType;
+    use crate::models::BlockedDomain;
+
+    let user = real_admin_user(&pool).await.unwrap();
+
+    let server: Rocket<Build> = build_test_server(pool.clone()).await;
+    let client = Client::tracked(server).await.unwrap();
+
+    crate::utils::test_helpers::login_user(&client, &user).await;
+
+    let response = client.post(uri!(super::create_domain_block_admin()))
+      .header(ContentType::Form)
+      .body("domain=Bad.Place&severity=silence&reason=spam&public_note=")
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::SeeOther);
+
+    let block = BlockedDomain::find("bad.place", &pool).await?.unwrap();
+    assert_eq!(block.severity, "silence");
+    assert_eq!(block.reason, Some("spam".to_string()));
+    assert_eq!(block.public_note, None);
+
+    let body = client.get(uri!(super::index_domain_blocks_admin())).dispatch().await.into_string().await.unwrap();
+    assert!(body.contains("bad.place"));
+
+    let body = client.get(uri!(super::export_domain_blocks_admin())).dispatch().await.into_string().await.unwrap();
+    assert!(body.contains("bad.place,silence"));
+
+    let response = client.post(uri!(super::import_domain_blocks_admin()))
+      .header(ContentType::Form)
+      .body("csv=worse.place%0Aworst.place")
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::SeeOther);
+    assert_eq!(BlockedDomain::all(&pool).await?.len(), 3);
+
+    let body = client.get(uri!(super::index_domain_blocks_admin())).dispatch().await.into_string().await.unwrap();
+    assert!(body.contains("Imported 2 blocks!"));
+
+    let response = client.delete(uri!(super::delete_domain_block_admin("bad.place"))).dispatch().await;
+    assert_eq!(response.status(), Status::SeeOther);
+    assert!(BlockedDomain::find("bad.place", &pool).await?.is_none());
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn relays_admin(pool: PgPool) -> sqlx::Result<()> {
+    use rocket::http::ContentType;
+    use crate::models::Relay;
+    use crate::utils::test_helpers::real_feed;
+
+    let mut relay_server = mockito::Server::new_async().await;
+    let follow = relay_server.mock("POST", "/inbox")
+      .with_status(202)
+      .match_body(mockito::Matcher::PartialJsonString(r#"{"type":"Follow"}"#.to_string()))
+      .create_async()
+      .await;
+    let undo = relay_server.mock("POST", "/inbox")
+      .with_status(202)
+      .match_body(mockito::Matcher::PartialJsonString(r#"{"type":"Undo"}"#.to_string()))
+      .create_async()
+      .await;
+
+    let user = real_admin_user(&pool).await.unwrap();
+    let admin_feed = real_feed(&pool).await?;
+    admin_feed.mark_admin(&pool).await?;
+
+    let server: Rocket<Build> = build_test_server(pool.clone()).await;
+    let client = Client::tracked(server).await.unwrap();
+
+    crate::utils::test_helpers::login_user(&client, &user).await;
+
+    let relay_url = format!("{}/inbox", relay_server.url());
+    let response = client.post(uri!(super::create_relay_admin()))
+      .header(ContentType::Form)
+      .body(format!("url={relay_url}"))
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::SeeOther);
+    follow.assert_async().await;
+
+    let relay = Relay::all(&pool).await?.pop().unwrap();
+    assert_eq!(relay.url, relay_url);
+
+    let body = client.get(uri!(super::index_admin(Some(1)))).dispatch().await.into_string().await.unwrap();
+    assert!(body.contains("(mastodon, pending)"));
+
+    let response = client.delete(uri!(super::delete_relay_admin(relay.id))).dispatch().await;
+    assert_eq!(response.status(), Status::SeeOther);
+    undo.assert_async().await;
+    assert!(Relay::all(&pool).await?.is_empty());
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn deliveries_admin(pool: PgPool) -> sqlx::Result<()> {
+    use crate::models::Delivery;
+    use crate::models::delivery::PENDING;
+    use crate::utils::test_helpers::real_feed;
+
+    let user = real_admin_user(&pool).await.unwrap();
+    let feed = real_feed(&pool).await?;
+
+    let mut delivery = Delivery::create(feed.id, "https://dead.place/inbox", "{}", &pool).await?;
+    delivery.record_attempt(Some(410), 50, Some("410 Gone".to_string()), &pool).await?;
+    delivery.mark_dead(&pool).await?;
+
+    let server: Rocket<Build> = build_test_server(pool.clone()).await;
+    let client = Client::tracked(server).await.unwrap();
+
+    crate::utils::test_helpers::login_user(&client, &user).await;
+
+    let body = client.get(uri!(super::index_deliveries_admin())).dispatch().await.into_string().await.unwrap();
+    assert!(body.contains("dead.place"));
+    assert!(body.contains("410 Gone"));
+
+    let response = client.post(uri!(super::replay_delivery_admin(delivery.id))).dispatch().await;
+    assert_eq!(response.status(), Status::SeeOther);
+    assert_eq!(Delivery::find(delivery.id, &pool).await?.unwrap().status, PENDING);
+
+    // only dead deliveries can be replayed
+    let response = client.post(uri!(super::replay_delivery_admin(delivery.id))).dispatch().await;
+    assert_eq!(response.status(), Status::NotFound);
+
+    let response = client.delete(uri!(super::delete_delivery_admin(delivery.id))).dispatch().await;
+    assert_eq!(response.status(), Status::SeeOther);
+    assert!(Delivery::find(delivery.id, &pool).await?.is_none());
+
+    Ok(())
+  }
 }
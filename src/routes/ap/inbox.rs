@@ -7,10 +7,17 @@ use rocket::State;
 use sqlx::postgres::PgPool;
 
 use crate::models::Actor;
+use crate::models::BlockedDomain;
+use crate::models::blocked_domain::REJECT;
 
 use crate::models::Feed;
 use crate::models::feed::AcceptedActivity;
 use crate::models::Message;
+use crate::tasks::HandleActivity;
+use crate::utils::queue::create_queue;
+
+use fang::AsyncRunnable;
+use fang::AsyncQueueable;
 
 use rocket::serde::json::Json;
 
@@ -21,6 +28,8 @@ use chrono::{Duration, NaiveDateTime, Utc};
 
 use std::env;
 
+use url::Url;
+
 use base64::{Engine as _, engine::general_purpose};
 
 
@@ -39,6 +48,39 @@ impl SignatureValidity {
   pub fn is_secure(self) -> bool {
     matches!(self, SignatureValidity::Valid(_))
   }
+
+  ///
+  /// Check if this signature is good enough for an authorized fetch. It
+  /// needs to be valid, and can't come from a blocked domain
+  ///
+  pub async fn permits_fetch(&self, pool: &PgPool) -> bool {
+    match self {
+      SignatureValidity::Valid(key_id) => {
+        let host = Url::parse(key_id)
+          .ok()
+          .and_then(|url| url.host_str().map(|host| host.to_string()));
+
+        match host {
+          Some(host) => !BlockedDomain::rejects(&host, pool).await.unwrap_or(true),
+          None => false
+        }
+      },
+      _ => false
+    }
+  }
+
+  ///
+  /// Make sure the request is allowed to see the ActivityPub data for
+  /// this feed. If the feed is in secure mode, we require a signature
+  ///
+  pub async fn authorize_fetch(&self, feed: &Feed, pool: &PgPool) -> Result<(), Status> {
+    let required = feed.requires_signed_fetch(pool).await.unwrap_or(true);
+    if required && !self.permits_fetch(pool).await {
+      return Err(Status::Unauthorized)
+    }
+
+    Ok(())
+  }
 }
 
 ///
@@ -85,7 +127,7 @@ impl<'r> FromRequest<'r> for SignatureValidity {
 
     let mut header_data:rocket::http::HeaderMap<'_> = request.headers().clone();
     if !header_data.contains("(request-target)") {
-      header_data.add_raw("(request-target)", format!("post {}", request.uri()));
+      header_data.add_raw("(request-target)", format!("{} {}", request.method().as_str().to_lowercase(), request.uri()));
     }
 
     let headers = headers
@@ -102,64 +144,87 @@ impl<'r> FromRequest<'r> for SignatureValidity {
         .join("\n");
 
     let key_id = key_id.expect("Missing key_id??");
-    let sender = Actor::find_or_fetch(key_id, pool).await;
-    match sender {
-      Ok(sender) => {
-        if sender.is_none() {
-          // log::info!("unable to find sender!");
-          return Outcome::Success(SignatureValidity::InvalidActor(String::from(key_id)));
-        }
-
-        let sender = sender.expect("Unable to load sender data!");
+    let signature = general_purpose::STANDARD.decode(signature).unwrap_or_default();
+
+    let local_feed = Feed::find_by_ap_url(key_id, pool).await;
+    if let Ok(Some(feed)) = local_feed {
+      // this is one of our feeds, so check the keys directly. this also
+      // covers the grace period after a key rotation
+      if !feed.verify_signature(&signature_verification_payload, &signature).unwrap_or(false) {
+        return Outcome::Success(SignatureValidity::InvalidSignature(String::from(key_id)));
+      }
+    } else {
+      let sender = Actor::find_or_fetch(key_id, pool).await;
+      match sender {
+        Ok(sender) => {
+          if sender.is_none() {
+            // log::info!("unable to find sender!");
+            return Outcome::Success(SignatureValidity::InvalidActor(String::from(key_id)));
+          }
 
-        // .verify_signature(&signature_verification_payload, &base64::decode(signature).unwrap_or_default())
-        if !sender
-          .verify_signature(&signature_verification_payload, &general_purpose::STANDARD.decode(signature).unwrap_or_default())
-          .unwrap_or(false)
-        {
-          // log::info!("unable to verify signature!");
-          return Outcome::Success(SignatureValidity::InvalidSignature(String::from(key_id)));
+          let sender = sender.expect("Unable to load sender data!");
+
+          if !sender
+            .verify_signature(&signature_verification_payload, &signature)
+            .unwrap_or(false)
+          {
+            // the sender might have rotated their keys, so refetch them
+            // and try again before giving up, unless we just did that
+            let verified = if sender.can_refetch() {
+              match Actor::refetch(key_id, pool).await {
+                Ok(Some(sender)) => sender.verify_signature(&signature_verification_payload, &signature).unwrap_or(false),
+                _ => false
+              }
+            } else {
+              false
+            };
+
+            if !verified {
+              // log::info!("unable to verify signature!");
+              return Outcome::Success(SignatureValidity::InvalidSignature(String::from(key_id)));
+            }
+          }
+        },
+        Err(_why) => {
+          // log::info!("fetch failure? {why:?}");
+          return Outcome::Success(SignatureValidity::Invalid);
         }
+      }
+    }
 
-        // @todo digest check
-        // if !headers.contains(&"digest") {
-        //   // signature is valid, but body content is not verified
-        //   // return SignatureValidity::ValidNoDigest;
-        //   return Outcome::Forward(());
-        // }
-
-        // let digest = request.headers().get_one("digest").unwrap_or("");
-        // let digest = request::Digest::from_header(digest);
-
-        // @todo get/check digest of body content
-        // if !digest.map(|d| d.verify_header(data)).unwrap_or(false) {
-        //   // signature was valid, but body content does not match its digest
-        //   // return SignatureValidity::Invalid;
-        //   return Outcome::Forward(());
-        // }
-
-        let date = request.headers().get_one("date");
-        if date.is_none() {
-          return Outcome::Success(SignatureValidity::Outdated(String::from(key_id)));
-        }
+    // @todo digest check
+    // if !headers.contains(&"digest") {
+    //   // signature is valid, but body content is not verified
+    //   // return SignatureValidity::ValidNoDigest;
+    //   return Outcome::Forward(());
+    // }
+
+    // let digest = request.headers().get_one("digest").unwrap_or("");
+    // let digest = request::Digest::from_header(digest);
+
+    // @todo get/check digest of body content
+    // if !digest.map(|d| d.verify_header(data)).unwrap_or(false) {
+    //   // signature was valid, but body content does not match its digest
+    //   // return SignatureValidity::Invalid;
+    //   return Outcome::Forward(());
+    // }
+
+    let date = request.headers().get_one("date");
+    if date.is_none() {
+      return Outcome::Success(SignatureValidity::Outdated(String::from(key_id)));
+    }
 
-        let date = NaiveDateTime::parse_from_str(date.unwrap(), "%a, %d %h %Y %T GMT");
-        if date.is_err() {
-          return Outcome::Success(SignatureValidity::Outdated(String::from(key_id)));
-        }
-        let diff = Utc::now().naive_utc() - date.unwrap();
-        let future = Duration::hours(12);
-        let past = Duration::hours(-12);
-        if diff < future && diff > past {
-          return Outcome::Success(SignatureValidity::Valid(String::from(key_id)));
-        } else {
-          return Outcome::Success(SignatureValidity::Outdated(String::from(key_id)));
-        }
-      },
-      Err(_why) => {
-        // log::info!("fetch failure? {why:?}");
-        Outcome::Success(SignatureValidity::Invalid)
-      }        
+    let date = NaiveDateTime::parse_from_str(date.unwrap(), "%a, %d %h %Y %T GMT");
+    if date.is_err() {
+      return Outcome::Success(SignatureValidity::Outdated(String::from(key_id)));
+    }
+    let diff = Utc::now().naive_utc() - date.unwrap();
+    let future = Duration::hours(12);
+    let past = Duration::hours(-12);
+    if diff < future && diff > past {
+      Outcome::Success(SignatureValidity::Valid(String::from(key_id)))
+    } else {
+      Outcome::Success(SignatureValidity::Outdated(String::from(key_id)))
     }
   }
 
@@ -176,8 +241,21 @@ impl<'r> FromRequest<'r> for SignatureValidity {
 ///
 /// https://www.w3.org/TR/activitypub/#inbox
 ///
+/// Once the activity is verified we log it and hand it off to the job
+/// queue, so we can respond right away with a 202
+///
 #[post("/feed/<username>/inbox", data="<activity>")]
-pub async fn user_inbox(digest: Option<SignatureValidity>, username: &str, activity: Json<AcceptedActivity>, db: &State<PgPool>) -> Result<(), Status> {
+pub async fn user_inbox(digest: Option<SignatureValidity>, username: &str, activity: Json<AcceptedActivity>, db: &State<PgPool>) -> Result<Status, Status> {
+  // refuse anything from a rejected domain before doing any more work
+  let (sender, _object, _act) = activity.deref().clone().into_parts();
+  if let Some(sender) = sender.as_single_id() {
+    if let Ok(Some(block)) = BlockedDomain::for_actor(sender.as_str(), db).await {
+      if block.severity == REJECT {
+        return Err(Status::Forbidden)
+      }
+    }
+  }
+
   let msg = serde_json::to_string(activity.deref()).unwrap();
   // log::info!("{:}", msg);
 
@@ -208,27 +286,25 @@ pub async fn user_inbox(digest: Option<SignatureValidity>, username: &str, activ
   }
 
 
-  let feed_lookup = Feed::find_by_name(&username.to_string(), db).await;
+  match Feed::find_by_name(&username.to_string(), db).await {
+    Ok(Some(_feed)) => {},
+    _ => return Err(Status::NotFound)
+  };
 
-  let result = match feed_lookup {
-    Ok(feed_lookup) => {
-      match feed_lookup {
-        Some(feed) => {
-          let handle = feed.handle_activity(db, &activity).await;
-          match handle {
-            Ok(_handle) => Status::Accepted,
-            Err(_why) => Status::NotFound
-          }
-        },
-        None => return Err(Status::NotFound)
-      }
-    },
-    Err(_why) => return Err(Status::NotFound)
+  let message = Message::log(&username.to_string(), &msg, actor.cloned(), error, false, db).await;
+  let message = match message {
+    Ok(message) => message,
+    Err(_why) => return Err(Status::InternalServerError)
   };
 
-  let _log_result = Message::log(&username.to_string(), &msg, actor.cloned(), error, result == Status::Accepted, db).await;
+  let task = HandleActivity::new(message.id);
+  let mut queue = create_queue().await;
+  queue.connect(fang::NoTls).await.unwrap();
 
-  Ok(())
+  match queue.insert_task(&task as &dyn AsyncRunnable).await {
+    Ok(_task) => Ok(Status::Accepted),
+    Err(_why) => Err(Status::InternalServerError)
+  }
 }
 
 #[cfg(test)]
@@ -243,6 +319,21 @@ mod test {
 
   use crate::utils::test_helpers::{build_test_server, real_feed};
   
+  #[sqlx::test]
+  async fn test_permits_fetch(pool: PgPool) -> sqlx::Result<()> {
+    use crate::models::BlockedDomain;
+    use super::SignatureValidity;
+
+    BlockedDomain::create(&"blocked.com".to_string(), &pool).await?;
+
+    assert!(SignatureValidity::Valid("https://ok.com/users/ok#main-key".to_string()).permits_fetch(&pool).await);
+    assert!(!SignatureValidity::Valid("https://blocked.com/users/bad#main-key".to_string()).permits_fetch(&pool).await);
+    assert!(!SignatureValidity::Absent.permits_fetch(&pool).await);
+    assert!(!SignatureValidity::Outdated("https://ok.com/users/ok#main-key".to_string()).permits_fetch(&pool).await);
+
+    Ok(())
+  }
+
   #[sqlx::test]
   async fn test_user_inbox(pool: PgPool) -> sqlx::Result<()> {
     let feed = real_feed(&pool).await.unwrap();
@@ -255,7 +346,27 @@ mod test {
 
     let response = req.dispatch().await;
 
-    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.status(), Status::Accepted);
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_user_inbox_blocked_domain(pool: PgPool) -> sqlx::Result<()> {
+    use crate::models::BlockedDomain;
+
+    BlockedDomain::block("blocked.com", "reject", None, None, &pool).await?;
+
+    let feed = real_feed(&pool).await.unwrap();
+    let json = json!({"actor":"https://blocked.com/users/bad","object":feed.ap_url(),"id":"https://blocked.com/follows/1","@context":["https://www.w3.org/ns/activitystreams"],"type":"Follow"});
+
+    let server:Rocket<Build> = build_test_server(pool).await;
+    let client = Client::tracked(server).await.unwrap();
+
+    let req = client.post(uri!(super::user_inbox(&feed.name))).json(&json);
+    let response = req.dispatch().await;
+
+    assert_eq!(response.status(), Status::Forbidden);
 
     Ok(())
   }
@@ -6,6 +6,8 @@ use rocket::State;
 use sqlx::postgres::PgPool;
 
 use crate::models::Feed;
+use crate::models::feed::PageCursor;
+use crate::routes::ap::inbox::SignatureValidity;
 use crate::traits::ActivityJsonContentType;
 
 
@@ -20,25 +22,30 @@ use crate::traits::ActivityJsonContentType;
 /// published by the user, though the number of available items is left to the
 /// discretion of those implementing and deploying the server. 
 ///
-#[get("/feed/<username>/outbox?<page>")]
-pub async fn render_feed_outbox(username: &str, page: Option<i32>, db: &State<PgPool>) -> Result<ActivityJsonContentType<String>, Status> {
+/// Pages are requested with ?page=true, and use max_id/min_id to
+/// move through the items
+///
+#[get("/feed/<username>/outbox?<page>&<max_id>&<min_id>")]
+pub async fn render_feed_outbox(signature: SignatureValidity, username: &str, page: Option<bool>, max_id: Option<i32>, min_id: Option<i32>, db: &State<PgPool>) -> Result<ActivityJsonContentType<String>, Status> {
   let feed_lookup = Feed::find_by_name(&username.to_string(), db).await;
 
   match feed_lookup {
     Ok(feed_lookup) => {
       match feed_lookup {
         Some(feed) => {
+          signature.authorize_fetch(&feed, db).await?;
+
           // if we got a page param, return a page of outbox items
           // otherwise, return the summary
-          let json = match page {
-            Some(page) => {
-              let result = feed.outbox_paged(page, db).await;
+          let json = match page.unwrap_or(false) || max_id.is_some() || min_id.is_some() {
+            true => {
+              let result = feed.outbox_paged(&PageCursor { max_id, min_id }, db).await;
               match result {
                 Ok(result) => Ok(ActivityJsonContentType(serde_json::to_string(&result).unwrap())),
                 Err(_why) => Err(Status::InternalServerError)
               }
             },
-            None => {
+            false => {
               let result = feed.outbox(db).await;
               match result {
                 Ok(result) => Ok(ActivityJsonContentType(serde_json::to_string(&result).unwrap())),
@@ -67,6 +74,24 @@ mod test {
 
   use crate::utils::test_helpers::{build_test_server, real_feed};
 
+  #[sqlx::test]
+  async fn test_render_feed_outbox_instance_secure_mode(pool: PgPool) -> sqlx::Result<()> {
+    use crate::models::Setting;
+
+    let feed = real_feed(&pool).await.unwrap();
+    Setting::update(&"secure_mode".to_string(), &"true".to_string(), &pool).await?;
+
+    let server: Rocket<Build> = build_test_server(pool).await;
+    let client = Client::tracked(server).await.unwrap();
+
+    let req = client.get(uri!(super::render_feed_outbox(&feed.name, None::<bool>, None::<i32>, None::<i32>)));
+    let response = req.dispatch().await;
+
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    Ok(())
+  }
+
   #[sqlx::test]
   async fn test_render_feed_outbox(pool: PgPool) -> sqlx::Result<()> {
     let feed = real_feed(&pool).await.unwrap();
@@ -75,12 +100,19 @@ mod test {
     let client = Client::tracked(server).await.unwrap();
 
     let name = feed.name;
-    let req = client.get(uri!(super::render_feed_outbox(&name, Some(2))));
+    let req = client.get(uri!(super::render_feed_outbox(&name, Some(true), None::<i32>, None::<i32>)));
     let response = req.dispatch().await;
 
     assert_eq!(response.status(), Status::Ok);
     assert_eq!(response.content_type().unwrap().to_string(), "application/activity+json");
 
+    let req = client.get(uri!(super::render_feed_outbox(&name, None::<bool>, Some(1), None::<i32>)));
+    let response = req.dispatch().await;
+
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_string().await.unwrap();
+    assert!(body.contains("OrderedCollectionPage"));
+
     Ok(())
   }
 }
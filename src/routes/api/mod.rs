@@ -0,0 +1,43 @@
+use rocket::catch;
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::{Serialize, json::Json};
+use rocket::Request;
+
+pub mod v1;
+
+///
+/// The body of every error the API returns
+///
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ApiError {
+  pub error: String
+}
+
+pub type ApiResult<T> = Result<T, Custom<Json<ApiError>>>;
+
+pub fn api_error(status: Status, message: &str) -> Custom<Json<ApiError>> {
+  Custom(status, Json(ApiError { error: message.to_string() }))
+}
+
+pub fn internal_error(why: impl std::fmt::Display) -> Custom<Json<ApiError>> {
+  log::info!("api error: {why}");
+  api_error(Status::InternalServerError, "Sorry, something went wrong!")
+}
+
+///
+/// Anything that goes wrong under /api before a handler runs, like a
+/// missing token or a body that doesn't parse, still gets a JSON answer
+///
+#[catch(default)]
+pub fn default_catcher(status: Status, _request: &Request) -> Custom<Json<ApiError>> {
+  let message = match status.code {
+    401 => "A valid API token is required",
+    404 => "Not found",
+    400 | 422 => "The request body isn't valid",
+    _ => status.reason().unwrap_or("Sorry, something went wrong!")
+  };
+
+  api_error(status, message)
+}
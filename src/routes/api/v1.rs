@@ -0,0 +1,451 @@
+use rocket::{get, post, put, delete};
+use rocket::http::Status;
+use rocket::response::content::RawJson;
+use rocket::response::status::{Accepted, Created, NoContent};
+use rocket::serde::{Serialize, Deserialize, json::Json};
+use rocket::State;
+
+use chrono::Utc;
+
+use sqlx::postgres::PgPool;
+
+use crate::PER_PAGE;
+use crate::models::{Feed, Follower, Item, User};
+use crate::models::follower::FollowerDetails;
+use crate::models::feed::feed_max_error_count;
+use crate::models::feed_member::{EDITOR, OWNER, VIEWER};
+use crate::routes::api::{api_error, internal_error, ApiResult};
+use crate::services::feed_manager::{create_feed, request_feed_update, update_feed as apply_feed_settings, FeedManagerError, FeedSettings};
+use crate::traits::ApiUser;
+
+///
+/// A feed as the API shows it. This leaves out the keys and anything
+/// else that only matters inside the app
+///
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ApiFeed {
+  pub id: i32,
+  pub name: String,
+  pub address: String,
+  pub ap_url: String,
+  pub url: String,
+  pub site_url: Option<String>,
+  pub image_url: Option<String>,
+  pub title: Option<String>,
+  pub description: Option<String>,
+  pub listed: bool,
+  pub status_publicity: Option<String>,
+  pub content_warning: Option<String>,
+  pub hashtag: Option<String>,
+  pub also_known_as: Option<String>,
+  pub moved_to: Option<String>,
+  pub secure_mode: bool,
+  pub manually_approves_followers: bool,
+  pub followers_visibility: Option<String>,
+  pub object_type: String,
+  pub redirect_items: bool,
+  pub paused: bool,
+  pub error: Option<String>,
+  pub error_count: i32,
+  pub refreshed_at: chrono::DateTime::<Utc>,
+  pub last_post_at: Option<chrono::DateTime::<Utc>>,
+  pub created_at: chrono::DateTime::<Utc>,
+  pub updated_at: chrono::DateTime::<Utc>
+}
+
+impl From<&Feed> for ApiFeed {
+  fn from(feed: &Feed) -> Self {
+    ApiFeed {
+      id: feed.id,
+      name: feed.name.clone(),
+      address: feed.address(),
+      ap_url: feed.ap_url(),
+      url: feed.url.clone(),
+      site_url: feed.site_url.clone(),
+      image_url: feed.image_url.clone(),
+      title: feed.title.clone(),
+      description: feed.description.clone(),
+      listed: feed.listed,
+      status_publicity: feed.status_publicity.clone(),
+      content_warning: feed.content_warning.clone(),
+      hashtag: feed.hashtag.clone(),
+      also_known_as: feed.also_known_as.clone(),
+      moved_to: feed.moved_to.clone(),
+      secure_mode: feed.secure_mode,
+      manually_approves_followers: feed.manually_approves_followers,
+      followers_visibility: feed.followers_visibility.clone(),
+      object_type: feed.object_type.clone(),
+      redirect_items: feed.redirect_items,
+      paused: feed.paused,
+      error: feed.error.clone(),
+      error_count: feed.error_count,
+      refreshed_at: feed.refreshed_at,
+      last_post_at: feed.last_post_at,
+      created_at: feed.created_at,
+      updated_at: feed.updated_at
+    }
+  }
+}
+
+///
+/// Whether a feed is refreshing happily. Once a feed fails too many
+/// times in a row we stop refreshing it until it's fixed
+///
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ApiFeedStatus {
+  pub paused: bool,
+  pub failing: bool,
+  pub error: Option<String>,
+  pub error_count: i32,
+  pub refreshed_at: chrono::DateTime::<Utc>,
+  pub last_post_at: Option<chrono::DateTime::<Utc>>
+}
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ApiFeedList {
+  pub feeds: Vec<ApiFeed>,
+  pub page: i32,
+  pub total: i32
+}
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ApiItemList {
+  pub items: Vec<Item>,
+  pub total: i32
+}
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ApiFollowerList {
+  pub followers: Vec<FollowerDetails>,
+  pub page: i32,
+  pub total: i32
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct NewFeed {
+  pub name: String,
+  pub url: String
+}
+
+/// The OpenAPI description of this version of the API
+const OPENAPI_DOCUMENT: &str = include_str!("openapi-v1.json");
+
+///
+/// Find a feed the user has at least the given role on. Members who
+/// don't have the role get a 403, everyone else a 404
+///
+async fn find_feed(user: &User, name: &str, role: &str, db: &PgPool) -> ApiResult<Feed> {
+  match Feed::find_by_user_and_name(user, &name.to_string(), role, db).await {
+    Ok(Some(feed)) => return Ok(feed),
+    Ok(None) => {},
+    Err(why) => return Err(internal_error(why))
+  };
+
+  match Feed::find_by_user_and_name(user, &name.to_string(), VIEWER, db).await {
+    Ok(Some(_feed)) => Err(api_error(Status::Forbidden, "You don't have permission to do that")),
+    Ok(None) => Err(api_error(Status::NotFound, "Feed not found")),
+    Err(why) => Err(internal_error(why))
+  }
+}
+
+#[get("/openapi.json")]
+pub fn openapi() -> RawJson<&'static str> {
+  RawJson(OPENAPI_DOCUMENT)
+}
+
+#[get("/feeds?<page>")]
+pub async fn list_feeds(user: ApiUser, page: Option<i32>, db: &State<PgPool>) -> ApiResult<Json<ApiFeedList>> {
+  let page = page.unwrap_or(1).max(1);
+  let feeds = Feed::paged_for_user(&user.0, page, db).await.map_err(internal_error)?;
+  let total = Feed::count_for_user(&user.0, db).await.map_err(internal_error)?;
+
+  Ok(Json(ApiFeedList {
+    feeds: feeds.iter().map(ApiFeed::from).collect(),
+    page,
+    total
+  }))
+}
+
+#[post("/feeds", data = "<body>")]
+pub async fn create_feed_api(user: ApiUser, db: &State<PgPool>, body: Json<NewFeed>) -> ApiResult<Created<Json<ApiFeed>>> {
+  match create_feed(&user.0, &body.name, &body.url, db).await {
+    Ok(feed) => {
+      let location = format!("/api/v1/feeds/{}", feed.name);
+      Ok(Created::new(location).body(Json(ApiFeed::from(&feed))))
+    },
+    Err(FeedManagerError::Database(why)) => Err(internal_error(why)),
+    Err(why) => {
+      let status = match why {
+        FeedManagerError::SignupsDisabled | FeedManagerError::UserQuotaReached(_) | FeedManagerError::InstanceQuotaReached => Status::Forbidden,
+        FeedManagerError::NameTaken => Status::Conflict,
+        _ => Status::UnprocessableEntity
+      };
+      Err(api_error(status, &why.to_string()))
+    }
+  }
+}
+
+#[get("/feeds/<name>")]
+pub async fn show_feed_api(user: ApiUser, name: &str, db: &State<PgPool>) -> ApiResult<Json<ApiFeed>> {
+  let feed = find_feed(&user.0, name, VIEWER, db).await?;
+  Ok(Json(ApiFeed::from(&feed)))
+}
+
+#[put("/feeds/<name>", data = "<body>")]
+pub async fn update_feed_api(user: ApiUser, name: &str, db: &State<PgPool>, body: Json<FeedSettings>) -> ApiResult<Json<ApiFeed>> {
+  let mut feed = find_feed(&user.0, name, EDITOR, db).await?;
+  apply_feed_settings(&mut feed, &body, db).await.map_err(internal_error)?;
+
+  Ok(Json(ApiFeed::from(&feed)))
+}
+
+#[delete("/feeds/<name>")]
+pub async fn delete_feed_api(user: ApiUser, name: &str, db: &State<PgPool>) -> ApiResult<NoContent> {
+  let feed = find_feed(&user.0, name, OWNER, db).await?;
+  Feed::delete(&user.0, feed.id, db).await.map_err(internal_error)?;
+
+  Ok(NoContent)
+}
+
+#[post("/feeds/<name>/pause")]
+pub async fn pause_feed_api(user: ApiUser, name: &str, db: &State<PgPool>) -> ApiResult<Json<ApiFeed>> {
+  let mut feed = find_feed(&user.0, name, EDITOR, db).await?;
+  feed.set_paused(true, db).await.map_err(internal_error)?;
+
+  Ok(Json(ApiFeed::from(&feed)))
+}
+
+#[post("/feeds/<name>/resume")]
+pub async fn resume_feed_api(user: ApiUser, name: &str, db: &State<PgPool>) -> ApiResult<Json<ApiFeed>> {
+  let mut feed = find_feed(&user.0, name, EDITOR, db).await?;
+  feed.set_paused(false, db).await.map_err(internal_error)?;
+
+  Ok(Json(ApiFeed::from(&feed)))
+}
+
+#[post("/feeds/<name>/refresh")]
+pub async fn refresh_feed_api(user: ApiUser, name: &str, db: &State<PgPool>) -> ApiResult<Accepted<Json<ApiFeed>>> {
+  let feed = find_feed(&user.0, name, EDITOR, db).await?;
+  request_feed_update(&feed).await.map_err(internal_error)?;
+
+  Ok(Accepted(Json(ApiFeed::from(&feed))))
+}
+
+#[get("/feeds/<name>/status")]
+pub async fn feed_status_api(user: ApiUser, name: &str, db: &State<PgPool>) -> ApiResult<Json<ApiFeedStatus>> {
+  let feed = find_feed(&user.0, name, VIEWER, db).await?;
+
+  Ok(Json(ApiFeedStatus {
+    paused: feed.paused,
+    failing: feed.error_count > feed_max_error_count(),
+    error: feed.error.clone(),
+    error_count: feed.error_count,
+    refreshed_at: feed.refreshed_at,
+    last_post_at: feed.last_post_at
+  }))
+}
+
+#[get("/feeds/<name>/items?<max_id>&<min_id>")]
+pub async fn feed_items_api(user: ApiUser, name: &str, max_id: Option<i32>, min_id: Option<i32>, db: &State<PgPool>) -> ApiResult<Json<ApiItemList>> {
+  let feed = find_feed(&user.0, name, VIEWER, db).await?;
+  let items = Item::page_for_feed(&feed, max_id, min_id, PER_PAGE as i64, db).await.map_err(internal_error)?;
+  let total = feed.entries_count(db).await.map_err(internal_error)?;
+
+  Ok(Json(ApiItemList { items, total }))
+}
+
+#[get("/feeds/<name>/followers?<page>")]
+pub async fn feed_followers_api(user: ApiUser, name: &str, page: Option<i32>, db: &State<PgPool>) -> ApiResult<Json<ApiFollowerList>> {
+  let feed = find_feed(&user.0, name, VIEWER, db).await?;
+  let page = page.unwrap_or(1).max(1);
+  let followers = Follower::details_for_feed(&feed, page, db).await.map_err(internal_error)?;
+  let total = feed.follower_count(db).await.map_err(internal_error)?;
+
+  Ok(Json(ApiFollowerList { followers, page, total }))
+}
+
+#[cfg(test)]
+mod test {
+  use rocket::local::asynchronous::Client;
+  use rocket::http::{ContentType, Header, Status};
+  use rocket::{Rocket, Build};
+  use sqlx::postgres::PgPool;
+  use std::fs;
+
+  use crate::models::{ApiToken, Feed, FeedMember, User};
+  use crate::models::feed_member::VIEWER;
+  use crate::utils::test_helpers::{build_test_server, real_feed, real_item, real_user};
+
+  fn bearer(token: &str) -> Header<'static> {
+    Header::new("Authorization", format!("Bearer {token}"))
+  }
+
+  #[sqlx::test]
+  async fn test_api_requires_token(pool: PgPool) -> sqlx::Result<()> {
+    let server: Rocket<Build> = build_test_server(pool.clone()).await;
+    let client = Client::tracked(server).await.unwrap();
+
+    let response = client.get("/api/v1/feeds").dispatch().await;
+    assert_eq!(response.status(), Status::Unauthorized);
+    assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+    let response = client.get("/api/v1/feeds").header(bearer("nope")).dispatch().await;
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_openapi(pool: PgPool) -> sqlx::Result<()> {
+    let server: Rocket<Build> = build_test_server(pool.clone()).await;
+    let client = Client::tracked(server).await.unwrap();
+
+    let response = client.get("/api/v1/openapi.json").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let doc: serde_json::Value = serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+    assert_eq!(doc["info"]["version"], "1");
+    for path in ["/feeds", "/feeds/{name}", "/feeds/{name}/pause", "/feeds/{name}/resume", "/feeds/{name}/refresh",
+        "/feeds/{name}/status", "/feeds/{name}/items", "/feeds/{name}/followers"] {
+      assert!(doc["paths"][path].is_object(), "{path} is missing");
+    }
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_list_and_show_feeds(pool: PgPool) -> sqlx::Result<()> {
+    let user = real_user(&pool).await?;
+    let feed = real_feed(&pool).await?;
+    real_item(&feed, &pool).await?;
+    let (_api_token, token) = ApiToken::create(&user, "test", &pool).await?;
+
+    let server: Rocket<Build> = build_test_server(pool.clone()).await;
+    let client = Client::tracked(server).await.unwrap();
+
+    let response = client.get("/api/v1/feeds").header(bearer(&token)).dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_string().await.unwrap();
+    assert!(!body.contains("PRIVATE KEY"));
+
+    let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["feeds"][0]["name"], feed.name);
+
+    let response = client.get(format!("/api/v1/feeds/{}", feed.name)).header(bearer(&token)).dispatch().await;
+    let body: serde_json::Value = serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+    assert_eq!(body["ap_url"], feed.ap_url());
+    assert_eq!(body["paused"], false);
+
+    let response = client.get(format!("/api/v1/feeds/{}/status", feed.name)).header(bearer(&token)).dispatch().await;
+    let body: serde_json::Value = serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+    assert_eq!(body["failing"], false);
+    assert_eq!(body["error_count"], 0);
+
+    let response = client.get(format!("/api/v1/feeds/{}/items", feed.name)).header(bearer(&token)).dispatch().await;
+    let body: serde_json::Value = serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["items"].as_array().unwrap().len(), 1);
+
+    let response = client.get(format!("/api/v1/feeds/{}/followers", feed.name)).header(bearer(&token)).dispatch().await;
+    let body: serde_json::Value = serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+    assert_eq!(body["total"], 0);
+
+    let response = client.get("/api/v1/feeds/not-a-feed").header(bearer(&token)).dispatch().await;
+    assert_eq!(response.status(), Status::NotFound);
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_update_pause_and_delete(pool: PgPool) -> sqlx::Result<()> {
+    let user = real_user(&pool).await?;
+    let feed = real_feed(&pool).await?;
+    let (_api_token, token) = ApiToken::create(&user, "test", &pool).await?;
+
+    let viewer = User::find_or_create_by_actor_url(&"https://activitypub.pizza/users/viewer".to_string(), &pool).await?;
+    FeedMember::add(&feed, &viewer, VIEWER, &pool).await?;
+    let (_api_token, viewer_token) = ApiToken::create(&viewer, "test", &pool).await?;
+
+    let server: Rocket<Build> = build_test_server(pool.clone()).await;
+    let client = Client::tracked(server).await.unwrap();
+    let path = format!("/api/v1/feeds/{}", feed.name);
+    let settings = r#"{"url": "https://foo.com/rss.xml", "title": "Muffins", "listed": true, "followers_visibility": "count"}"#;
+
+    let response = client.put(&path).header(bearer(&viewer_token)).header(ContentType::JSON).body(settings).dispatch().await;
+    assert_eq!(response.status(), Status::Forbidden);
+
+    let response = client.put(&path).header(bearer(&token)).header(ContentType::JSON).body(settings).dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let updated = Feed::find(feed.id, &pool).await?;
+    assert_eq!(updated.title, Some("Muffins".to_string()));
+    assert!(updated.listed);
+    assert_eq!(updated.followers_visibility, Some("count".to_string()));
+
+    let response = client.put(&path).header(bearer(&token)).header(ContentType::JSON).body("{}").dispatch().await;
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+    assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+    let response = client.post(format!("{path}/pause")).header(bearer(&token)).dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    assert!(Feed::find(feed.id, &pool).await?.paused);
+
+    let response = client.post(format!("{path}/resume")).header(bearer(&token)).dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    assert!(!Feed::find(feed.id, &pool).await?.paused);
+
+    let response = client.post(format!("{path}/refresh")).header(bearer(&token)).dispatch().await;
+    assert_eq!(response.status(), Status::Accepted);
+
+    let response = client.delete(&path).header(bearer(&viewer_token)).dispatch().await;
+    assert_eq!(response.status(), Status::Forbidden);
+
+    let response = client.delete(&path).header(bearer(&token)).dispatch().await;
+    assert_eq!(response.status(), Status::NoContent);
+    assert!(Feed::find_by_name(&feed.name, &pool).await?.is_none());
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_create_feed(pool: PgPool) -> sqlx::Result<()> {
+    let user = User::find_or_create_by_email(&"muffins@muffin.industries".to_string(), &pool).await?;
+    let (_api_token, token) = ApiToken::create(&user, "test", &pool).await?;
+
+    let mut server = mockito::Server::new_async().await;
+    let _m = server.mock("GET", "/feed.xml")
+      .with_status(200)
+      .with_body(fs::read_to_string("fixtures/test_feed_to_entries.xml").unwrap())
+      .create_async()
+      .await;
+    let url = format!("{}/feed.xml", server.url());
+
+    let rocket: Rocket<Build> = build_test_server(pool.clone()).await;
+    let client = Client::tracked(rocket).await.unwrap();
+
+    let body = serde_json::json!({ "name": "muffins", "url": url }).to_string();
+    let response = client.post("/api/v1/feeds").header(bearer(&token)).header(ContentType::JSON).body(&body).dispatch().await;
+    assert_eq!(response.status(), Status::Created);
+    assert_eq!(response.headers().get_one("Location"), Some("/api/v1/feeds/muffins"));
+
+    let feed = Feed::find_by_name(&"muffins".to_string(), &pool).await?.unwrap();
+    assert_eq!(feed.url, url);
+    assert_eq!(Feed::for_user(&user, &pool).await?.len(), 1);
+
+    let response = client.post("/api/v1/feeds").header(bearer(&token)).header(ContentType::JSON).body(&body).dispatch().await;
+    assert_eq!(response.status(), Status::Conflict);
+
+    let body = serde_json::json!({ "name": "bad name", "url": url }).to_string();
+    let response = client.post("/api/v1/feeds").header(bearer(&token)).header(ContentType::JSON).body(&body).dispatch().await;
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+
+    Ok(())
+  }
+}
@@ -8,9 +8,6 @@ use rocket_dyn_templates::{Template, context};
 use rocket::uri;
 use rocket::serde::{Serialize, json::Json};
 
-use fang::AsyncRunnable;
-use fang::AsyncQueueable;
-
 use std::env;
 
 use sqlx::postgres::PgPool;
@@ -18,13 +15,24 @@ use sqlx::postgres::PgPool;
 use crate::models::User;
 use crate::models::Feed;
 use crate::models::Item;
-use crate::models::Setting;
+use crate::models::FollowRequest;
+use crate::models::Follower;
+use crate::models::DmSubscriber;
+use crate::models::FeedBlock;
+use crate::models::FeedMember;
+use crate::models::feed_member::{role_allows, OWNER, EDITOR, VIEWER, ROLES, LAST_OWNER_ERROR};
+use crate::models::feed_block::{ACTOR, DOMAIN};
+use crate::models::feed::{FOLLOWERS_PUBLIC, FOLLOWERS_HIDDEN, PageCursor};
+
+use crate::PER_PAGE;
+use crate::DeliveryError;
 
 use crate::services::url_to_feed::url_to_feed_url;
+use crate::services::feed_manager::{create_feed, FeedManagerError, FeedSettings};
 
 use crate::utils::queue::create_queue;
 
-use crate::tasks::RefreshFeed;
+use crate::routes::ap::inbox::SignatureValidity;
 use crate::traits::ActivityJsonContentType;
 
 
@@ -38,14 +46,28 @@ pub struct FeedForm {
 
 #[derive(FromForm, serde::Deserialize)]
 #[serde(crate = "rocket::serde")]
-pub struct FeedUpdateForm {
-  url: String,
-  listed: bool,
-  status_publicity: Option<String>,
-  content_warning: Option<String>,
-  hashtag: Option<String>,
-  title: Option<String>,
-  description: Option<String>
+pub struct MoveFeedForm {
+  target: String
+}
+
+#[derive(FromForm, serde::Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct PauseFeedForm {
+  paused: bool
+}
+
+#[derive(FromForm, serde::Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct FeedBlockForm {
+  kind: String,
+  target: String
+}
+
+#[derive(FromForm, serde::Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct FeedMemberForm {
+  handle: String,
+  role: String
 }
 
 #[derive(Serialize)]
@@ -56,110 +78,316 @@ pub struct FeedLookup {
   error: Option<String>
 }
 
-///
-/// After creating/updating a feed, let's refresh its data
-/// 
-async fn request_feed_update(feed: &Feed) -> Result<fang::Task, fang::AsyncQueueError> {
-  let task = RefreshFeed { id: feed.id };
-  let mut queue = create_queue().await;
-  queue.connect(fang::NoTls).await.unwrap();
-
-  queue
-    .insert_task(&task as &dyn AsyncRunnable)
-    .await
-}
-
 ///
 /// POST action to create a new feed
 ///
 #[post("/feed", data = "<form>")]
 pub async fn add_feed(user: User, db: &State<PgPool>, form: Form<FeedForm>) -> Result<Flash<Redirect>, Status> {
-  let signups_enabled = Setting::value_or(&"signups_enabled".to_string(), &"true".to_string(), db).await.unwrap();
+  match create_feed(&user, &form.name, &form.url, db).await {
+    Ok(feed) => {
+      let dest = uri!(show_feed(feed.name, Some(1)));
+      Ok(Flash::success(Redirect::to(dest), "Feed created!"))
+    },
+    Err(FeedManagerError::InvalidUrl) => Err(Status::NotFound),
+    Err(FeedManagerError::Database(why)) => {
+      log::info!("{why}");
+      Ok(Flash::error(Redirect::to("/"), "Sorry, something went wrong!"))
+    },
+    Err(why) => Ok(Flash::error(Redirect::to("/"), why.to_string()))
+  }
+}
 
-  if signups_enabled != "true" {
-    return Ok(Flash::error(Redirect::to("/"), "Sorry, something went wrong!"));
+///
+/// Update settings on a feed
+///
+#[put("/feed/<username>", data = "<form>")]
+pub async fn update_feed(user: User, username: &str, db: &State<PgPool>, form: Form<FeedSettings>) -> Result<Flash<Redirect>, Status> {
+  let mut feed = match Feed::find_by_user_and_name(&user, &username.to_string(), EDITOR, db).await {
+    Ok(Some(feed)) => feed,
+    _ => return Err(Status::NotFound)
+  };
+
+  let dest = uri!(show_feed(&feed.name, None::<i32>));
+  match crate::services::feed_manager::update_feed(&mut feed, &form, db).await {
+    Ok(_result) => Ok(Flash::success(Redirect::to(dest), "Feed updated!")),
+    Err(_why) => Ok(Flash::error(Redirect::to(dest), "Sorry, something went wrong!"))
   }
+}
 
-  //
-  // follow the URL to make sure we add a valid RSS feed at this point
-  //
-  let url = url_to_feed_url(&form.url).await;
-  match url {
-    Err(_why) =>{
-      Err(Status::NotFound)
-    },
-    Ok(result) => {
-      if result.is_some() {
-        let url = result.unwrap();
-        let feed = Feed::create(&user, &url, &form.name, db).await;
-  
-        match feed {
-          Ok(feed) => {
-            let _ = request_feed_update(&feed).await;
-      
-            let notify = user.send_link_to_feed(&feed, db).await;
-            match notify {
-              Ok(_notify) => log::debug!("user notified!"),
-              Err(why) => log::info!("something went wrong with notification: {why:?}")
-            }
-      
-            let dest = uri!(show_feed(feed.name, Some(1)));
-            Ok(Flash::success(Redirect::to(dest), "Feed created!"))
-          },
-          Err(why) => {
-            log::info!("{why}");
-            Ok(Flash::error(Redirect::to("/"), "Sorry, something went wrong!"))
-          }
+///
+/// Stop or start refreshing a feed
+///
+#[post("/feed/<username>/pause", data = "<form>")]
+pub async fn pause_feed(user: User, username: &str, db: &State<PgPool>, form: Form<PauseFeedForm>) -> Result<Flash<Redirect>, Status> {
+  let mut feed = match Feed::find_by_user_and_name(&user, &username.to_string(), EDITOR, db).await {
+    Ok(Some(feed)) => feed,
+    _ => return Err(Status::NotFound)
+  };
+
+  let dest = uri!(show_feed(&feed.name, None::<i32>));
+  match feed.set_paused(form.paused, db).await {
+    Ok(_result) if form.paused => Ok(Flash::success(Redirect::to(dest), "Feed paused!")),
+    Ok(_result) => Ok(Flash::success(Redirect::to(dest), "Feed resumed!")),
+    Err(_why) => Ok(Flash::error(Redirect::to(dest), "Sorry, something went wrong!"))
+  }
+}
+
+///
+/// Migrate a feed to another account
+///
+#[post("/feed/<username>/move", data = "<form>")]
+pub async fn move_feed(user: User, username: &str, db: &State<PgPool>, form: Form<MoveFeedForm>) -> Result<Flash<Redirect>, Status> {
+  let feed_lookup = Feed::find_by_user_and_name(&user, &username.to_string(), OWNER, db).await;
+
+  match feed_lookup {
+    Ok(Some(mut feed)) => {
+      let dest = uri!(show_feed(&feed.name, None::<i32>));
+
+      let mut queue = create_queue().await;
+      queue.connect(fang::NoTls).await.unwrap();
+
+      let result = feed.migrate_to(&user, &form.target, db, &mut queue).await;
+      match result {
+        Ok(target) => Ok(Flash::success(Redirect::to(dest), format!("Feed moved to {target}!"))),
+        Err(why) => {
+          log::info!("migration failed: {why}");
+          Ok(Flash::error(Redirect::to(dest), "Sorry, we couldn't move this feed. Make sure the new account lists this one as an alias!"))
         }
-      } else {
-        Err(Status::NotFound)
       }
-    }
+    },
+    Ok(None) => Err(Status::NotFound),
+    Err(_why) => Err(Status::NotFound)
   }
 }
 
 ///
-/// Update settings on a feed
+/// Generate a new signing key for a feed
 ///
-#[put("/feed/<username>", data = "<form>")]
-pub async fn update_feed(user: User, username: &str, db: &State<PgPool>, form: Form<FeedUpdateForm>) -> Result<Flash<Redirect>, Status> {
-  let feed_lookup = Feed::find_by_user_and_name(&user, &username.to_string(), db).await;
+#[post("/feed/<username>/rotate-key")]
+pub async fn rotate_feed_key(user: User, username: &str, db: &State<PgPool>) -> Result<Flash<Redirect>, Status> {
+  let feed_lookup = Feed::find_by_user_and_name(&user, &username.to_string(), OWNER, db).await;
 
   match feed_lookup {
-    Ok(feed_lookup) => {
-      match feed_lookup {
-        Some(mut feed) => {
-          feed.listed = form.listed;
-          feed.hashtag = None;
-          feed.content_warning = form.content_warning.clone();
-          feed.hashtag = form.hashtag.clone();
-          feed.status_publicity = form.status_publicity.clone();
-          feed.url = form.url.clone();
-
-          // user has tweaked title/description, let's mark that
-          if form.title != feed.title || feed.description != form.description {
-            feed.tweaked_profile_data = true;
-          }
+    Ok(Some(mut feed)) => {
+      let dest = uri!(show_feed(&feed.name, None::<i32>));
+
+      let mut queue = create_queue().await;
+      queue.connect(fang::NoTls).await.unwrap();
+
+      let result = feed.rotate_keys(db, &mut queue).await;
+      match result {
+        Ok(_result) => Ok(Flash::success(Redirect::to(dest), "Signing key updated!")),
+        Err(why) => {
+          log::info!("key rotation failed: {why}");
+          Ok(Flash::error(Redirect::to(dest), "Sorry, something went wrong!"))
+        }
+      }
+    },
+    Ok(None) => Err(Status::NotFound),
+    Err(_why) => Err(Status::NotFound)
+  }
+}
 
-          feed.title = form.title.clone();
-          feed.description = form.description.clone();
+///
+/// Approve or reject a pending follow request
+///
+#[post("/feed/<username>/follow-requests/<id>/<action>")]
+pub async fn respond_to_follow_request(user: User, username: &str, id: i32, action: &str, db: &State<PgPool>) -> Result<Flash<Redirect>, Status> {
+  let feed = match Feed::find_by_user_and_name(&user, &username.to_string(), EDITOR, db).await {
+    Ok(Some(feed)) => feed,
+    _ => return Err(Status::NotFound)
+  };
+
+  let request = match FollowRequest::find_for_feed(&feed, id, db).await {
+    Ok(Some(request)) => request,
+    _ => return Err(Status::NotFound)
+  };
 
-          let result = feed.save(db).await;
-          let dest = uri!(show_feed(&feed.name, None::<i32>));
+  let mut queue = create_queue().await;
+  queue.connect(fang::NoTls).await.unwrap();
 
-          match result {
-            Ok(_result) => {
-              let _ = request_feed_update(&feed).await;
+  let dest = uri!(show_feed(&feed.name, None::<i32>));
+  let result = match action {
+    "approve" => feed.approve_follow_request(db, &mut queue, &request).await,
+    "reject" => feed.reject_follow_request(db, &mut queue, &request).await,
+    _ => return Err(Status::NotFound)
+  };
 
-              Ok(Flash::success(Redirect::to(dest), "Feed updated!"))
-            },
-            Err(_why) => Ok(Flash::error(Redirect::to(dest), "Sorry, something went wrong!"))
-          }
-        },
-        None => Err(Status::NotFound)
-      }
+  match result {
+    Ok(_result) => Ok(Flash::success(Redirect::to(dest), "Follow request updated!")),
+    Err(why) => {
+      log::info!("follow request response failed: {why}");
+      Ok(Flash::error(Redirect::to(dest), "The request was updated, but we couldn't notify the follower"))
+    }
+  }
+}
+
+///
+/// Show the followers of a feed to its owner, along with any blocks
+///
+#[get("/feed/<username>/followers/manage?<page>")]
+pub async fn manage_followers(user: User, username: &str, page: Option<i32>, flash: Option<FlashMessage<'_>>, db: &State<PgPool>) -> Result<Template, Status> {
+  let feed = match Feed::find_by_user_and_name(&user, &username.to_string(), VIEWER, db).await {
+    Ok(Some(feed)) => feed,
+    _ => return Err(Status::NotFound)
+  };
+
+  let can_edit = match FeedMember::role_for(&feed, &user, db).await {
+    Ok(Some(role)) => role_allows(&role, EDITOR),
+    _ => false
+  };
+
+  let page: i32 = page.unwrap_or(1);
+  let followers = Follower::details_for_feed(&feed, page, db).await;
+  let blocks = FeedBlock::for_feed(&feed, db).await;
+  let count = feed.follower_count(db).await;
+
+  match (followers, blocks, count) {
+    (Ok(followers), Ok(blocks), Ok(count)) => {
+      let total_pages:i32 = (count / PER_PAGE) + 1;
+
+      Ok(Template::render("feed-followers", context! {
+        flash: flash,
+        logged_in: true,
+        username: user.full_username(),
+        feed: feed,
+        can_edit: can_edit,
+        followers: followers,
+        blocks: blocks,
+        page: page,
+        total_pages: total_pages,
+        total: count
+      }))
     },
-    Err(_why) => Err(Status::NotFound)
+    _ => Err(Status::InternalServerError)
+  }
+}
+
+///
+/// Remove a follower from a feed
+///
+#[post("/feed/<username>/followers/<id>/remove")]
+pub async fn remove_follower(user: User, username: &str, id: i32, db: &State<PgPool>) -> Result<Flash<Redirect>, Status> {
+  let feed = match Feed::find_by_user_and_name(&user, &username.to_string(), EDITOR, db).await {
+    Ok(Some(feed)) => feed,
+    _ => return Err(Status::NotFound)
+  };
+
+  let follower = match Follower::find_for_feed(&feed, id, db).await {
+    Ok(Some(follower)) => follower,
+    _ => return Err(Status::NotFound)
+  };
+
+  let mut queue = create_queue().await;
+  queue.connect(fang::NoTls).await.unwrap();
+
+  let dest = uri!(manage_followers(&feed.name, None::<i32>));
+  match feed.remove_follower(db, &mut queue, &follower).await {
+    Ok(_result) => Ok(Flash::success(Redirect::to(dest), "Follower removed!")),
+    Err(why) => {
+      log::info!("follower removal notification failed: {why}");
+      Ok(Flash::error(Redirect::to(dest), "The follower was removed, but we couldn't notify them"))
+    }
+  }
+}
+
+///
+/// Block an actor or a domain from following a feed
+///
+#[post("/feed/<username>/blocks", data = "<form>")]
+pub async fn add_feed_block(user: User, username: &str, db: &State<PgPool>, form: Form<FeedBlockForm>) -> Result<Flash<Redirect>, Status> {
+  let feed = match Feed::find_by_user_and_name(&user, &username.to_string(), EDITOR, db).await {
+    Ok(Some(feed)) => feed,
+    _ => return Err(Status::NotFound)
+  };
+
+  let dest = uri!(manage_followers(&feed.name, None::<i32>));
+  if (form.kind != ACTOR && form.kind != DOMAIN) || form.target.trim().is_empty() {
+    return Ok(Flash::error(Redirect::to(dest), "Sorry, something went wrong!"))
+  }
+
+  let mut queue = create_queue().await;
+  queue.connect(fang::NoTls).await.unwrap();
+
+  match feed.block(db, &mut queue, &form.kind, &form.target).await {
+    Ok(_block) => Ok(Flash::success(Redirect::to(dest), "Blocked!")),
+    Err(_why) => Ok(Flash::error(Redirect::to(dest), "Sorry, something went wrong!"))
+  }
+}
+
+///
+/// Remove a block from a feed
+///
+#[delete("/feed/<username>/blocks/<id>")]
+pub async fn delete_feed_block(user: User, username: &str, id: i32, db: &State<PgPool>) -> Result<Flash<Redirect>, Status> {
+  let feed = match Feed::find_by_user_and_name(&user, &username.to_string(), EDITOR, db).await {
+    Ok(Some(feed)) => feed,
+    _ => return Err(Status::NotFound)
+  };
+
+  let block = match FeedBlock::find_for_feed(&feed, id, db).await {
+    Ok(Some(block)) => block,
+    _ => return Err(Status::NotFound)
+  };
+
+  let dest = uri!(manage_followers(&feed.name, None::<i32>));
+  match block.delete(db).await {
+    Ok(_result) => Ok(Flash::success(Redirect::to(dest), "Block removed!")),
+    Err(_why) => Ok(Flash::error(Redirect::to(dest), "Sorry, something went wrong!"))
+  }
+}
+
+///
+/// Invite a fediverse account to help look after a feed
+///
+#[post("/feed/<username>/members", data = "<form>")]
+pub async fn add_feed_member(user: User, username: &str, db: &State<PgPool>, form: Form<FeedMemberForm>) -> Result<Flash<Redirect>, Status> {
+  let feed = match Feed::find_by_user_and_name(&user, &username.to_string(), OWNER, db).await {
+    Ok(Some(feed)) => feed,
+    _ => return Err(Status::NotFound)
+  };
+
+  let dest = uri!(show_feed(&feed.name, None::<i32>));
+  if !ROLES.contains(&form.role.as_str()) || form.handle.trim().is_empty() {
+    return Ok(Flash::error(Redirect::to(dest), "Sorry, something went wrong!"))
+  }
+
+  let mut queue = create_queue().await;
+  queue.connect(fang::NoTls).await.unwrap();
+
+  match feed.invite_member(&form.handle, &form.role, db, &mut queue).await {
+    Ok(_member) => Ok(Flash::success(Redirect::to(dest), "Invite sent!")),
+    Err(DeliveryError::Error(why)) if why == LAST_OWNER_ERROR => Ok(Flash::error(Redirect::to(dest), LAST_OWNER_ERROR)),
+    Err(why) => {
+      log::info!("feed invite failed: {why}");
+      Ok(Flash::error(Redirect::to(dest), "Sorry, we couldn't send that invite. Check the account name and try again!"))
+    }
+  }
+}
+
+///
+/// Remove a member from a feed. A feed always keeps at least one owner
+///
+#[delete("/feed/<username>/members/<id>")]
+pub async fn delete_feed_member(user: User, username: &str, id: i32, db: &State<PgPool>) -> Result<Flash<Redirect>, Status> {
+  let feed = match Feed::find_by_user_and_name(&user, &username.to_string(), OWNER, db).await {
+    Ok(Some(feed)) => feed,
+    _ => return Err(Status::NotFound)
+  };
+
+  let member = match FeedMember::find(&feed, id, db).await {
+    Ok(Some(member)) => member,
+    _ => return Err(Status::NotFound)
+  };
+
+  let dest = uri!(show_feed(&feed.name, None::<i32>));
+  if member.role == OWNER && FeedMember::owner_count(&feed, db).await.unwrap_or(0) <= 1 {
+    return Ok(Flash::error(Redirect::to(dest), LAST_OWNER_ERROR))
+  }
+
+  match member.delete(db).await {
+    Ok(_result) => Ok(Flash::success(Redirect::to(dest), "Member removed!")),
+    Err(_why) => Ok(Flash::error(Redirect::to(dest), "Sorry, something went wrong!"))
   }
 }
 
@@ -238,10 +466,16 @@ pub async fn show_feed(user: Option<User>, username: &str, flash: Option<FlashMe
       match feed_lookup {
         Some(feed) => {
           let logged_in = user.is_some();
-          let owned_by = logged_in && user.as_ref().unwrap().id == feed.user_id;
+          let role = match &user {
+            Some(user) => FeedMember::role_for(&feed, user, db).await.unwrap_or(None),
+            None => None
+          };
+          let is_member = role.is_some();
+          let owned_by = role.as_deref().is_some_and(|role| role_allows(role, EDITOR));
+          let is_owner = role.as_deref().is_some_and(|role| role_allows(role, OWNER));
           let follow_url = feed.permalink_url();
 
-          let items = if !owned_by && !feed.show_statuses_in_outbox() {
+          let items = if !is_member && !feed.show_statuses_in_outbox() {
             Ok(Vec::<Item>::new())
           } else {
             Item::for_feed(&feed, 10, db).await
@@ -254,6 +488,31 @@ pub async fn show_feed(user: Option<User>, username: &str, flash: Option<FlashMe
             None
           };
 
+          let follow_requests = if owned_by {
+            FollowRequest::for_feed(&feed, db).await.unwrap_or_default()
+          } else {
+            Vec::<FollowRequest>::new()
+          };
+
+          let followers_visibility = feed.followers_visibility(db).await.unwrap_or(FOLLOWERS_HIDDEN.to_string());
+          let follower_count = if is_member || followers_visibility != FOLLOWERS_HIDDEN {
+            feed.follower_count(db).await.ok()
+          } else {
+            None
+          };
+
+          let dm_subscriber_count = if is_member {
+            DmSubscriber::count_for_feed(&feed, db).await.ok()
+          } else {
+            None
+          };
+
+          let members = if is_owner {
+            FeedMember::details_for_feed(&feed, db).await.unwrap_or_default()
+          } else {
+            Vec::new()
+          };
+
           // let username = if user.is_some() {
           //   user.as_ref().unwrap().full_username()
           // } else {
@@ -269,8 +528,16 @@ pub async fn show_feed(user: Option<User>, username: &str, flash: Option<FlashMe
                 logged_in: logged_in,
                 username: username,
                 owned_by: owned_by,
+                is_owner: is_owner,
+                role: role,
+                members: members,
+                roles: ROLES,
                 feed: feed,
                 items: items,
+                follow_requests: follow_requests,
+                follower_count: follower_count,
+                dm_subscriber_count: dm_subscriber_count,
+                followers_visibility: followers_visibility,
                 follow_url: follow_url,
                 added: added.is_some(),
                 instance_domain: env::var("DOMAIN_NAME").expect("DOMAIN_NAME is not set")
@@ -291,14 +558,21 @@ pub async fn show_feed(user: Option<User>, username: &str, flash: Option<FlashMe
 /// show a feed's ActivityPub output
 ///
 #[get("/feed/<username>", format = "any", rank = 2)]
-pub async fn render_feed(username: &str, db: &State<PgPool>) -> Result<ActivityJsonContentType<String>, Status> {
+pub async fn render_feed(signature: SignatureValidity, username: &str, db: &State<PgPool>) -> Result<ActivityJsonContentType<String>, Status> {
   let feed_lookup = Feed::find_by_name(&username.to_string(), db).await;
 
   match feed_lookup {
     Ok(feed_lookup) => {
       match feed_lookup {
         Some(feed) => {
-          let ap = feed.to_activity_pub(db).await;
+          // unsigned requests to a feed in secure mode still get the
+          // public key, so other servers can verify our signatures
+          let ap = if signature.authorize_fetch(&feed, db).await.is_ok() {
+            feed.to_activity_pub(db).await
+          } else {
+            feed.to_minimal_activity_pub()
+          };
+
           match ap {
             Ok(ap) => Ok(ActivityJsonContentType(ap)),
             Err(why) => {
@@ -318,25 +592,31 @@ pub async fn render_feed(username: &str, db: &State<PgPool>) -> Result<ActivityJ
 ///
 /// Render the AP data for a feed's followers
 ///
-#[get("/feed/<username>/followers?<page>")]
-pub async fn render_feed_followers(username: &str, page: Option<i32>, db: &State<PgPool>) -> Result<ActivityJsonContentType<String>, Status> {
+#[get("/feed/<username>/followers?<page>&<max_id>&<min_id>")]
+pub async fn render_feed_followers(signature: SignatureValidity, username: &str, page: Option<bool>, max_id: Option<i32>, min_id: Option<i32>, db: &State<PgPool>) -> Result<ActivityJsonContentType<String>, Status> {
   let feed_lookup = Feed::find_by_name(&username.to_string(), db).await;
 
   match feed_lookup {
     Ok(feed_lookup) => {
       match feed_lookup {
         Some(feed) => {
+          signature.authorize_fetch(&feed, db).await?;
+
           // if we got a page param, return a page of followers
-          // otherwise, return the summary
-          let json = match page {
-            Some(page) => {
-              let result = feed.followers_paged(page, db).await;
+          // otherwise, return the summary. If the feed doesn't share
+          // its followers, there's only ever the summary
+          let visibility = feed.followers_visibility(db).await.unwrap_or(FOLLOWERS_HIDDEN.to_string());
+          let paged = (page.unwrap_or(false) || max_id.is_some() || min_id.is_some()) && visibility == FOLLOWERS_PUBLIC;
+
+          let json = match paged {
+            true => {
+              let result = feed.followers_paged(&PageCursor { max_id, min_id }, db).await;
               match result {
                 Ok(result) => Ok(ActivityJsonContentType(serde_json::to_string(&result).unwrap())),
                 Err(_why) => Err(Status::InternalServerError)
               }
             },
-            None => {
+            false => {
               let result = feed.followers(db).await;
               match result {
                 Ok(result) => Ok(ActivityJsonContentType(serde_json::to_string(&result).unwrap())),
@@ -444,6 +724,82 @@ mod test {
     Ok(())
   }
 
+  #[sqlx::test]
+  async fn test_render_feed_secure_mode(pool: PgPool) -> sqlx::Result<()> {
+    let mut feed = real_feed(&pool).await?;
+    feed.secure_mode = true;
+    feed.save(&pool).await?;
+
+    let server: Rocket<Build> = build_test_server(pool).await;
+    let client = Client::tracked(server).await.unwrap();
+
+    // unsigned requests only get enough to verify signatures
+    let req = client.get(uri!(super::render_feed(&feed.name))).header(Header::new("Accept", "application/activity+json"));
+    let response = req.dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_string().await.unwrap();
+    let v: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(v["publicKey"]["id"], format!("{}#main-key", feed.ap_url()));
+    assert!(v.get("outbox").is_none());
+    assert!(v.get("followers").is_none());
+
+    let req = client.get(uri!(super::render_feed_followers(&feed.name, None::<bool>, None::<i32>, None::<i32>)));
+    let response = req.dispatch().await;
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    // the HTML version is still public
+    let req = client.get(uri!(super::show_feed(&feed.name, None::<i32>))).header(Header::new("Accept", "text/html"));
+    let response = req.dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_render_feed_secure_mode_signed(pool: PgPool) -> sqlx::Result<()> {
+    use std::env;
+    use openssl::{hash::MessageDigest, pkey::PKey, sign::Signer};
+    use base64::{Engine as _, engine::general_purpose};
+
+    let instance_domain = env::var("DOMAIN_NAME").expect("DOMAIN_NAME is not set");
+
+    let mut feed = real_feed(&pool).await?;
+    feed.secure_mode = true;
+    feed.save(&pool).await?;
+
+    let signer = real_feed(&pool).await?;
+
+    let server: Rocket<Build> = build_test_server(pool).await;
+    let client = Client::tracked(server).await.unwrap();
+
+    let path = uri!(super::render_feed(&feed.name)).to_string();
+    let date = Utc::now().format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+    let signing_string = format!("(request-target): get {path}\nhost: {instance_domain}\ndate: {date}");
+
+    let key = PKey::private_key_from_pem(signer.private_key.as_bytes()).unwrap();
+    let mut sign = Signer::new(MessageDigest::sha256(), &key).unwrap();
+    sign.update(signing_string.as_bytes()).unwrap();
+    let signature = general_purpose::STANDARD.encode(sign.sign_to_vec().unwrap());
+
+    let header = format!(r#"keyId="{}#main-key",algorithm="rsa-sha256",headers="(request-target) host date",signature="{}""#, signer.ap_url(), signature);
+
+    let req = client.get(path)
+      .header(Header::new("Accept", "application/activity+json"))
+      .header(Header::new("Host", instance_domain))
+      .header(Header::new("Date", date))
+      .header(Header::new("Signature", header));
+    let response = req.dispatch().await;
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_string().await.unwrap();
+    let v: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(v["outbox"], feed.outbox_url());
+
+    Ok(())
+  }
+
   #[sqlx::test]
   async fn test_render_feed_text_accept(pool: PgPool) -> sqlx::Result<()> {
     let feed = real_feed(&pool).await.unwrap();
@@ -512,6 +868,237 @@ mod test {
   }
 
 
+  #[sqlx::test]
+  async fn test_move_feed(pool: PgPool) -> sqlx::Result<()> {
+    use rocket::http::ContentType;
+
+    let user = real_user(&pool).await.unwrap();
+    let feed = Feed::create(&user, &"https://foo.com/rss.xml".to_string(), &"oldfeed".to_string(), &pool).await?;
+    let dest = Feed::create(&user, &"https://foo.com/rss.xml".to_string(), &"newfeed".to_string(), &pool).await?;
+
+    let server: Rocket<Build> = build_test_server(pool.clone()).await;
+    let client = Client::tracked(server).await.unwrap();
+
+    crate::utils::test_helpers::login_user(&client, &user).await;
+
+    let post = client.post(uri!(super::move_feed(&feed.name)))
+      .header(ContentType::Form)
+      .body("target=newfeed");
+    let response = post.dispatch().await;
+
+    assert_eq!(response.status(), Status::SeeOther);
+
+    let feed = Feed::find(feed.id, &pool).await?;
+    assert_eq!(feed.moved_to, Some(dest.ap_url()));
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_pause_feed(pool: PgPool) -> sqlx::Result<()> {
+    use rocket::http::ContentType;
+
+    let user = real_user(&pool).await.unwrap();
+    let feed = real_feed(&pool).await?;
+
+    let server: Rocket<Build> = build_test_server(pool.clone()).await;
+    let client = Client::tracked(server).await.unwrap();
+
+    crate::utils::test_helpers::login_user(&client, &user).await;
+
+    let post = client.post(uri!(super::pause_feed(&feed.name)))
+      .header(ContentType::Form)
+      .body("paused=true");
+    let response = post.dispatch().await;
+
+    assert_eq!(response.status(), Status::SeeOther);
+    assert!(Feed::find(feed.id, &pool).await?.paused);
+    assert!(Feed::stale(&pool, 0, 10).await?.is_empty());
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_rotate_feed_key(pool: PgPool) -> sqlx::Result<()> {
+    let user = real_user(&pool).await.unwrap();
+    let feed = real_feed(&pool).await?;
+
+    let server: Rocket<Build> = build_test_server(pool.clone()).await;
+    let client = Client::tracked(server).await.unwrap();
+
+    crate::utils::test_helpers::login_user(&client, &user).await;
+
+    let post = client.post(uri!(super::rotate_feed_key(&feed.name)));
+    let response = post.dispatch().await;
+
+    assert_eq!(response.status(), Status::SeeOther);
+
+    let updated = Feed::find(feed.id, &pool).await?;
+    assert_ne!(updated.public_key, feed.public_key);
+    assert_eq!(updated.previous_public_key, Some(feed.public_key));
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_feed_member_roles(pool: PgPool) -> sqlx::Result<()> {
+    use rocket::http::ContentType;
+    use crate::models::{FeedMember, User};
+    use crate::models::feed_member::{EDITOR, VIEWER};
+
+    let feed = real_feed(&pool).await?;
+    let editor = User::find_or_create_by_actor_url(&"https://activitypub.pizza/users/editor".to_string(), &pool).await?;
+    let viewer = User::find_or_create_by_actor_url(&"https://activitypub.pizza/users/viewer".to_string(), &pool).await?;
+    FeedMember::add(&feed, &editor, EDITOR, &pool).await?;
+    FeedMember::add(&feed, &viewer, VIEWER, &pool).await?;
+
+    let server: Rocket<Build> = build_test_server(pool.clone()).await;
+    let client = Client::tracked(server).await.unwrap();
+
+    // viewers can look at followers, but not change anything
+    crate::utils::test_helpers::login_user(&client, &viewer).await;
+
+    let response = client.get(uri!(super::manage_followers(&feed.name, None::<i32>))).dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    assert!(!response.into_string().await.unwrap().contains("Unblock"));
+
+    let response = client.post(uri!(super::add_feed_block(&feed.name)))
+      .header(ContentType::Form)
+      .body("kind=domain&target=bad.place")
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::NotFound);
+
+    // editors can change settings, but not the signing key
+    crate::utils::test_helpers::login_user(&client, &editor).await;
+
+    let response = client.post(uri!(super::add_feed_block(&feed.name)))
+      .header(ContentType::Form)
+      .body("kind=domain&target=bad.place")
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::SeeOther);
+
+    let response = client.post(uri!(super::rotate_feed_key(&feed.name))).dispatch().await;
+    assert_eq!(response.status(), Status::NotFound);
+
+    let response = client.delete(uri!(super::delete_feed(feed.id))).dispatch().await;
+    assert_eq!(response.status(), Status::SeeOther);
+    assert!(Feed::find_by_name(&feed.name, &pool).await?.is_some());
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_delete_feed_member(pool: PgPool) -> sqlx::Result<()> {
+    use crate::models::{FeedMember, User};
+    use crate::models::feed_member::EDITOR;
+
+    let user = real_user(&pool).await.unwrap();
+    let feed = real_feed(&pool).await?;
+    let editor = User::find_or_create_by_actor_url(&"https://activitypub.pizza/users/editor".to_string(), &pool).await?;
+    let member = FeedMember::add(&feed, &editor, EDITOR, &pool).await?;
+
+    let server: Rocket<Build> = build_test_server(pool.clone()).await;
+    let client = Client::tracked(server).await.unwrap();
+
+    crate::utils::test_helpers::login_user(&client, &user).await;
+
+    let response = client.get(uri!(super::show_feed(&feed.name, None::<i32>))).dispatch().await;
+    assert!(response.into_string().await.unwrap().contains("activitypub.pizza"));
+
+    let response = client.delete(uri!(super::delete_feed_member(&feed.name, member.id))).dispatch().await;
+    assert_eq!(response.status(), Status::SeeOther);
+    assert_eq!(FeedMember::role_for(&feed, &editor, &pool).await?, None);
+
+    // the last owner has to stay
+    let owner = FeedMember::details_for_feed(&feed, &pool).await?.remove(0);
+    let response = client.delete(uri!(super::delete_feed_member(&feed.name, owner.id))).dispatch().await;
+    assert_eq!(response.status(), Status::SeeOther);
+    assert_eq!(FeedMember::owner_count(&feed, &pool).await?, 1);
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_respond_to_follow_request(pool: PgPool) -> sqlx::Result<()> {
+    use crate::models::FollowRequest;
+
+    let actor = "https://activitypub.pizza/users/colin".to_string();
+
+    let user = real_user(&pool).await.unwrap();
+    let mut feed = real_feed(&pool).await?;
+    feed.manually_approves_followers = true;
+    feed.save(&pool).await?;
+
+    FollowRequest::create(&feed, &actor, &format!("{actor}/follows/1"), &pool).await?;
+    let request = FollowRequest::for_feed(&feed, &pool).await?.pop().unwrap();
+
+    let server: Rocket<Build> = build_test_server(pool.clone()).await;
+    let client = Client::tracked(server).await.unwrap();
+
+    crate::utils::test_helpers::login_user(&client, &user).await;
+
+    let req = client.get(uri!(super::show_feed(&feed.name, None::<i32>))).header(Header::new("Accept", "text/html"));
+    let body = req.dispatch().await.into_string().await.unwrap();
+    assert!(body.contains(&actor.replace('/', "&#x2F;")));
+
+    let post = client.post(uri!(super::respond_to_follow_request(&feed.name, request.id, "approve")));
+    let response = post.dispatch().await;
+    assert_eq!(response.status(), Status::SeeOther);
+
+    assert!(FollowRequest::for_feed(&feed, &pool).await?.is_empty());
+    assert_eq!(feed.follower_count(&pool).await?, 1);
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_manage_followers(pool: PgPool) -> sqlx::Result<()> {
+    use crate::models::Follower;
+    use crate::models::FeedBlock;
+
+    let actor = "https://activitypub.pizza/users/colin".to_string();
+
+    let user = real_user(&pool).await.unwrap();
+    let feed = real_feed(&pool).await?;
+
+    sqlx::query!("INSERT INTO followers (feed_id, actor, created_at, updated_at) VALUES($1, $2, $3, $4)", feed.id, actor, Utc::now(), Utc::now())
+      .execute(&pool)
+      .await?;
+    let follower = Follower::details_for_feed(&feed, 1, &pool).await?.pop().unwrap();
+
+    let server: Rocket<Build> = build_test_server(pool.clone()).await;
+    let client = Client::tracked(server).await.unwrap();
+
+    crate::utils::test_helpers::login_user(&client, &user).await;
+
+    let req = client.get(uri!(super::manage_followers(&feed.name, None::<i32>)));
+    let body = req.dispatch().await.into_string().await.unwrap();
+    assert!(body.contains(&actor.replace('/', "&#x2F;")));
+
+    let post = client.post(uri!(super::remove_follower(&feed.name, follower.id)));
+    let response = post.dispatch().await;
+    assert_eq!(response.status(), Status::SeeOther);
+
+    assert_eq!(feed.follower_count(&pool).await?, 0);
+
+    let post = client.post(uri!(super::add_feed_block(&feed.name)))
+      .header(rocket::http::ContentType::Form)
+      .body(format!("kind=actor&target={actor}"));
+    let response = post.dispatch().await;
+    assert_eq!(response.status(), Status::SeeOther);
+
+    let block = FeedBlock::for_feed(&feed, &pool).await?.pop().unwrap();
+    assert_eq!(block.target, actor);
+
+    let response = client.delete(uri!(super::delete_feed_block(&feed.name, block.id))).dispatch().await;
+    assert_eq!(response.status(), Status::SeeOther);
+    assert!(FeedBlock::for_feed(&feed, &pool).await?.is_empty());
+
+    Ok(())
+  }
+
   #[sqlx::test]
   async fn test_test_feed_not_logged_in(pool: PgPool) -> sqlx::Result<()> {
     let server: Rocket<Build> = build_test_server(pool).await;
@@ -530,6 +1117,32 @@ mod test {
     Ok(())
   }
 
+  #[sqlx::test]
+  async fn test_render_feed_followers_count_only(pool: PgPool) -> sqlx::Result<()> {
+    let mut feed = real_feed(&pool).await.unwrap();
+    feed.followers_visibility = Some("count".to_string());
+    feed.save(&pool).await?;
+
+    let now = Utc::now();
+    sqlx::query!("INSERT INTO followers (feed_id, actor, created_at, updated_at) VALUES($1, $2, $3, $4)", feed.id, "https://activitypub.pizza/users/colin", now, now)
+      .execute(&pool)
+      .await?;
+
+    let server: Rocket<Build> = build_test_server(pool).await;
+    let client = Client::tracked(server).await.unwrap();
+
+    let req = client.get(uri!(super::render_feed_followers(&feed.name, Some(true), None::<i32>, None::<i32>)));
+    let response = req.dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_string().await.unwrap();
+    assert!(!body.contains("OrderedCollectionPage"));
+    assert!(!body.contains("/colin"));
+    assert!(body.contains(r#""totalItems":1"#));
+
+    Ok(())
+  }
+
   #[sqlx::test]
   async fn test_render_feed_followers(pool: PgPool) -> sqlx::Result<()> {
     let feed = real_feed(&pool).await.unwrap();
@@ -547,24 +1160,41 @@ mod test {
     let client = Client::tracked(server).await.unwrap();
 
     let name = feed.name;
-    let req = client.get(uri!(super::render_feed_followers(&name, Some(2))));
+    let req = client.get(uri!(super::render_feed_followers(&name, Some(true), None::<i32>, None::<i32>)));
     let response = req.dispatch().await;
 
     assert_eq!(response.status(), Status::Ok);
     assert_eq!(response.content_type().unwrap().to_string(), "application/activity+json");
 
     let body = response.into_string().await.unwrap();
- 
-    assert!(body.contains("OrderedCollectionPage"));
-    assert!(body.contains("/colin11"));
-    assert!(body.contains("/colin12"));
-    assert!(body.contains("/colin13"));
-    assert!(body.contains(&format!(r#"first":"{}"#, path_to_url(&uri!(super::render_feed_followers(name.clone(), Some(1)))))));
-    assert!(body.contains(&format!(r#"prev":"{}"#, path_to_url(&uri!(super::render_feed_followers(name.clone(), Some(1)))))));      
-    assert!(body.contains(&format!(r#"next":"{}"#, path_to_url(&uri!(super::render_feed_followers(name.clone(), Some(3)))))));
-    assert!(body.contains(&format!(r#"last":"{}"#, path_to_url(&uri!(super::render_feed_followers(name.clone(), Some(4)))))));
-    assert!(body.contains(&format!(r#"current":"{}"#, path_to_url(&uri!(super::render_feed_followers(name.clone(), Some(2)))))));
-    
+    let v: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    // newest followers come first
+    assert_eq!(v["type"], "OrderedCollectionPage");
+    assert!(body.contains("/colin34"));
+    assert!(body.contains("/colin25"));
+    assert!(!body.contains("/colin24"));
+    assert_eq!(v["partOf"], path_to_url(&uri!(super::render_feed_followers(name.clone(), None::<bool>, None::<i32>, None::<i32>))));
+    assert!(v["prev"].is_null());
+
+    let next = v["next"].as_str().unwrap();
+    assert!(next.contains("max_id="));
+
+    let req = client.get(next.replace(&format!("https://{}", std::env::var("DOMAIN_NAME").unwrap()), ""));
+    let response = req.dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_string().await.unwrap();
+    let v: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(v["id"], next);
+    assert!(body.contains("/colin24"));
+    assert!(body.contains("/colin15"));
+    assert!(!body.contains("/colin25"));
+    assert!(!body.contains("/colin14"));
+    assert!(v["prev"].as_str().unwrap().contains("min_id="));
+    assert!(v["next"].as_str().unwrap().contains("max_id="));
+
     Ok(())
   }
 }
@@ -1,55 +1,96 @@
 use rocket::get;
 use rocket::http::Status;
 use rocket::response::Redirect;
+use rocket::Either;
 use rocket::State;
+use rocket_dyn_templates::{Template, context};
 
 use sqlx::postgres::PgPool;
 
+use std::env;
+
+use crate::models::Enclosure;
 use crate::models::Feed;
+use crate::models::FeedMember;
+use crate::models::Interaction;
+use crate::models::interaction::{LIKE, ANNOUNCE, REPLY};
 use crate::models::Item;
+use crate::models::User;
+use crate::routes::ap::inbox::SignatureValidity;
+use crate::utils::web_url;
 
 
+///
+/// Show an item on its own page. Feeds can opt to send visitors to the
+/// original entry instead, which is also what happens when the feed's
+/// statuses aren't public
+///
 #[get("/feed/<username>/items/<id>", format = "text/html", rank = 1)]
-pub async fn show_item(username: &str, id: i32, db: &State<PgPool>) -> Result<Redirect, Status> {
-  let lookup_feed = Feed::find_by_name(&username.to_string(), db).await;
-
-  match lookup_feed {
-    Ok(lookup_feed) => {
-      if lookup_feed.is_some() {
-        let feed = lookup_feed.unwrap();
-        let item = Item::find_by_feed_and_id(&feed, id, db).await;
-        match item {
-          Ok(item) => {
-            if item.is_some() {
-              let data = item.unwrap();
-              if data.url.is_some() {
-                return Ok(Redirect::to(data.url.unwrap()))
-              } else if feed.site_url.is_some() {
-                return Ok(Redirect::to(feed.site_url.unwrap()))                
-              }
-            }
-
-            Err(Status::NotFound)
-          },
-          Err(_why) => Err(Status::NotFound)
-        }
-      }
-      else {
-        Err(Status::NotFound)
-      }
-    },
-    Err(_why) => Err(Status::NotFound)
+pub async fn show_item(user: Option<User>, username: &str, id: i32, db: &State<PgPool>) -> Result<Either<Template, Redirect>, Status> {
+  let feed = match Feed::find_by_name(&username.to_string(), db).await {
+    Ok(Some(feed)) => feed,
+    _ => return Err(Status::NotFound)
+  };
+
+  let item = match Item::find_by_feed_and_id(&feed, id, db).await {
+    Ok(Some(item)) => item,
+    _ => return Err(Status::NotFound)
+  };
+
+  let is_member = match &user {
+    Some(user) => FeedMember::role_for(&feed, user, db).await.unwrap_or(None).is_some(),
+    None => false
+  };
+
+  if feed.redirect_items || (!is_member && !feed.show_statuses_in_outbox()) {
+    return match item.url.as_ref().or(feed.site_url.as_ref()) {
+      Some(url) => Ok(Either::Right(Redirect::to(url.clone()))),
+      None => Err(Status::NotFound)
+    }
   }
+
+  let enclosures: Vec<Enclosure> = Enclosure::for_item(&item, db).await
+    .unwrap_or_default()
+    .into_iter()
+    .filter(|enclosure| web_url(&enclosure.url).is_some())
+    .collect();
+  let like_count = Interaction::for_item(&item, LIKE, db).await.map(|result| result.len()).unwrap_or(0);
+  let announce_count = Interaction::for_item(&item, ANNOUNCE, db).await.map(|result| result.len()).unwrap_or(0);
+  let reply_count = Interaction::for_item(&item, REPLY, db).await.map(|result| result.len()).unwrap_or(0);
+
+  let item_url = format!("{}/items/{}", feed.ap_url(), item.id);
+  let canonical_url = item.url.as_deref().and_then(web_url).unwrap_or(item_url.clone());
+  let source_url = item.url.as_deref().and_then(web_url).or(feed.site_url.as_deref().and_then(web_url));
+
+  let username = user.as_ref().and_then(|user| user.full_username());
+
+  Ok(Either::Left(Template::render("item", context! {
+    noindex: !feed.listed,
+    logged_in: user.is_some(),
+    username: username,
+    feed: feed,
+    item: item,
+    enclosures: enclosures,
+    like_count: like_count,
+    announce_count: announce_count,
+    reply_count: reply_count,
+    item_url: item_url,
+    canonical_url: canonical_url,
+    source_url: source_url,
+    instance_domain: env::var("DOMAIN_NAME").expect("DOMAIN_NAME is not set")
+  })))
 }
 
 
 #[get("/feed/<username>/items/<id>", format = "application/json", rank = 2)]
-pub async fn show_item_json(username: &str, id: i32, db: &State<PgPool>) -> Result<String, Status> {
+pub async fn show_item_json(signature: SignatureValidity, username: &str, id: i32, db: &State<PgPool>) -> Result<String, Status> {
   let lookup_feed = Feed::find_by_name(&username.to_string(), db).await;
   match lookup_feed {
     Ok(lookup_feed) => {
       if lookup_feed.is_some() {
         let feed = lookup_feed.unwrap();
+        signature.authorize_fetch(&feed, db).await?;
+
         let item = Item::find_by_feed_and_id(&feed, id, db).await;
         match item {
           Ok(item) => {
@@ -101,6 +142,79 @@ mod test {
     let req = client.get(uri!(super::show_item(&feed.name, item.id)));
     let response = req.dispatch().await;
 
+    assert_eq!(response.status(), Status::Ok);
+
+    let body = response.into_string().await.unwrap();
+    assert!(body.contains(r#"<link rel="canonical""#));
+    assert!(body.contains(&item.title.unwrap()));
+    assert!(body.contains("View the original entry"));
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_show_item_unsafe_urls(pool: PgPool) -> sqlx::Result<()> {
+    let feed: Feed = real_feed(&pool).await?;
+    let item: Item = real_item(&feed, &pool).await?;
+
+    sqlx::query!("UPDATE items SET url = $1 WHERE id = $2", "javascript:alert(1)", item.id)
+      .execute(&pool)
+      .await?;
+    sqlx::query!("INSERT INTO enclosures (item_id, url, content_type, created_at, updated_at) VALUES($1, $2, $3, NOW(), NOW())",
+      item.id, r#"https://foo.com/a.png" onerror="alert(1)"#, "image/png")
+      .execute(&pool)
+      .await?;
+    sqlx::query!("INSERT INTO enclosures (item_id, url, created_at, updated_at) VALUES($1, $2, NOW(), NOW())",
+      item.id, "javascript:alert(2)")
+      .execute(&pool)
+      .await?;
+
+    let server: Rocket<Build> = build_test_server(pool).await;
+    let client = Client::tracked(server).await.unwrap();
+
+    let req = client.get(uri!(super::show_item(&feed.name, item.id)));
+    let body = req.dispatch().await.into_string().await.unwrap();
+
+    assert!(!body.contains("javascript:"));
+    assert!(!body.contains(r#"" onerror=""#));
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_show_item_redirect(pool: PgPool) -> sqlx::Result<()> {
+    let mut feed: Feed = real_feed(&pool).await?;
+    feed.redirect_items = true;
+    feed.save(&pool).await?;
+
+    let item: Item = real_item(&feed, &pool).await?;
+
+    let server: Rocket<Build> = build_test_server(pool).await;
+    let client = Client::tracked(server).await.unwrap();
+
+    let req = client.get(uri!(super::show_item(&feed.name, item.id)));
+    let response = req.dispatch().await;
+
+    assert_eq!(response.status(), Status::SeeOther);
+    assert_eq!(response.headers().get_one("Location"), item.url.as_deref());
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_show_item_direct_status(pool: PgPool) -> sqlx::Result<()> {
+    let mut feed: Feed = real_feed(&pool).await?;
+    feed.status_publicity = Some("direct".to_string());
+    feed.save(&pool).await?;
+
+    let item: Item = real_item(&feed, &pool).await?;
+
+    let server: Rocket<Build> = build_test_server(pool).await;
+    let client = Client::tracked(server).await.unwrap();
+
+    let req = client.get(uri!(super::show_item(&feed.name, item.id)));
+    let response = req.dispatch().await;
+
     assert_eq!(response.status(), Status::SeeOther);
 
     Ok(())
@@ -1,7 +1,7 @@
-use rocket::{FromForm, get, post};
+use rocket::{FromForm, get, post, delete};
 use rocket::form::Form;
 use rocket::http::Status;
-use rocket::response::Redirect;
+use rocket::response::{Flash, Redirect};
 use rocket::State;
 use rocket::http::{Cookie, CookieJar, SameSite};
 use rocket::uri;
@@ -9,27 +9,38 @@ use rocket_dyn_templates::{Template, context};
 
 use sqlx::postgres::PgPool;
 
+use std::net::IpAddr;
 
 use crate::models::user::User;
+use crate::models::{ApiToken, LoginRequest, Session};
+use crate::traits::UserAgent;
+use crate::traits::user_guard::SESSION_COOKIE;
+use crate::services::email::Mailer;
 
 #[derive(FromForm)]
 pub struct LoginForm {
   pub email: String
 }
 
+#[derive(FromForm)]
+pub struct ApiTokenForm {
+  pub name: String
+}
+
 
 #[get("/user/auth/<login_token>")]
-pub async fn attempt_login(db: &State<PgPool>, cookies: &CookieJar<'_>, login_token: &str) -> Result<Redirect, Status> {
-  let user = User::find_by_login(&login_token.to_string(), db).await;
+pub async fn attempt_login(db: &State<PgPool>, cookies: &CookieJar<'_>, ip: Option<IpAddr>, user_agent: UserAgent, login_token: &str) -> Result<Redirect, Status> {
+  let user = User::redeem_login_token(&login_token.to_string(), db).await;
   
   match user {
     Ok(user) => {
       if user.is_some() {
         let user = user.unwrap();
-        let token = user.apply_access_token(db).await;
-        match token {
-          Ok(token) => {
-            let mut cookie = Cookie::new("access_token", token);
+        let ip = ip.map(|ip| ip.to_string());
+        let session = Session::create(&user, user_agent.0.as_deref(), ip.as_deref(), db).await;
+        match session {
+          Ok((_session, token)) => {
+            let mut cookie = Cookie::new(SESSION_COOKIE, token);
             cookie.set_same_site(SameSite::Lax);
             cookie.make_permanent();
             cookies.add_private(cookie);
@@ -53,18 +64,145 @@ pub async fn attempt_login(db: &State<PgPool>, cookies: &CookieJar<'_>, login_to
     }
   }
 }
+
 #[get("/user/logout")]
-pub async fn do_logout(cookies: &CookieJar<'_>) -> Result<Redirect, Status> {
-  cookies.remove_private(Cookie::from("access_token"));
+pub async fn do_logout(db: &State<PgPool>, cookies: &CookieJar<'_>) -> Result<Redirect, Status> {
+  if let Some(cookie) = cookies.get_private(SESSION_COOKIE) {
+    if let Err(why) = Session::delete_by_token(cookie.value(), db).await {
+      log::info!("{why}");
+    }
+  }
+
+  cookies.remove_private(Cookie::from(SESSION_COOKIE));
   Ok(Redirect::to("/"))
 }
 
+#[get("/user/sessions")]
+pub async fn index_sessions(user: User, db: &State<PgPool>, cookies: &CookieJar<'_>) -> Result<Template, Status> {
+  let sessions = Session::for_user(&user, db).await;
+  let current_token = cookies.get_private(SESSION_COOKIE).map(|cookie| cookie.value().to_string());
+
+  match sessions {
+    Ok(sessions) => {
+      let current_id = sessions
+        .iter()
+        .find(|session| current_token.as_ref().is_some_and(|token| session.matches_token(token)))
+        .map(|session| session.id);
+
+      Ok(Template::render("sessions", context! {
+        sessions: sessions,
+        current_id: current_id,
+        logged_in: true,
+        username: user.full_username()
+      }))
+    },
+    Err(_why) => Err(Status::InternalServerError)
+  }
+}
+
+#[delete("/user/sessions/<id>")]
+pub async fn delete_session(user: User, id: i32, db: &State<PgPool>) -> Result<Flash<Redirect>, Status> {
+  let dest = uri!(index_sessions());
+  match Session::delete(&user, id, db).await {
+    Ok(_result) => Ok(Flash::success(Redirect::to(dest), "Session revoked!")),
+    Err(_why) => Ok(Flash::error(Redirect::to(dest), "Sorry, something went wrong!"))
+  }
+}
+
+#[post("/user/sessions/logout-all")]
+pub async fn logout_everywhere(user: User, db: &State<PgPool>, cookies: &CookieJar<'_>) -> Result<Redirect, Status> {
+  match Session::delete_for_user(&user, db).await {
+    Ok(_result) => {
+      cookies.remove_private(Cookie::from(SESSION_COOKIE));
+      Ok(Redirect::to("/"))
+    },
+    Err(_why) => Err(Status::InternalServerError)
+  }
+}
+
+#[get("/user/tokens")]
+pub async fn index_api_tokens(user: User, db: &State<PgPool>) -> Result<Template, Status> {
+  match ApiToken::for_user(&user, db).await {
+    Ok(tokens) => Ok(Template::render("api-tokens", context! {
+      tokens: tokens,
+      new_token: None::<String>,
+      logged_in: true,
+      username: user.full_username()
+    })),
+    Err(_why) => Err(Status::InternalServerError)
+  }
+}
+
+///
+/// Create an API token. We only have a hash of it after this, so the
+/// page shows it right away instead of redirecting
+///
+#[post("/user/tokens", data = "<form>")]
+pub async fn create_api_token(user: User, db: &State<PgPool>, form: Form<ApiTokenForm>) -> Result<Template, Status> {
+  let name = form.name.trim();
+  let name = if name.is_empty() { "API token" } else { name };
+
+  let new_token = match ApiToken::create(&user, name, db).await {
+    Ok((_api_token, token)) => token,
+    Err(_why) => return Err(Status::InternalServerError)
+  };
+
+  match ApiToken::for_user(&user, db).await {
+    Ok(tokens) => Ok(Template::render("api-tokens", context! {
+      tokens: tokens,
+      new_token: Some(new_token),
+      logged_in: true,
+      username: user.full_username()
+    })),
+    Err(_why) => Err(Status::InternalServerError)
+  }
+}
+
+#[delete("/user/tokens/<id>")]
+pub async fn delete_api_token(user: User, id: i32, db: &State<PgPool>) -> Result<Flash<Redirect>, Status> {
+  let dest = uri!(index_api_tokens());
+  match ApiToken::delete(&user, id, db).await {
+    Ok(_result) => Ok(Flash::success(Redirect::to(dest), "Token revoked!")),
+    Err(_why) => Ok(Flash::error(Redirect::to(dest), "Sorry, something went wrong!"))
+  }
+}
+
+///
+/// Email a login link. Requests are rate limited by address and by IP.
+/// The IP comes from the header named by Rocket's `ip_header` setting
+/// (X-Real-IP by default), so a reverse proxy needs to set that header,
+/// or every request will look like it came from the proxy
+///
 #[post("/login", data = "<form>")]
-pub async fn do_login(db: &State<PgPool>, form: Form<LoginForm>) -> Result<Redirect, Status> {
-  let user = User::find_or_create_by_email(&form.email, db).await;
+pub async fn do_login(db: &State<PgPool>, mailer: &State<Mailer>, ip: Option<IpAddr>, form: Form<LoginForm>) -> Result<Redirect, Status> {
+  let email = form.email.trim().to_string();
+  if email.is_empty() || !email.contains('@') {
+    return Err(Status::BadRequest)
+  }
+
+  let ip = ip.map(|ip| ip.to_string());
+  match LoginRequest::allowed(&email, ip.as_deref(), db).await {
+    Ok(true) => {},
+    Ok(false) => return Err(Status::TooManyRequests),
+    Err(why) => {
+      log::info!("{why}");
+      return Err(Status::InternalServerError)
+    }
+  }
+
+  let user = User::find_or_create_by_email(&email, db).await;
   
   match user {
-    Ok(_user) => {
+    Ok(user) => {
+      if let Err(why) = LoginRequest::create(&email, ip.as_deref(), db).await {
+        log::info!("{why}");
+      }
+
+      if let Err(why) = user.send_login_email(mailer, db).await {
+        log::info!("unable to send login email: {why}");
+        return Err(Status::InternalServerError)
+      }
+
       let dest = uri!(login_result());
       Ok(Redirect::to(dest))
     },
@@ -79,3 +217,137 @@ pub async fn do_login(db: &State<PgPool>, form: Form<LoginForm>) -> Result<Redir
 pub async fn login_result() -> Template {
   Template::render("login-after", context! { logged_in: false })
 }
+
+#[cfg(test)]
+mod test {
+  use rocket::local::asynchronous::Client;
+  use rocket::http::{ContentType, Header, Status};
+  use rocket::uri;
+  use rocket::{Rocket, Build};
+  use sqlx::postgres::PgPool;
+
+  use crate::models::Session;
+  use crate::models::login_request::login_email_limit;
+  use crate::utils::test_helpers::{build_test_server, login_user, real_user};
+
+  #[sqlx::test]
+  async fn test_login_link_works_once(pool: PgPool) -> sqlx::Result<()> {
+    let user = real_user(&pool).await?;
+
+    let server: Rocket<Build> = build_test_server(pool.clone()).await;
+    let client = Client::tracked(server).await.unwrap();
+
+    let response = client.get(uri!(super::attempt_login(&user.login_token)))
+      .header(Header::new("User-Agent", "Test Browser"))
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::SeeOther);
+    assert!(response.cookies().get_private("session_token").is_some());
+
+    let sessions = Session::for_user(&user, &pool).await?;
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].user_agent, Some("Test Browser".to_string()));
+
+    // a second browser can't reuse the link
+    let client2 = Client::tracked(build_test_server(pool.clone()).await).await.unwrap();
+    client2.get(uri!(super::attempt_login(&user.login_token))).dispatch().await;
+    let response = client2.get(uri!(super::index_sessions())).dispatch().await;
+    assert_eq!(response.status(), Status::Unauthorized);
+    assert_eq!(Session::for_user(&user, &pool).await?.len(), 1);
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_api_tokens(pool: PgPool) -> sqlx::Result<()> {
+    use crate::models::ApiToken;
+
+    let user = real_user(&pool).await?;
+
+    let server: Rocket<Build> = build_test_server(pool.clone()).await;
+    let client = Client::tracked(server).await.unwrap();
+    login_user(&client, &user).await;
+
+    let response = client.post(uri!(super::create_api_token()))
+      .header(ContentType::Form)
+      .body("name=deploy")
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_string().await.unwrap();
+    assert!(body.contains("You won't be able to see it again"));
+
+    let tokens = ApiToken::for_user(&user, &pool).await?;
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0].name, "deploy");
+
+    let body = client.get(uri!(super::index_api_tokens())).dispatch().await.into_string().await.unwrap();
+    assert!(body.contains("deploy"));
+    assert!(!body.contains("You won't be able to see it again"));
+
+    let response = client.delete(uri!(super::delete_api_token(tokens[0].id))).dispatch().await;
+    assert_eq!(response.status(), Status::SeeOther);
+    assert!(ApiToken::for_user(&user, &pool).await?.is_empty());
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_sessions(pool: PgPool) -> sqlx::Result<()> {
+    let user = real_user(&pool).await?;
+    let (other, _token) = Session::create(&user, Some("Other Browser"), None, &pool).await?;
+    let (_another, _token) = Session::create(&user, Some("Another Browser"), None, &pool).await?;
+
+    let server: Rocket<Build> = build_test_server(pool.clone()).await;
+    let client = Client::tracked(server).await.unwrap();
+    login_user(&client, &user).await;
+
+    let body = client.get(uri!(super::index_sessions())).dispatch().await.into_string().await.unwrap();
+    assert!(body.contains("Other Browser"));
+    assert!(body.contains("(this browser)"));
+
+    let response = client.delete(uri!(super::delete_session(other.id))).dispatch().await;
+    assert_eq!(response.status(), Status::SeeOther);
+    assert_eq!(Session::for_user(&user, &pool).await?.len(), 2);
+
+    let response = client.post(uri!(super::logout_everywhere())).dispatch().await;
+    assert_eq!(response.status(), Status::SeeOther);
+    assert!(Session::for_user(&user, &pool).await?.is_empty());
+
+    let response = client.get(uri!(super::index_sessions())).dispatch().await;
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_do_login_rate_limit(pool: PgPool) -> sqlx::Result<()> {
+    let server: Rocket<Build> = build_test_server(pool.clone()).await;
+    let client = Client::tracked(server).await.unwrap();
+
+    for _ in 0..login_email_limit() {
+      let response = client.post(uri!(super::do_login()))
+        .header(ContentType::Form)
+        .body("email=foo%40bar.com")
+        .dispatch()
+        .await;
+      assert_eq!(response.status(), Status::SeeOther);
+    }
+
+    let response = client.post(uri!(super::do_login()))
+      .header(ContentType::Form)
+      .body("email=foo%40bar.com")
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::TooManyRequests);
+
+    let response = client.post(uri!(super::do_login()))
+      .header(ContentType::Form)
+      .body("email=nope")
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::BadRequest);
+
+    Ok(())
+  }
+}
@@ -1,5 +1,6 @@
 pub mod admin;
 pub mod ap;
+pub mod api;
 pub mod feeds;
 pub mod items;
 pub mod index;
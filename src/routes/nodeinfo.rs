@@ -10,41 +10,110 @@ use crate::models::NodeInfo;
 use std::env;
 
 
-#[get("/nodeinfo/2.0")]
-pub async fn nodeinfo(db: &State<PgPool>) -> Result<(ContentType, String), Status> {
-  let data = NodeInfo::current(db).await;
+const SCHEMA_PREFIX: &str = "http://nodeinfo.diaspora.software/ns/schema";
+
+///
+/// Content type for a NodeInfo document, including the schema profile
+///
+fn nodeinfo_content_type(version: &str) -> ContentType {
+  ContentType::new("application", "json")
+    .with_params(("profile", format!("{SCHEMA_PREFIX}/{version}#")))
+}
 
-  if data.is_ok() {
-    let data = data.unwrap();
+///
+/// Build the NodeInfo document. 2.1 is the same as 2.0, except the
+/// software section can include a repository and homepage
+///
+async fn nodeinfo_document(version: &str, db: &PgPool) -> Result<serde_json::Value, sqlx::Error> {
+  let data = NodeInfo::current(db).await?;
 
-    let results = json!({
-      "version": "2.0",
-      "software": {
-        "name": env!("CARGO_PKG_NAME"),
-        "version": env!("CARGO_PKG_VERSION")
-      },
-      "protocols": [
-        "activitypub"
-      ],
-      "services": {
-        "outbound": [],
-        "inbound": []
+  let mut software = json!({
+    "name": env!("CARGO_PKG_NAME"),
+    "version": env!("CARGO_PKG_VERSION")
+  });
+
+  if version == "2.1" {
+    let repository = env!("CARGO_PKG_REPOSITORY");
+    if !repository.is_empty() {
+      software["repository"] = json!(repository);
+    }
+
+    let homepage = env!("CARGO_PKG_HOMEPAGE");
+    if !homepage.is_empty() {
+      software["homepage"] = json!(homepage);
+    }
+  }
+
+  let mut metadata = json!({});
+  if !data.node_name.is_empty() {
+    metadata["nodeName"] = json!(data.node_name);
+  }
+  if !data.node_description.is_empty() {
+    metadata["nodeDescription"] = json!(data.node_description);
+  }
+  if !data.contact.is_empty() {
+    metadata["contact"] = json!(data.contact);
+  }
+
+  Ok(json!({
+    "version": version,
+    "software": software,
+    "protocols": [
+      "activitypub"
+    ],
+    "services": {
+      "outbound": [],
+      "inbound": []
+    },
+    "usage": {
+      "users": {
+        "total": data.users,
+        "activeMonth": data.active_month,
+        "activeHalfyear": data.active_halfyear
       },
-      "usage": {
-        "users": {
-          "total": data.users,
-          "activeMonth": data.users,
-          "activeHalfyear": data.users
-        },
-        "localPosts": data.posts
+      "localPosts": data.posts
+    },
+    "openRegistrations": data.open_registrations,
+    "metadata": metadata
+  }))
+}
+
+///
+/// Point crawlers at the NodeInfo documents we support
+///
+#[get("/.well-known/nodeinfo")]
+pub async fn nodeinfo_discovery() -> (ContentType, String) {
+  let instance_domain = env::var("DOMAIN_NAME").expect("DOMAIN_NAME is not set");
+
+  let results = json!({
+    "links": [
+      {
+        "rel": format!("{SCHEMA_PREFIX}/2.0"),
+        "href": format!("https://{instance_domain}/nodeinfo/2.0")
       },
-      "openRegistrations": true,
-      "metadata": {}
-    });
+      {
+        "rel": format!("{SCHEMA_PREFIX}/2.1"),
+        "href": format!("https://{instance_domain}/nodeinfo/2.1")
+      }
+    ]
+  });
+
+  (ContentType::JSON, results.to_string())
+}
 
-    Ok((ContentType::JSON, results.to_string()))
-  } else {
-    Err(Status::NotFound)
+#[get("/nodeinfo/2.0")]
+pub async fn nodeinfo(db: &State<PgPool>) -> Result<(ContentType, String), Status> {
+  match nodeinfo_document("2.0", db).await {
+    Ok(results) => Ok((nodeinfo_content_type("2.0"), results.to_string())),
+    Err(_why) => Err(Status::NotFound)
+  }
+}
+
+#[get("/nodeinfo/2.1")]
+pub async fn nodeinfo_2_1(db: &State<PgPool>) -> Result<(ContentType, String), Status> {
+  match nodeinfo_document("2.1", db).await {
+    Ok(results) => Ok((nodeinfo_content_type("2.1"), results.to_string())),
+    Err(_why) => Err(Status::NotFound)
   }
 }
 
@@ -55,7 +124,9 @@ mod test {
   use rocket::uri;
   use rocket::{Rocket, Build};
   use sqlx::postgres::PgPool;
-  
+  use serde_json::Value;
+
+  use crate::models::Setting;
   use crate::utils::test_helpers::{build_test_server};
 
   #[sqlx::test]
@@ -73,4 +144,38 @@ mod test {
       None => panic!()
     }
   }
+
+  #[sqlx::test]
+  async fn test_nodeinfo_2_1(pool: PgPool) {
+    Setting::update(&"signups_enabled".to_string(), &"false".to_string(), &pool).await.unwrap();
+    Setting::update(&"nodeinfo_name".to_string(), &"Feeds!".to_string(), &pool).await.unwrap();
+
+    let server:Rocket<Build> = build_test_server(pool).await;
+    let client = Client::tracked(server).await.unwrap();
+
+    let response = client.get(uri!(super::nodeinfo_2_1)).dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    assert!(response.headers().get_one("Content-Type").unwrap().contains("schema/2.1#"));
+
+    let output: Value = serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+    assert_eq!(output["version"], "2.1");
+    assert_eq!(output["openRegistrations"], false);
+    assert_eq!(output["metadata"]["nodeName"], "Feeds!");
+    assert_eq!(output["usage"]["users"]["activeMonth"], 0);
+  }
+
+  #[sqlx::test]
+  async fn test_nodeinfo_discovery(pool: PgPool) {
+    let server:Rocket<Build> = build_test_server(pool).await;
+    let client = Client::tracked(server).await.unwrap();
+
+    let response = client.get(uri!(super::nodeinfo_discovery)).dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let output: Value = serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+    let links = output["links"].as_array().unwrap();
+    assert_eq!(links.len(), 2);
+    assert_eq!(links[1]["rel"], "http://nodeinfo.diaspora.software/ns/schema/2.1");
+    assert!(links[1]["href"].as_str().unwrap().ends_with("/nodeinfo/2.1"));
+  }
 }
\ No newline at end of file
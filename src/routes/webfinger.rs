@@ -2,11 +2,13 @@ use std::env;
 
 use rocket::get;
 use rocket::http::Status;
+use rocket::response::Redirect;
 use rocket::State;
 use rocket::uri;
 
 use sqlx::postgres::PgPool;
 
+use url::Url;
 use webfinger::*;
 
 use crate::models::Feed;
@@ -16,56 +18,119 @@ use crate::utils::urls::path_to_url;
 use crate::traits::CustomContentType;
 
 
+/// rel for the link remote servers use to follow an account from here
+pub const SUBSCRIBE_REL: &str = "http://ostatus.org/schema/1.0/subscribe";
+
+///
+/// Figure out which feed a webfinger resource refers to. We accept
+/// acct:name@domain (with or without the acct: prefix), as well as
+/// the URL of the feed's actor
+///
+pub fn resource_to_feed_name(resource: &str, instance_domain: &str) -> Option<String> {
+  let resource = resource.trim();
+
+  if let Ok(url) = Url::parse(resource) {
+    if url.scheme() == "http" || url.scheme() == "https" {
+      let host = match url.port() {
+        Some(port) => format!("{}:{port}", url.host_str()?),
+        None => url.host_str()?.to_string()
+      };
+
+      if !host.eq_ignore_ascii_case(instance_domain) {
+        return None
+      }
+
+      let mut segments = url.path_segments()?.filter(|segment| !segment.is_empty());
+      return match (segments.next(), segments.next(), segments.next()) {
+        (Some("feed"), Some(name), None) => Some(name.to_lowercase()),
+        _ => None
+      }
+    }
+  }
+
+  // https://github.com/Plume-org/webfinger/blob/main/src/async_resolver.rs
+  let res = match resource.split_once(':') {
+    Some((prefix, res)) if prefix.eq_ignore_ascii_case("acct") => res,
+    Some(_) => return None,
+    None => resource
+  };
+
+  let (user, domain) = res.trim_start_matches('@').split_once('@')?;
+  if user.is_empty() || !domain.eq_ignore_ascii_case(instance_domain) {
+    return None
+  }
+
+  Some(user.to_lowercase())
+}
+
 ///
 /// Respond to webfinger requests
 ///
-#[get("/.well-known/webfinger?<resource>")]
-pub async fn lookup_webfinger(resource: &str, db: &State<PgPool>) -> Result<CustomContentType<String>, Status> {
+#[get("/.well-known/webfinger?<resource>&<rel>")]
+pub async fn lookup_webfinger(resource: &str, rel: Vec<String>, db: &State<PgPool>) -> Result<CustomContentType<String>, Status> {
   let instance_domain = env::var("DOMAIN_NAME").expect("DOMAIN_NAME is not set");
-  
-  // https://github.com/Plume-org/webfinger/blob/main/src/async_resolver.rs
-  let mut parsed_query = resource.splitn(2, ':');
-  let _res_prefix = Prefix::from(parsed_query.next().ok_or(Status::NotFound)?);
-  let res = parsed_query.next().ok_or(Status::NotFound)?;
-  
-  let mut parsed_res = res.splitn(2, '@');
-  let user = parsed_res.next().ok_or(Status::NotFound)?;
-  let domain = parsed_res.next().ok_or(Status::NotFound)?;
 
-  if domain != instance_domain {
-    return Err(Status::NotFound)
-  }
-  
-  let userstr = user.to_string();
+  let name = resource_to_feed_name(resource, &instance_domain).ok_or(Status::NotFound)?;
 
   // ensure feed exists
-  let feed_exists = Feed::exists_by_name(&userstr, db).await;
-
-  if feed_exists.is_ok() && feed_exists.unwrap() {
-    let href = path_to_url(&uri!(render_feed(&userstr)));
-    let results = serde_json::to_string(&Webfinger {
-      subject: format!("acct:{}@{}", userstr.clone(), instance_domain),
-      aliases: vec![userstr.clone()],
-      links: vec![
-        Link {
-          rel: "http://webfinger.net/rel/profile-page".to_string(),
-          mime_type: None,
-          href: Some(href.clone()),
-          template: None,
-        },
-        Link {
-          rel: "self".to_string(),
-          mime_type: Some("application/activity+json".to_string()),
-          href: Some(href),
-          template: None,
-        }
-      ],
-    }).unwrap();
-
-    Ok(CustomContentType("application/jrd+json".to_string(), results))
-  }
-  else {
-    Err(Status::NotFound)
+  let feed = match Feed::find_by_name_case_insensitive(&name, db).await {
+    Ok(Some(feed)) => feed,
+    _ => return Err(Status::NotFound)
+  };
+
+  // the actor URL and the profile page are one and the same
+  let href = path_to_url(&uri!(render_feed(&feed.name)));
+
+  let links: Vec<Link> = vec![
+    Link {
+      rel: "http://webfinger.net/rel/profile-page".to_string(),
+      mime_type: Some("text/html".to_string()),
+      href: Some(href.clone()),
+      template: None,
+    },
+    Link {
+      rel: "self".to_string(),
+      mime_type: Some("application/activity+json".to_string()),
+      href: Some(href.clone()),
+      template: None,
+    },
+    Link {
+      rel: SUBSCRIBE_REL.to_string(),
+      mime_type: None,
+      href: None,
+      template: Some(format!("https://{instance_domain}/authorize_interaction?uri={{uri}}")),
+    }
+  ];
+
+  // only send the links that were asked for
+  let links = if rel.is_empty() {
+    links
+  } else {
+    links.into_iter().filter(|link| rel.contains(&link.rel)).collect()
+  };
+
+  let results = serde_json::to_string(&Webfinger {
+    subject: format!("acct:{}@{}", feed.name, instance_domain),
+    aliases: vec![href],
+    links,
+  }).unwrap();
+
+  Ok(CustomContentType("application/jrd+json".to_string(), results))
+}
+
+///
+/// Target of the subscribe template. We don't have local accounts that
+/// can follow anyone, so the best we can do is send people to the feed
+/// they asked about
+///
+#[get("/authorize_interaction?<uri>")]
+pub async fn authorize_interaction(uri: &str, db: &State<PgPool>) -> Result<Redirect, Status> {
+  let instance_domain = env::var("DOMAIN_NAME").expect("DOMAIN_NAME is not set");
+
+  let name = resource_to_feed_name(uri, &instance_domain).ok_or(Status::NotFound)?;
+  match Feed::find_by_name_case_insensitive(&name, db).await {
+    Ok(Some(feed)) => Ok(Redirect::to(uri!(show_feed(&feed.name, None::<i32>)))),
+    _ => Err(Status::NotFound)
   }
 }
 
@@ -74,10 +139,12 @@ pub async fn lookup_webfinger(resource: &str, db: &State<PgPool>) -> Result<Cust
 mod test {
   use rocket::local::asynchronous::Client;
   use rocket::http::Status;
-  use rocket::uri;
   use rocket::{Rocket, Build};
   use sqlx::postgres::PgPool;
   use std::env;
+  use serde_json::Value;
+
+  use crate::routes::webfinger::{resource_to_feed_name, SUBSCRIBE_REL};
   use crate::utils::test_helpers::{build_test_server, real_feed};
 
   
@@ -86,7 +153,7 @@ mod test {
     let server:Rocket<Build> = build_test_server(pool).await;
     let client = Client::tracked(server).await.unwrap();
 
-    let req = client.get(uri!(super::lookup_webfinger("acct:foo@bar.com")));
+    let req = client.get("/.well-known/webfinger?resource=acct:foo@bar.com");
     let response = req.dispatch().await;
 
     assert_eq!(response.status(), Status::NotFound);
@@ -101,7 +168,7 @@ mod test {
     let server: Rocket<Build> = build_test_server(pool).await;
     let client = Client::tracked(server).await.unwrap();
     
-    let req = client.get(uri!(super::lookup_webfinger(format!("acct:{}@{}", &feed.name, instance_domain))));
+    let req = client.get(format!("/.well-known/webfinger?resource=acct:{}@{}", feed.name, instance_domain));
     let response = req.dispatch().await;
 
     assert_eq!(response.status(), Status::Ok);
@@ -112,4 +179,52 @@ mod test {
 
     Ok(())
   }
+
+  #[test]
+  fn test_resource_to_feed_name() {
+    assert_eq!(resource_to_feed_name("acct:Muffin@Feeds.Place", "feeds.place"), Some("muffin".to_string()));
+    assert_eq!(resource_to_feed_name("@muffin@feeds.place", "feeds.place"), Some("muffin".to_string()));
+    assert_eq!(resource_to_feed_name("https://feeds.place/feed/muffin", "feeds.place"), Some("muffin".to_string()));
+    assert_eq!(resource_to_feed_name("https://feeds.place/feed/muffin/", "feeds.place"), Some("muffin".to_string()));
+
+    assert_eq!(resource_to_feed_name("acct:muffin@other.place", "feeds.place"), None);
+    assert_eq!(resource_to_feed_name("https://other.place/feed/muffin", "feeds.place"), None);
+    assert_eq!(resource_to_feed_name("https://feeds.place/feed/muffin/items/1", "feeds.place"), None);
+    assert_eq!(resource_to_feed_name("mailto:muffin@feeds.place", "feeds.place"), None);
+  }
+
+  #[sqlx::test]
+  async fn test_lookup_webfinger_variants(pool: PgPool) -> sqlx::Result<()> {
+    let instance_domain = env::var("DOMAIN_NAME").expect("DOMAIN_NAME is not set");
+
+    let feed = real_feed(&pool).await.unwrap();
+    let actor_url = feed.ap_url();
+
+    let server: Rocket<Build> = build_test_server(pool).await;
+    let client = Client::tracked(server).await.unwrap();
+
+    // url resources, and names in the wrong case
+    for resource in [actor_url.clone(), format!("acct:{}@{}", feed.name.to_uppercase(), instance_domain.to_uppercase())] {
+      let response = client.get(format!("/.well-known/webfinger?resource={resource}")).dispatch().await;
+      assert_eq!(response.status(), Status::Ok);
+
+      let body: Value = serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+      assert_eq!(body["subject"], format!("acct:{}@{}", feed.name, instance_domain));
+      assert_eq!(body["aliases"][0], actor_url);
+      assert_eq!(body["links"].as_array().unwrap().len(), 3);
+    }
+
+    // only return the requested links
+    let response = client.get(format!("/.well-known/webfinger?resource={actor_url}&rel=self&rel={SUBSCRIBE_REL}")).dispatch().await;
+    let body: Value = serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+    let links = body["links"].as_array().unwrap();
+    assert_eq!(links.len(), 2);
+    assert_eq!(links[0]["rel"], "self");
+    assert!(links[1]["template"].as_str().unwrap().ends_with("/authorize_interaction?uri={uri}"));
+
+    let response = client.get(format!("/authorize_interaction?uri={actor_url}")).dispatch().await;
+    assert_eq!(response.status(), Status::SeeOther);
+
+    Ok(())
+  }
 }
@@ -1,18 +1,45 @@
 use std::env;
-use rocket::response::content;
 use rocket::get;
 
-#[get("/.well-known/host-meta")]
-pub async fn host_meta() -> content::RawXml<String> {
+use serde_json::json;
+
+use crate::traits::CustomContentType;
+
+///
+/// Template pointing clients at our webfinger endpoint. This needs to
+/// accept the same resources as lookup_webfinger does
+///
+fn lrdd_template() -> String {
   let instance_domain = env::var("DOMAIN_NAME").expect("DOMAIN_NAME is not set");
+  format!("https://{instance_domain}/.well-known/webfinger?resource={{uri}}")
+}
+
+#[get("/.well-known/host-meta")]
+pub async fn host_meta() -> CustomContentType<String> {
+  let template = lrdd_template();
 
   let output: String = format!(
     r#"<?xml version="1.0" encoding="UTF-8"?>
   <XRD xmlns="http://docs.oasis-open.org/ns/xri/xrd-1.0">
-    <Link rel="lrdd" template="https://{instance_domain:}/.well-known/webfinger?resource={{uri}}"/>
+    <Link rel="lrdd" type="application/jrd+json" template="{template:}"/>
   </XRD>"#);
 
-  content::RawXml(output)
+  CustomContentType("application/xrd+xml".to_string(), output)
+}
+
+#[get("/.well-known/host-meta.json")]
+pub async fn host_meta_json() -> CustomContentType<String> {
+  let output = json!({
+    "links": [
+      {
+        "rel": "lrdd",
+        "type": "application/jrd+json",
+        "template": lrdd_template()
+      }
+    ]
+  });
+
+  CustomContentType("application/json".to_string(), output.to_string())
 }
 
 #[cfg(test)]
@@ -41,4 +68,17 @@ mod test {
       None => panic!()
     }
   }
+
+  #[sqlx::test]
+  async fn test_host_meta_json(pool: PgPool) {
+    let server:Rocket<Build> = build_test_server(pool).await;
+    let client = Client::tracked(server).await.unwrap();
+
+    let response = client.get(uri!(super::host_meta_json)).dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let output: serde_json::Value = serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+    assert_eq!(output["links"][0]["rel"], "lrdd");
+    assert!(output["links"][0]["template"].as_str().unwrap().ends_with("/.well-known/webfinger?resource={uri}"));
+  }
 }
\ No newline at end of file
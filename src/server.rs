@@ -1,6 +1,7 @@
 use sqlx::postgres::PgPool;
 
 use rocket::{
+  catchers,
   routes,
   Rocket,
   Build,
@@ -8,6 +9,7 @@ use rocket::{
 };
 use rocket_dyn_templates::Template;
 
+use crate::services::email::Mailer;
 use crate::utils::admin::create_admin_feed;
 
 pub async fn build_server(pool: PgPool) -> Rocket<Build> {
@@ -22,9 +24,16 @@ pub async fn build_server(pool: PgPool) -> Rocket<Build> {
     Ok(result) => log::info!("create_admin_feed {result:?}"),
     Err(why) => panic!("{}", why)
   };
+
+  // check the mail settings now, rather than when someone tries to log in
+  let mailer = match Mailer::from_env() {
+    Ok(mailer) => mailer,
+    Err(why) => panic!("{}", why)
+  };
   
   rocket::build()
     .manage(pool)
+    .manage(mailer)
     .mount("/assets", FileServer::from(relative!("assets")))
     .mount("/", routes![
       crate::routes::index::index,
@@ -33,10 +42,37 @@ pub async fn build_server(pool: PgPool) -> Rocket<Build> {
       crate::routes::login::do_logout,
       crate::routes::login::login_result,
       crate::routes::login::attempt_login,
+      crate::routes::login::index_sessions,
+      crate::routes::login::delete_session,
+      crate::routes::login::logout_everywhere,
+      crate::routes::login::index_api_tokens,
+      crate::routes::login::create_api_token,
+      crate::routes::login::delete_api_token,
       crate::routes::enclosures::show_enclosure,
       crate::routes::feeds::add_feed,
       crate::routes::feeds::test_feed,
       crate::routes::feeds::update_feed,
+      crate::routes::feeds::pause_feed,
+      crate::routes::feeds::move_feed,
+      crate::routes::feeds::rotate_feed_key,
+      crate::routes::feeds::respond_to_follow_request,
+      crate::routes::admin::index_domain_blocks_admin,
+      crate::routes::admin::create_relay_admin,
+      crate::routes::admin::subscribe_relay_admin,
+      crate::routes::admin::delete_relay_admin,
+      crate::routes::admin::create_domain_block_admin,
+      crate::routes::admin::delete_domain_block_admin,
+      crate::routes::admin::export_domain_blocks_admin,
+      crate::routes::admin::import_domain_blocks_admin,
+      crate::routes::admin::index_deliveries_admin,
+      crate::routes::admin::replay_delivery_admin,
+      crate::routes::admin::delete_delivery_admin,
+      crate::routes::feeds::manage_followers,
+      crate::routes::feeds::remove_follower,
+      crate::routes::feeds::add_feed_block,
+      crate::routes::feeds::delete_feed_block,
+      crate::routes::feeds::add_feed_member,
+      crate::routes::feeds::delete_feed_member,
       crate::routes::feeds::delete_feed,
       crate::routes::feeds::render_feed,
       crate::routes::feeds::render_feed_followers,
@@ -44,15 +80,36 @@ pub async fn build_server(pool: PgPool) -> Rocket<Build> {
       crate::routes::items::show_item,
       crate::routes::items::show_item_json,
       crate::routes::webfinger::lookup_webfinger,
+      crate::routes::webfinger::authorize_interaction,
       crate::routes::ap::inbox::user_inbox,
       crate::routes::ap::outbox::render_feed_outbox,
       crate::routes::admin::index_admin,
       crate::routes::admin::show_feed_admin,
       crate::routes::admin::update_settings_admin,
+      crate::routes::admin::update_user_quota_admin,
+      crate::routes::admin::rotate_feed_key_admin,
       crate::routes::admin::delete_feed_admin,
       crate::routes::well_known::host_meta,
-      crate::routes::nodeinfo::nodeinfo
+      crate::routes::well_known::host_meta_json,
+      crate::routes::nodeinfo::nodeinfo,
+      crate::routes::nodeinfo::nodeinfo_2_1,
+      crate::routes::nodeinfo::nodeinfo_discovery
 
     ])
+    .mount("/api/v1", routes![
+      crate::routes::api::v1::openapi,
+      crate::routes::api::v1::list_feeds,
+      crate::routes::api::v1::create_feed_api,
+      crate::routes::api::v1::show_feed_api,
+      crate::routes::api::v1::update_feed_api,
+      crate::routes::api::v1::delete_feed_api,
+      crate::routes::api::v1::pause_feed_api,
+      crate::routes::api::v1::resume_feed_api,
+      crate::routes::api::v1::refresh_feed_api,
+      crate::routes::api::v1::feed_status_api,
+      crate::routes::api::v1::feed_items_api,
+      crate::routes::api::v1::feed_followers_api
+    ])
+    .register("/api", catchers![crate::routes::api::default_catcher])
     .attach(Template::fairing())
 }
@@ -1,6 +1,9 @@
 use sqlx::postgres::PgPool;
 use fang::FangError;
-use crate::models::{Item, Message};
+use crate::models::{Delivery, Item, LoginRequest, Message, FollowRequest, Session};
+use crate::models::follow_request::follow_request_expiry;
+use crate::models::quota::DeliveryBudget;
+use crate::services::fediverse_creator::cleanup_creators;
 
 
 use std::{
@@ -8,9 +11,27 @@ use std::{
   str::FromStr
 };
 
+/// How long to keep the log of successful deliveries, in seconds
+const DELIVERY_MAX_AGE: i64 = 60 * 60 * 24 * 7;
 
 pub async fn cleanup_messages(pool: &PgPool) -> Result<(), FangError> {
-  let result = Message::cleanup(pool, 365, 10000).await;
+  let mut result = Message::cleanup(pool, 365, 10000).await;
+  if result.is_ok() {
+    result = Delivery::cleanup(pool, DELIVERY_MAX_AGE, 10000).await;
+  }
+  if result.is_ok() {
+    result = LoginRequest::cleanup(pool).await;
+  }
+  if result.is_ok() {
+    result = Session::cleanup(pool).await;
+  }
+  if result.is_ok() {
+    result = DeliveryBudget::cleanup(pool).await;
+  }
+  if result.is_ok() {
+    result = cleanup_creators(pool).await;
+  }
+
   match result {
     Ok(result) => Ok(result),
     Err(err) => {
@@ -33,6 +54,17 @@ pub async fn cleanup_items(pool: &PgPool) -> Result<(), FangError> {
   }
 }
 
+pub async fn cleanup_follow_requests(pool: &PgPool) -> Result<(), FangError> {
+  let result = FollowRequest::cleanup(pool, follow_request_expiry()).await;
+  match result {
+    Ok(result) => Ok(result),
+    Err(err) => {
+      let description = format!("{err:?}");
+
+      Err(FangError { description })
+    }
+  }
+}
 
 
 const ACTOR_ERROR_COUNT: i32 = 10;
@@ -0,0 +1,147 @@
+use lettre::{
+  AsyncFileTransport,
+  AsyncSmtpTransport,
+  AsyncTransport,
+  Message,
+  Tokio1Executor,
+  message::{header::ContentType, Mailbox},
+  transport::smtp::authentication::Credentials
+};
+
+use std::env;
+use std::path::PathBuf;
+
+use crate::DeliveryError;
+use crate::utils::templates::{render, Context};
+
+///
+/// Where outgoing email goes. SMTP is for real use, while the file and
+/// stdout transports are handy for development and tests
+///
+pub enum MailTransport {
+  Smtp(AsyncSmtpTransport<Tokio1Executor>),
+  File(AsyncFileTransport<Tokio1Executor>),
+  Stdout
+}
+
+pub struct Mailer {
+  pub from: Mailbox,
+  pub transport: MailTransport
+}
+
+fn mail_error<E: std::fmt::Display>(why: E) -> DeliveryError {
+  DeliveryError::Error(format!("mail error: {why}"))
+}
+
+impl Mailer {
+  pub fn new(from: &str, transport: MailTransport) -> Result<Mailer, DeliveryError> {
+    let from = from.parse::<Mailbox>().map_err(mail_error)?;
+    Ok(Mailer { from, transport })
+  }
+
+  ///
+  /// Set up a mailer from the environment:
+  ///
+  /// MAIL_TRANSPORT is smtp, file or stdout (the default)
+  /// MAIL_FROM is the sender address, defaulting to noreply@DOMAIN_NAME
+  /// SMTP_HOST, SMTP_PORT, SMTP_USERNAME and SMTP_PASSWORD configure SMTP
+  /// SMTP_SECURITY is starttls (the default), tls, or none
+  /// MAIL_FILE_PATH is the directory the file transport writes to
+  ///
+  pub fn from_env() -> Result<Mailer, DeliveryError> {
+    let domain_name = env::var("DOMAIN_NAME").map_err(|_| mail_error("DOMAIN_NAME is not set"))?;
+    let host = domain_name.split(':').next().unwrap_or_default();
+    let from = env::var("MAIL_FROM").unwrap_or_else(|_| format!("noreply@{host}"));
+
+    let transport = match env::var("MAIL_TRANSPORT").unwrap_or_default().as_str() {
+      "smtp" => {
+        let host = env::var("SMTP_HOST").map_err(|_| mail_error("SMTP_HOST is not set"))?;
+        let security = env::var("SMTP_SECURITY").unwrap_or_else(|_| "starttls".to_string());
+
+        let mut builder = match security.as_str() {
+          "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(&host).map_err(mail_error)?,
+          "none" => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&host),
+          _ => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host).map_err(mail_error)?
+        };
+
+        if let Ok(port) = env::var("SMTP_PORT") {
+          builder = builder.port(port.parse::<u16>().map_err(mail_error)?);
+        }
+
+        if let (Ok(username), Ok(password)) = (env::var("SMTP_USERNAME"), env::var("SMTP_PASSWORD")) {
+          builder = builder.credentials(Credentials::new(username, password));
+        }
+
+        MailTransport::Smtp(builder.build())
+      },
+      "file" => {
+        let path = PathBuf::from(env::var("MAIL_FILE_PATH").unwrap_or_else(|_| "tmp/mail".to_string()));
+        std::fs::create_dir_all(&path).map_err(mail_error)?;
+
+        MailTransport::File(AsyncFileTransport::<Tokio1Executor>::new(path))
+      },
+      _ => MailTransport::Stdout
+    };
+
+    Mailer::new(&from, transport)
+  }
+
+  ///
+  /// Render the template and send it to the given address
+  ///
+  pub async fn send(&self, to: &str, subject: &str, template: &str, context: &Context) -> Result<(), DeliveryError> {
+    let body = render(template, context).map_err(mail_error)?;
+
+    let message = Message::builder()
+      .from(self.from.clone())
+      .to(to.parse::<Mailbox>().map_err(mail_error)?)
+      .subject(subject)
+      .header(ContentType::TEXT_PLAIN)
+      .body(body)
+      .map_err(mail_error)?;
+
+    match &self.transport {
+      MailTransport::Smtp(transport) => {
+        transport.send(message).await.map_err(mail_error)?;
+      },
+      MailTransport::File(transport) => {
+        transport.send(message).await.map_err(mail_error)?;
+      },
+      MailTransport::Stdout => {
+        println!("{}", String::from_utf8_lossy(&message.formatted()));
+      }
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use lettre::{AsyncFileTransport, Tokio1Executor};
+  use std::fs;
+
+  use crate::services::email::{Mailer, MailTransport};
+  use crate::utils::templates::Context;
+
+  #[tokio::test]
+  async fn test_send_to_file() {
+    let dir = std::env::temp_dir().join(format!("mail-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let mailer = Mailer::new("noreply@feeds.place", MailTransport::File(AsyncFileTransport::<Tokio1Executor>::new(&dir))).unwrap();
+
+    let mut context = Context::new();
+    context.insert("link", "https://feeds.place/user/auth/abc123");
+    mailer.send("foo@bar.com", "Your login link", "email/send-login-email", &context).await.unwrap();
+
+    let files: Vec<_> = fs::read_dir(&dir).unwrap().collect();
+    assert_eq!(files.len(), 1);
+
+    let body = fs::read_to_string(files[0].as_ref().unwrap().path()).unwrap();
+    assert!(body.contains("To: foo@bar.com"));
+    assert!(body.contains("https://feeds.place/user/auth/abc123"));
+
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
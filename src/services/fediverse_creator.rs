@@ -0,0 +1,273 @@
+use scraper::{Html, Selector};
+use sqlx::postgres::PgPool;
+
+use chrono::{Duration, Utc};
+
+use std::net::IpAddr;
+
+use crate::DeliveryError;
+use crate::utils::http::*;
+
+/// How long to hold on to the actor we found for a handle, in seconds
+const CREATOR_CACHE_AGE: i64 = 60 * 60 * 24;
+
+///
+/// Pull the author's fediverse handle out of the fediverse:creator meta
+/// tag on a page, ie <meta name="fediverse:creator" content="@user@domain">
+///
+pub fn parse_fediverse_creator(html: &str) -> Option<String> {
+  let document = Html::parse_document(html);
+  let selector = Selector::parse(r#"meta[name="fediverse:creator"][content]"#).unwrap();
+
+  let handle = document.select(&selector).next()?.value().attr("content")?;
+  let handle = handle.trim().trim_start_matches('@');
+
+  // we need both a user and a domain
+  match handle.split_once('@') {
+    Some((user, domain)) if !user.is_empty() && !domain.is_empty() && !domain.contains('@') => Some(format!("@{handle}")),
+    _ => None
+  }
+}
+
+///
+/// Get the webfinger URL for a handle like @user@domain
+///
+pub fn webfinger_url(handle: &str) -> Option<String> {
+  let (user, domain) = handle.trim_start_matches('@').split_once('@')?;
+  Some(format!("https://{domain}/.well-known/webfinger?resource=acct:{user}@{domain}"))
+}
+
+///
+/// Check if an address is one we'd be happy to send a request to. We
+/// don't want a tag on somebody's page pointing us at our own network
+///
+pub fn is_public_ip(ip: IpAddr) -> bool {
+  match ip {
+    IpAddr::V4(ip) => !(ip.is_private() || ip.is_loopback() || ip.is_link_local() || ip.is_unspecified() || ip.is_broadcast()),
+    IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+      Some(ip) => is_public_ip(IpAddr::V4(ip)),
+      None => !(ip.is_loopback() || ip.is_unspecified() || ip.is_unique_local() || ip.is_unicast_link_local())
+    }
+  }
+}
+
+///
+/// Check that a host only resolves to public addresses
+///
+pub async fn is_public_host(host: &str) -> bool {
+  let host = host.trim_start_matches('[').trim_end_matches(']');
+  if host.eq_ignore_ascii_case("localhost") {
+    return false
+  }
+
+  if let Ok(ip) = host.parse::<IpAddr>() {
+    return is_public_ip(ip)
+  }
+
+  match tokio::net::lookup_host((host, 443)).await {
+    Ok(addresses) => {
+      let addresses: Vec<_> = addresses.collect();
+      !addresses.is_empty() && addresses.iter().all(|address| is_public_ip(address.ip()))
+    },
+    Err(_) => false
+  }
+}
+
+async fn fetch_text(url: &str, accept: &str) -> Result<Option<String>, DeliveryError> {
+  let client = http_client()?;
+  let mut heads = generate_request_headers();
+  heads.insert("accept", accept.parse().unwrap());
+
+  let response = client
+    .get(url)
+    .headers(heads)
+    .send()
+    .await
+    .map_err(|err| DeliveryError::Error(err.to_string()))?;
+
+  if !response.status().is_success() {
+    return Ok(None)
+  }
+
+  Ok(Some(response.text().await?))
+}
+
+///
+/// Load the page at the given URL and check it for a fediverse:creator tag
+///
+pub async fn fetch_fediverse_creator(url: &str) -> Result<Option<String>, DeliveryError> {
+  match fetch_text(url, "text/html").await? {
+    Some(html) => Ok(parse_fediverse_creator(&html)),
+    None => Ok(None)
+  }
+}
+
+///
+/// Query a webfinger endpoint and return the URL of the actor it points to
+///
+pub async fn lookup_actor_url(webfinger_url: &str) -> Result<Option<String>, DeliveryError> {
+  let data = match fetch_text(webfinger_url, "application/jrd+json").await? {
+    Some(data) => data,
+    None => return Ok(None)
+  };
+
+  let data: serde_json::Value = serde_json::from_str(&data)?;
+  let actor_url = data["links"].as_array()
+    .and_then(|links| links.iter().find(|link| {
+      link["rel"] == "self" && link["type"].as_str().is_some_and(|kind| kind.contains("activity+json") || kind.contains("ld+json"))
+    }))
+    .and_then(|link| link["href"].as_str())
+    .map(|href| href.to_string());
+
+  Ok(actor_url)
+}
+
+///
+/// Turn a handle like @user@domain into an actor URL. Handles on
+/// private or loopback hosts don't resolve to anything
+///
+pub async fn resolve_handle(handle: &str) -> Result<Option<String>, DeliveryError> {
+  let domain = match handle.trim_start_matches('@').split_once('@') {
+    Some((_user, domain)) => domain,
+    None => return Ok(None)
+  };
+
+  if !is_public_host(domain).await {
+    log::info!("not resolving {handle}, the host isn't public");
+    return Ok(None)
+  }
+
+  match webfinger_url(handle) {
+    Some(url) => lookup_actor_url(&url).await,
+    None => Ok(None)
+  }
+}
+
+///
+/// Turn a handle into an actor URL, using the answer from the last
+/// lookup if it's recent enough. Handles that didn't resolve are
+/// remembered too, so we don't keep asking
+///
+pub async fn find_creator_url(handle: &str, pool: &PgPool) -> Result<Option<String>, DeliveryError> {
+  let age = Utc::now() - Duration::seconds(CREATOR_CACHE_AGE);
+  let cached = sqlx::query!("SELECT actor_url FROM fediverse_creators WHERE handle = $1 AND checked_at > $2",
+    handle, age)
+    .fetch_optional(pool)
+    .await?;
+
+  if let Some(cached) = cached {
+    return Ok(cached.actor_url)
+  }
+
+  let actor_url = resolve_handle(handle).await?;
+
+  sqlx::query!("INSERT INTO fediverse_creators (handle, actor_url, checked_at)
+      VALUES($1, $2, $3)
+      ON CONFLICT (handle) DO UPDATE SET actor_url = EXCLUDED.actor_url, checked_at = EXCLUDED.checked_at",
+      handle, actor_url, Utc::now())
+    .execute(pool)
+    .await?;
+
+  Ok(actor_url)
+}
+
+///
+/// Forget lookups that are too old to use
+///
+pub async fn cleanup_creators(pool: &PgPool) -> Result<(), sqlx::Error> {
+  let age = Utc::now() - Duration::seconds(CREATOR_CACHE_AGE);
+  sqlx::query!("DELETE FROM fediverse_creators WHERE checked_at < $1", age)
+    .execute(pool)
+    .await?;
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod test {
+  use sqlx::postgres::PgPool;
+
+  use crate::services::fediverse_creator::*;
+
+  #[test]
+  fn test_parse_fediverse_creator() {
+    let html = r#"<html><head><meta name="fediverse:creator" content="@colin@muffin.industries" /></head><body></body></html>"#;
+    assert_eq!(parse_fediverse_creator(html), Some("@colin@muffin.industries".to_string()));
+
+    let html = r#"<html><head><meta name="fediverse:creator" content="colin@muffin.industries" /></head></html>"#;
+    assert_eq!(parse_fediverse_creator(html), Some("@colin@muffin.industries".to_string()));
+
+    let html = r#"<html><head><meta name="fediverse:creator" content="@colin" /></head></html>"#;
+    assert_eq!(parse_fediverse_creator(html), None);
+
+    assert_eq!(parse_fediverse_creator("<html><head></head></html>"), None);
+  }
+
+  #[test]
+  fn test_webfinger_url() {
+    assert_eq!(webfinger_url("@colin@muffin.industries"), Some("https://muffin.industries/.well-known/webfinger?resource=acct:colin@muffin.industries".to_string()));
+    assert_eq!(webfinger_url("colin"), None);
+  }
+
+  #[test]
+  fn test_is_public_ip() {
+    assert!(is_public_ip("93.184.216.34".parse().unwrap()));
+    assert!(is_public_ip("2606:2800:220:1::".parse().unwrap()));
+    assert!(!is_public_ip("127.0.0.1".parse().unwrap()));
+    assert!(!is_public_ip("10.1.2.3".parse().unwrap()));
+    assert!(!is_public_ip("192.168.1.1".parse().unwrap()));
+    assert!(!is_public_ip("169.254.169.254".parse().unwrap()));
+    assert!(!is_public_ip("::1".parse().unwrap()));
+    assert!(!is_public_ip("fd00::1".parse().unwrap()));
+    assert!(!is_public_ip("::ffff:127.0.0.1".parse().unwrap()));
+  }
+
+  #[tokio::test]
+  async fn test_resolve_private_handle() {
+    assert_eq!(resolve_handle("@colin@localhost").await.unwrap(), None);
+    assert_eq!(resolve_handle("@colin@127.0.0.1").await.unwrap(), None);
+    assert_eq!(resolve_handle("@colin@[::1]").await.unwrap(), None);
+  }
+
+  #[sqlx::test]
+  async fn test_find_creator_url(pool: PgPool) -> Result<(), String> {
+    let handle = "@colin@muffin.industries";
+    let actor_url = "https://muffin.industries/users/colin";
+
+    sqlx::query!("INSERT INTO fediverse_creators (handle, actor_url) VALUES($1, $2)", handle, actor_url)
+      .execute(&pool)
+      .await
+      .unwrap();
+
+    assert_eq!(find_creator_url(handle, &pool).await.unwrap(), Some(actor_url.to_string()));
+
+    // private hosts don't resolve, and we remember that
+    assert_eq!(find_creator_url("@colin@localhost", &pool).await.unwrap(), None);
+    let cached = sqlx::query!("SELECT actor_url FROM fediverse_creators WHERE handle = $1", "@colin@localhost")
+      .fetch_one(&pool)
+      .await
+      .unwrap();
+    assert!(cached.actor_url.is_none());
+
+    Ok(())
+  }
+
+  #[tokio::test]
+  async fn test_lookup_actor_url() -> Result<(), String> {
+    let mut server = mockito::Server::new_async().await;
+    let actor = format!("{}/users/colin", server.url());
+
+    let body = format!(r#"{{"subject":"acct:colin@muffin.industries","links":[{{"rel":"http://webfinger.net/rel/profile-page","type":"text/html","href":"https://muffin.industries/@colin"}},{{"rel":"self","type":"application/activity+json","href":"{actor}"}}]}}"#);
+    let _m = server.mock("GET", "/.well-known/webfinger")
+      .match_query(mockito::Matcher::Any)
+      .with_status(200)
+      .with_body(body)
+      .create_async()
+      .await;
+
+    let url = format!("{}/.well-known/webfinger?resource=acct:colin@muffin.industries", server.url());
+    assert_eq!(lookup_actor_url(&url).await.unwrap(), Some(actor));
+
+    Ok(())
+  }
+}
@@ -0,0 +1,269 @@
+use rocket::FromForm;
+
+use fang::AsyncQueueable;
+use fang::AsyncRunnable;
+
+use sqlx::postgres::PgPool;
+
+use std::fmt;
+
+use crate::DeliveryError;
+use crate::models::Feed;
+use crate::models::Quota;
+use crate::models::Setting;
+use crate::models::User;
+use crate::models::feed::{FOLLOWERS_PUBLIC, FOLLOWERS_COUNT, FOLLOWERS_HIDDEN, OBJECT_TYPE_NOTE, OBJECT_TYPE_ARTICLE, OBJECT_TYPE_AUTO};
+use crate::services::url_to_feed::url_to_feed_url;
+use crate::tasks::RefreshFeed;
+use crate::utils::queue::create_queue;
+
+///
+/// The things that can go wrong when managing a feed. The HTML forms and
+/// the API turn these into a flash message or a status code
+///
+#[derive(Debug)]
+pub enum FeedManagerError {
+  SignupsDisabled,
+  InvalidName,
+  NameTaken,
+  InvalidUrl,
+  UserQuotaReached(i64),
+  InstanceQuotaReached,
+  Database(sqlx::Error)
+}
+
+impl fmt::Display for FeedManagerError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      FeedManagerError::SignupsDisabled => write!(f, "Sorry, new feeds aren't being accepted right now"),
+      FeedManagerError::InvalidName => write!(f, "Sorry, please limit the username to letters and digits"),
+      FeedManagerError::NameTaken => write!(f, "Sorry, that username is already taken"),
+      FeedManagerError::InvalidUrl => write!(f, "Sorry, we couldn't find a feed at that URL"),
+      FeedManagerError::UserQuotaReached(limit) => write!(f, "Sorry, you've reached your limit of {limit} feeds"),
+      FeedManagerError::InstanceQuotaReached => write!(f, "Sorry, this server can't take on any more feeds right now"),
+      FeedManagerError::Database(why) => write!(f, "{why}")
+    }
+  }
+}
+
+impl From<sqlx::Error> for FeedManagerError {
+  fn from(error: sqlx::Error) -> Self {
+    FeedManagerError::Database(error)
+  }
+}
+
+///
+/// The settings someone can change on a feed
+///
+#[derive(FromForm, serde::Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct FeedSettings {
+  pub url: String,
+  #[serde(default)]
+  pub listed: bool,
+  pub status_publicity: Option<String>,
+  pub content_warning: Option<String>,
+  pub hashtag: Option<String>,
+  pub title: Option<String>,
+  pub description: Option<String>,
+  pub also_known_as: Option<String>,
+  #[serde(default)]
+  pub secure_mode: bool,
+  #[serde(default)]
+  pub manually_approves_followers: bool,
+  pub followers_visibility: Option<String>,
+  pub object_type: Option<String>,
+  #[serde(default)]
+  pub redirect_items: bool
+}
+
+///
+/// Check a feed name against the same rules the signup form uses
+///
+pub fn valid_feed_name(name: &str) -> bool {
+  let name = name.to_lowercase();
+  let edge = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_';
+
+  match (name.chars().next(), name.chars().last()) {
+    (Some(first), Some(last)) => edge(first) && edge(last) && name.chars().all(|c| edge(c) || c == '.' || c == '-'),
+    _ => false
+  }
+}
+
+///
+/// After creating/updating a feed, let's refresh its data
+///
+pub async fn request_feed_update(feed: &Feed) -> Result<fang::Task, fang::AsyncQueueError> {
+  let task = RefreshFeed { id: feed.id };
+  let mut queue = create_queue().await;
+  queue.connect(fang::NoTls).await.unwrap();
+
+  queue
+    .insert_task(&task as &dyn AsyncRunnable)
+    .await
+}
+
+///
+/// Let followers know that the profile for this feed has changed
+///
+pub async fn request_profile_update(feed: &Feed) -> Result<(), DeliveryError> {
+  let mut queue = create_queue().await;
+  queue.connect(fang::NoTls).await.unwrap();
+
+  feed.queue_profile_update(&mut queue).await
+}
+
+///
+/// Create a feed for the user, after making sure they're within their
+/// quota and the URL points at a real feed, and queue up its first refresh
+///
+pub async fn create_feed(user: &User, name: &str, url: &str, pool: &PgPool) -> Result<Feed, FeedManagerError> {
+  let signups_enabled = Setting::value_or(&"signups_enabled".to_string(), &"true".to_string(), pool).await?;
+  if signups_enabled != "true" {
+    return Err(FeedManagerError::SignupsDisabled)
+  }
+
+  let quota = Quota::load(pool).await?;
+  if quota.instance_full(user, pool).await? {
+    return Err(FeedManagerError::InstanceQuotaReached)
+  }
+
+  if let Some(limit) = quota.user_full(user, pool).await? {
+    return Err(FeedManagerError::UserQuotaReached(limit))
+  }
+
+  let name = name.trim().to_string();
+  if !valid_feed_name(&name) {
+    return Err(FeedManagerError::InvalidName)
+  }
+
+  if Feed::exists_by_name(&name, pool).await? {
+    return Err(FeedManagerError::NameTaken)
+  }
+
+  //
+  // follow the URL to make sure we add a valid RSS feed at this point
+  //
+  let url = match url_to_feed_url(&url.to_string()).await {
+    Ok(Some(url)) => url,
+    _ => return Err(FeedManagerError::InvalidUrl)
+  };
+
+  let feed = Feed::create(user, &url, &name, pool).await?;
+  let _ = request_feed_update(&feed).await;
+
+  if user.actor_url.is_some() {
+    match user.send_link_to_feed(&feed, pool).await {
+      Ok(_notify) => log::debug!("user notified!"),
+      Err(why) => log::info!("something went wrong with notification: {why:?}")
+    }
+  }
+
+  Ok(feed)
+}
+
+///
+/// Apply new settings to a feed and save it. The feed is refreshed, and
+/// followers hear about it if the profile changed
+///
+pub async fn update_feed(feed: &mut Feed, settings: &FeedSettings, pool: &PgPool) -> Result<(), sqlx::Error> {
+  let profile = feed.profile_snapshot();
+
+  feed.listed = settings.listed;
+  feed.content_warning = settings.content_warning.clone();
+  feed.hashtag = settings.hashtag.clone();
+  feed.status_publicity = settings.status_publicity.clone();
+  feed.url = settings.url.clone();
+
+  // user has tweaked title/description, let's mark that
+  if settings.title != feed.title || feed.description != settings.description {
+    feed.tweaked_profile_data = true;
+  }
+
+  feed.title = settings.title.clone();
+  feed.description = settings.description.clone();
+  feed.also_known_as = settings.also_known_as.as_deref().and_then(Feed::clean_aliases);
+  feed.secure_mode = settings.secure_mode;
+  feed.manually_approves_followers = settings.manually_approves_followers;
+  feed.redirect_items = settings.redirect_items;
+  feed.followers_visibility = settings.followers_visibility.clone()
+    .filter(|visibility| [FOLLOWERS_PUBLIC, FOLLOWERS_COUNT, FOLLOWERS_HIDDEN].contains(&visibility.as_str()));
+
+  if let Some(object_type) = &settings.object_type {
+    if [OBJECT_TYPE_NOTE, OBJECT_TYPE_ARTICLE, OBJECT_TYPE_AUTO].contains(&object_type.as_str()) {
+      feed.object_type = object_type.clone();
+    }
+  }
+
+  feed.save(pool).await?;
+
+  let _ = request_feed_update(feed).await;
+
+  if feed.profile_snapshot() != profile {
+    let _ = request_profile_update(feed).await;
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod test {
+  use sqlx::postgres::PgPool;
+
+  use crate::models::Setting;
+  use crate::models::quota::{MAX_FEEDS, MAX_FEEDS_PER_USER};
+  use crate::services::feed_manager::*;
+  use crate::utils::test_helpers::real_user;
+
+  #[test]
+  fn test_valid_feed_name() {
+    assert!(valid_feed_name("muffins"));
+    assert!(valid_feed_name("Muffin_Feed"));
+    assert!(valid_feed_name("muffin.feed-2"));
+    assert!(!valid_feed_name(""));
+    assert!(!valid_feed_name("-muffins"));
+    assert!(!valid_feed_name("muffins."));
+    assert!(!valid_feed_name("muffin feed"));
+    assert!(!valid_feed_name("muffin/feed"));
+  }
+
+  #[sqlx::test]
+  async fn test_create_feed_checks(pool: PgPool) -> sqlx::Result<()> {
+    let user = real_user(&pool).await?;
+
+    let result = create_feed(&user, "no way", "https://foo.com/rss.xml", &pool).await;
+    assert!(matches!(result, Err(FeedManagerError::InvalidName)));
+
+    Feed::create(&user, &"https://foo.com/rss.xml".to_string(), &"muffins".to_string(), &pool).await?;
+    let result = create_feed(&user, "muffins", "https://foo.com/rss.xml", &pool).await;
+    assert!(matches!(result, Err(FeedManagerError::NameTaken)));
+
+    Setting::update(&"signups_enabled".to_string(), &"false".to_string(), &pool).await?;
+    let result = create_feed(&user, "cupcakes", "https://foo.com/rss.xml", &pool).await;
+    assert!(matches!(result, Err(FeedManagerError::SignupsDisabled)));
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_create_feed_quotas(pool: PgPool) -> sqlx::Result<()> {
+    let mut user = real_user(&pool).await?;
+    Feed::create(&user, &"https://foo.com/rss.xml".to_string(), &"muffins".to_string(), &pool).await?;
+
+    Setting::update(&MAX_FEEDS_PER_USER.to_string(), &"1".to_string(), &pool).await?;
+    let result = create_feed(&user, "cupcakes", "https://foo.com/rss.xml", &pool).await;
+    assert!(matches!(result, Err(FeedManagerError::UserQuotaReached(1))));
+
+    // an override lets the user past the instance-wide limit
+    user.set_quota(Some(2), None, &pool).await?;
+    let result = create_feed(&user, "no way", "https://foo.com/rss.xml", &pool).await;
+    assert!(matches!(result, Err(FeedManagerError::InvalidName)));
+
+    Setting::update(&MAX_FEEDS.to_string(), &"1".to_string(), &pool).await?;
+    let result = create_feed(&user, "cupcakes", "https://foo.com/rss.xml", &pool).await;
+    assert!(matches!(result, Err(FeedManagerError::InstanceQuotaReached)));
+    assert_eq!(result.unwrap_err().to_string(), "Sorry, this server can't take on any more feeds right now");
+
+    Ok(())
+  }
+}
@@ -6,8 +6,8 @@ use http_signature_normalization_reqwest::{
   digest::SignExt
 };
 
-use reqwest::Request;
-use reqwest_middleware::RequestBuilder;
+use reqwest::{Request, Response};
+use reqwest_middleware::{ClientWithMiddleware, RequestBuilder};
 use reqwest::header::HeaderValue;
 
 use sqlx::postgres::PgPool;
@@ -31,6 +31,8 @@ use base64::{Engine as _, engine::general_purpose};
 
 use serde::Serialize;
 
+use chrono::{DateTime, Utc};
+
 pub async fn admin_fetch_object(url: &str, pool: &PgPool) -> Result<Option<String>, DeliveryError> {
   let admin_feed = Feed::for_admin(pool).await?;
 
@@ -99,6 +101,29 @@ pub async fn fetch_object(url: &str, key_id: Option<&str>, private_key: Option<&
 ///
 pub async fn deliver_to_inbox<T: Serialize + ?Sized>(inbox: &Url, key_id: &str, private_key: &str, json: &T) -> Result<(), DeliveryError> {
   let client = http_client()?;
+  let response = post_to_inbox(&client, inbox, key_id, private_key, json).await?;
+
+  if response.status().is_success() {
+    Ok(())
+  } else {
+    let status = response.status().to_string();
+    let text = response.text().await.unwrap_or_default();
+
+    Err(DeliveryError::Error(format!("{status:} {text:}")))
+  }
+}
+
+///
+/// sign and post a payload to an inbox, and return the response whatever
+/// its status is
+///
+pub async fn post_to_inbox<T: Serialize + ?Sized>(
+  client: &ClientWithMiddleware,
+  inbox: &Url,
+  key_id: &str,
+  private_key: &str,
+  json: &T
+) -> Result<Response, DeliveryError> {
   let mut heads = generate_request_headers();
   let payload = serde_json::to_vec(json).unwrap();
 
@@ -115,7 +140,6 @@ pub async fn deliver_to_inbox<T: Serialize + ?Sized>(inbox: &Url, key_id: &str,
     .post(inbox.to_string())
     .headers(heads)
     .json(json);
-  
 
   let request = sign_request(
     request_builder,
@@ -127,22 +151,22 @@ pub async fn deliver_to_inbox<T: Serialize + ?Sized>(inbox: &Url, key_id: &str,
 
   log::debug!("{:?}", request);
 
-  let response = client.execute(request).await;
-  match response {
-    Ok(response) => {
-      if response.status().is_success() {
-        Ok(())
-      } else {
-        let status = response.status().to_string();
-        let text = response.text().await.unwrap();
+  client.execute(request).await.map_err(DeliveryError::HttpMiddlewareError)
+}
 
-        Err(DeliveryError::Error(format!("{status:} {text:}")))
-      }
-    },
-			Err(why) => {
-        Err(DeliveryError::HttpMiddlewareError(why))
-			}
+///
+/// parse a Retry-After header, which is either a number of seconds or an HTTP date
+///
+pub fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+  let value = value.trim();
+
+  if let Ok(seconds) = value.parse::<i64>() {
+    return Some(now + chrono::Duration::seconds(seconds.max(0)));
   }
+
+  httpdate::parse_http_date(value)
+    .ok()
+    .map(DateTime::<Utc>::from)
 }
 
 pub async fn sign_request(
@@ -172,3 +196,19 @@ pub async fn sign_request(
     )
     .await
 }
+
+#[cfg(test)]
+mod test {
+  use chrono::{Duration, TimeZone, Utc};
+
+  use crate::services::mailer::parse_retry_after;
+
+  #[test]
+  fn test_parse_retry_after() {
+    let now = Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+
+    assert_eq!(parse_retry_after("120", now), Some(now + Duration::seconds(120)));
+    assert_eq!(parse_retry_after("Sun, 18 Oct 2026 13:00:00 GMT", now), Some(Utc.with_ymd_and_hms(2026, 10, 18, 13, 0, 0).unwrap()));
+    assert_eq!(parse_retry_after("soon", now), None);
+  }
+}
@@ -1,4 +1,7 @@
 pub mod cleanup;
+pub mod email;
+pub mod feed_manager;
+pub mod fediverse_creator;
 pub mod loader;
 pub mod mailer;
 pub mod url_to_feed;
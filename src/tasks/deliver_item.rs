@@ -0,0 +1,122 @@
+use fang::async_trait;
+use fang::asynk::async_queue::AsyncQueueable;
+use fang::serde::{Deserialize, Serialize};
+use fang::typetag;
+use fang::AsyncRunnable;
+use fang::FangError;
+
+use sqlx::postgres::PgPool;
+
+use crate::models::{Feed, Item, Quota};
+use crate::utils::pool::db_pool;
+use crate::DeliveryError;
+
+
+///
+/// Send a new item to a feed's followers. Checking the item's page for
+/// a fediverse:creator tag means a couple of HTTP requests, so that
+/// happens here rather than while the feed is refreshing
+///
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "fang::serde")]
+pub struct DeliverItem {
+  pub feed_id: i32,
+  pub item_id: i32
+}
+
+impl DeliverItem {
+  pub fn new(feed_id: i32, item_id: i32) -> Self {
+    Self { feed_id, item_id }
+  }
+
+  pub async fn deliver(&self, pool: &PgPool, queue: &mut dyn AsyncQueueable) -> Result<(), DeliveryError> {
+    let feed = Feed::find(self.feed_id, pool).await?;
+    let mut item = Item::find(self.item_id, pool).await?;
+
+    if let Err(why) = item.load_fediverse_creator(pool).await {
+      log::info!("couldn't check {:?} for a creator: {why:?}", item.url);
+    }
+
+    let budget = Quota::load(pool).await?.delivery_budget(&feed, pool).await?;
+    item.deliver(&feed, &budget, pool, queue).await?;
+
+    Ok(())
+  }
+}
+
+
+#[async_trait]
+#[typetag::serde]
+impl AsyncRunnable for DeliverItem {
+  async fn run(&self, queue: &mut dyn AsyncQueueable) -> Result<(), FangError> {
+    let pool = db_pool().await;
+
+    match self.deliver(&pool, queue).await {
+      Ok(_result) => Ok(()),
+      Err(why) => {
+        log::info!("DeliverItem: unable to deliver item {:} {why:?}", self.item_id);
+        Err(FangError { description: format!("{why:?}") })
+      }
+    }
+  }
+
+  /// Don't retry, some of the followers might already have the item
+  fn max_retries(&self) -> i32 {
+    0
+  }
+
+  // If `uniq` is set to true and the task is already in the storage, it won't be inserted again
+  // The existing record will be returned for for any insertions operaiton
+  fn uniq(&self) -> bool {
+    true
+  }
+}
+
+
+#[cfg(test)]
+mod test {
+  use sqlx::postgres::PgPool;
+
+  use crate::models::{Actor, Item};
+  use crate::tasks::DeliverItem;
+  use crate::utils::test_helpers::{real_feed, real_item, test_queue, queued_deliveries};
+
+  #[sqlx::test]
+  async fn test_deliver(pool: PgPool) -> Result<(), String> {
+    let mut server = mockito::Server::new_async().await;
+    let _m = server.mock("GET", "/post")
+      .with_status(200)
+      .with_body(r#"<html><head><meta name="fediverse:creator" content="@colin@muffin.industries" /></head></html>"#)
+      .create_async()
+      .await;
+
+    let creator = "https://muffin.industries/users/colin";
+    sqlx::query!("INSERT INTO fediverse_creators (handle, actor_url) VALUES($1, $2)", "@colin@muffin.industries", creator)
+      .execute(&pool)
+      .await
+      .unwrap();
+
+    let feed = real_feed(&pool).await.unwrap();
+    let actor = "https://activitypub.pizza/users/colin";
+    Actor::create(&actor.to_string(), &format!("{actor}/inbox"), &format!("{actor}#main-key"), &"public_key".to_string(), &"colin".to_string(), &pool).await.unwrap();
+    feed.add_follower(&pool, actor).await.unwrap();
+
+    let item = real_item(&feed, &pool).await.unwrap();
+    sqlx::query!("UPDATE items SET url = $1 WHERE id = $2", format!("{}/post", server.url()), item.id)
+      .execute(&pool)
+      .await
+      .unwrap();
+
+    let mut queue = test_queue(&pool).await;
+    DeliverItem::new(feed.id, item.id).deliver(&pool, &mut queue).await.unwrap();
+
+    let item = Item::find(item.id, &pool).await.unwrap();
+    assert_eq!(item.fediverse_creator_url, Some(creator.to_string()));
+
+    let deliveries = queued_deliveries(&format!("{actor}/inbox"), &pool).await;
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0]["type"], "Create");
+
+    Ok(())
+  }
+}
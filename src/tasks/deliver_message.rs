@@ -4,72 +4,234 @@ use fang::serde::{Deserialize, Serialize};
 use fang::typetag;
 use fang::AsyncRunnable;
 use fang::FangError;
+use fang::Scheduled;
+
+use chrono::{DateTime, Utc};
+use sqlx::postgres::PgPool;
 
 use url::Url;
 
+use std::time::Instant;
+
 use crate:: {
+  DeliveryError,
   services::mailer::*,
   models:: {
     Actor,
+    BlockedDomain,
+    Delivery,
+    DeliveryDomain,
     Feed
   },
+  models::delivery::{retry_delay, PENDING},
+  utils::http::single_attempt_http_client,
   utils::pool::db_pool
 };
 
 use serde_json::Value;
 
+///
+/// Deliver a message to an inbox. Every delivery is tracked in the
+/// deliveries table. Failures are retried with backoff until the retry
+/// window runs out, at which point the delivery is marked dead
+///
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "fang::serde")]
 pub struct DeliverMessage {
   pub feed_id: i32,
   pub actor_url: String,
-  pub message: String
+  pub message: String,
+
+  // set when this is a retry of a delivery we're already tracking
+  #[serde(default)]
+  pub delivery_id: Option<i32>,
+
+  #[serde(skip)]
+  pub run_at: Option<DateTime<Utc>>
 }
 
 impl DeliverMessage {
   pub fn new(feed_id: i32, actor_url: String, message: String) -> Self {
-    Self { feed_id, actor_url, message }
+    Self { feed_id, actor_url, message, delivery_id: None, run_at: None }
+  }
+
+  ///
+  /// Build a task to try the delivery again at the given time
+  ///
+  pub fn retry(delivery: &Delivery, run_at: DateTime<Utc>) -> Self {
+    Self {
+      feed_id: delivery.feed_id,
+      actor_url: delivery.inbox_url.clone(),
+      message: delivery.message.clone(),
+      delivery_id: Some(delivery.id),
+      run_at: Some(run_at)
+    }
+  }
+
+  async fn load_delivery(&self, pool: &PgPool) -> Result<Option<Delivery>, DeliveryError> {
+    match self.delivery_id {
+      Some(id) => Ok(Delivery::find(id, pool).await?),
+      None => Ok(Some(Delivery::create(self.feed_id, &self.actor_url, &self.message, pool).await?))
+    }
+  }
+
+  ///
+  /// Queue another try at the delivery, or give up on it if we're past
+  /// its retry window
+  ///
+  async fn reschedule(delivery: &mut Delivery, run_at: DateTime<Utc>, pool: &PgPool, queue: &mut dyn AsyncQueueable) -> Result<(), DeliveryError> {
+    if !delivery.retryable_at(run_at) {
+      log::info!("DeliverMessage: giving up on delivery {} to {}", delivery.id, delivery.inbox_url);
+      delivery.mark_dead(pool).await?;
+      return Ok(())
+    }
+
+    delivery.mark_retry(run_at, pool).await?;
+
+    let task = DeliverMessage::retry(delivery, run_at);
+    match queue.schedule_task(&task as &dyn AsyncRunnable).await {
+      Ok(_) => Ok(()),
+      Err(why) => Err(DeliveryError::Error(why.to_string()))
+    }
+  }
+
+  pub async fn deliver(&self, pool: &PgPool, queue: &mut dyn AsyncQueueable) -> Result<(), DeliveryError> {
+    // don't deliver anything to a domain we've rejected
+    if let Some(host) = Url::parse(&self.actor_url).ok().as_ref().and_then(|url| url.host_str()) {
+      if BlockedDomain::rejects(host, pool).await.unwrap_or(false) {
+        log::info!("DeliverMessage: skipping blocked domain {host}");
+        return Ok(())
+      }
+    }
+
+    // this task isn't retried by the queue, so make sure the delivery is
+    // tracked before anything else can go wrong
+    let delivery = self.load_delivery(pool).await?;
+    if delivery.is_none() {
+      return Ok(())
+    }
+    let mut delivery = delivery.unwrap();
+
+    // the delivery might have been discarded, or already sent
+    if delivery.status != PENDING {
+      return Ok(())
+    }
+
+    let dest_url = match Url::parse(&self.actor_url) {
+      Ok(dest_url) => dest_url,
+      Err(why) => {
+        delivery.record_attempt(None, 0, Some(why.to_string()), pool).await?;
+        delivery.mark_dead(pool).await?;
+        return Ok(())
+      }
+    };
+
+    let feed = match Feed::find(self.feed_id, pool).await {
+      Ok(feed) => feed,
+      Err(why) => {
+        delivery.record_attempt(None, 0, Some(why.to_string()), pool).await?;
+        let run_at = Utc::now() + retry_delay(delivery.attempts);
+        return DeliverMessage::reschedule(&mut delivery, run_at, pool, queue).await;
+      }
+    };
+
+    // if the domain has been failing, wait until it's had a rest
+    if let Some(paused_until) = DeliveryDomain::paused_until(&delivery.domain, pool).await? {
+      log::info!("DeliverMessage: deliveries to {} paused until {paused_until}", delivery.domain);
+      return DeliverMessage::reschedule(&mut delivery, paused_until, pool, queue).await;
+    }
+
+    // we've gotten a JSON object. We'll deserialize it so we can send something that
+    // is serializable to reqwest, since right now we can't manage deserializable objects
+    // with fang
+    let message_object:Value = serde_json::from_str(&self.message)?;
+
+    let client = single_attempt_http_client()?;
+    let started = Instant::now();
+    let response = post_to_inbox(&client, &dest_url, &feed.ap_url(), &feed.private_key, &message_object).await;
+    let latency_ms = started.elapsed().as_millis().min(i32::MAX as u128) as i32;
+
+    let retry_at = match response {
+      Ok(response) => {
+        let status = response.status();
+        let code = status.as_u16() as i32;
+
+        if status.is_success() {
+          delivery.record_attempt(Some(code), latency_ms, None, pool).await?;
+          delivery.mark_delivered(pool).await?;
+          DeliveryDomain::record_success(&delivery.domain, pool).await?;
+          return Ok(())
+        }
+
+        let retry_after = response
+          .headers()
+          .get("Retry-After")
+          .and_then(|value| value.to_str().ok())
+          .and_then(|value| parse_retry_after(value, Utc::now()));
+        let text = response.text().await.unwrap_or_default();
+        let error = format!("{status:} {text:}");
+
+        delivery.record_attempt(Some(code), latency_ms, Some(error), pool).await?;
+
+        // the server is up but doesn't want this message. trying again
+        // won't help, so it goes straight to the dead letters
+        if status.is_client_error() && code != 408 && code != 429 {
+          delivery.mark_dead(pool).await?;
+          DeliveryDomain::record_success(&delivery.domain, pool).await?;
+
+          // the inbox is gone, so check if the actor has moved it
+          if code == 410 {
+            if let Some(actor) = Actor::find(&self.actor_url, pool).await? {
+              Actor::queue_refresh(&actor.url, queue).await?;
+            }
+          }
+
+          return Ok(())
+        }
+
+        if status.is_server_error() {
+          DeliveryDomain::record_failure(&delivery.domain, pool).await?;
+        }
+
+        retry_after
+      },
+      Err(why) => {
+        delivery.record_attempt(None, latency_ms, Some(why.to_string()), pool).await?;
+        DeliveryDomain::record_failure(&delivery.domain, pool).await?;
+
+        None
+      }
+    };
+
+    let _ = Actor::log_error(&self.actor_url, pool).await;
+
+    let run_at = retry_at.unwrap_or_else(|| Utc::now() + retry_delay(delivery.attempts));
+    DeliverMessage::reschedule(&mut delivery, run_at, pool, queue).await
   }
 }
 
 #[async_trait]
 #[typetag::serde]
 impl AsyncRunnable for DeliverMessage {
-  async fn run(&self, _queue: &mut dyn AsyncQueueable) -> Result<(), FangError> {
+  async fn run(&self, queue: &mut dyn AsyncQueueable) -> Result<(), FangError> {
     let pool = db_pool().await;
-    let feed = Feed::find(self.feed_id, &pool).await;
-    match feed {
-      Ok(feed) => {
-        let dest_url = &Url::parse(&self.actor_url).unwrap();
-
-        // we've gotten a JSON object. We'll deserialize it so we can send something that
-        // is serializable to reqwest, since right now we can't manage deserializable objects
-        // with fang
-        let message_object:Value = serde_json::from_str(&self.message).unwrap();
-        let result = deliver_to_inbox(dest_url, &feed.ap_url(), &feed.private_key, &message_object).await;
-
-        if result.is_err() {
-          let _ = Actor::log_error(&dest_url.to_string(), &pool).await;
-        }
-
-        Ok(())
-      },
+    match self.deliver(&pool, queue).await {
+      Ok(_) => Ok(()),
       Err(why) => {
         log::info!("DeliverMessage failed: {why:}");
         Err(FangError { description: why.to_string() })
-      }   
+      }
     }
   }
 
-  // the maximum number of retries. Set it to 0 to make it not retriable
-  // the default value is 20
-  fn max_retries(&self) -> i32 {
-    3
+  fn cron(&self) -> Option<Scheduled> {
+    self.run_at.map(Scheduled::ScheduleOnce)
   }
 
-  // backoff mode for retries
-  fn backoff(&self, attempt: u32) -> u32 {
-    u32::pow(2, attempt)
+  // retries are scheduled by the task itself, so that we can track them
+  // and follow any Retry-After header from the server
+  fn max_retries(&self) -> i32 {
+    0
   }
 
   // If `uniq` is set to true and the task is already in the storage, it won't be inserted again
@@ -87,21 +249,21 @@ mod test {
   use fang::AsyncRunnable;
   use fang::NoTls;
 
+  use chrono::{Duration, Utc};
   use sqlx::postgres::PgPool;
   use std::env;
 
+  use crate::models::{Actor, Delivery};
+  use crate::models::delivery::{DEAD, DELIVERED, PENDING};
   use crate::tasks::DeliverMessage;
+  use crate::utils::test_helpers::{queued_deliveries, real_feed, test_queue};
 
 
   #[sqlx::test]
   async fn test_deliver_message_run(_pool: PgPool) {
     let db_uri = env::var("DATABASE_URL").expect("DATABASE_URL is not set");
 
-    let msg = DeliverMessage {
-      feed_id: 1i32,
-      actor_url: "https://muffinlabs.pizza/".to_string(),
-      message: "{}".to_string()   
-    };
+    let msg = DeliverMessage::new(1i32, "https://muffinlabs.pizza/".to_string(), "{}".to_string());
 
     let mut queue:AsyncQueue<NoTls> = AsyncQueue::builder()
       .uri(db_uri)
@@ -111,4 +273,110 @@ mod test {
     let result = msg.run(&mut queue).await;
     assert!(result.is_err());
   }
+
+  #[sqlx::test]
+  async fn test_deliver_success(pool: PgPool) -> Result<(), String> {
+    let mut server = mockito::Server::new_async().await;
+    let m = server.mock("POST", "/inbox")
+      .with_status(202)
+      .create_async()
+      .await;
+
+    let feed = real_feed(&pool).await.unwrap();
+    let mut queue = test_queue(&pool).await;
+    let inbox = format!("{}/inbox", server.url());
+
+    let msg = DeliverMessage::new(feed.id, inbox.clone(), r#"{"type":"Create"}"#.to_string());
+    msg.deliver(&pool, &mut queue).await.unwrap();
+    m.assert_async().await;
+
+    let delivery = sqlx::query_as!(Delivery, "SELECT * FROM deliveries").fetch_one(&pool).await.unwrap();
+    assert_eq!(delivery.status, DELIVERED);
+    assert_eq!(delivery.attempts, 1);
+    assert_eq!(delivery.last_status_code, Some(202));
+    assert!(queued_deliveries(&inbox, &pool).await.is_empty());
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_deliver_retry_after(pool: PgPool) -> Result<(), String> {
+    let mut server = mockito::Server::new_async().await;
+    let m = server.mock("POST", "/inbox")
+      .with_status(503)
+      .with_header("Retry-After", "600")
+      .create_async()
+      .await;
+
+    let feed = real_feed(&pool).await.unwrap();
+    let mut queue = test_queue(&pool).await;
+    let inbox = format!("{}/inbox", server.url());
+
+    let msg = DeliverMessage::new(feed.id, inbox.clone(), r#"{"type":"Create"}"#.to_string());
+    msg.deliver(&pool, &mut queue).await.unwrap();
+    m.assert_async().await;
+
+    let delivery = sqlx::query_as!(Delivery, "SELECT * FROM deliveries").fetch_one(&pool).await.unwrap();
+    assert_eq!(delivery.status, PENDING);
+    assert_eq!(delivery.last_status_code, Some(503));
+
+    let next_attempt_at = delivery.next_attempt_at.unwrap();
+    assert!(next_attempt_at > Utc::now() + Duration::seconds(590));
+    assert!(next_attempt_at < Utc::now() + Duration::seconds(610));
+
+    // the retry is queued up for the same delivery
+    assert_eq!(queued_deliveries(&inbox, &pool).await.len(), 1);
+
+    let retry = DeliverMessage::retry(&delivery, Utc::now());
+    assert_eq!(retry.delivery_id, Some(delivery.id));
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_deliver_bad_inbox(pool: PgPool) -> Result<(), String> {
+    let feed = real_feed(&pool).await.unwrap();
+    let mut queue = test_queue(&pool).await;
+
+    let msg = DeliverMessage::new(feed.id, "not a url".to_string(), r#"{"type":"Create"}"#.to_string());
+    msg.deliver(&pool, &mut queue).await.unwrap();
+
+    // the failure is recorded instead of disappearing with the task
+    let delivery = sqlx::query_as!(Delivery, "SELECT * FROM deliveries").fetch_one(&pool).await.unwrap();
+    assert_eq!(delivery.status, DEAD);
+    assert_eq!(delivery.attempts, 1);
+    assert!(delivery.last_error.is_some());
+
+    Ok(())
+  }
+
+  #[sqlx::test]
+  async fn test_deliver_gone(pool: PgPool) -> Result<(), String> {
+    let mut server = mockito::Server::new_async().await;
+    let m = server.mock("POST", "/inbox")
+      .with_status(410)
+      .create_async()
+      .await;
+
+    let feed = real_feed(&pool).await.unwrap();
+    let mut queue = test_queue(&pool).await;
+    let inbox = format!("{}/inbox", server.url());
+    let actor_url = format!("{}/users/gone", server.url());
+
+    Actor::create(&actor_url, &inbox, &format!("{actor_url}#main-key"), &"key".to_string(), &"gone".to_string(), &pool).await.unwrap();
+
+    let msg = DeliverMessage::new(feed.id, inbox.clone(), r#"{"type":"Create"}"#.to_string());
+    msg.deliver(&pool, &mut queue).await.unwrap();
+    m.assert_async().await;
+
+    let delivery = sqlx::query_as!(Delivery, "SELECT * FROM deliveries").fetch_one(&pool).await.unwrap();
+    assert_eq!(delivery.status, DEAD);
+    assert!(queued_deliveries(&inbox, &pool).await.is_empty());
+
+    // we know who owns the inbox, so their data gets refreshed
+    let refresh = sqlx::query!("SELECT metadata FROM fang_tasks WHERE metadata->>'type' = 'RefreshActor'").fetch_one(&pool).await.unwrap();
+    assert_eq!(refresh.metadata["url"], actor_url);
+
+    Ok(())
+  }
 }
@@ -0,0 +1,127 @@
+use fang::async_trait;
+use fang::asynk::async_queue::AsyncQueueable;
+use fang::serde::{Deserialize, Serialize};
+use fang::typetag;
+use fang::AsyncRunnable;
+use fang::FangError;
+
+use sqlx::postgres::PgPool;
+
+use crate::models::Feed;
+use crate::models::Message;
+use crate::models::feed::AcceptedActivity;
+use crate::utils::pool::db_pool;
+use crate::DeliveryError;
+
+///
+/// Handle an activity that was posted to a feed's inbox. The inbox
+/// checks the signature and logs the message, then queues this up so
+/// that slow remote servers don't hold up the request
+///
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "fang::serde")]
+pub struct HandleActivity {
+  pub message_id: i32,
+}
+
+impl HandleActivity {
+  pub fn new(message_id: i32) -> Self {
+    Self { message_id }
+  }
+
+  pub async fn handle(&self, pool: &PgPool, queue: &mut dyn AsyncQueueable) -> Result<(), DeliveryError> {
+    let message = Message::find(self.message_id, pool).await?;
+    if message.is_none() {
+      log::info!("HandleActivity: message {} is gone", self.message_id);
+      return Ok(())
+    }
+    let mut message = message.unwrap();
+
+    let feed = Feed::find_by_name(&message.username, pool).await?;
+    if feed.is_none() {
+      log::info!("HandleActivity: feed {} is gone", message.username);
+      return Ok(())
+    }
+    let feed = feed.unwrap();
+
+    let activity: AcceptedActivity = serde_json::from_str(&message.text)?;
+    let result = feed.handle_activity(pool, queue, &activity).await;
+
+    match result {
+      Ok(_result) => {
+        message.mark_handled(true, None, pool).await?;
+        Ok(())
+      },
+      Err(why) => {
+        message.mark_handled(false, Some(why.to_string()), pool).await?;
+        Err(why)
+      }
+    }
+  }
+}
+
+#[async_trait]
+#[typetag::serde]
+impl AsyncRunnable for HandleActivity {
+  async fn run(&self, queue: &mut dyn AsyncQueueable) -> Result<(), FangError> {
+    let pool = db_pool().await;
+
+    match self.handle(&pool, queue).await {
+      Ok(_result) => Ok(()),
+      Err(why) => {
+        log::info!("HandleActivity failed: {why:}");
+        Err(FangError { description: why.to_string() })
+      }
+    }
+  }
+
+  // the maximum number of retries. Set it to 0 to make it not retriable
+  // the default value is 20
+  fn max_retries(&self) -> i32 {
+    3
+  }
+
+  // backoff mode for retries
+  fn backoff(&self, attempt: u32) -> u32 {
+    u32::pow(2, attempt)
+  }
+
+  // If `uniq` is set to true and the task is already in the storage, it won't be inserted again
+  // The existing record will be returned for for any insertions operaiton
+  fn uniq(&self) -> bool {
+    true
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use sqlx::postgres::PgPool;
+
+  use crate::models::Message;
+  use crate::tasks::HandleActivity;
+  use crate::utils::test_helpers::{real_feed, test_queue, queued_deliveries};
+
+  #[sqlx::test]
+  async fn test_handle(pool: PgPool) -> Result<(), String> {
+    let actor = "https://activitypub.pizza/users/colin";
+    let feed = real_feed(&pool).await.unwrap();
+
+    let json = format!(r#"{{"id": "{}/follows/1", "actor":"{}","object":"{}","type":"Follow"}}"#, actor, actor, feed.ap_url());
+    let message = Message::log(&feed.name, &json, Some(actor.to_string()), None, false, &pool).await.unwrap();
+
+    let mut queue = test_queue(&pool).await;
+    HandleActivity::new(message.id).handle(&pool, &mut queue).await.unwrap();
+
+    let message = Message::find(message.id, &pool).await.unwrap().unwrap();
+    assert!(message.handled);
+
+    let followers = feed.followers_list(&pool).await.unwrap();
+    assert_eq!(followers[0].actor, actor);
+
+    let deliveries = queued_deliveries(&format!("{actor}/inbox"), &pool).await;
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0]["type"], "Accept");
+
+    Ok(())
+  }
+}
@@ -1,11 +1,21 @@
 pub mod deliver_message;
+pub mod handle_activity;
 pub mod refresh_feed;
 pub mod update_stale_feeds;
 pub mod delete_old_messages;
 pub mod delete_bad_actors;
+pub mod update_profile;
+pub mod delete_expired_follow_requests;
+pub mod refresh_actor;
+pub mod deliver_item;
 
 pub use deliver_message::DeliverMessage;
+pub use handle_activity::HandleActivity;
 pub use refresh_feed::RefreshFeed;
 pub use update_stale_feeds::UpdateStaleFeeds;
 pub use delete_old_messages::DeleteOldMessages;
-pub use delete_bad_actors::DeleteBadActors;
\ No newline at end of file
+pub use delete_bad_actors::DeleteBadActors;
+pub use update_profile::UpdateProfile;
+pub use delete_expired_follow_requests::DeleteExpiredFollowRequests;
+pub use refresh_actor::RefreshActor;
+pub use deliver_item::DeliverItem;
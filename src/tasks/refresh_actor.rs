@@ -0,0 +1,54 @@
+use fang::async_trait;
+use fang::asynk::async_queue::AsyncQueueable;
+use fang::serde::{Deserialize, Serialize};
+use fang::typetag;
+use fang::AsyncRunnable;
+use fang::FangError;
+
+use crate::models::Actor;
+use crate::utils::pool::db_pool;
+
+
+///
+/// Fetch a remote actor again and update our cached copy
+///
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "fang::serde")]
+pub struct RefreshActor {
+  pub url: String,
+}
+
+impl RefreshActor {
+  pub fn new(url: String) -> Self {
+    Self { url }
+  }
+}
+
+
+#[async_trait]
+#[typetag::serde]
+impl AsyncRunnable for RefreshActor {
+  async fn run(&self, _queue: &mut dyn AsyncQueueable) -> Result<(), FangError> {
+    let pool = db_pool().await;
+
+    match Actor::refetch(&self.url, &pool).await {
+      Ok(_result) => Ok(()),
+      Err(why) => {
+        log::info!("RefreshActor: unable to refresh {:} {why:}", self.url);
+        Err(FangError { description: why.to_string() })
+      }
+    }
+  }
+
+  /// Don't retry, the cached data is still there and we'll try again
+  /// once it's stale
+  fn max_retries(&self) -> i32 {
+    0
+  }
+
+  // If `uniq` is set to true and the task is already in the storage, it won't be inserted again
+  // The existing record will be returned for for any insertions operaiton
+  fn uniq(&self) -> bool {
+    true
+  }
+}
@@ -0,0 +1,105 @@
+use fang::async_trait;
+use fang::asynk::async_queue::AsyncQueueable;
+use fang::serde::{Deserialize, Serialize};
+use fang::typetag;
+use fang::AsyncRunnable;
+use fang::FangError;
+use fang::Scheduled;
+
+use chrono::{DateTime, Utc};
+
+use crate::models::Feed;
+use crate::utils::pool::db_pool;
+
+
+///
+/// Send an actor Update for a feed to all of its followers
+///
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "fang::serde")]
+pub struct UpdateProfile {
+  pub feed_id: i32,
+
+  // this isn't serialized, so it doesn't count towards the uniqueness
+  // of the task. that way any changes that come in while an update is
+  // already pending get rolled into it
+  #[serde(skip)]
+  pub run_at: Option<DateTime<Utc>>
+}
+
+impl UpdateProfile {
+  pub fn new(feed_id: i32, run_at: DateTime<Utc>) -> Self {
+    Self { feed_id, run_at: Some(run_at) }
+  }
+}
+
+#[async_trait]
+#[typetag::serde]
+impl AsyncRunnable for UpdateProfile {
+  async fn run(&self, queue: &mut dyn AsyncQueueable) -> Result<(), FangError> {
+    let pool = db_pool().await;
+
+    let feed = Feed::find(self.feed_id, &pool).await;
+    match feed {
+      Ok(feed) => {
+        feed.broadcast_profile_update(&pool, queue).await?;
+        Ok(())
+      },
+      Err(why) => {
+        log::info!("UpdateProfile: Feed missing? {why:}");
+        Err(FangError { description: why.to_string() })
+      }
+    }
+  }
+
+  fn cron(&self) -> Option<Scheduled> {
+    Some(Scheduled::ScheduleOnce(self.run_at.unwrap_or_else(Utc::now)))
+  }
+
+  fn max_retries(&self) -> i32 {
+    0
+  }
+
+  // If `uniq` is set to true and the task is already in the storage, it won't be inserted again
+  // The existing record will be returned for for any insertions operaiton
+  fn uniq(&self) -> bool {
+    true
+  }
+}
+
+
+#[cfg(test)]
+mod test {
+  use fang::asynk::async_queue::AsyncQueue;
+  use fang::AsyncRunnable;
+  use fang::NoTls;
+
+  use chrono::Utc;
+  use sqlx::postgres::PgPool;
+  use std::env;
+
+  use crate::tasks::UpdateProfile;
+
+  #[sqlx::test]
+  async fn test_update_profile_missing_feed(_pool: PgPool) {
+    let db_uri = env::var("DATABASE_URL").expect("DATABASE_URL is not set");
+
+    let task = UpdateProfile::new(-1, Utc::now());
+
+    let mut queue:AsyncQueue<NoTls> = AsyncQueue::builder()
+      .uri(db_uri)
+      .max_pool_size(1u32)
+      .build();
+
+    let result = task.run(&mut queue).await;
+    assert!(result.is_err());
+  }
+
+  #[test]
+  fn test_run_at_not_serialized() {
+    let task = UpdateProfile::new(12, Utc::now());
+    let output = serde_json::to_string(&task).unwrap();
+
+    assert_eq!(output, r#"{"feed_id":12}"#);
+  }
+}
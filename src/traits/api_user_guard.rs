@@ -0,0 +1,42 @@
+use sqlx::postgres::PgPool;
+use crate::models::{ApiToken, User};
+
+use rocket::request::{self, FromRequest, Request};
+use rocket::outcome::Outcome;
+use rocket::http::Status;
+
+///
+/// A user authenticated with an API token, passed in an
+/// `Authorization: Bearer <token>` header. API requests never look at
+/// the session cookie
+///
+pub struct ApiUser(pub User);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ApiUser {
+  type Error = ();
+
+  async fn from_request(request: &'r Request<'_>) -> request::Outcome<ApiUser, Self::Error> {
+    let pool = request.rocket().state::<PgPool>().unwrap();
+
+    let token = request
+      .headers()
+      .get_one("Authorization")
+      .and_then(|value| value.strip_prefix("Bearer "))
+      .map(|value| value.trim());
+
+    let token = match token {
+      Some(token) if !token.is_empty() => token,
+      _ => return Outcome::Error((Status::Unauthorized, ()))
+    };
+
+    match ApiToken::user_for_token(token, pool).await {
+      Ok(Some(user)) => Outcome::Success(ApiUser(user)),
+      Ok(None) => Outcome::Error((Status::Unauthorized, ())),
+      Err(why) => {
+        log::debug!("ERR: {why:?}");
+        Outcome::Error((Status::InternalServerError, ()))
+      }
+    }
+  }
+}
@@ -173,3 +173,20 @@ impl Default for ContentMapNote {
     Self::new()
   }
 }
+
+pub type ContentMapArticle = ContentMap<ApObject<Article>>;
+
+impl ContentMapArticle {
+  pub fn new() -> ContentMapArticle {
+    ContentMapArticle {
+      contentMap: ContentMapValues::new(),
+      inner: ApObject::new(Article::new()),
+    }
+  }
+}
+
+impl Default for ContentMapArticle {
+  fn default() -> Self {
+    Self::new()
+  }
+}
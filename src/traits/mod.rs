@@ -1,10 +1,14 @@
 pub mod sensitive;
 pub mod user_guard;
+pub mod user_agent_guard;
+pub mod api_user_guard;
 pub mod custom_content_type;
 pub mod content_map;
 pub mod property_value;
 
 pub use sensitive::CanBeSensitive;
+pub use user_agent_guard::UserAgent;
+pub use api_user_guard::ApiUser;
 pub use content_map::ContentMap;
 pub use custom_content_type::CustomContentType;
 pub use custom_content_type::ActivityJsonContentType;
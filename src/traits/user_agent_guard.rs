@@ -0,0 +1,22 @@
+use rocket::request::{self, FromRequest, Request};
+use rocket::outcome::Outcome;
+
+///
+/// The User-Agent header of the request, if there is one. We keep this
+/// with each session so people can tell their devices apart
+///
+pub struct UserAgent(pub Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for UserAgent {
+  type Error = std::convert::Infallible;
+
+  async fn from_request(request: &'r Request<'_>) -> request::Outcome<UserAgent, Self::Error> {
+    let user_agent = request
+      .headers()
+      .get_one("User-Agent")
+      .map(|value| value.chars().take(255).collect());
+
+    Outcome::Success(UserAgent(user_agent))
+  }
+}
@@ -1,13 +1,16 @@
 use std::env;
 
 use sqlx::postgres::PgPool;
-use crate::models::User;
+use crate::models::{Session, User};
 
 use rocket::request::{self, FromRequest, Request};
 use rocket::outcome::Outcome;
 use rocket::http::Status;
 
 
+/// The private cookie that holds the session token
+pub const SESSION_COOKIE: &str = "session_token";
+
 fn user_to_outcome(user: Result<Option<User>, sqlx::Error>) -> request::Outcome<User, std::convert::Infallible> {
   match user {
     Ok(user) => {
@@ -37,13 +40,12 @@ impl<'r> FromRequest<'r> for User {
       return user_to_outcome(user)
     }
 
-    let cookie = request.cookies().get_private("access_token");
+    let cookie = request.cookies().get_private(SESSION_COOKIE);
 
     match cookie {
       Some(cookie) => {
-        let access_token = cookie.value();
-        // log::info!("access token: {:}", access_token);
-        let user = User::find_by_access(&access_token.to_string(), pool).await;
+        let ip = request.client_ip().map(|ip| ip.to_string());
+        let user = Session::user_for_token(cookie.value(), ip.as_deref(), pool).await;
         user_to_outcome(user)
       },
       None => {
@@ -35,11 +35,15 @@ pub fn generate_request_headers() -> HeaderMap {
   headers
 }
 
-pub fn http_client() -> Result<reqwest_middleware::ClientWithMiddleware, reqwest::Error> {
+fn base_client() -> Result<reqwest::Client, reqwest::Error> {
   let request_timeout = Duration::from_secs(30);
-    let base_client = reqwest::Client::builder()
-        .timeout(request_timeout)
-        .build()?;
+  reqwest::Client::builder()
+    .timeout(request_timeout)
+    .build()
+}
+
+pub fn http_client() -> Result<reqwest_middleware::ClientWithMiddleware, reqwest::Error> {
+  let base_client = base_client()?;
 
   // Retry up to 3 times with increasing intervals between attempts.
   let retry_policy = ExponentialBackoff::builder().build_with_max_retries(3);
@@ -48,3 +52,11 @@ pub fn http_client() -> Result<reqwest_middleware::ClientWithMiddleware, reqwest
     .with(RetryTransientMiddleware::new_with_policy(retry_policy))
     .build())
 }
+
+///
+/// A client that makes a single attempt at each request, for when the
+/// caller is handling retries itself
+///
+pub fn single_attempt_http_client() -> Result<reqwest_middleware::ClientWithMiddleware, reqwest::Error> {
+  Ok(ClientBuilder::new(base_client()?).build())
+}
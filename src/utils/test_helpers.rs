@@ -12,6 +12,12 @@ use crate::server::build_server;
 
 use chrono::Utc;
 use uuid::Uuid;
+use url::Url;
+
+use fang::asynk::async_queue::AsyncQueue;
+use fang::NoTls;
+
+use std::env;
 
 use rocket::uri;
 use rocket::{Rocket, Build};
@@ -31,14 +37,14 @@ pub fn fake_user() -> User {
   User { 
     id: 1, 
     admin: false,
+    max_feeds: None,
+    max_daily_deliveries: None,
     email: Some("foo@bar.com".to_string()), 
     actor_url: Some("http://foobar.com".to_string()), 
     login_token: "lt".to_string(), 
-    access_token: Some("at".to_string()), 
     username: Some("username".to_string()),
     created_at: Utc::now(), 
     updated_at: Utc::now() ,
-    access_token_updated_at: Utc::now(),
     login_token_updated_at: Utc::now(),
   }
 }
@@ -115,7 +121,18 @@ pub fn fake_feed() -> Feed {
     language: None,
     error: None,
     error_count: 0,
-    tweaked_profile_data: false
+    tweaked_profile_data: false,
+    profile_updated_at: None,
+    also_known_as: None,
+    moved_to: None,
+    previous_public_key: None,
+    key_rotated_at: None,
+    secure_mode: false,
+    manually_approves_followers: false,
+    followers_visibility: None,
+    object_type: "note".to_string(),
+    redirect_items: false,
+    paused: false
   }
 }
 
@@ -139,7 +156,10 @@ pub fn fake_item() -> Item {
     url: Some("http://google.com".to_string()),
     created_at: Utc::now(),
     updated_at: Utc::now(),
-    language: None
+    language: None,
+    author: None,
+    fediverse_creator: None,
+    fediverse_creator_url: None
   }
 }
 
@@ -205,3 +225,35 @@ pub async fn real_enclosure(item: &Item, pool: &PgPool) -> sqlx::Result<Enclosur
   Enclosure::find(enclosure_id, &pool).await
 }
 
+
+///
+/// Build a job queue which uses the same database as the given test
+/// pool, so we can check what got queued up
+///
+pub async fn test_queue(pool: &PgPool) -> AsyncQueue<NoTls> {
+  let default_db_uri = env::var("DATABASE_URL").expect("DATABASE_URL is not set");
+  let mut parsed_uri = Url::parse(&default_db_uri).unwrap();
+  parsed_uri.set_path(pool.connect_options().get_database().unwrap());
+
+  let mut queue = AsyncQueue::builder()
+    .uri(parsed_uri)
+    .max_pool_size(1u32)
+    .build();
+
+  queue.connect(NoTls).await.unwrap();
+
+  queue
+}
+
+///
+/// Get the messages that have been queued up for delivery to the given inbox
+///
+pub async fn queued_deliveries(inbox: &str, pool: &PgPool) -> Vec<serde_json::Value> {
+  sqlx::query!("SELECT metadata FROM fang_tasks WHERE metadata->>'type' = 'DeliverMessage' AND metadata->>'actor_url' = $1 ORDER BY created_at", inbox)
+    .fetch_all(pool)
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|task| serde_json::from_str(task.metadata["message"].as_str().unwrap()).unwrap())
+    .collect()
+}
@@ -1,5 +1,7 @@
 use std::env;
 
+use url::Url;
+
 ///
 /// convert path to absolute URL
 ///
@@ -7,3 +9,14 @@ pub fn path_to_url(frag: &rocket::http::uri::Origin) -> String {
   let host = env::var("DOMAIN_NAME").expect("DOMAIN_NAME is not set");
   format!("https://{host}{frag}")
 }
+
+///
+/// Check that a URL from a remote feed is a plain web link before we put
+/// it on a page. Anything that isn't http(s) is dropped
+///
+pub fn web_url(url: &str) -> Option<String> {
+  match Url::parse(url) {
+    Ok(parsed) if parsed.scheme() == "https" || parsed.scheme() == "http" => Some(url.to_string()),
+    _ => None
+  }
+}